@@ -0,0 +1,69 @@
+//! Benchmarks of the Gaussian elimination of `algebra`, row by row on `Vob`s against the Method
+//! of Four Russians on a `PackedMatrix`, on random matrices with more rows than columns like the
+//! left-hand sides of the systems.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vob::Vob;
+
+use crush::algebra::{self, Matrix, PackedMatrix};
+
+const SIZES: [usize; 3] = [128, 512, 1024];
+
+/// Return a random matrix of the size given.
+fn random_matrix(rows: usize, columns: usize) -> Matrix {
+    let mut rng = StdRng::seed_from_u64(rows as u64);
+    let rows: Vec<Vob> = (0..rows)
+        .map(|_| (0..columns).map(|_| rng.gen_bool(0.5)).collect())
+        .collect();
+    Matrix::from_rows(rows)
+}
+
+fn bench_dependencies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("linear dependencies");
+    group.sample_size(10);
+    for size in SIZES.iter() {
+        let m = random_matrix(*size, size / 2);
+        group.bench_with_input(BenchmarkId::new("vob", size), &m, |b, m| {
+            b.iter(|| algebra::extract_linear_dependencies(m.clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("packed", size), &m, |b, m| {
+            b.iter(|| PackedMatrix::from_matrix(m).linear_dependencies())
+        });
+    }
+    group.finish();
+}
+
+fn bench_solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("elimination");
+    group.sample_size(10);
+    for size in SIZES.iter() {
+        let m = random_matrix(size / 2, *size);
+        let rhs = Vob::from_elem(m.row_size(), true);
+        group.bench_with_input(BenchmarkId::new("vob", size), &m, |b, m| {
+            b.iter(|| algebra::solve_linear_system(m.clone(), rhs.clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("packed rank", size), &m, |b, m| {
+            b.iter(|| PackedMatrix::from_matrix(m).rank())
+        });
+        group.bench_with_input(BenchmarkId::new("packed kernel", size), &m, |b, m| {
+            b.iter(|| PackedMatrix::from_matrix(m).kernel())
+        });
+    }
+    group.finish();
+}
+
+fn bench_inverse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inverse");
+    group.sample_size(10);
+    for size in SIZES.iter() {
+        let m = PackedMatrix::from_matrix(&random_matrix(*size, *size));
+        group.bench_with_input(BenchmarkId::new("packed", size), &m, |b, m| {
+            b.iter(|| m.inverse())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_dependencies, bench_solve, bench_inverse);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! Fuzz the parser of the .bdd format: any input should either be rejected with a `SpecError`
+//! or give a `System`, without panicking.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use crush::soc::utils;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    if let Ok(spec) = utils::parse_system_spec_from_str(text) {
+        let _ = utils::try_build_system_from_spec(spec);
+    }
+});
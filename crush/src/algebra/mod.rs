@@ -9,6 +9,9 @@
 //! * Left mul two matrices.
 //! * Extract a linear layer from a System description.
 //! * Extract any solution(s) to a matrix and its right-hand side vector.
+//! * Extract the affine space of all the solutions to a matrix and its right-hand side vector.
+//! * Draw a random solution to a matrix and its right-hand side vector.
+//! * Eliminate large matrices word by word with the Method of Four Russians (see `packed`).
 //!
 //! More functions are expected to be added when the need arise.
 //!
@@ -24,8 +27,13 @@ use std::fmt;
 use std::iter;
 use std::slice::Iter;
 
+use rand::Rng;
 use vob::{vob, Vob};
 
+pub mod packed;
+
+pub use packed::PackedMatrix;
+
 /// `matrix!` is sugar around Matrix::from_rows().
 ///
 /// Macro to easily create a `Matrix` object from a
@@ -197,7 +205,7 @@ pub fn get_max_set_bit(vob: &Vob) -> Option<usize> {
 /// -> return the lower part of the identity containing the dependencies
 pub fn extract_linear_dependencies(mut mat: Matrix) -> Matrix {
     let mut id = identity(mat.row_size());
-    let mut loop_id = 0;
+    let mut loop_id = mat.row_size();
     for i in (0..mat.row_size()).rev() {
         let mut highest_set_bit = get_max_set_bit(&mat.rows[i]);
         let mut max_row = i;
@@ -277,6 +285,8 @@ pub fn extract_linear_dependencies(mut mat: Matrix) -> Matrix {
 ///
 /// Once the matrix is reduced the solution will be a `Vec` of `Some(bool)` for every fixed variable,
 /// and `None` for every free variable.
+///
+/// The relations between the free variables are lost, use `solve_affine_system` to keep them.
 pub fn solve_linear_system(mut lhs: Matrix, mut rhs: Vob) -> Vec<Option<bool>> {
     for i in (0..lhs.row_size()).rev() {
         let mut highest_set_bit = get_max_set_bit(&lhs.rows[i]);
@@ -333,5 +343,256 @@ pub fn solve_linear_system(mut lhs: Matrix, mut rhs: Vob) -> Vec<Option<bool>> {
     solutions
 }
 
+/// Draw a solution of the linear system represented by a `Matrix` (left hand side) and a `Vob`
+/// (right hand side) uniformly at random.
+///
+/// The system is put in echelon form (one row per highest set bit), the free variables are
+/// given a random value, then the fixed variables are computed from the lowest highest set bit
+/// to the highest.
+///
+/// Return `None` if the system has no solution.
+pub fn random_solution<R: Rng + ?Sized>(lhs: Matrix, rhs: Vob, rng: &mut R) -> Option<Vob> {
+    let nvar = lhs.column_size();
+    let mut echelon: Vec<Option<(Vob, bool)>> = vec![None; nvar];
+    for (mut row, mut value) in lhs.rows.into_iter().zip(rhs.iter()) {
+        while let Some(highest_set_bit) = get_max_set_bit(&row) {
+            match &echelon[highest_set_bit] {
+                Some((pivot_row, pivot_value)) => {
+                    row.xor(pivot_row);
+                    value ^= pivot_value;
+                }
+                None => break,
+            }
+        }
+        match get_max_set_bit(&row) {
+            Some(highest_set_bit) => echelon[highest_set_bit] = Some((row, value)),
+            // 0 = 1
+            None if value => return None,
+            None => (),
+        }
+    }
+    let mut solution = Vob::from_elem(nvar, false);
+    for (var, pivot) in echelon.iter().enumerate() {
+        let value = match pivot {
+            Some((row, value)) => row
+                .iter_set_bits(..var)
+                .fold(*value, |acc, other| acc ^ solution[other]),
+            None => rng.gen(),
+        };
+        solution.set(var, value);
+    }
+    Some(solution)
+}
+
+/// The solutions of a linear system : a particular solution plus the span of a basis of the
+/// kernel of its lhs.
+///
+/// The space is kept in a canonical form : each vector of the basis has a lowest set bit, its
+/// free variable, which is unset in the other vectors of the basis and in the particular
+/// solution. The particular solution is then the solution whose free variables are all 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AffineSpace {
+    particular: Vob,
+    basis: Vec<Vob>,
+}
+
+impl AffineSpace {
+    /// Construct the affine space `particular + span(vectors)`, the vectors need not be
+    /// linearly independent.
+    pub fn new(particular: Vob, vectors: Vec<Vob>) -> AffineSpace {
+        let mut space = AffineSpace {
+            particular,
+            basis: Vec::with_capacity(vectors.len()),
+        };
+        for mut vector in vectors {
+            for other in space.basis.iter() {
+                let free = other.iter_set_bits(..).next().unwrap();
+                if vector[free] {
+                    vector.xor(other);
+                }
+            }
+            let free = match vector.iter_set_bits(..).next() {
+                Some(free) => free,
+                // linearly dependent
+                None => continue,
+            };
+            for other in space.basis.iter_mut() {
+                if other[free] {
+                    other.xor(&vector);
+                }
+            }
+            if space.particular[free] {
+                space.particular.xor(&vector);
+            }
+            space.basis.push(vector);
+        }
+        space
+            .basis
+            .sort_by_key(|vector| vector.iter_set_bits(..).next().unwrap());
+        space
+    }
+
+    /// Return the solution whose free variables are all 0.
+    pub fn get_particular(&self) -> &Vob {
+        &self.particular
+    }
+
+    /// Return the basis of the kernel, sorted by free variable.
+    pub fn get_basis(&self) -> &[Vob] {
+        &self.basis
+    }
+
+    /// Return the number of variables.
+    pub fn nvar(&self) -> usize {
+        self.particular.len()
+    }
+
+    /// Return the rank of the linear system, the number of variables determined by the others.
+    pub fn rank(&self) -> usize {
+        self.nvar() - self.nullity()
+    }
+
+    /// Return the dimension of the space, there are `2**nullity` solutions.
+    pub fn nullity(&self) -> usize {
+        self.basis.len()
+    }
+
+    /// Return the free variables, the ones which can take any value in a solution, the others
+    /// being an affine function of them.
+    pub fn free_vars(&self) -> Vec<usize> {
+        self.basis
+            .iter()
+            .map(|vector| vector.iter_set_bits(..).next().unwrap())
+            .collect()
+    }
+
+    /// Return the value of each variable, `None` for the variables which don't have the same
+    /// value in every solution.
+    pub fn fixed(&self) -> Vec<Option<bool>> {
+        let mut fixed: Vec<Option<bool>> = self.particular.iter().map(Some).collect();
+        for vector in self.basis.iter() {
+            for var in vector.iter_set_bits(..) {
+                fixed[var] = None;
+            }
+        }
+        fixed
+    }
+
+    /// Return true if `solution` is in the space.
+    pub fn contains(&self, solution: &Vob) -> bool {
+        if solution.len() != self.nvar() {
+            return false;
+        }
+        let mut diff = solution.clone();
+        diff.xor(&self.particular);
+        for vector in self.basis.iter() {
+            if diff[vector.iter_set_bits(..).next().unwrap()] {
+                diff.xor(vector);
+            }
+        }
+        diff.iter_set_bits(..).next().is_none()
+    }
+
+    /// Return the space of the first `len` variables, the projection of the solutions.
+    pub fn truncate(&self, len: usize) -> AffineSpace {
+        let truncate = |vector: &Vob| {
+            let mut vector = vector.clone();
+            vector.truncate(len);
+            vector
+        };
+        AffineSpace::new(
+            truncate(&self.particular),
+            self.basis.iter().map(truncate).collect(),
+        )
+    }
+
+    /// Return an iterator over the `2**nullity` solutions, in the order of a Gray code so that
+    /// two consecutive solutions differ by a vector of the basis.
+    pub fn iter(&self) -> AffineSpaceIter<'_> {
+        AffineSpaceIter {
+            space: self,
+            current: Some(self.particular.clone()),
+            counter: vec![false; self.nullity()],
+        }
+    }
+}
+
+/// Iterator over the solutions of an `AffineSpace`, created by `AffineSpace::iter`.
+pub struct AffineSpaceIter<'a> {
+    space: &'a AffineSpace,
+    current: Option<Vob>,
+    counter: Vec<bool>,
+}
+
+impl<'a> Iterator for AffineSpaceIter<'a> {
+    type Item = Vob;
+
+    fn next(&mut self) -> Option<Vob> {
+        let solution = self.current.take()?;
+        // increment the counter, the bit which becomes set gives the vector to add
+        if let Some(flip) = self.counter.iter().position(|bit| !bit) {
+            for bit in self.counter[..flip].iter_mut() {
+                *bit = false;
+            }
+            self.counter[flip] = true;
+            let mut next = solution.clone();
+            next.xor(&self.space.basis[flip]);
+            self.current = Some(next);
+        }
+        Some(solution)
+    }
+}
+
+/// Return the `AffineSpace` of the solutions of the linear system represented by a `Matrix`
+/// (left hand side) and a `Vob` (right hand side) over `nvar` variables, or `None` if the system
+/// has no solution.
+///
+/// The system is put in echelon form (one row per highest set bit), the variables without a row
+/// are the free variables. The particular solution gives them the value 0 and each vector of
+/// the basis gives one of them the value 1, the other variables being computed from the lowest
+/// highest set bit to the highest.
+///
+/// `nvar` is given as `lhs` may have no row, the rows must have `nvar` columns.
+pub fn solve_affine_system(lhs: Matrix, rhs: Vob, nvar: usize) -> Option<AffineSpace> {
+    let mut echelon: Vec<Option<(Vob, bool)>> = vec![None; nvar];
+    for (mut row, mut value) in lhs.rows.into_iter().zip(rhs.iter()) {
+        assert_eq!(row.len(), nvar, "the rows must have nvar columns");
+        while let Some(highest_set_bit) = get_max_set_bit(&row) {
+            match &echelon[highest_set_bit] {
+                Some((pivot_row, pivot_value)) => {
+                    row.xor(pivot_row);
+                    value ^= pivot_value;
+                }
+                None => break,
+            }
+        }
+        match get_max_set_bit(&row) {
+            Some(highest_set_bit) => echelon[highest_set_bit] = Some((row, value)),
+            // 0 = 1
+            None if value => return None,
+            None => (),
+        }
+    }
+    // the solution of the system whose free variables are given, with the rhs or without
+    let solve = |free: Option<usize>, with_rhs: bool| {
+        let mut solution = Vob::from_elem(nvar, false);
+        for (var, pivot) in echelon.iter().enumerate() {
+            let value = match pivot {
+                Some((row, value)) => row
+                    .iter_set_bits(..var)
+                    .fold(*value && with_rhs, |acc, other| acc ^ solution[other]),
+                None => free == Some(var),
+            };
+            solution.set(var, value);
+        }
+        solution
+    };
+    let basis = (0..nvar)
+        .filter(|var| echelon[*var].is_none())
+        .map(|var| solve(Some(var), false))
+        .collect();
+    Some(AffineSpace::new(solve(None, true), basis))
+}
+
 #[cfg(test)]
 mod test;
@@ -0,0 +1,429 @@
+//! Word-packed matrices over GF(2), reduced with the Method of Four Russians (M4RI).
+//!
+//! The rows of a `PackedMatrix` are stored one after the other as 64-bit words, the column `j`
+//! being the bit `j % 64` of the word `j / 64` of the row, like in a `Vob`. The columns are
+//! eliminated from the highest to the lowest, `K` at a time : once the pivots of `K` columns are
+//! found, a table of the `2**K` sums of their rows is built and each other row is reduced with a
+//! single lookup and a single addition instead of up to `K`. The table of `2**K` rows stays in
+//! the cache for the width of matrices we handle.
+//!
+//! See "M. Albrecht, G. Bard, W. Hart, Algorithm 898: Efficient Multiplication of Dense Matrices
+//! over GF(2)" and "G. Bard, Accelerating Cryptanalysis with the Method of Four Russians".
+//!
+//! The results are the same as the ones of the functions of `algebra` working on `Matrix`,
+//! `linear_dependencies` giving exactly the `Matrix` of `extract_linear_dependencies`.
+
+use std::fmt;
+
+use vob::Vob;
+
+use crate::algebra::Matrix;
+
+/// The number of bits of a word.
+const WORD: usize = 64;
+/// The number of columns eliminated at once, each step building a table of `2**K` rows.
+const K: usize = 8;
+
+/// A matrix over GF(2) whose rows are packed in 64-bit words.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct PackedMatrix {
+    nrows: usize,
+    ncols: usize,
+    /// The number of words of a row.
+    stride: usize,
+    data: Vec<u64>,
+}
+
+impl PackedMatrix {
+    /// Create an all-zero matrix of size (rows,columns) specified.
+    pub fn new(rows: usize, columns: usize) -> PackedMatrix {
+        let stride = columns.div_ceil(WORD);
+        PackedMatrix {
+            nrows: rows,
+            ncols: columns,
+            stride,
+            data: vec![0; rows * stride],
+        }
+    }
+
+    /// Create an identity matrix (a matrix where only the [a,a] elements are set).
+    pub fn identity(size: usize) -> PackedMatrix {
+        let mut m = PackedMatrix::new(size, size);
+        for i in 0..size {
+            m.set(i, i, true);
+        }
+        m
+    }
+
+    /// Create a `PackedMatrix` from `Vob` rows.
+    ///
+    /// Will panic if any of the `Vob`s in `rows` are of different lengths.
+    pub fn from_rows(rows: &[Vob]) -> PackedMatrix {
+        let ncols = rows.first().map_or(0, |row| row.len());
+        let mut m = PackedMatrix::new(rows.len(), ncols);
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != ncols {
+                panic!("Trying to create a matrix with rows of different size")
+            }
+            for j in row.iter_set_bits(..) {
+                m.set(i, j, true);
+            }
+        }
+        m
+    }
+
+    /// Create a `PackedMatrix` from a `Matrix`.
+    pub fn from_matrix(matrix: &Matrix) -> PackedMatrix {
+        PackedMatrix::from_rows(&matrix.rows)
+    }
+
+    /// Return the rows of the matrix as `Vob`s.
+    pub fn to_rows(&self) -> Vec<Vob> {
+        (0..self.nrows)
+            .map(|i| {
+                let mut row = Vob::from_elem(self.ncols, false);
+                for j in self.iter_set_bits(i) {
+                    row.set(j, true);
+                }
+                row
+            })
+            .collect()
+    }
+
+    /// Return the matrix as a `Matrix`.
+    pub fn to_matrix(&self) -> Matrix {
+        Matrix::from_rows(self.to_rows())
+    }
+
+    /// Return the number of rows of the matrix.
+    #[inline]
+    pub fn row_size(&self) -> usize {
+        self.nrows
+    }
+
+    /// Return the number of columns of the matrix.
+    #[inline]
+    pub fn column_size(&self) -> usize {
+        self.ncols
+    }
+
+    /// Return the element [row,column].
+    #[inline]
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        debug_assert!(row < self.nrows && column < self.ncols);
+        self.data[row * self.stride + column / WORD] >> (column % WORD) & 1 == 1
+    }
+
+    /// Set the element [row,column] to `value`.
+    #[inline]
+    pub fn set(&mut self, row: usize, column: usize, value: bool) {
+        debug_assert!(row < self.nrows && column < self.ncols);
+        let word = &mut self.data[row * self.stride + column / WORD];
+        if value {
+            *word |= 1 << (column % WORD);
+        } else {
+            *word &= !(1 << (column % WORD));
+        }
+    }
+
+    /// Return the rank of the matrix.
+    pub fn rank(&self) -> usize {
+        self.clone().reduce(0).len()
+    }
+
+    /// Return the transpose of the matrix.
+    pub fn transpose(&self) -> PackedMatrix {
+        let mut trans = PackedMatrix::new(self.ncols, self.nrows);
+        for i in 0..self.nrows {
+            for j in self.iter_set_bits(i) {
+                trans.set(j, i, true);
+            }
+        }
+        trans
+    }
+
+    /// Perform an Self * Right = PackedMatrix op, with the Method of Four Russians : the rows
+    /// of `right` are taken `K` at a time and the sums of each subset of them are looked up.
+    ///
+    /// Will panic if the matrices are not of compatible sizes.
+    pub fn mul(&self, right: &PackedMatrix) -> PackedMatrix {
+        assert_eq!(self.ncols, right.nrows);
+        let mut out = PackedMatrix::new(self.nrows, right.ncols);
+        let mut table = vec![0_u64; (1 << K) * right.stride];
+        for start in (0..right.nrows).step_by(K) {
+            let rows: Vec<usize> = (start..right.nrows.min(start + K)).collect();
+            right.fill_table(&rows, &mut table, right.stride);
+            for i in 0..self.nrows {
+                let index = self.index(i, &rows);
+                if index != 0 {
+                    let words = &table[index * right.stride..(index + 1) * right.stride];
+                    for (word, add) in out.row_mut(i).iter_mut().zip(words) {
+                        *word ^= add;
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Perform an Self * Transpose(Right) = PackedMatrix op, each element being the parity of
+    /// the `and` of two packed rows.
+    ///
+    /// Will panic if the matrices don't have the same number of columns.
+    pub fn mul_transpose(&self, right: &PackedMatrix) -> PackedMatrix {
+        assert_eq!(self.ncols, right.ncols);
+        let mut out = PackedMatrix::new(self.nrows, right.nrows);
+        for i in 0..self.nrows {
+            for j in 0..right.nrows {
+                let ones: u32 = self
+                    .row(i)
+                    .iter()
+                    .zip(right.row(j))
+                    .map(|(a, b)| (a & b).count_ones())
+                    .sum();
+                if ones % 2 == 1 {
+                    out.set(i, j, true);
+                }
+            }
+        }
+        out
+    }
+
+    /// Return a basis of the kernel of the matrix, the vectors `x` such that `Self * x = 0`, as
+    /// the rows of a `PackedMatrix`.
+    ///
+    /// The columns without pivot once reduced are the free variables, each vector of the basis
+    /// sets one of them.
+    pub fn kernel(&self) -> PackedMatrix {
+        let mut reduced = self.clone();
+        let pivots = reduced.reduce(0);
+        let mut is_pivot = vec![false; self.ncols];
+        for column in pivots.iter() {
+            is_pivot[*column] = true;
+        }
+        let free: Vec<usize> = (0..self.ncols).filter(|j| !is_pivot[*j]).collect();
+        let mut kernel = PackedMatrix::new(free.len(), self.ncols);
+        for (k, f) in free.iter().enumerate() {
+            kernel.set(k, *f, true);
+            for (row, pivot) in pivots.iter().enumerate() {
+                if reduced.get(row, *f) {
+                    kernel.set(k, *pivot, true);
+                }
+            }
+        }
+        kernel
+    }
+
+    /// Return the inverse of the matrix, or `None` if it is not invertible.
+    ///
+    /// Will panic if the matrix is not square.
+    pub fn inverse(&self) -> Option<PackedMatrix> {
+        assert_eq!(
+            self.nrows, self.ncols,
+            "only a square matrix has an inverse"
+        );
+        let n = self.nrows;
+        let mut augmented = self.augmented();
+        let pivots = augmented.reduce(n);
+        if pivots.len() < n {
+            return None;
+        }
+        let mut inverse = PackedMatrix::new(n, n);
+        for (row, pivot) in pivots.iter().enumerate() {
+            for j in augmented.iter_set_bits(row).take_while(|j| *j < n) {
+                inverse.set(pivot - n, j, true);
+            }
+        }
+        Some(inverse)
+    }
+
+    /// Return the matrix of linear dependencies of the rows, the same as
+    /// `algebra::extract_linear_dependencies` : the rows are reduced with an identity on the
+    /// side, the identity rows of the rows reduced to zero are the dependencies, which are then
+    /// reduced (one row per highest set bit, sorted by increasing highest set bit).
+    pub fn linear_dependencies(&self) -> Matrix {
+        let n = self.nrows;
+        let mut augmented = self.augmented();
+        let rank = augmented.reduce(n).len();
+        let mut dependencies = PackedMatrix::new(n - rank, n);
+        for (k, row) in (rank..n).enumerate() {
+            for j in augmented.iter_set_bits(row).take_while(|j| *j < n) {
+                dependencies.set(k, j, true);
+            }
+        }
+        dependencies.row_echelon();
+        let mut rows = dependencies.to_rows();
+        rows.reverse();
+        Matrix::from_rows(rows)
+    }
+
+    /// Reduce the matrix to its reduced row echelon form and return its rank. The pivot of each
+    /// row is its highest set bit, the rows being sorted by decreasing pivot, followed by the
+    /// zero rows.
+    pub fn row_echelon(&mut self) -> usize {
+        self.reduce(0).len()
+    }
+
+    /// Return the matrix `[I | Self]`, the identity being on the lowest columns.
+    fn augmented(&self) -> PackedMatrix {
+        let n = self.nrows;
+        let mut augmented = PackedMatrix::new(n, n + self.ncols);
+        for i in 0..n {
+            augmented.set(i, i, true);
+            for j in self.iter_set_bits(i) {
+                augmented.set(i, n + j, true);
+            }
+        }
+        augmented
+    }
+
+    /// Reduce the matrix to its reduced row echelon form, the pivots being taken among the
+    /// columns `low..ncols` from the highest, and return the column of the pivot of each of the
+    /// first rows. The pivots are sorted by decreasing column, the other rows being zero on the
+    /// columns `low..ncols`.
+    ///
+    /// The columns are handled `K` at a time (see the module documentation).
+    fn reduce(&mut self, low: usize) -> Vec<usize> {
+        let mut pivots: Vec<usize> = Vec::new();
+        let mut table = vec![0_u64; (1 << K) * self.stride];
+        let mut end = self.ncols;
+        while end > low && pivots.len() < self.nrows {
+            let start = end.saturating_sub(K).max(low);
+            // the rows below the pivots are zero on the columns above `end`
+            let words = end.div_ceil(WORD);
+            let first = pivots.len();
+            let mut block: Vec<usize> = Vec::new();
+            for column in (start..end).rev() {
+                let next = first + block.len();
+                if next == self.nrows {
+                    break;
+                }
+                let mut found = None;
+                for i in next..self.nrows {
+                    for (k, pivot) in block.iter().enumerate() {
+                        if self.get(i, *pivot) {
+                            self.xor_row(i, first + k, words);
+                        }
+                    }
+                    if self.get(i, column) {
+                        found = Some(i);
+                        break;
+                    }
+                }
+                if let Some(i) = found {
+                    self.swap_rows(i, next);
+                    for k in 0..block.len() {
+                        if self.get(first + k, column) {
+                            self.xor_row(first + k, next, words);
+                        }
+                    }
+                    block.push(column);
+                }
+            }
+            if !block.is_empty() {
+                let rows: Vec<usize> = (first..first + block.len()).collect();
+                self.fill_table(&rows, &mut table, words);
+                for i in (0..self.nrows).filter(|i| *i < first || *i >= first + block.len()) {
+                    let index = self.index(i, &block);
+                    if index != 0 {
+                        let offset = i * self.stride;
+                        let add = &table[index * self.stride..index * self.stride + words];
+                        for (word, add) in self.data[offset..offset + words].iter_mut().zip(add) {
+                            *word ^= add;
+                        }
+                    }
+                }
+                pivots.extend(block);
+            }
+            end = start;
+        }
+        pivots
+    }
+
+    /// Fill `table` with the sums of each subset of `rows`, the bit `k` of the index of a sum
+    /// telling if `rows[k]` is in it. Only the first `words` words of the rows are summed.
+    fn fill_table(&self, rows: &[usize], table: &mut [u64], words: usize) {
+        let stride = self.stride;
+        for w in table[..stride].iter_mut() {
+            *w = 0;
+        }
+        for index in 1_usize..1 << rows.len() {
+            // the sum without the lowest row, plus the lowest row
+            let lowest = index.trailing_zeros() as usize;
+            let previous = (index & (index - 1)) * stride;
+            let row = rows[lowest] * stride;
+            for w in 0..words {
+                table[index * stride + w] = table[previous + w] ^ self.data[row + w];
+            }
+        }
+    }
+
+    /// Return the index in a table (see `fill_table`) of the row `i`, its bit `k` being the
+    /// element [i,columns[k]].
+    fn index(&self, i: usize, columns: &[usize]) -> usize {
+        columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| self.get(i, **column))
+            .fold(0, |index, (k, _)| index | 1 << k)
+    }
+
+    /// Return the words of the row `i`.
+    #[inline]
+    fn row(&self, i: usize) -> &[u64] {
+        &self.data[i * self.stride..(i + 1) * self.stride]
+    }
+
+    /// Return the words of the row `i`, mutably.
+    #[inline]
+    fn row_mut(&mut self, i: usize) -> &mut [u64] {
+        &mut self.data[i * self.stride..(i + 1) * self.stride]
+    }
+
+    /// Add the first `words` words of the row `src` to the row `dst`.
+    #[inline]
+    fn xor_row(&mut self, dst: usize, src: usize, words: usize) {
+        let (dst, src) = (dst * self.stride, src * self.stride);
+        for w in 0..words {
+            self.data[dst + w] ^= self.data[src + w];
+        }
+    }
+
+    /// Swap the rows `a` and `b`.
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        if a != b {
+            for w in 0..self.stride {
+                self.data.swap(a * self.stride + w, b * self.stride + w);
+            }
+        }
+    }
+
+    /// Return an iterator over the set bits of the row `i`, in increasing order.
+    fn iter_set_bits(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.row(i).iter().enumerate().flat_map(|(w, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(w * WORD + bit)
+            })
+        })
+    }
+}
+
+impl fmt::Debug for PackedMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "PackedMatrix :")?;
+        for i in 0..self.nrows {
+            write!(f, "[")?;
+            for j in 0..self.ncols {
+                write!(f, "{}", if self.get(i, j) { 1 } else { 0 })?;
+            }
+            writeln!(f, "]")?;
+        }
+        Ok(())
+    }
+}
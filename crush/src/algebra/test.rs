@@ -1,4 +1,19 @@
-use crate::algebra;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vob::Vob;
+
+use crate::algebra::{self, Matrix, PackedMatrix};
+
+/// Return a random matrix, each element being set with probability `density`.
+fn random_matrix(rows: usize, columns: usize, density: f64, rng: &mut StdRng) -> Matrix {
+    let rows: Vec<Vob> = (0..rows)
+        .map(|_| (0..columns).map(|_| rng.gen_bool(density)).collect())
+        .collect();
+    if rows.is_empty() {
+        Matrix::new(0, 0)
+    } else {
+        matrix![rows]
+    }
+}
 
 #[test]
 fn solving_linear_system_test() {
@@ -43,3 +58,165 @@ fn identity_test() {
     ]];
     assert_eq!(id, expected);
 }
+
+#[test]
+fn random_solution_test() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let m = matrix![vec![
+        vob![true, true, false, false],
+        vob![false, true, true, false],
+    ]];
+    let v = vob![true, false];
+    for _ in 0..20 {
+        let sol = algebra::random_solution(m.clone(), v.clone(), &mut rng).unwrap();
+        assert!(sol[0] ^ sol[1]);
+        assert_eq!(sol[1], sol[2]);
+    }
+
+    let m = matrix![vec![
+        vob![true, true, false],
+        vob![false, true, true],
+        vob![true, false, true],
+    ]];
+    let v = vob![true, false, false];
+    assert_eq!(algebra::random_solution(m, v, &mut rng), None);
+}
+
+#[test]
+fn affine_space_test() {
+    // x0 + x1 = 1, x1 + x2 = 0 and x3 is free
+    let m = matrix![vec![
+        vob![true, true, false, false],
+        vob![false, true, true, false],
+    ]];
+    let v = vob![true, false];
+    let space = algebra::solve_affine_system(m, v, 4).unwrap();
+    assert_eq!((space.rank(), space.nullity()), (2, 2));
+    assert_eq!(space.free_vars(), vec![0, 3]);
+    assert_eq!(space.fixed(), vec![None; 4]);
+    let mut solutions: Vec<_> = space.iter().collect();
+    assert_eq!(solutions.len(), 4);
+    for sol in solutions.iter() {
+        assert!(space.contains(sol));
+        assert!(sol[0] ^ sol[1]);
+        assert_eq!(sol[1], sol[2]);
+    }
+    solutions.sort_by_key(|sol| sol.iter().collect::<Vec<_>>());
+    solutions.dedup();
+    assert_eq!(solutions.len(), 4);
+    assert!(!space.contains(&vob![true, true, true, false]));
+
+    // the projection on x0, x1, x2 and the spaces given by other vectors are the same
+    let truncated = space.truncate(3);
+    assert_eq!(truncated.nullity(), 1);
+    assert!(truncated.contains(&vob![false, true, true]));
+    let same = algebra::AffineSpace::new(
+        vob![false, true, true, true],
+        vec![vob![true, true, true, true], vob![true, true, true, false], vob![4; false]],
+    );
+    assert_eq!(same, space);
+
+    // x1 is now fixed
+    let m = matrix![vec![
+        vob![true, true, false, false],
+        vob![false, true, true, false],
+        vob![true, false, true, false],
+        vob![false, true, false, false],
+    ]];
+    let space = algebra::solve_affine_system(m, vob![true, false, true, true], 4).unwrap();
+    assert_eq!(space.fixed(), vec![Some(false), Some(true), Some(true), None]);
+    let m = matrix![vec![
+        vob![true, true, false],
+        vob![false, true, true],
+        vob![true, false, true],
+    ]];
+    assert_eq!(algebra::solve_affine_system(m, vob![true, false, false], 3), None);
+    assert_eq!(
+        algebra::solve_affine_system(matrix![Vec::new()], vob::Vob::new(), 3).map(|space| space.nullity()),
+        Some(3)
+    );
+}
+
+#[test]
+fn linear_dependencies_test() {
+    let m = matrix![vec![
+        vob![true, false, true],
+        vob![false, true, true],
+        vob![true, true, false]
+    ]];
+    assert_eq!(algebra::extract_linear_dependencies(m), matrix![vec![vob![true, true, true]]]);
+    let m = matrix![vec![vob![true, false, true], vob![false, true, true]]];
+    assert_eq!(algebra::extract_linear_dependencies(m).row_size(), 0);
+    // an all-zero matrix has all its rows as dependencies
+    assert_eq!(algebra::extract_linear_dependencies(Matrix::new(3, 5)), algebra::identity(3));
+    assert_eq!(algebra::extract_linear_dependencies(Matrix::new(1, 2)), algebra::identity(1));
+}
+
+#[test]
+fn packed_matrix_test() {
+    let mut rng = StdRng::seed_from_u64(0);
+    // sparse and tall matrices have dependencies, the sizes cross the words and the blocks
+    for (rows, columns, density) in [
+        (4, 4, 0.5),
+        (20, 9, 0.5),
+        (70, 65, 0.05),
+        (130, 100, 0.5),
+        (100, 130, 0.1),
+        (200, 64, 0.5),
+    ]
+    .iter()
+    {
+        let m = random_matrix(*rows, *columns, *density, &mut rng);
+        let packed = PackedMatrix::from_matrix(&m);
+        assert_eq!(packed.to_matrix(), m);
+        let dependencies = packed.linear_dependencies();
+        assert_eq!(dependencies, algebra::extract_linear_dependencies(m.clone()));
+        assert_eq!(packed.rank(), rows - dependencies.row_size());
+        if dependencies.row_size() > 0 {
+            let zero = PackedMatrix::new(dependencies.row_size(), *columns);
+            assert_eq!(PackedMatrix::from_matrix(&dependencies).mul(&packed), zero);
+        }
+
+        // the kernel is annihilated by the matrix and has the dimension of the free columns
+        let kernel = packed.kernel();
+        assert_eq!(kernel.row_size(), columns - packed.rank());
+        assert_eq!(kernel.rank(), kernel.row_size());
+        assert_eq!(packed.mul_transpose(&kernel), PackedMatrix::new(*rows, kernel.row_size()));
+
+        let right = random_matrix(*columns, 70, 0.5, &mut rng);
+        let product = packed.mul(&PackedMatrix::from_matrix(&right));
+        assert_eq!(product.to_matrix(), m.left_mul(&right));
+        let transposed = PackedMatrix::from_matrix(&right).transpose();
+        assert_eq!(packed.mul_transpose(&transposed), product);
+    }
+
+    // an all-zero matrix has all its rows as dependencies
+    let m = Matrix::new(3, 5);
+    assert_eq!(PackedMatrix::from_matrix(&m).linear_dependencies(), algebra::identity(3));
+}
+
+#[test]
+fn packed_inverse_test() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut invertible = 0;
+    for size in [1, 8, 63, 64, 65, 150].iter() {
+        for _ in 0..4 {
+            let m = PackedMatrix::from_matrix(&random_matrix(*size, *size, 0.5, &mut rng));
+            match m.inverse() {
+                Some(inverse) => {
+                    invertible += 1;
+                    assert_eq!(inverse.mul(&m), PackedMatrix::identity(*size));
+                    assert_eq!(m.mul(&inverse), PackedMatrix::identity(*size));
+                }
+                None => assert!(m.rank() < *size),
+            }
+        }
+    }
+    assert!(invertible > 0);
+    let m = matrix![vec![
+        vob![true, true, false],
+        vob![false, true, true],
+        vob![true, false, true],
+    ]];
+    assert_eq!(PackedMatrix::from_matrix(&m).inverse(), None);
+}
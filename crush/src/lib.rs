@@ -10,6 +10,5 @@ pub mod soc;
 pub mod solver;
 
 use core::hash::BuildHasherDefault;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 type AHashMap<K, V> = HashMap<K, V, BuildHasherDefault<ahash::AHasher>>;
-type AHashSet<K> = HashSet<K, BuildHasherDefault<ahash::AHasher>>;
@@ -0,0 +1,457 @@
+//! Module providing an import of systems of polynomials over GF(2) in algebraic normal form
+//! (ANF), as produced by SageMath or PolyBoRi.
+//!
+//! The format has one polynomial per line, each polynomial being equal to 0. A polynomial is a
+//! sum of monomials separated by `+`, a monomial a product of variables and constants separated
+//! by `*` :
+//!
+//! ```text
+//! # comments and empty lines are ignored
+//! x1*x5 + x7 + 1
+//! x0*x1*x2 + x(3) + x_4
+//! ```
+//!
+//! The variable `j` can be written `xj`, `x_j`, `x(j)` or `x[j]`, and is the variable `j` of the
+//! `System`. As over GF(2) `x*x = x` and `x + x = 0`, the polynomials are reduced when parsed.
+//!
+//! To build the `System`, the polynomials of degree 2 or more are grouped greedily by shared
+//! variables, as long as the variables appearing in their monomials of degree 2 or more, the
+//! nonlinear variables, are at most `max_vars` in a group. Each group becomes a `Bdd` whose top
+//! levels are its nonlinear variables and whose lower levels are the linear parts of its
+//! polynomials : the paths enumerate the assignments of the nonlinear variables which are zeros
+//! of the polynomials without a linear part, the edges taken in the lower levels being the values
+//! the linear parts must take for the polynomials to be 0. Each linear polynomial becomes a `Bdd`
+//! with a single level.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::soc::{bdd::Bdd, Id, level::Level, system::System, utils::{self, SyntaxError}};
+
+pub use crate::soc::utils::MAX_VARS;
+
+/// A polynomial over GF(2) in algebraic normal form.
+///
+/// The monomials are sorted, each monomial being the sorted list of its variables, the empty
+/// monomial being the constant 1.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Polynomial {
+    monomials: Vec<Vec<usize>>,
+}
+
+impl Polynomial {
+    /// Construct the sum of `monomials`, reducing it.
+    pub fn new(monomials: Vec<Vec<usize>>) -> Polynomial {
+        let mut count: HashMap<Vec<usize>, bool> = HashMap::new();
+        for mut monomial in monomials {
+            monomial.sort_unstable();
+            monomial.dedup();
+            let odd = count.entry(monomial).or_insert(false);
+            *odd = !*odd;
+        }
+        let mut monomials: Vec<Vec<usize>> = count
+            .into_iter()
+            .filter(|(_, odd)| *odd)
+            .map(|(monomial, _)| monomial)
+            .collect();
+        monomials.sort();
+        Polynomial { monomials }
+    }
+
+    /// Return the monomials of the polynomial.
+    pub fn get_monomials(&self) -> &[Vec<usize>] {
+        &self.monomials
+    }
+
+    /// Return the degree of the polynomial, 0 for the constants.
+    pub fn degree(&self) -> usize {
+        self.monomials.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Return the sorted variables appearing in a monomial of degree 2 or more.
+    pub fn nonlinear_vars(&self) -> Vec<usize> {
+        let mut vars: Vec<usize> = self
+            .monomials
+            .iter()
+            .filter(|monomial| monomial.len() > 1)
+            .flatten()
+            .cloned()
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars
+    }
+
+    /// Return the variables of the monomials of degree 1.
+    fn linear_vars(&self) -> Vec<usize> {
+        self.monomials
+            .iter()
+            .filter(|monomial| monomial.len() == 1)
+            .map(|monomial| monomial[0])
+            .collect()
+    }
+
+    /// Return the value of the monomials of degree 0 and of degree 2 or more, each variable being
+    /// set by the bit of `assignment` given by `positions`.
+    fn eval_nonlinear(&self, positions: &HashMap<usize, usize>, assignment: usize) -> bool {
+        self.monomials
+            .iter()
+            .filter(|monomial| monomial.len() != 1)
+            .fold(false, |acc, monomial| {
+                acc ^ monomial
+                    .iter()
+                    .all(|var| assignment >> positions[var] & 1 == 1)
+            })
+    }
+}
+
+impl FromStr for Polynomial {
+    type Err = AnfError;
+
+    /// Parse a single polynomial, the errors are reported on line 1.
+    fn from_str(s: &str) -> Result<Polynomial, AnfError> {
+        parse_polynomial(s, 1)
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.monomials.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, monomial) in self.monomials.iter().enumerate() {
+            if i > 0 {
+                write!(f, " + ")?;
+            }
+            if monomial.is_empty() {
+                write!(f, "1")?;
+            }
+            for (j, var) in monomial.iter().enumerate() {
+                if j > 0 {
+                    write!(f, "*")?;
+                }
+                write!(f, "x{}", var)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error found while parsing polynomials or building a `System` from them.
+///
+/// Lines and columns start at 1, the polynomials are numbered from 0 in the order they are given.
+#[derive(Debug)]
+pub enum AnfError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// A line is not a polynomial.
+    Syntax(SyntaxError),
+    /// A polynomial has more nonlinear variables than allowed in a group.
+    TooManyVars {
+        polynomial: usize,
+        vars: usize,
+        max_vars: usize,
+    },
+    /// The polynomials have no common zero, so the `System` has no solution.
+    NoSolution { polynomials: Vec<usize> },
+    /// A polynomial uses a variable `var` which is not below `utils::MAX_NVAR`.
+    VarOutOfRange { polynomial: usize, var: usize },
+}
+
+impl fmt::Display for AnfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnfError::Io(e) => write!(f, "{}", e),
+            AnfError::Syntax(e) => write!(f, "{}", e),
+            AnfError::TooManyVars {
+                polynomial,
+                vars,
+                max_vars,
+            } => write!(
+                f,
+                "polynomial {} has {} nonlinear variables, at most {} are allowed",
+                polynomial, vars, max_vars
+            ),
+            AnfError::NoSolution { polynomials } => write!(
+                f,
+                "the polynomials {:?} have no common zero, the system has no solution",
+                polynomials
+            ),
+            AnfError::VarOutOfRange { polynomial, var } => write!(
+                f,
+                "polynomial {} uses the variable {}, at most {} variables are supported",
+                polynomial,
+                var,
+                utils::MAX_NVAR
+            ),
+        }
+    }
+}
+
+impl error::Error for AnfError {}
+
+impl From<io::Error> for AnfError {
+    fn from(e: io::Error) -> Self {
+        AnfError::Io(e)
+    }
+}
+
+impl From<AnfError> for io::Error {
+    fn from(e: AnfError) -> Self {
+        match e {
+            AnfError::Io(e) => e,
+            e => io::Error::new(ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+/// Parse the polynomials of `content`, one per line (see the module documentation).
+pub fn parse_anf_from_str(content: &str) -> Result<Vec<Polynomial>, AnfError> {
+    let mut polynomials = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        polynomials.push(parse_polynomial(line, i + 1)?);
+    }
+    Ok(polynomials)
+}
+
+/// Parse the polynomials of the file at `path`, one per line (see the module documentation).
+pub fn parse_anf_from_file(path: &PathBuf) -> Result<Vec<Polynomial>, AnfError> {
+    parse_anf_from_str(&fs::read_to_string(path)?)
+}
+
+/// Build a `System` whose solutions are the common zeros of `polynomials`, grouping them so
+/// that each `Bdd` has at most `max_vars` nonlinear variables (see the module documentation).
+///
+/// The `nvar` of the `System` is one more than the highest variable. The polynomials equal to
+/// 0 are ignored.
+///
+/// Returns an `Error` if a variable is not below `utils::MAX_NVAR`, if a polynomial has more
+/// than `max_vars` nonlinear variables, if the polynomials of a group, a constant 1 for
+/// example, have no common zero, or if the built `System` breaks one of its invariants.
+pub fn build_system_from_anf(
+    polynomials: &[Polynomial],
+    max_vars: usize,
+) -> Result<System, AnfError> {
+    let highest = polynomials
+        .iter()
+        .enumerate()
+        .flat_map(|(i, polynomial)| polynomial.monomials.iter().flatten().map(move |var| (*var, i)))
+        .max();
+    // every lhs holds nvar bits, the variables may come from a file
+    if let Some((var, polynomial)) = highest.filter(|(var, _)| *var >= utils::MAX_NVAR) {
+        return Err(AnfError::VarOutOfRange { polynomial, var });
+    }
+    let nvar = highest.map_or(0, |(var, _)| var + 1);
+    let mut system = System::new();
+    system.set_nvar(nvar);
+    for (i, (vars, group)) in group_polynomials(polynomials, max_vars)?.iter().enumerate() {
+        let mut bdd = build_bdd(polynomials, group, vars, nvar)?;
+        bdd.set_id(Id::new(i));
+        system.push_bdd(bdd)?;
+    }
+    system.check_invariants()?;
+    Ok(system)
+}
+
+/// Group the polynomials (see `utils::group_by_shared_vars`), returning for each group its
+/// nonlinear variables and the index of its polynomials. The linear polynomials are left alone.
+fn group_polynomials(
+    polynomials: &[Polynomial],
+    max_vars: usize,
+) -> Result<Vec<utils::VarGroup>, AnfError> {
+    let vars: Vec<Vec<usize>> = polynomials.iter().map(Polynomial::nonlinear_vars).collect();
+    let items: Vec<usize> = (0..polynomials.len())
+        .filter(|i| !polynomials[*i].monomials.is_empty())
+        .collect();
+    if let Some(i) = items.iter().find(|i| vars[**i].len() > max_vars) {
+        return Err(AnfError::TooManyVars {
+            polynomial: *i,
+            vars: vars[*i].len(),
+            max_vars,
+        });
+    }
+    Ok(utils::group_by_shared_vars(&vars, items, max_vars))
+}
+
+/// Build the `Bdd` of a group of polynomials whose nonlinear variables are `vars`.
+fn build_bdd(
+    polynomials: &[Polynomial],
+    group: &[usize],
+    vars: &[usize],
+    nvar: usize,
+) -> Result<Bdd, AnfError> {
+    let positions: HashMap<usize, usize> =
+        vars.iter().enumerate().map(|(i, var)| (*var, i)).collect();
+    // the polynomials without a linear part restrict the assignments, the others give a level
+    let (outputs, filters): (Vec<usize>, Vec<usize>) = group
+        .iter()
+        .partition(|i| !polynomials[**i].linear_vars().is_empty());
+
+    let mut levels: Vec<Level> = Vec::with_capacity(vars.len() + outputs.len() + 1);
+    for var in vars.iter() {
+        let mut level = Level::new();
+        level.set_lhs(vec![*var], nvar);
+        levels.push(level);
+    }
+    for i in outputs.iter() {
+        let mut level = Level::new();
+        level.set_lhs(polynomials[*i].linear_vars(), nvar);
+        levels.push(level);
+    }
+    let mut sink_level = Level::new();
+    sink_level.set_lhs(Vec::new(), nvar);
+    let sink = sink_level.add_new_node();
+    levels.push(sink_level);
+
+    // the nodes of each level by their edges, so that equal nodes are shared
+    let mut nodes = vec![HashMap::new(); levels.len()];
+    let mut node = |levels: &mut Vec<Level>, index: usize, e0: Option<Id>, e1: Option<Id>| {
+        *nodes[index]
+            .entry((e0, e1))
+            .or_insert_with(|| levels[index].add_edged_node(e0, e1))
+    };
+
+    // the node reached by each assignment of the nonlinear variables, below their levels
+    let mut reached: Vec<Option<Id>> = Vec::with_capacity(1 << vars.len());
+    for assignment in 0..1_usize << vars.len() {
+        if filters
+            .iter()
+            .any(|i| polynomials[*i].eval_nonlinear(&positions, assignment))
+        {
+            reached.push(None);
+            continue;
+        }
+        let mut current = sink;
+        for (t, i) in outputs.iter().enumerate().rev() {
+            // the linear part must be equal to the rest of the polynomial
+            current = match polynomials[*i].eval_nonlinear(&positions, assignment) {
+                false => node(&mut levels, vars.len() + t, Some(current), None),
+                true => node(&mut levels, vars.len() + t, None, Some(current)),
+            };
+        }
+        reached.push(Some(current));
+    }
+    // the bit i of an assignment is the value of the variable of the level i
+    for index in (0..vars.len()).rev() {
+        reached = (0..1_usize << index)
+            .map(|prefix| match (reached[prefix], reached[prefix | 1 << index]) {
+                (None, None) => None,
+                (e0, e1) => Some(node(&mut levels, index, e0, e1)),
+            })
+            .collect();
+    }
+    if reached[0].is_none() {
+        return Err(AnfError::NoSolution {
+            polynomials: group.to_vec(),
+        });
+    }
+
+    let mut bdd = Bdd::new();
+    for level in levels {
+        bdd.add_existing_level(level);
+    }
+    Ok(bdd)
+}
+
+/// Parse a polynomial on the line `line`.
+fn parse_polynomial(s: &str, line: usize) -> Result<Polynomial, AnfError> {
+    let mut chars = s.char_indices().peekable();
+    let mut monomials = Vec::new();
+    let mut monomial = Some(Vec::new());
+    let mut expect_factor = true;
+    let error = |column: usize, token: &str, expected: &'static str| {
+        AnfError::Syntax(SyntaxError {
+            line,
+            column: column + 1,
+            token: token.to_string(),
+            expected,
+        })
+    };
+    while let Some((column, c)) = chars.next() {
+        match c {
+            ' ' | '\t' | '\r' => (),
+            '+' | '*' if expect_factor => {
+                return Err(error(column, &c.to_string(), "a variable or a constant"))
+            }
+            '+' => {
+                monomials.extend(monomial.replace(Vec::new()));
+                expect_factor = true;
+            }
+            '*' => expect_factor = true,
+            '0' | '1' if expect_factor => {
+                if c == '0' {
+                    monomial = None;
+                }
+                expect_factor = false;
+            }
+            'x' if expect_factor => {
+                let var = parse_var(&mut chars).map_err(|(column, token)| {
+                    error(column.unwrap_or(s.len()), &token, "the index of a variable")
+                })?;
+                if let Some(monomial) = monomial.as_mut() {
+                    monomial.push(var);
+                }
+                expect_factor = false;
+            }
+            _ if expect_factor => {
+                return Err(error(column, &c.to_string(), "a variable or a constant"))
+            }
+            _ => return Err(error(column, &c.to_string(), "`+` or `*`")),
+        }
+    }
+    if expect_factor {
+        return Err(error(s.len(), "", "a variable or a constant"));
+    }
+    monomials.extend(monomial);
+    Ok(Polynomial::new(monomials))
+}
+
+/// Parse the index of a variable after its `x`, returning the column and the token of the
+/// error if there is one (no column at the end of the line).
+fn parse_var<I: Iterator<Item = (usize, char)>>(
+    chars: &mut std::iter::Peekable<I>,
+) -> Result<usize, (Option<usize>, String)> {
+    let close = match chars.peek() {
+        Some((_, '(')) => Some(')'),
+        Some((_, '[')) => Some(']'),
+        _ => None,
+    };
+    if let Some((_, '(' | '[' | '_')) = chars.peek() {
+        chars.next();
+    }
+    let mut digits = String::new();
+    let mut start = None;
+    while let Some((column, c)) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        start.get_or_insert(*column);
+        digits.push(*c);
+        chars.next();
+    }
+    if digits.is_empty() {
+        return Err(match chars.next() {
+            Some((column, c)) => (Some(column), c.to_string()),
+            None => (None, String::new()),
+        });
+    }
+    if let Some(close) = close {
+        match chars.next() {
+            Some((_, c)) if c == close => (),
+            Some((column, c)) => return Err((Some(column), c.to_string())),
+            None => return Err((None, String::new())),
+        }
+    }
+    digits
+        .parse()
+        .map_err(|_| (start, digits.clone()))
+}
@@ -1,20 +1,15 @@
 //! This is implementation of a `BDD` (Binary Decision Diagram).
 //! A `BDD` is defined by an `id` and an array of `levels`.
-//! For performance purpose we also store the `next_id` variable
-//! which is the id to use when inserting a new node inside the BDD.
-//! This ensure that we never have 2 nodes with the same id inside.
 //!
-//! /!\ Because we are joining BDDs and this involve merging the sets
-//! of ids of the nodes we have to make sure that there is no similar
-//! id between 2 BDD. The way we do it is by reducing the sets of possible
-//! id and making the nodes id dependant on the Id of the BDD in which
-//! they are created.
-//! All nodes Id are equal to `next_id * 10000 + bdd_id`.
-//! This assumes that:
-//! - You have less than 10 000 BDDs in your system
-//! - Your bdd_id is between 0 and 10 000
-//! - You will create less than ~2**53 nodes in your BDD (would overflow a 64 bits usize otherwise)
-//! - Generally you are running on a 64 bit system
+//! The `Id` of a node is its index in the vector of nodes of its level (see the `Level`
+//! documentation), and an edge is the index of the child in the level below. Ids are
+//! therefore local to a level, and joining BDDs doesn't need to rename any node.
+//! The operations removing nodes (reducing, absorbing...) leave empty slots in the levels,
+//! so that the ids of the remaining nodes stay valid while reducing, and `compact` the BDD
+//! once they are done. The pruning of the differential module removes nodes in many steps
+//! and only compacts the levels whose fraction of empty slots passes `SPARSE_THRESHOLD`
+//! (`compact_sparse`). The ids of the nodes in the .bdd files are only used when loading
+//! a BDD and are not kept.
 //!
 //! Out of the array of `levels` 2 are specific : the first and the last.
 //! While they are stored as any level, the first level will only be one node
@@ -25,20 +20,25 @@
 //! - removing the dead end nodes (skip the last level)
 //! - removing the orphan nodes (skip the first level)
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt;
 use std::hash::BuildHasherDefault;
 
-use num_bigint::ToBigUint;
+use num_bigint::{BigUint, RandBigInt, ToBigUint};
+use rand::Rng;
 use vob::Vob;
 
-use crate::{AHashMap, AHashSet};
+use crate::AHashMap;
 use crate::soc::{Id, level::Level};
 use crate::soc::node::Node;
 
 #[allow(unused_variables)] // FIXME remove unused variables when ready
 #[cfg(feature = "differential")]
 pub mod differential;
+pub mod sifting;
+
+/// Fraction of empty slots above which `Bdd::compact_sparse` compacts a level.
+pub const SPARSE_THRESHOLD: f64 = 0.5;
 
 /// A `LinEq` is a linear equation found in the BDD.
 /// A level which has only outgoing 1-edges or 0-edges
@@ -96,7 +96,6 @@ impl LinEq {
 pub struct Bdd {
     levels: Vec<Level>,
     id: Id,
-    next_id: usize,
 }
 
 impl Bdd {
@@ -117,12 +116,6 @@ impl Bdd {
         self.id
     }
 
-    /// Set next id for the next node to be inserted
-    #[inline]
-    pub fn set_next_id(&mut self, next_id: usize) {
-        self.next_id = next_id;
-    }
-
     /// Return the index of the last level
     #[inline]
     pub fn get_sink_level_index(&self) -> usize {
@@ -208,40 +201,6 @@ impl Bdd {
         self.levels[level_index].get_lhs()
     }
 
-    /// Repeatedly calls the `add_node` function on the level specified by the `level_index`
-    /// for each id in `nodes_id`
-    /// /!\ no update is made to self.next_id, you are expected to set it yourself
-    pub fn add_nodes_to_level(&mut self, level_index: usize, nodes_id: Vec<Id>) {
-        let mut nodes = Vec::new();
-        for node_id in nodes_id.iter() {
-            let new_id = Id::new(**node_id * 10000 + *self.id);
-            self.levels[level_index].add_new_node(new_id);
-            nodes.push(new_id);//Why this?  Is the vector 'nodes' used for anything?
-        }
-    }
-
-    /// Iterate through the levels of the BDD to find a node of id `parent` and set its
-    /// edge 0 or 1 (depending on the value of `edge`) to the provided `child_id`
-    ///
-    /// This is obviously very slow on large BDD but this method is only use when constructing
-    /// the BDDs initially making it virtually no cost as BDDs are usually extremely small
-    /// at this stage
-    pub fn connect_nodes_from_spec(&mut self, parent: Id, child_id: Id, edge: i8) {
-        assert!(edge == 0 || edge == 1);
-        let child_id = Id::new(*child_id * 10000 + *self.id);
-        let parent_id = Id::new(*parent * 10000 + *self.id);
-        self.levels.iter_mut().for_each(|level| {
-            if let Some(n) = level.get_mut_nodes().get_mut(&parent_id) {
-                match edge {
-                    0 => n.connect_e0(child_id),
-                    1 => n.connect_e1(child_id),
-                    _ => panic!("impossible due to the assert"),
-                }
-                return;
-            }
-        });
-    }
-
     /// Remove every node which represent a dead-end starting from the level start going upwards
     /// and "clean" the BDD by removing any reference to a removed node.
     ///
@@ -255,12 +214,11 @@ impl Bdd {
     /// be removed.
     ///
     /// Short circuited -> will exit when no dead end was found in the previous level
-    pub fn remove_all_dead_ends_start(&mut self, start: usize) {
+    ///
+    /// Return the index of the highest level whose nodes may have been modified.
+    pub fn remove_all_dead_ends_start(&mut self, start: usize) -> usize {
         for i in (0..=start).rev() {
-            let mut to_remove: AHashSet<Id> = AHashSet::with_capacity_and_hasher(
-                self.levels[i].get_nodes_len(),
-                Default::default(),
-            );
+            let mut to_remove: Vec<Id> = Vec::new();
             let (above, below) = self.levels.split_at_mut(i + 1);
             above
                 .last_mut()
@@ -269,53 +227,46 @@ impl Bdd {
                 .for_each(|(id, node)| {
                     let mut edges = (false, false);
                     if let Some(e0) = node.get_e0() {
-                        match below[0].get_nodes().get(&e0) {
+                        match below[0].get_node(&e0) {
                             Some(_) => edges.0 = true,
                             None => node.disconnect_e0(),
                         }
                     }
                     if let Some(e1) = node.get_e1() {
-                        match below[0].get_nodes().get(&e1) {
+                        match below[0].get_node(&e1) {
                             Some(_) => edges.1 = true,
                             None => node.disconnect_e1(),
                         }
                     }
                     if !edges.0 && !edges.1 {
-                        to_remove.insert(*id);
+                        to_remove.push(id);
                     }
                 });
             if to_remove.is_empty() {
-                return;
+                return i;
+            }
+            for id in to_remove {
+                self.levels[i].remove_node(id);
             }
-            self.levels[i].remove_nodes_from_set(&to_remove);
-            to_remove.clear()
         }
+        0
     }
 
     /// Remove every orphan node starting from the level `start` going downwards
     ///
     /// An orphan is defined as a node which doesn't have any node pointing to him in the levels above
     ///
-    /// To keep track of the expected child we start from the `level` `start-1` and keep all the edges inside a hashset.
-    /// We then iterate through every level, removing any `node` which is not in the set and adding the remaining outgoing edges to the set.
+    /// To keep track of the expected child we start from the `level` `start-1` and mark all the edges inside a vector.
+    /// We then iterate through every level, removing any `node` which is not marked and marking the remaining outgoing edges.
     /// `start` should be the level where you want the removing to begin and therefore never equal to `0`
     /// Short circuited -> will exit when no orphans was found in the previous level
     pub fn remove_orphans_start(&mut self, start: usize) {
         assert!(start != 0);
-        let mut parents: AHashSet<Id> = AHashSet::with_capacity_and_hasher(
-            self.levels[start - 1].get_nodes_len(),
-            Default::default(),
-        );
-        self.levels[start - 1].iter_nodes().for_each(|(_, node)| {
-            if let Some(e0) = node.get_e0() {
-                parents.insert(e0);
-            }
-            if let Some(e1) = node.get_e1() {
-                parents.insert(e1);
-            }
-        });
+        // all the nodes of the level `start - 1` are kept, this only marks their children
+        let mut reached = vec![true; self.levels[start - 1].get_slots_len()];
+        self.levels[start - 1].remove_orphans(&mut reached);
         for i in start..self.levels.len() - 1 {
-            let removed = self.levels[i].remove_orphans(&mut parents);
+            let removed = self.levels[i].remove_orphans(&mut reached);
             if !removed {
                 return;
             }
@@ -356,18 +307,15 @@ impl Bdd {
         let max_level_size = self.levels[level_index_below].get_nodes_len() * 2;
         let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> =
             AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
-        let mut nodes: AHashMap<Id, Node> =
-            AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
+        let mut nodes: Vec<Node> = Vec::with_capacity(max_level_size);
         let (above, below) = self.levels.split_at_mut(level_index_above + 1);
-        let mut next_id = self.next_id;
-        let bdd_id = *self.id;
         above
             .last_mut()
             .unwrap()
             .iter_mut_nodes()
             .for_each(|(_, node)| {
                 let e0_edges = match node.get_e0() {
-                    Some(e0) => match below[0].get_nodes().get(&e0) {
+                    Some(e0) => match below[0].get_node(&e0) {
                         Some(e0_below) => (e0_below.get_e0(), e0_below.get_e1()),
                         None => {
                             node.disconnect_e0();
@@ -377,7 +325,7 @@ impl Bdd {
                     None => (None, None),
                 };
                 let e1_edges = match node.get_e1() {
-                    Some(e1) => match below[0].get_nodes().get(&e1) {
+                    Some(e1) => match below[0].get_node(&e1) {
                         Some(e1_below) => (e1_below.get_e0(), e1_below.get_e1()),
                         None => {
                             node.disconnect_e1();
@@ -392,12 +340,9 @@ impl Bdd {
                             node.connect_e0(*existing_node);
                         }
                         None => {
-                            let new_id = {
-                                next_id += 1;
-                                Id::new(next_id * 10000 + bdd_id)
-                            };
+                            let new_id = Id::new(nodes.len());
                             node.connect_e0(new_id);
-                            nodes.insert(new_id, Node::with_edges(e0_edges.0, e1_edges.0));
+                            nodes.push(Node::with_edges(e0_edges.0, e1_edges.0));
                             known_functions.insert((e0_edges.0, e1_edges.0), new_id);
                         }
                     }
@@ -410,12 +355,9 @@ impl Bdd {
                             node.connect_e1(*existing_node);
                         }
                         None => {
-                            let new_id = {
-                                next_id += 1;
-                                Id::new(next_id * 10000 + bdd_id)
-                            };
+                            let new_id = Id::new(nodes.len());
                             node.connect_e1(new_id);
-                            nodes.insert(new_id, Node::with_edges(e0_edges.1, e1_edges.1));
+                            nodes.push(Node::with_edges(e0_edges.1, e1_edges.1));
                             known_functions.insert((e0_edges.1, e1_edges.1), new_id);
                         }
                     }
@@ -423,7 +365,6 @@ impl Bdd {
                     node.disconnect_e1()
                 }
             });
-        self.next_id = next_id;
         self.levels[level_index_below].replace_nodes(nodes);
         let lhs_1 = self.levels[level_index_above].get_lhs();
         let lhs_2 = self.levels[level_index_below].get_lhs();
@@ -453,8 +394,8 @@ impl Bdd {
     /// -> instead of generating, connect node to this already existing node
     ///
     /// For the 0edge we just perform the check that it is not representing an already existing
-    /// function, if no we add it to the `generated_nodes` and `known_function` else connect
-    /// to the already existing node
+    /// function, if no we add a copy of the 0edge child to the `generated_nodes` and
+    /// `known_function` else connect to the already existing node
     ///
     /// Finally add the `lhs` of `level_1` to `level_2`
     pub fn add(&mut self, mut level_index_above: usize, level_index_below: usize) {
@@ -464,16 +405,13 @@ impl Bdd {
             level_index_above += 1;
         }
         let max_level_size = self.levels[level_index_below].get_nodes_len() * 2;
-        let mut nodes: AHashMap<Id, Node> =
-            AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
+        let mut nodes: Vec<Node> = Vec::with_capacity(max_level_size);
         let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> =
             AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
         let (above, below) = self.levels.split_at_mut(level_index_above + 1);
-        let mut next_id = self.next_id;
-        let bdd_id = *self.id;
         for (_, node) in above.last_mut().unwrap().iter_mut_nodes() {
             if let Some(e0) = node.get_e0() {
-                match below[0].get_nodes().get(&e0) {
+                match below[0].get_node(&e0) {
                     Some(e0_node) => {
                         let e0_edges = (e0_node.get_e0(), e0_node.get_e1());
                         match known_functions.get(&e0_edges) {
@@ -481,8 +419,10 @@ impl Bdd {
                                 node.connect_e0(*existing_node);
                             }
                             None => {
-                                nodes.insert(e0, Node::with_edges(e0_edges.0, e0_edges.1));
-                                known_functions.insert(e0_edges, e0);
+                                let new_id = Id::new(nodes.len());
+                                node.connect_e0(new_id);
+                                nodes.push(Node::with_edges(e0_edges.0, e0_edges.1));
+                                known_functions.insert(e0_edges, new_id);
                             }
                         }
                     }
@@ -490,7 +430,7 @@ impl Bdd {
                 }
             };
             if let Some(e1) = node.get_e1() {
-                match below[0].get_nodes().get(&e1) {
+                match below[0].get_node(&e1) {
                     Some(e1_node) => {
                         let e1_edges = (e1_node.get_e1(), e1_node.get_e0());
                         match known_functions.get(&(e1_edges)) {
@@ -498,12 +438,9 @@ impl Bdd {
                                 node.connect_e1(*existing_node);
                             }
                             None => {
-                                let new_id = {
-                                    next_id += 1;
-                                    Id::new(next_id * 10000 + bdd_id)
-                                };
+                                let new_id = Id::new(nodes.len());
                                 node.connect_e1(new_id);
-                                nodes.insert(new_id, Node::with_edges(e1_edges.0, e1_edges.1));
+                                nodes.push(Node::with_edges(e1_edges.0, e1_edges.1));
                                 known_functions.insert(e1_edges, new_id);
                             }
                         }
@@ -512,7 +449,6 @@ impl Bdd {
                 }
             }
         }
-        self.next_id = next_id;
         self.levels[level_index_below].replace_nodes(nodes);
         let lhs_1 = self.levels[level_index_above].get_lhs();
         self.levels[level_index_below].add_lhs(&lhs_1);
@@ -522,7 +458,7 @@ impl Bdd {
     /// `swap` the `level` to the bottom just above the sink,
     /// Connect each edge of the `level` above to the sink if they were connected to the `level` to drop,
     /// remove the level to drop,
-    /// finally merge the equal nodes in the bdd.
+    /// finally merge the equal nodes in the bdd and compact it.
     pub fn drop(&mut self, mut level_index: usize) {
         while level_index != self.get_levels_size() - 2 {
             self.swap(level_index, level_index + 1);
@@ -534,10 +470,10 @@ impl Bdd {
             if let Some((sink_id, _)) = sink[0].iter_nodes().next() {
                 above[len - 2].iter_mut_nodes().for_each(|(_, node)| {
                     if node.get_e0().is_some() {
-                        node.connect_e0(*sink_id);
+                        node.connect_e0(sink_id);
                     }
                     if node.get_e1().is_some() {
-                        node.connect_e1(*sink_id);
+                        node.connect_e1(sink_id);
                     }
                 });
             }
@@ -546,6 +482,7 @@ impl Bdd {
         if level_index > 1 {
             self.merge_equals_node_start(level_index - 1);
         }
+        self.compact();
     }
 
     /// Perform an "absorbtion" of a `level` -> assume the lhs is equal to `edge`,
@@ -553,49 +490,55 @@ impl Bdd {
     /// The opposite edges are now non-valid (if the lhs is equal to zero, cannot be equal to one and viceversa).
     /// The level is then remove and reducing is perform on the bdd (removing orphans and dead ends).
     pub fn absorb(&mut self, level_index: usize, edge: bool) {
-        let mut new_level = AHashMap::with_capacity_and_hasher(
-            self.levels[level_index].get_nodes_len(),
-            Default::default(),
-        );
-
         // If the level to absorb is the source of the bdd, different strategy
         if level_index == 0 {
             self.absorb_source(edge);
             return;
         }
 
-        if !edge {
-            for (id, node) in self.levels[level_index].iter_nodes() {
-                let e0 = node.get_e0();
-                if let Some(e0) = e0 {
-                    new_level.insert(*id, e0);
-                }
-            }
-        } else {
-            for (id, node) in self.levels[level_index].iter_nodes() {
-                let e1 = node.get_e1();
-                if let Some(e1) = e1 {
-                    new_level.insert(*id, e1);
-                }
+        let mut new_level = vec![None; self.levels[level_index].get_slots_len()];
+        let mut found = false;
+        for (id, node) in self.levels[level_index].iter_nodes() {
+            let child = if edge { node.get_e1() } else { node.get_e0() };
+            if child.is_some() {
+                new_level[*id] = child;
+                found = true;
             }
         }
         // If there is no valid outgoing edges then there is no solution
         // Basically this means that we absorbed a level along an edge and
         // the level had only outgoing edges of the other type.
         // This would be a 0 = 1
-        if new_level.is_empty() {
+        if !found {
             panic!("System has no solutions")
         }
-        self.point_all_parents_to_new_level_map(&new_level, level_index - 1, level_index);
+        self.levels[level_index - 1].remap_edges(&new_level);
         self.levels.remove(level_index);
-        self.remove_all_dead_ends_start(level_index - 1);
+        // removing the dead ends may disconnect edges up to `top`, creating equal nodes in
+        // every level in between
+        let top = self.remove_all_dead_ends_start(level_index - 1);
         self.remove_orphans_start(level_index);
-        self.merge_equals_node_start(level_index - 1);
+        self.merge_equals_node_between(top.max(1), level_index - 1);
+        self.compact();
+    }
+
+    /// Perform the `absorb` of the `level` along `edge` if one of its nodes has an outgoing `edge`.
+    ///
+    /// Return false, leaving the bdd unchanged, if none has : no path of the bdd gives the value
+    /// `edge` to the lhs of the level.
+    pub fn try_absorb(&mut self, level_index: usize, edge: bool) -> bool {
+        let (has_0edge, has_1edge) = self.levels[level_index].check_outgoing_edges();
+        if (edge && !has_1edge) || (!edge && !has_0edge) {
+            return false;
+        }
+        self.absorb(level_index, edge);
+        true
     }
 
     /// Absorb the source of the bdd along the edge precised.
     /// To absorb it we remove the opposing edge of the next level.
     /// The level 0 is then removed and then the orphans removed starting at new level 1
+    /// before compacting the bdd.
     fn absorb_source(&mut self, edge: bool) {
         let node = &self.levels[0].pop_source();
         // if the top node has both edges pointing to same node, we don't need to remove the wrong edge
@@ -614,6 +557,7 @@ impl Bdd {
             panic!("System has no solutions")
         }
         self.remove_orphans_start(1);
+        self.compact();
     }
 
     /// Iterate through the bdd to find linear equations
@@ -630,6 +574,12 @@ impl Bdd {
                 // in the unlikely event that there is a 0 level remaining in the BDD
                 // we absorb it but the equation is 0 = 0 so we don't grab it
                 if level.iter_set_lhs().count() == 0 {
+                    if !level.check_outgoing_edges().0 {
+                        // only 1edges, the equation 0 = 1 is returned for the caller to find out
+                        // that there is no solution
+                        lin_eqs_absorbed.push(LinEq::new(level.get_lhs(), true));
+                        return lin_eqs_absorbed;
+                    }
                     self.absorb(i, false);
                     absorbed = true;
                     break;
@@ -656,123 +606,112 @@ impl Bdd {
         lin_eqs_absorbed
     }
 
-    /// Used to remove any jumping edges in a bdd, ensuring that if a node has a parent
-    /// it is located in the level just above. This is important for performance since we don't
-    /// keep track of the parents of a node.
-    ///
-    /// Should be use only when loading the system at the start (jumping edges cannot appear after).
-    pub fn add_same_edges_node_at_level(&mut self, level_index: usize) {
-        let mut changed = false;
-        if level_index != 0 {
-            let mut childs: HashSet<Id, BuildHasherDefault<ahash::AHasher>> =
-                AHashSet::with_capacity_and_hasher(
-                    self.levels[level_index - 1].get_nodes_len(),
-                    Default::default(),
-                );
-            for (_, node) in self.levels[level_index - 1].iter_nodes() {
-                if let Some(e0) = node.get_e0() {
-                    childs.insert(e0);
-                }
-                if let Some(e1) = node.get_e1() {
-                    childs.insert(e1);
-                }
-            }
-            for (id, _) in self.levels[level_index].iter_nodes() {
-                childs.remove(id);
-            }
-            let mut new_level: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
-                AHashMap::with_capacity_and_hasher(childs.len(), Default::default());
-            if !childs.is_empty() {
-                changed = true;
-            }
-            for node in childs.iter() {
-                let new_id = {
-                    let next_id = self.next_id + 1;
-                    self.next_id = next_id;
-                    Id::new(next_id * 10000 + *self.id)
-                };
-                self.levels[level_index].add_edged_node(new_id, Some(*node), Some(*node));
-                new_level.insert(*node, new_id);
-            }
-            if changed {
-                self.point_all_parents_to_new_level_map(&new_level, 0, level_index);
-            }
-        }
-    }
-
     /// Merge nodes which represent the same function in a level.
-    /// Start with the level_index and goes upwards.
+    /// Start with the level_index and goes upwards, up to the level 1.
     ///
     /// Short circuited -> will stop when no change were found in the previous level.
     pub fn merge_equals_node_start(&mut self, mut level_index: usize) {
         let mut changed = true;
-        let max_size_map = self.levels[level_index].get_nodes_len();
-        let mut known_functions: HashMap<
-            (Option<Id>, Option<Id>),
-            Id,
-            BuildHasherDefault<ahash::AHasher>,
-        > = AHashMap::with_capacity_and_hasher(max_size_map, Default::default());
-        let mut map: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
-            AHashMap::with_capacity_and_hasher(max_size_map, Default::default());
-        while changed && level_index > 1 {
-            changed = false;
-            for (id, node) in self.levels[level_index].iter_nodes() {
-                match known_functions.get(&(node.get_e0(), node.get_e1())) {
-                    Some(existing_node) => {
-                        changed = true;
-                        map.insert(*id, *existing_node);
-                    }
-                    None => {
-                        known_functions.insert((node.get_e0(), node.get_e1()), *id);
-                    }
-                };
-            }
-            self.point_all_parents_to_new_level_map(&map, level_index - 1, level_index);
-            self.levels[level_index].remove_nodes_from_map(&map);
-            known_functions.clear();
-            map.clear();
+        while changed && level_index >= 1 {
+            changed = self.merge_equals_node_level(level_index);
             level_index -= 1;
         }
     }
 
-    /// For all `nodes` located on the range `level_start..level_max` (level_max not included) :
+    /// Merge nodes which represent the same function in every level, from the level above the
+    /// sink up to the level 1.
     ///
-    /// point their existing edges to a new node following the `HashMap` passed as a parameter.
-    /// The `HashMap` should contain the `Id` of the old node as the key and the id of the new node as its content
-    fn point_all_parents_to_new_level_map(
-        &mut self,
-        map: &AHashMap<Id, Id>,
-        level_start: usize,
-        level_max: usize,
-    ) {
-        self.levels
-            .iter_mut()
-            .skip(level_start)
-            .take(level_max - level_start)
-            .for_each(|level| {
-                level.iter_mut_nodes().for_each(|(_, node)| {
-                    let e0 = node.get_e0();
-                    if let Some(e0) = e0 {
-                        if let Some(new_node) = map.get(&e0) {
-                            node.connect_e0(*new_node);
-                        }
-                    }
-                    let e1 = node.get_e1();
-                    if let Some(e1) = e1 {
-                        if let Some(new_node) = map.get(&e1) {
-                            node.connect_e1(*new_node);
-                        }
-                    }
-                });
-            });
+    /// Unlike `merge_equals_node_start` this is not short circuited, it is meant to reduce a
+    /// `Bdd` which was not built by the operations (when loading the system at the start).
+    pub fn merge_all_equals_node(&mut self) {
+        let sink = self.levels.len().saturating_sub(1);
+        if sink > 1 {
+            self.merge_equals_node_between(1, sink - 1);
+        }
+    }
+
+    /// Merge nodes which represent the same function in every level from `bottom` up to `top`
+    /// (both included, `top` should not be 0), then keep going upwards as
+    /// `merge_equals_node_start` does.
+    pub fn merge_equals_node_between(&mut self, top: usize, bottom: usize) {
+        let mut changed = false;
+        for level_index in (top..=bottom).rev() {
+            changed = self.merge_equals_node_level(level_index);
+        }
+        if changed && top > 1 {
+            self.merge_equals_node_start(top - 1);
+        }
+    }
+
+    /// Merge the nodes of the level `level_index` which represent the same function, pointing
+    /// their parents to the node kept. Return true if at least a node was merged.
+    fn merge_equals_node_level(&mut self, level_index: usize) -> bool {
+        let level = &self.levels[level_index];
+        let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> =
+            AHashMap::with_capacity_and_hasher(level.get_nodes_len(), Default::default());
+        let mut map = vec![None; level.get_slots_len()];
+        let mut merged = Vec::new();
+        for (id, node) in level.iter_nodes() {
+            let existing_node = *known_functions
+                .entry((node.get_e0(), node.get_e1()))
+                .or_insert(id);
+            if existing_node != id {
+                merged.push(id);
+            }
+            map[*id] = Some(existing_node);
+        }
+        if merged.is_empty() {
+            return false;
+        }
+        self.levels[level_index - 1].remap_edges(&map);
+        for id in merged {
+            self.levels[level_index].remove_node(id);
+        }
+        true
+    }
+
+    /// Remove the empty slots left by the removed nodes in every level, giving new ids to the
+    /// nodes and pointing the edges to the new ids (see the `Level` documentation).
+    ///
+    /// The ids of the nodes are not valid anymore after compacting.
+    pub fn compact(&mut self) {
+        self.compact_levels(|_| true);
+    }
+
+    /// Compact the levels whose fraction of empty slots is above `SPARSE_THRESHOLD`, as `compact`
+    /// does for every level.
+    ///
+    /// Meant for the pruning loops, which remove nodes in many steps without compacting: the
+    /// ids of the nodes of a compacted level change, so it must only be called between steps.
+    pub fn compact_sparse(&mut self) {
+        self.compact_levels(|level| {
+            let empty = level.get_slots_len() - level.get_nodes_len();
+            empty as f64 > SPARSE_THRESHOLD * level.get_slots_len() as f64
+        });
+    }
+
+    fn compact_levels<F: Fn(&Level) -> bool>(&mut self, selected: F) {
+        for level_index in 0..self.levels.len() {
+            if !selected(&self.levels[level_index]) {
+                continue;
+            }
+            if let Some(map) = self.levels[level_index].compact() {
+                if level_index > 0 {
+                    self.levels[level_index - 1].remap_edges(&map);
+                }
+            }
+        }
     }
 
     /// Use when joining BDDs to merge the source of the BDD join to below
     /// with the sink of the BDD above it
     pub fn merge_sink_source(&mut self, sink_level_index: usize) {
-        let (sink_bdd, source_bdd) = self.levels.split_at_mut(sink_level_index + 1);
-        if let Some((_, source)) = source_bdd[0].iter_nodes().next() {
-            if let Some((_, sink)) = sink_bdd.last_mut().unwrap().iter_mut_nodes().next() {
+        let source = self.levels[sink_level_index + 1]
+            .iter_nodes()
+            .next()
+            .map(|(_, source)| source.clone());
+        if let Some(source) = source {
+            if let Some((_, sink)) = self.levels[sink_level_index].iter_mut_nodes().next() {
                 if let Some(e0) = source.get_e0() {
                     sink.connect_e0(e0);
                 }
@@ -791,154 +730,103 @@ impl Bdd {
     /// A path is defined as a `Vec` of `LinEq` made of the `lhs` of the `levels`
     /// and an outgoing edge of the `level`.
     ///
-    /// /!\ This is VERY SLOW you should avoid using it on a big BDD
-    ///
-    /// To produce all path we start from the top to the bottom.
-    /// We keep a stack (LIFO) of tuples containing the state of the path,
-    /// the index of the `level` and the reference to the `node`. We push to
-    /// everytime we find a `node` that has both edges not set to `None`.
-    /// When we reach the sink we go back to the stack to find the next path
-    /// up until the stack is exhausted.
-    /// If a BDD contain more that 20 paths we only return the first 20 to avoid
-    /// exploding in memory size.
+    /// /!\ This collects every path of the BDD, use `paths` to walk them lazily
+    /// when the BDD may contain a lot of paths.
     pub fn get_all_valid_path(&self) -> Vec<Vec<LinEq>> {
-        if self.get_sink_level_index() == 0 {
-            return vec![vec![]];
-        }
-        let mut paths = Vec::new();
-        let mut last_double_edge_node: Vec<(Vec<LinEq>, usize, (Option<Id>, Option<Id>))> =
-            Vec::new();
-        while !last_double_edge_node.is_empty() || paths.is_empty() {
-            let mut path;
-            let mut node: (Option<Id>, Option<Id>);
-            let mut level_index;
-            let mut visited = if !last_double_edge_node.is_empty() {
-                // We have something to go back found in a previous path
-                let last = last_double_edge_node.pop().unwrap();
-                path = last.0;
-                level_index = last.1;
-                node = last.2;
-                true
-            } else {
-                // we are starting from top
-
-                if let Some((_, n)) = self.levels[0].iter_nodes().next() {
-                    node = (n.get_e0(), n.get_e1())
-                } else {
-                    panic!("Cannot happen")
-                }
-                path = Vec::new();
-                level_index = 0;
-                false
-            };
-            //while we haven't reach the sink
-            loop {
-                // sink reached
-                if node.0.is_none() && node.1.is_none() {
-                    break;
-                }
-                // Already been there so we already know the e0 path -> follow e1
-                if visited {
-                    path.push(LinEq::new(self.levels[level_index].get_lhs(), true));
-                    let next = node.1.unwrap();
-                    if let Some(n) = self.levels[level_index + 1].get_nodes().get(&next) {
-                        node = (n.get_e0(), n.get_e1());
-                    }
-                    level_index += 1;
-                    visited = false;
-                    continue;
-                }
-                // double edge node -> let's store it for later
-                if node.0.is_some() && node.1.is_some() {
-                    last_double_edge_node.push((path.clone(), level_index, node));
-                }
-                let has_e0 = node.0;
-                if let Some(e0) = has_e0 {
-                    path.push(LinEq::new(self.levels[level_index].get_lhs(), false));
-                    if let Some(n) = self.levels[level_index + 1].get_nodes().get(&e0) {
-                        node = (n.get_e0(), n.get_e1());
-                    }
-                    level_index += 1;
-                    continue;
-                }
-                let has_e1 = node.1;
-                if let Some(e1) = has_e1 {
-                    path.push(LinEq::new(self.levels[level_index].get_lhs(), true));
-                    if let Some(n) = self.levels[level_index + 1].get_nodes().get(&e1) {
-                        node = (n.get_e0(), n.get_e1());
-                    }
-                    level_index += 1;
-                    continue;
-                }
-            }
-            paths.push(path);
-            //just checking to avoid exploding in memory if called on a really large bdd
-            if paths.len() > 20 {
-                return paths;
-            }
+        self.paths().collect()
+    }
+
+    /// Returns a lazy iterator over all the valid paths of a `Bdd`.
+    ///
+    /// A path is defined as a `Vec` of `LinEq` made of the `lhs` of the `levels`
+    /// and an outgoing edge of the `level`. A `Bdd` made only of a sink has a
+    /// single empty path.
+    ///
+    /// Paths are produced one at a time, see `Paths` for more details.
+    pub fn paths(&self) -> Paths<'_> {
+        Paths {
+            bdd: self,
+            path: Vec::with_capacity(self.levels.len()),
+            stack: Vec::new(),
+            started: false,
         }
-        paths
     }
 
     /// Count the number of paths inside a `Bdd`.  The return value is a BigUint, as the number of
     /// paths may be huge.
     ///
-    /// To count the number of paths we go from bottom to top.
-    ///
-    /// We keep a map of the previous level containing a mapping from `Id` to `weight`.
-    /// `weight` is the number of path that leads from that node to the sink and is the sum of the weights
-    /// of the children of the node. If the weight of a child is zero the child is the sink
-    /// therefore 0 -> 1 path.
-    ///
-    /// When we reach the top the `previous_level_weight` will contain only the top node
-    /// So we can grab it and its weight will be the number of paths of the bdd
+    /// The counting is done by `path_weights`, the weight of the source being the number of
+    /// paths of the bdd.
     /// If the bdd is only a sink (number of level < 2), we return `0`
-    pub fn count_paths(&self) -> num_bigint::BigUint {
-        let mut previous_level_weigths: HashMap<Id, num_bigint::BigUint, BuildHasherDefault<ahash::AHasher>> =
-            AHashMap::with_hasher(Default::default());
+    pub fn count_paths(&self) -> BigUint {
         if self.levels.len() < 2 {
             return 0.to_biguint().unwrap();
         }
+        match self.path_weights().first() {
+            Some(source_level) => source_level.iter().sum(),
+            None => 0.to_biguint().unwrap(),
+        }
+    }
+
+    /// Return for each level the `weight` of its nodes, indexed by their `Id` (the removed nodes
+    /// having a weight of 0).
+    ///
+    /// `weight` is the number of paths that leads from that node to the sink. We go from bottom
+    /// to top, the weight of the sink being 1 and the weight of a node being the sum of the
+    /// weights of its children.
+    ///
+    /// When we reach the top the first level will contain only the source and its weight will
+    /// be the number of paths of the bdd.
+    pub fn path_weights(&self) -> Vec<Vec<BigUint>> {
+        let mut weights: Vec<Vec<BigUint>> = Vec::with_capacity(self.levels.len());
         for level in self.iter_levels().rev() {
-            let mut current_level_weights = AHashMap::with_hasher(Default::default());
+            let mut current_level_weights = vec![0.to_biguint().unwrap(); level.get_slots_len()];
             for (id, node) in level.iter_nodes() {
-                let weight0 = match node.get_e0() {
-                    Some(e0_id) => match previous_level_weigths.get(&e0_id) {
-                        Some(weight) => {
-                            if *weight == 0.to_biguint().unwrap() {
-                                1.to_biguint().unwrap()
-                            } else {
-                                weight.clone()
-                            }
-                        }
-                        None => 0.to_biguint().unwrap(),
-                    },
-                    None => 0.to_biguint().unwrap(),
-                };
-		let weight1 = match node.get_e1() {
-                    Some(e1_id) => match previous_level_weigths.get(&e1_id) {
-                        Some(weight) => {
-                            if *weight == 0.to_biguint().unwrap() {
-                                1.to_biguint().unwrap()
-                            } else {
-                                weight.clone()
-                            }
-                        }
-                        None => 0.to_biguint().unwrap(),
-                    },
-                    None => 0.to_biguint().unwrap(),
+                let weight = match weights.last() {
+                    Some(previous_level_weights) => {
+                        let child_weight = |child: Option<Id>| match child {
+                            Some(child) => match previous_level_weights.get(*child) {
+                                Some(weight) => weight.clone(),
+                                None => 0.to_biguint().unwrap(),
+                            },
+                            None => 0.to_biguint().unwrap(),
+                        };
+                        child_weight(node.get_e0()) + child_weight(node.get_e1())
+                    }
+                    // the sink
+                    None => 1.to_biguint().unwrap(),
                 };
-                current_level_weights.insert(*id, weight0+weight1);
+                current_level_weights[*id] = weight;
             }
-            previous_level_weigths = current_level_weights;
+            weights.push(current_level_weights);
+        }
+        weights.reverse();
+        weights
+    }
+
+    /// Draw a path of the `Bdd` uniformly at random.
+    ///
+    /// Return `None` if the `Bdd` has no path. To draw many paths, use `path_sampler` to avoid
+    /// computing the weights of the nodes again for each path.
+    pub fn random_path<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Vec<LinEq>> {
+        self.path_sampler().sample(rng)
+    }
+
+    /// Return a `PathSampler` able to draw paths of the `Bdd` uniformly at random.
+    pub fn path_sampler(&self) -> PathSampler<'_> {
+        PathSampler {
+            bdd: self,
+            weights: self.path_weights(),
         }
-        previous_level_weigths.iter().next().unwrap().1.clone()
     }
 
     /// Replace a variable in all the lhs of the bdd by a linear combination.
     /// If the linear combination is equal to true:flip all the edges of the level.
     /// If when replacing the lhs a zero level is created -> absorb it along its zero edges.
-    pub fn replace_var_in_bdd(&mut self, var: usize, eq: &LinEq) {
+    ///
+    /// Return false if a zero level has no zero edge to be absorbed along, meaning that the bdd
+    /// has no solution once `eq` holds. The remaining zero levels are then left in the bdd.
+    pub fn replace_var_in_bdd(&mut self, var: usize, eq: &LinEq) -> bool {
         let mut to_absorbe: Vec<usize> = Vec::with_capacity(self.levels.len());
         // We should be skipping the last level, but since we are explicitly checking that
         // the level has the var bit set and the last level has an all-zero lhs
@@ -955,9 +843,12 @@ impl Bdd {
                 }
             }
         });
-        for _ in 0..to_absorbe.len() {
-            self.absorb(to_absorbe.pop().unwrap(), false);
+        while let Some(level_index) = to_absorbe.pop() {
+            if !self.try_absorb(level_index, false) {
+                return false;
+            }
         }
+        true
     }
 }
 
@@ -994,21 +885,22 @@ impl PartialEq for Bdd {
         if self.get_lhs() != other.get_lhs() {
             return false;
         }
-        // node_mapping will map the id of a node in self to ref of a node in other
+        // node_mapping will map the id of a node in self to the id of a node in other,
+        // ids being local to a level it is cleared when going to the next level
         let mut node_mapping: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
             AHashMap::with_hasher(Default::default());
+        let mut next_mapping = node_mapping.clone();
 
         // Initialize the hashmap with the sources
         node_mapping.insert(
-            *self
-                .iter_levels()
+            self.iter_levels()
                 .next()
                 .unwrap()
                 .iter_nodes()
                 .next()
                 .unwrap()
                 .0,
-            *other
+            other
                 .iter_levels()
                 .next()
                 .unwrap()
@@ -1021,15 +913,19 @@ impl PartialEq for Bdd {
         // but again this was mainly implemented for testing
         // so we didn't bother making it optimized
         for (level_index, level_self) in self.iter_levels().enumerate() {
+            if level_index > 0 {
+                std::mem::swap(&mut node_mapping, &mut next_mapping);
+                next_mapping.clear();
+            }
             for (id_self, node_self) in level_self.iter_nodes() {
                 let (e0_self, e1_self) = (node_self.get_e0(), node_self.get_e1());
-                let id_other = node_mapping.get(id_self).unwrap();
-                if let Some(node_other) = other.levels[level_index].get_nodes().get(&id_other) {
+                let id_other = node_mapping.get(&id_self).unwrap();
+                if let Some(node_other) = other.levels[level_index].get_node(id_other) {
                     let (e0_other, e1_other) = (node_other.get_e0(), node_other.get_e1());
                     match e0_self {
                         Some(e0_self) => match e0_other {
                             Some(e0_other) => {
-                                node_mapping.insert(e0_self, e0_other);
+                                next_mapping.insert(e0_self, e0_other);
                             }
                             None => {
                                 return false;
@@ -1044,7 +940,7 @@ impl PartialEq for Bdd {
                     match e1_self {
                         Some(e1_self) => match e1_other {
                             Some(e1_other) => {
-                                node_mapping.insert(e1_self, e1_other);
+                                next_mapping.insert(e1_self, e1_other);
                             }
                             None => {
                                 return false;
@@ -1064,3 +960,128 @@ impl PartialEq for Bdd {
 }
 
 impl Eq for Bdd {}
+
+/// Lazy iterator over the valid paths of a `Bdd`, created by `Bdd::paths`.
+///
+/// To produce all paths we walk from the top to the bottom, always following
+/// the 0-edge first. We keep a stack (LIFO) of the 1-edges left behind by the nodes
+/// having both edges set, along with the index of their level. When we reach the sink
+/// the path is returned, and the next call goes back to the stack to find the next path
+/// up until the stack is exhausted.
+///
+/// Only the current path and the stack are kept in memory, so walking a `Bdd` with
+/// a huge number of paths is fine as long as the caller doesn't collect them.
+pub struct Paths<'a> {
+    bdd: &'a Bdd,
+    path: Vec<LinEq>,
+    stack: Vec<(usize, Id)>,
+    started: bool,
+}
+
+impl<'a> Paths<'a> {
+    /// Follow the edges from the node `id` at `level_index` down to the sink, pushing
+    /// the `LinEq` of each level crossed to the current path.
+    ///
+    /// Return false if the walk ended somewhere else than in the sink.
+    fn walk(&mut self, mut level_index: usize, mut id: Id) -> bool {
+        let sink_level_index = self.bdd.get_sink_level_index();
+        while level_index < sink_level_index {
+            let node = match self.bdd.levels[level_index].get_node(&id) {
+                Some(node) => node,
+                None => return false,
+            };
+            let lhs = self.bdd.levels[level_index].get_lhs();
+            match (node.get_e0(), node.get_e1()) {
+                (Some(e0), e1) => {
+                    if let Some(e1) = e1 {
+                        self.stack.push((level_index, e1));
+                    }
+                    self.path.push(LinEq::new(lhs, false));
+                    id = e0;
+                }
+                (None, Some(e1)) => {
+                    self.path.push(LinEq::new(lhs, true));
+                    id = e1;
+                }
+                (None, None) => return false,
+            }
+            level_index += 1;
+        }
+        self.bdd.levels[level_index].get_node(&id).is_some()
+    }
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = Vec<LinEq>;
+
+    fn next(&mut self) -> Option<Vec<LinEq>> {
+        if !self.started {
+            self.started = true;
+            let source = match self.bdd.levels.first() {
+                Some(level) => match level.iter_nodes().next() {
+                    Some((id, _)) => id,
+                    None => return None,
+                },
+                None => return None,
+            };
+            if self.walk(0, source) {
+                return Some(self.path.clone());
+            }
+        }
+        while let Some((level_index, e1)) = self.stack.pop() {
+            self.path.truncate(level_index);
+            self.path
+                .push(LinEq::new(self.bdd.levels[level_index].get_lhs(), true));
+            if self.walk(level_index + 1, e1) {
+                return Some(self.path.clone());
+            }
+        }
+        None
+    }
+}
+
+/// Draw paths of a `Bdd` uniformly at random, created by `Bdd::path_sampler`.
+///
+/// The sampler holds the weights of all the nodes (see `Bdd::path_weights`), the path is then
+/// drawn from the top to the bottom: from a node we follow an edge with a probability
+/// proportional to the weight of the child it points to. Each path is therefore drawn with
+/// probability `1 / number of paths`.
+pub struct PathSampler<'a> {
+    bdd: &'a Bdd,
+    weights: Vec<Vec<BigUint>>,
+}
+
+impl<'a> PathSampler<'a> {
+    /// Return the number of paths of the `Bdd` the paths are drawn from.
+    ///
+    /// Unlike `Bdd::count_paths`, a `Bdd` made only of a sink has one (empty) path.
+    pub fn count_paths(&self) -> BigUint {
+        match self.weights.first() {
+            Some(source_level) => source_level.iter().sum(),
+            None => 0.to_biguint().unwrap(),
+        }
+    }
+
+    /// Draw a path uniformly at random, or `None` if the `Bdd` has no path.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Vec<LinEq>> {
+        let zero = 0.to_biguint().unwrap();
+        let (mut id, _) = self.bdd.levels.first()?.iter_nodes().next()?;
+        if self.weights[0][*id] == zero {
+            return None;
+        }
+        let mut path = Vec::with_capacity(self.weights.len() - 1);
+        for (level_index, level) in self.bdd.iter_levels().enumerate().take(self.weights.len() - 1) {
+            let node = level.get_node(&id)?;
+            let child_weight = |child: Option<Id>| {
+                child.and_then(|child| Some((child, self.weights[level_index + 1].get(*child)?)))
+            };
+            let (e0, e1) = (child_weight(node.get_e0()), child_weight(node.get_e1()));
+            let w0 = e0.map_or(&zero, |(_, w)| w);
+            let w1 = e1.map_or(&zero, |(_, w)| w);
+            let edge = rng.gen_biguint_below(&(w0 + w1)) >= *w0;
+            path.push(LinEq::new(level.get_lhs(), edge));
+            id = if edge { e1?.0 } else { e0?.0 };
+        }
+        Some(path)
+    }
+}
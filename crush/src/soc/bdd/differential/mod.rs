@@ -44,10 +44,11 @@ pub use dependency_finder::{DepBoolFinder, DepPathFinder};
 use logging::builders::*;
 pub use logging::PruneLogger;
 pub use logging::records::*;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::num::NonZeroUsize;
 use std::ops::{Bound, Range, RangeBounds};
-use w_arenas::{NWAreaLevel, NWArena};
+use w_arenas::NWAreaLevel;
+pub use w_arenas::NWArena;
 use w_arenas::{PWCArenaLevel, PWCount};
 pub use wide_count_prune_core::{PPFactory, StyledProgressBar};
 
@@ -200,8 +201,8 @@ impl Bdd {
         // (We use u128, as we expect to prune before we exceed a path w/ weight 127).
         let bc_arena: HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>> =
             // "- top" is an offset, since work_area is a slice of all Levels in self.
-            work_area[base_case_index - top].get_nodes().keys()
-                .map(|id| (id, DepBoolFinder::new(*id, base_case_index, nz_step,
+            work_area[base_case_index - top].iter_ids()
+                .map(|id| (id, DepBoolFinder::new(id, base_case_index, nz_step,
                                                   self)))
                 .map(|(id, deps)| {
                     // OR together the correct weights for each path
@@ -209,7 +210,7 @@ impl Bdd {
                         //Did we pass at least one 1-edge on the way to id?
                         .map(|(_id, edge)| if *edge {2} else {1} )
                         .fold(0, |acc, weight| { acc | weight });
-                    (*id, trails)
+                    (id, trails)
                 }).collect();
 
 
@@ -230,11 +231,11 @@ impl Bdd {
             .for_each(|(i, level)| {
                 let depth = base_case_index - step*(i+1);
                 let next_level = base_case_index - step*i;
-                for id in level.get_nodes().keys() {
+                for id in level.iter_ids() {
                     let trails = self.calculate_trail_weights_for_node(&id, depth,
                                                                        nz_step,
                                                                        &arena.arena.get(&next_level).unwrap());
-                    arena.insert(*id, trails, depth);
+                    arena.insert(id, trails, depth);
                 }
             });
 
@@ -259,13 +260,13 @@ impl Bdd {
         debug_assert_eq!(c_depth + m_step.clone().get() + short_step.clone().get(), p_depth);
 
         let mut m_level: NWAreaLevel = HashMap::default();
-        for c_id in self.levels[c_depth].get_nodes().keys() {
+        for c_id in self.levels[c_depth].iter_ids() {
 
             // We want to know the intersection of dependencies between Centurion and Member
             // Therefore, for each node in the Centurion, we first step down to the dependencies
             // in Member, and then onwards to the dependencies in previous Centurion reachable from
             // those original dependencies in Member.
-            for (m_id, c_edge) in DepBoolFinder::new(*c_id, c_depth,
+            for (m_id, c_edge) in DepBoolFinder::new(c_id, c_depth,
                                                      m_step,
                                                      self) {
 
@@ -338,9 +339,8 @@ impl Bdd {
         // We need to take into account that previous_centurion may be the sink node.
         if top == bottom {
             return PWCArenaLevel::new_from(
-                self.levels[bottom].get_nodes()
-                    .keys()
-                    .map(|id| (*id, PWCount::fresh_trivial()))
+                self.levels[bottom].iter_ids()
+                    .map(|id| (id, PWCount::fresh_trivial()))
                     .collect()
             )
         }
@@ -348,8 +348,8 @@ impl Bdd {
 
         // Init "base case": Go `step` down, and see if an 1-edge or more was traversed, set weight accordingly
         let base_case: HashMap<Id, PWCount, BuildHasherDefault<ahash::AHasher>> =
-            work_area[base_case_index - top].get_nodes().keys()
-                .map(|id| (id, DepBoolFinder::new(*id, base_case_index, nz_step,
+            work_area[base_case_index - top].iter_ids()
+                .map(|id| (id, DepBoolFinder::new(id, base_case_index, nz_step,
                                                   self)))
 
                 .map(|(id, deps)| {
@@ -361,7 +361,7 @@ impl Bdd {
                         )
                         .fold(PWCount::new(),
                               |acc, weight| { acc + weight });
-                    (*id, trails)
+                    (id, trails)
                 }).collect();
 
         let base_case = PWCArenaLevel::new_from(base_case);
@@ -402,12 +402,12 @@ impl Bdd {
             };
 
             let node_depth = base_case_index - step*(i+1);
-            for id in level.get_nodes().keys() {
+            for id in level.iter_ids() {
                 // Calculate weights
-                let counts = self.calculate_trail_counts_for_node(id, node_depth,
+                let counts = self.calculate_trail_counts_for_node(&id, node_depth,
                                                                   nz_step, prev);
                 // insert into fill
-                fill.insert(*id, counts);
+                fill.insert(id, counts);
             }
             even = !even;
         }
@@ -435,8 +435,8 @@ impl Bdd {
         debug_assert_eq!(c_depth + m_step.clone().get() + short_step.clone().get(), p_depth);
 
         let mut m_level: PWCArenaLevel = PWCArenaLevel::new_from(HashMap::default());
-        for c_id in self.levels[c_depth].get_nodes().keys() {
-            for (m_id, c_edge) in DepBoolFinder::new(*c_id, c_depth,
+        for c_id in self.levels[c_depth].iter_ids() {
+            for (m_id, c_edge) in DepBoolFinder::new(c_id, c_depth,
                                                      m_step,
                                                      self) {
 
@@ -505,11 +505,10 @@ impl Bdd {
         // Remove nodes
         {
             let children = self.levels.get_mut(depth)
-                .expect("Level does not exist")
-                .get_mut_nodes();
+                .expect("Level does not exist");
             for id in delete.iter() {
                 // Remove the node
-                children.remove(id);
+                children.remove_node(**id);
             }
         }
 
@@ -671,8 +670,7 @@ impl Bdd {
             // Remove the node
             if
             self.levels.get_mut(depth).unwrap()
-                .get_mut_nodes()
-                .remove(child_id)
+                .remove_node(*child_id)
                 // Nodes marked on other levels may have been removed as part of a reduce op
                 .is_none()
             {
@@ -690,8 +688,7 @@ impl Bdd {
             for parent_id in my_parents.iter() {
                 // Quickfix to make the compiler happy. Would like a way not to do all these repeated calls...
                 let parent = self.levels.get_mut(depth - 1).unwrap()
-                    .get_mut_nodes()
-                    .get_mut(parent_id);
+                    .get_mut_node(parent_id);
 
                 // Parent found, update edge(s).
                 if parent.is_some() {
@@ -774,22 +771,21 @@ impl Bdd {
 
          self.levels
              // Get nodes on parent level
-             .get_mut(parents_depth ).unwrap()
-             .get_mut_nodes()
+             .get(parents_depth ).unwrap()
              // create mapping
-             .iter()
+             .iter_nodes()
              .for_each(|(parent_id, node)| {
 
                  if let Some(e0) = node.get_e0() {
                      let parents = child_parent_map.entry(e0)
                          .or_insert(Default::default());
-                     parents.insert(parent_id.clone());
+                     parents.insert(parent_id);
                  }
 
                  if let Some(e1) = node.get_e1() {
                      let parents = child_parent_map.entry(e1)
                          .or_insert(Default::default());
-                     parents.insert(parent_id.clone());
+                     parents.insert(parent_id);
                  }
              });
         child_parent_map
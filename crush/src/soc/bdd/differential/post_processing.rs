@@ -199,14 +199,14 @@ impl Bdd {
                 if let Some(e0) = parent_node.1.get_e0() {
                     if e0 == current_node {
                         path.push_front(false);
-                        current_node = *parent_node.0;
+                        current_node = parent_node.0;
                         break;
                     }
                 }
                 if let Some(e1) = parent_node.1.get_e1() {
                     if e1 == current_node {
                         path.push_front(true);
-                        current_node = *parent_node.0;
+                        current_node = parent_node.0;
                         break;
                     }
                 }
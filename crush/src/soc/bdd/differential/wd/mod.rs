@@ -114,12 +114,12 @@ impl Shard {
             };
 
             // Fill 'fill': Centurion for current depth
-            for node_id in self.levels[current_depth].get_nodes().keys() {
+            for node_id in self.levels[current_depth].iter_ids() {
                 // Calculate weights
-                let distribution = self.calculate_distribution_for_node(node_id, current_depth,
+                let distribution = self.calculate_distribution_for_node(&node_id, current_depth,
                                                                         step, prev);
                 // insert into fill,
-                fill.insert(*node_id, distribution);
+                fill.insert(node_id, distribution);
             }
 
 
@@ -271,9 +271,9 @@ impl Shard {
         // Init "base case": Go `step` down, and see if an 1-edge or more was traversed,
         // set distribution accordingly
         let mut base_case: WDLevel<W> =
-            self.levels[base_case_index].get_nodes().keys()
+            self.levels[base_case_index].iter_ids()
                 .map(|id| {
-                    (*id, bc_distribution(DepBoolFinder::new(*id,
+                    (id, bc_distribution(DepBoolFinder::new(id,
                                                              base_case_index,
                                                              step,
                                                              self), factory))
@@ -324,15 +324,15 @@ impl Shard {
         debug_assert_eq!(p_depth - centurion_depth, step.get());
 
         let mut c_level: WDLevel<W> = WDLevel::new(Some(centurion_depth));
-        for c_id in self.levels[centurion_depth].get_nodes().keys() {
+        for c_id in self.levels[centurion_depth].iter_ids() {
 
-            let distribution = self.calculate_distribution_for_node(c_id,
+            let distribution = self.calculate_distribution_for_node(&c_id,
                                                                     centurion_depth,
                                                                     step,
                                                                     previous_centurion);
 
             // Update existing count
-            c_level.insert(*c_id, distribution);
+            c_level.insert(c_id, distribution);
         }
         c_level
     }
@@ -358,12 +358,12 @@ impl Shard {
         debug_assert_eq!(c_depth + cm_step.clone().get() + mpc_step.clone().get(), p_depth);
 
         let mut m_level: WDLevel<W> = WDLevel::new(Some(member_depth));
-        for c_id in self.levels[c_depth].get_nodes().keys() {
+        for c_id in self.levels[c_depth].iter_ids() {
             // We want to know the intersection of dependencies between Centurion and Member
             // Therefore, for each node in the Centurion, we first step down to the dependencies
             // in Member, and then onwards to the dependencies in previous Centurion reachable from
             // those original dependencies in Member.
-            for (m_id, c_edge) in DepBoolFinder::new(*c_id, c_depth,
+            for (m_id, c_edge) in DepBoolFinder::new(c_id, c_depth,
                                                      cm_step,
                                                      self) {
 
@@ -459,9 +459,8 @@ impl Shard {
         // ===================================================================================
         // Part 1): Filling base case
         let top: WDLevel<W> = self.levels[active_area.start]
-            .get_nodes()
-            .keys()
-            .map(|id| (id.clone(), factory.new_trivial(id)))
+            .iter_ids()
+            .map(|id| (id, factory.new_trivial(&id)))
             .collect();
 
         // Are we done?
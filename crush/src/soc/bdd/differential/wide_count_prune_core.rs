@@ -92,6 +92,8 @@ impl Bdd {
             let size_before = self.get_size();
             progress.set_message("Pruning: Deleting nodes");
             self.delete_nodes_from_level_until(complexity_target, delete, widest.0, step, &mut loop_logger);
+            // the deleted nodes leave empty slots, the next loop computes its ids afresh
+            self.compact_sparse();
             let size_after = self.get_size();
             assert!(size_after < size_before, "No nodes were deleted, we risk an infinite loop now, aborting!");
             progress.inc((size_before - size_after) as u64);
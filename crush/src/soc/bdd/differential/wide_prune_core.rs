@@ -85,6 +85,8 @@ impl Bdd {
                                                         lsb, roof_marked, delete.len());
 
             self.delete_nodes_from_level_until(complexity_target, delete, widest.0, step, &mut loop_logger);
+            // the deleted nodes leave empty slots, the next loop computes its ids afresh
+            self.compact_sparse();
             prune_logger.register_loop_rec(loop_logger.finalize(self.get_size()));
 
             //Done
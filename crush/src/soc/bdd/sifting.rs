@@ -0,0 +1,200 @@
+//! Dynamic reordering of the levels of a `Bdd` to reduce its number of nodes.
+//!
+//! The number of nodes of a `Bdd` depends heavily on the order of its levels. Sifting
+//! (R. Rudell, "Dynamic variable ordering for ordered binary decision diagrams", 1993) takes the
+//! levels one at a time, from the widest to the narrowest, moves each of them through every
+//! position of the `Bdd` with `swap` and leaves it where the `Bdd` was the smallest.
+//!
+//! Linear sifting (C. Meinel, F. Somenzi, T. Theobald, "Linear sifting of decision diagrams",
+//! 1997) also tries, after each swap, to `add` the upper of the two levels swapped to the lower
+//! one, and keeps the sum if it made the `Bdd` smaller. The `lhs` of the levels are then changed, but
+//! as `add` is an invertible linear transformation the paths of the `Bdd` still describe the same
+//! solutions.
+//!
+//! Both `swap` and `add` are their own inverse, so moving a level back to its best position is
+//! done by applying again the recorded operations, in reverse order.
+//!
+//! The source can be moved like any other level, the sink stays at the bottom.
+
+use crate::soc::bdd::Bdd;
+
+/// The parameters of a sifting pass (see the module documentation).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sifting {
+    /// If true, try to add the neighbouring levels to the moved level (linear sifting).
+    pub linear: bool,
+    /// A level stops being moved in a direction once the `Bdd` is more than `max_growth` times
+    /// as big as the smallest size found, as it is unlikely to shrink back.
+    pub max_growth: f64,
+}
+
+impl Default for Sifting {
+    fn default() -> Sifting {
+        Sifting {
+            linear: false,
+            max_growth: 1.2,
+        }
+    }
+}
+
+impl Sifting {
+    /// Sifting using only `swap`.
+    pub fn rudell() -> Sifting {
+        Default::default()
+    }
+
+    /// Sifting using `swap` and `add`.
+    pub fn linear() -> Sifting {
+        Sifting {
+            linear: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// An operation applied while sifting, recorded to be undone.
+#[derive(Clone, Copy, Debug)]
+enum Move {
+    /// `swap(i, i + 1)`
+    Swap(usize),
+    /// `add(i, i + 1)`
+    Add(usize),
+}
+
+impl Bdd {
+    /// Sift every level of the `Bdd` once, from the widest to the narrowest, and return the
+    /// number of nodes of the `Bdd` afterwards.
+    pub fn sift(&mut self, sifting: Sifting) -> usize {
+        let movables = self.get_sink_level_index();
+        if movables < 2 {
+            return self.get_size();
+        }
+        // the level now at index i was at index positions[i] before sifting
+        let mut positions: Vec<usize> = (0..movables).collect();
+        let mut order = positions.clone();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.levels[i].get_nodes_len()));
+        for level in order {
+            let from = positions.iter().position(|&p| p == level).unwrap();
+            for m in self.sift_moves(from, sifting) {
+                if let Move::Swap(i) = m {
+                    positions.swap(i, i + 1);
+                }
+            }
+        }
+        self.get_size()
+    }
+
+    /// Move the level `level_index` to the position where the `Bdd` is the smallest and return
+    /// its new position.
+    ///
+    /// The sink can't be moved, `level_index` should be above it.
+    pub fn sift_level(&mut self, level_index: usize, sifting: Sifting) -> usize {
+        assert!(level_index < self.get_sink_level_index());
+        let moves = self.sift_moves(level_index, sifting);
+        moves.iter().fold(level_index, |position, m| match m {
+            Move::Swap(i) if *i == position => position + 1,
+            Move::Swap(i) if *i + 1 == position => position - 1,
+            _ => position,
+        })
+    }
+
+    /// Sift the level at `level_index` and return the operations leading from the original
+    /// `Bdd` to the one kept.
+    fn sift_moves(&mut self, level_index: usize, sifting: Sifting) -> Vec<Move> {
+        let last = self.get_sink_level_index() - 1;
+        // go towards the closest end first
+        let down_first = last - level_index < level_index;
+        let start_size = self.get_size();
+
+        let mut first = self.sift_towards(level_index, down_first, sifting);
+        let (first_best, first_size) = best_of(&first, start_size);
+        self.undo(&first);
+
+        let mut second = self.sift_towards(level_index, !down_first, sifting);
+        let (second_best, second_size) = best_of(&second, start_size);
+        let kept = if second_size <= first_size {
+            self.undo(&second[second_best..]);
+            second.truncate(second_best);
+            second
+        } else {
+            self.undo(&second);
+            first.truncate(first_best);
+            for (m, _) in first.iter() {
+                self.apply(*m);
+            }
+            first
+        };
+        kept.into_iter().map(|(m, _)| m).collect()
+    }
+
+    /// Move the level at `level_index` up to the source or down to the level above the sink,
+    /// returning the operations applied along with the size of the `Bdd` after each of them.
+    fn sift_towards(
+        &mut self,
+        mut level_index: usize,
+        down: bool,
+        sifting: Sifting,
+    ) -> Vec<(Move, usize)> {
+        let last = self.get_sink_level_index() - 1;
+        let mut moves = Vec::new();
+        let mut best = self.get_size();
+        loop {
+            // the index of the level above in the pair swapped, and the new index of the level
+            let (above, moved) = match down {
+                true if level_index < last => (level_index, level_index + 1),
+                false if level_index > 0 => (level_index - 1, level_index - 1),
+                _ => break,
+            };
+            self.apply(Move::Swap(above));
+            let mut size = self.get_size();
+            moves.push((Move::Swap(above), size));
+            level_index = moved;
+            // try the sum of the two levels just swapped, it is stored in the lower one
+            if sifting.linear {
+                self.apply(Move::Add(above));
+                let added = self.get_size();
+                if added < size {
+                    size = added;
+                    moves.push((Move::Add(above), size));
+                } else {
+                    self.apply(Move::Add(above));
+                }
+            }
+            best = best.min(size);
+            if size as f64 > sifting.max_growth * best as f64 {
+                break;
+            }
+        }
+        moves
+    }
+
+    /// Apply a recorded operation.
+    fn apply(&mut self, m: Move) {
+        match m {
+            Move::Swap(i) => self.swap(i, i + 1),
+            Move::Add(i) => self.add(i, i + 1),
+        }
+    }
+
+    /// Undo the recorded operations, the last one first.
+    fn undo(&mut self, moves: &[(Move, usize)]) {
+        for (m, _) in moves.iter().rev() {
+            self.apply(*m);
+        }
+    }
+}
+
+/// Return the number of operations of `moves` leading to the smallest `Bdd` and its size,
+/// `start_size` being the size before any operation.
+fn best_of(moves: &[(Move, usize)], start_size: usize) -> (usize, usize) {
+    moves
+        .iter()
+        .enumerate()
+        .fold((0, start_size), |(best, best_size), (i, (_, size))| {
+            if *size < best_size {
+                (i + 1, *size)
+            } else {
+                (best, best_size)
+            }
+        })
+}
@@ -0,0 +1,391 @@
+//! Module providing a compact binary format to save and load a `System`, as an alternative to the
+//! textual .bdd format for large systems.
+//!
+//! All integers are written as unsigned LEB128 varints (7 bits per byte, the high bit set on all
+//! bytes but the last), and a `lhs` is written as a bitset of `nvar` bits packed in
+//! `ceil(nvar / 8)` bytes, bit `i` being the bit `i % 8` of byte `i / 8`.
+//!
+//! The format is as follows :
+//!
+//! ```text
+//! magic            "CRHS"
+//! version          varint (currently 2)
+//! nvar             varint
+//! number of bdds   varint
+//! for each bdd, by increasing id :
+//!     id                  varint
+//!     number of levels    varint (including the sink)
+//!     for each level :
+//!         lhs                 bitset
+//!         number of nodes     varint
+//!         for each node :
+//!             e0                  varint
+//!             e1                  varint
+//! number of LinEq  varint
+//! for each LinEq, in the order of the LinBank :
+//!     lhs              bitset
+//!     rhs              1 byte (0 or 1)
+//! number of dropped variables  varint
+//! for each dropped variable :
+//!     var              varint
+//! number of symbols    varint (since version 2, 0 if the system has no symbol table)
+//! for each symbol, by increasing var :
+//!     var              varint
+//!     role             1 byte (its position in `Role::ALL`)
+//!     name length      varint
+//!     name             utf-8
+//! ```
+//!
+//! The nodes of a level are numbered from 1 in the order they are written, and an edge is written
+//! as the number of the node it points to in the level below, 0 meaning that the edge points
+//! to nothing. The node numbered `k` is given the id `k - 1` when reading, so a compacted `Bdd`
+//! is read back with the same ids.
+//!
+//! Reading and writing are streaming, the only thing kept in memory besides the `System` is the
+//! numbering of the nodes of a level.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::PathBuf;
+
+use vob::Vob;
+
+use crate::soc::{
+    bdd::{Bdd, LinEq},
+    Id,
+    level::Level,
+    symbols::{Role, SymbolTable},
+    system::System,
+    utils,
+};
+
+/// The first bytes of a file in the binary format.
+pub const MAGIC: &[u8; 4] = b"CRHS";
+/// The version of the format written by `write_system`.
+pub const VERSION: usize = 2;
+
+/// Write the `System` in the binary format to `writer`.
+///
+/// The `Bdd`s are written by increasing id so that writing the same `System` twice gives the
+/// same output.
+pub fn write_system<W: Write>(system: &System, writer: &mut W) -> io::Result<()> {
+    let nvar = system.get_nvar();
+    writer.write_all(MAGIC)?;
+    write_varint(writer, VERSION)?;
+    write_varint(writer, nvar)?;
+    let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+    ids.sort();
+    write_varint(writer, ids.len())?;
+    for id in ids {
+        write_bdd(system.get_bdd(id)?, nvar, writer)?;
+    }
+    write_varint(writer, system.get_lin_bank_size())?;
+    for lin_eq in system.iter_lin_eqs() {
+        write_bitset(writer, &lin_eq.get_lhs(), nvar)?;
+        writer.write_all(&[lin_eq.get_rhs() as u8])?;
+    }
+    write_varint(writer, system.get_dropped_vars().len())?;
+    for var in system.get_dropped_vars() {
+        write_varint(writer, *var)?;
+    }
+    match system.get_symbols() {
+        Some(symbols) => {
+            write_varint(writer, symbols.len())?;
+            for (var, symbol) in symbols.iter() {
+                write_varint(writer, var)?;
+                let role = Role::ALL.iter().position(|role| *role == symbol.role).unwrap();
+                writer.write_all(&[role as u8])?;
+                write_varint(writer, symbol.name.len())?;
+                writer.write_all(symbol.name.as_bytes())?;
+            }
+        }
+        None => write_varint(writer, 0)?,
+    }
+    Ok(())
+}
+
+/// Read a `System` in the binary format from `reader`, the files of version 1 having no
+/// symbol table.
+///
+/// Returns an `Error` if the data doesn't start with the magic bytes, if the version is not
+/// supported, if the data is truncated, if an edge points to a node that doesn't exist, if
+/// a symbol is invalid or if the `System` breaks one of its invariants (see
+/// `System::check_invariants`).
+pub fn read_system<R: Read>(reader: &mut R) -> io::Result<System> {
+    let mut magic = [0_u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a CRHS binary file (wrong magic bytes)",
+        ));
+    }
+    let version = read_varint(reader)?;
+    if version == 0 || version > VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "unsupported version {} of the binary format (supported up to {})",
+                version, VERSION
+            ),
+        ));
+    }
+    let nvar = read_varint(reader)?;
+    let mut system = System::new();
+    system.set_nvar(nvar);
+    let nbdds = read_varint(reader)?;
+    for _ in 0..nbdds {
+        system.push_bdd(read_bdd(reader, nvar)?)?;
+    }
+    let nlin_eqs = read_varint(reader)?;
+    // the counts are read from the input, the items are only allocated once they are read
+    let mut lin_eqs = Vec::new();
+    for _ in 0..nlin_eqs {
+        let lhs = read_bitset(reader, nvar)?;
+        let mut rhs = [0_u8];
+        reader.read_exact(&mut rhs)?;
+        lin_eqs.push(LinEq::new(lhs, rhs[0] != 0));
+    }
+    system.restore_lin_bank(lin_eqs);
+    let ndropped = read_varint(reader)?;
+    let mut dropped_vars = Vec::new();
+    for _ in 0..ndropped {
+        dropped_vars.push(read_varint(reader)?);
+    }
+    system.restore_dropped_vars(dropped_vars);
+    if version >= 2 {
+        let nsymbols = read_varint(reader)?;
+        if nsymbols > 0 {
+            system.set_symbols(read_symbols(reader, nsymbols, nvar)?);
+        }
+    }
+    system.check_invariants()?;
+    Ok(system)
+}
+
+/// Write the `System` in the binary format to a file at `path`.
+pub fn write_system_to_file(system: &System, path: &PathBuf) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_system(system, &mut writer)?;
+    writer.flush()
+}
+
+/// Read a `System` in the binary format from a file at `path`.
+pub fn read_system_from_file(path: &PathBuf) -> io::Result<System> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_system(&mut reader)
+}
+
+/// Return true if the file at `path` starts with the magic bytes of the binary format.
+pub fn is_binary_file(path: &PathBuf) -> io::Result<bool> {
+    let mut magic = [0_u8; 4];
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Convert a .bdd file at `input` to a file in the binary format at `output`.
+pub fn convert_text_to_binary(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
+    let system = utils::try_build_system_from_spec(utils::try_parse_system_spec_from_file(input)?)?;
+    write_system_to_file(&system, output)
+}
+
+/// Convert a file in the binary format at `input` to a .bdd file at `output`.
+///
+/// /!\ The .bdd format doesn't hold the `LinBank` nor the dropped variables, they are lost
+/// when converting.
+pub fn convert_binary_to_text(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
+    let system = read_system_from_file(input)?;
+    utils::print_system_to_file(&system, output);
+    Ok(())
+}
+
+/// Write a `Bdd` to `writer`, numbering the nodes of each level in their iteration order.
+fn write_bdd<W: Write>(bdd: &Bdd, nvar: usize, writer: &mut W) -> io::Result<()> {
+    write_varint(writer, *bdd.get_id())?;
+    write_varint(writer, bdd.get_levels_size())?;
+    let mut levels = bdd.iter_levels().peekable();
+    while let Some(level) = levels.next() {
+        write_bitset(writer, &level.get_lhs(), nvar)?;
+        write_varint(writer, level.get_nodes_len())?;
+        // the number of the nodes of the level below indexed by their id, 0 for removed nodes
+        let mut below = Vec::new();
+        if let Some(level) = levels.peek() {
+            below.resize(level.get_slots_len(), 0);
+            for (i, (id, _)) in level.iter_nodes().enumerate() {
+                below[*id] = i + 1;
+            }
+        }
+        let edge = |edge: Option<Id>| match edge {
+            Some(id) => below.get(*id).cloned().filter(|&k| k != 0).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("edge to node {} which is not in the level below", id),
+                )
+            }),
+            None => Ok(0),
+        };
+        for (_, node) in level.iter_nodes() {
+            write_varint(writer, edge(node.get_e0())?)?;
+            write_varint(writer, edge(node.get_e1())?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a `Bdd` from `reader`.
+fn read_bdd<R: Read>(reader: &mut R, nvar: usize) -> io::Result<Bdd> {
+    let mut bdd = Bdd::new();
+    let bdd_id = read_varint(reader)?;
+    bdd.set_id(Id::new(bdd_id));
+    let nlevels = read_varint(reader)?;
+    if nlevels == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("bdd {} has no level", bdd_id),
+        ));
+    }
+    for level_index in 0..nlevels {
+        let mut level = Level::new();
+        level.set_lhs_from_vob(read_bitset(reader, nvar)?);
+        let nnodes = read_varint(reader)?;
+        for _ in 0..nnodes {
+            let mut edges = [None, None];
+            for edge in edges.iter_mut() {
+                let child = read_varint(reader)?;
+                if child == 0 {
+                    continue;
+                }
+                if level_index + 1 == nlevels {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("the sink of bdd {} has an outgoing edge", bdd_id),
+                    ));
+                }
+                *edge = Some(Id::new(child - 1));
+            }
+            level.add_edged_node(edges[0], edges[1]);
+        }
+        bdd.add_existing_level(level);
+    }
+    check_edges(&bdd)?;
+    Ok(bdd)
+}
+
+/// Read `nsymbols` symbols of variables in `0..nvar`.
+fn read_symbols<R: Read>(reader: &mut R, nsymbols: usize, nvar: usize) -> io::Result<SymbolTable> {
+    let mut symbols = SymbolTable::new();
+    for _ in 0..nsymbols {
+        let var = read_varint(reader)?;
+        if var >= nvar {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("symbol of variable {} but nvar is {}", var, nvar),
+            ));
+        }
+        let mut role = [0_u8];
+        reader.read_exact(&mut role)?;
+        let role = *Role::ALL.get(role[0] as usize).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, format!("unknown role {}", role[0]))
+        })?;
+        let len = read_varint(reader)?;
+        let mut name = Vec::new();
+        reader.take(len as u64).read_to_end(&mut name)?;
+        if name.len() != len {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated symbol name"));
+        }
+        let name = String::from_utf8(name)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "symbol name is not utf-8"))?;
+        symbols
+            .insert(var, &name, role)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+    }
+    Ok(symbols)
+}
+
+/// Return an `Error` if an edge of `bdd` points to a node which is not in the level below.
+fn check_edges(bdd: &Bdd) -> io::Result<()> {
+    let levels: Vec<&Level> = bdd.iter_levels().collect();
+    for pair in levels.windows(2) {
+        for (id, node) in pair[0].iter_nodes() {
+            for child in [node.get_e0(), node.get_e1()].iter().flatten() {
+                if pair[1].get_node(child).is_none() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "edge from node {} of bdd {} to a node that doesn't exist",
+                            id,
+                            bdd.get_id()
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write `value` as an unsigned LEB128 varint.
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: usize) -> io::Result<()> {
+    let mut buf = [0_u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buf[..len])
+}
+
+/// Read an unsigned LEB128 varint.
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut value: usize = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0_u8];
+        reader.read_exact(&mut byte)?;
+        if shift >= usize::MAX.count_ones() {
+            return Err(Error::new(ErrorKind::InvalidData, "varint overflow"));
+        }
+        value |= ((byte[0] & 0x7f) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Write the first `nvar` bits of `lhs` packed in bytes.
+fn write_bitset<W: Write>(writer: &mut W, lhs: &Vob, nvar: usize) -> io::Result<()> {
+    let mut bytes = vec![0_u8; nvar.div_ceil(8)];
+    for var in lhs.iter_set_bits(..nvar.min(lhs.len())) {
+        bytes[var / 8] |= 1 << (var % 8);
+    }
+    writer.write_all(&bytes)
+}
+
+/// Read `nvar` bits packed in bytes.
+fn read_bitset<R: Read>(reader: &mut R, nvar: usize) -> io::Result<Vob> {
+    // nvar is read from the input, the bytes are only allocated once they are read
+    let len = nvar.div_ceil(8);
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated bitset"));
+    }
+    let mut lhs = Vob::from_elem(nvar, false);
+    for var in 0..nvar {
+        if bytes[var / 8] & (1 << (var % 8)) != 0 {
+            lhs.set(var, true);
+        }
+    }
+    Ok(lhs)
+}
@@ -0,0 +1,675 @@
+//! Module providing an export of a `System` to CNF with XOR clauses, to cross-check the results
+//! of Crush with SAT solvers supporting them natively (CryptoMiniSat, for example).
+//!
+//! The variable `x_j` of the `System` is the DIMACS variable `j + 1`, the variables above `nvar`
+//! are auxiliary variables. Each `Bdd` is encoded as follows :
+//! - each level whose lhs has more than one variable gets an auxiliary variable `y` with the XOR
+//!   clause `lhs + y = 0`, so that `y` is the value of the lhs. For a level with a single variable
+//!   this variable is used directly.
+//! - each node but the sink gets an auxiliary variable `r`, true if the path chosen by the
+//!   assignment goes through the node. The source is true, and for a node whose level has the
+//!   lhs `y` the clauses `r & !y -> r_0` and `r & y -> r_1` are added, `r_0` and `r_1` being the
+//!   variables of its children (the clause is shortened to `r -> y` or `r -> !y` when the edge
+//!   doesn't exist, and dropped when the child is the sink).
+//!
+//! An assignment of the variables of the `System` is then a solution of a `Bdd` if and only if it
+//! can be extended to the auxiliary variables to satisfy its clauses. Each `LinEq` of the
+//! `LinBank` becomes a XOR clause.
+//!
+//! The DIMACS output uses the `x` lines of CryptoMiniSat for the XOR clauses : `x1 -2 3 0` means
+//! `x_1 + !x_2 + x_3 = 1`, so a XOR clause whose rhs is 0 is written with its first literal negated.
+//!
+//! The other way around, a DIMACS file with XOR clauses can be read into a `Cnf` and turned into
+//! a `System` whose variables are the DIMACS variables minus 1. The clauses are grouped by shared
+//! variables, each group of at most `max_vars` variables becoming a `Bdd` with a level per
+//! variable, while the XOR clauses and the clauses of a single literal go to the `LinBank`.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Write};
+use std::path::PathBuf;
+
+use vob::Vob;
+
+use crate::AHashMap;
+use crate::soc::{
+    bdd::{Bdd, LinEq},
+    Id,
+    system::System,
+    utils::{self, BddSpec, LevelSpec, NodeSpec, SyntaxError},
+};
+
+pub use crate::soc::utils::MAX_VARS;
+
+/// A `System` encoded as clauses and XOR clauses over DIMACS variables (see the module
+/// documentation).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cnf {
+    nvar: usize,
+    nb_vars: usize,
+    clauses: Vec<Vec<isize>>,
+    xors: Vec<(Vec<usize>, bool)>,
+}
+
+/// An error found while parsing DIMACS or building a `System` from a `Cnf`.
+///
+/// Lines and columns start at 1, the clauses and XOR clauses are numbered from 0 in the order of
+/// `iter_clauses` and `iter_xors`.
+#[derive(Debug)]
+pub enum DimacsError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// A line doesn't follow the DIMACS format.
+    Syntax(SyntaxError),
+    /// A literal uses a variable above the number of variables of the header.
+    VarOutOfRange {
+        line: usize,
+        var: usize,
+        nb_vars: usize,
+    },
+    /// The clauses and XOR clauses have no common solution, so the `System` has none.
+    Unsatisfiable {
+        clauses: Vec<usize>,
+        xors: Vec<usize>,
+    },
+    /// The `Cnf` has more variables than `utils::MAX_NVAR`.
+    TooManyVars { nb_vars: usize },
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DimacsError::Io(e) => write!(f, "{}", e),
+            DimacsError::Syntax(e) => write!(f, "{}", e),
+            DimacsError::VarOutOfRange { line, var, nb_vars } => write!(
+                f,
+                "line {}: variable {} is above the {} variables of the header",
+                line, var, nb_vars
+            ),
+            DimacsError::Unsatisfiable { clauses, xors } => write!(
+                f,
+                "the clauses {:?} and the XOR clauses {:?} have no common solution",
+                clauses, xors
+            ),
+            DimacsError::TooManyVars { nb_vars } => write!(
+                f,
+                "{} variables, at most {} are supported",
+                nb_vars,
+                utils::MAX_NVAR
+            ),
+        }
+    }
+}
+
+impl error::Error for DimacsError {}
+
+impl From<io::Error> for DimacsError {
+    fn from(e: io::Error) -> Self {
+        DimacsError::Io(e)
+    }
+}
+
+impl From<DimacsError> for io::Error {
+    fn from(e: DimacsError) -> Self {
+        match e {
+            DimacsError::Io(e) => e,
+            e => io::Error::new(ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+/// The value of the lhs of a level in the encoding.
+#[derive(Clone, Copy)]
+enum Lhs {
+    /// The lhs is the all zero vector.
+    Zero,
+    /// The lhs is equal to this literal.
+    Lit(isize),
+}
+
+impl Cnf {
+    /// Encode all the `Bdd`s and the `LinBank` of `system`.
+    ///
+    /// The `Bdd`s are encoded by increasing id so that encoding the same `System` twice gives the
+    /// same `Cnf`.
+    pub fn from_system(system: &System) -> Cnf {
+        let nvar = system.get_nvar();
+        let mut cnf = Cnf {
+            nvar,
+            nb_vars: nvar,
+            clauses: Vec::new(),
+            xors: Vec::new(),
+        };
+        let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort();
+        for id in ids {
+            cnf.push_bdd(system.get_bdd(id).unwrap());
+        }
+        for lin_eq in system.iter_lin_eqs() {
+            let vars = lin_eq.get_lhs().iter_set_bits(..).map(|var| var + 1).collect();
+            cnf.push_xor(vars, lin_eq.get_rhs());
+        }
+        cnf
+    }
+
+    /// Return the number of variables of the `System`, the DIMACS variables `1..=nvar`.
+    pub fn get_nvar(&self) -> usize {
+        self.nvar
+    }
+
+    /// Return the number of DIMACS variables, including the auxiliary ones.
+    pub fn get_nb_vars(&self) -> usize {
+        self.nb_vars
+    }
+
+    /// Return an iterator over the clauses, as DIMACS literals.
+    pub fn iter_clauses(&self) -> std::slice::Iter<'_, Vec<isize>> {
+        self.clauses.iter()
+    }
+
+    /// Return an iterator over the XOR clauses, as the DIMACS variables summed and the rhs.
+    pub fn iter_xors(&self) -> std::slice::Iter<'_, (Vec<usize>, bool)> {
+        self.xors.iter()
+    }
+
+    /// Write the `Cnf` in the DIMACS format, with `x` lines for the XOR clauses.
+    pub fn write_dimacs<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "c variables 1 to {} are the variables of the system, the others are auxiliary",
+            self.nvar
+        )?;
+        writeln!(
+            writer,
+            "p cnf {} {}",
+            self.nb_vars,
+            self.clauses.len() + self.xors.len()
+        )?;
+        for clause in self.clauses.iter() {
+            for lit in clause.iter() {
+                write!(writer, "{} ", lit)?;
+            }
+            writeln!(writer, "0")?;
+        }
+        for (vars, rhs) in self.xors.iter() {
+            write!(writer, "x")?;
+            for (i, var) in vars.iter().enumerate() {
+                if i == 0 && !rhs {
+                    write!(writer, "-{} ", var)?;
+                } else {
+                    write!(writer, "{} ", var)?;
+                }
+            }
+            writeln!(writer, "0")?;
+        }
+        Ok(())
+    }
+
+    /// Write the `Cnf` in the DIMACS format to a file at `path`.
+    pub fn write_dimacs_to_file(&self, path: &PathBuf) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dimacs(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Map a model of the `Cnf`, as DIMACS literals, back to the variables of the `System`.
+    ///
+    /// The value of `x_j` is the one of the literal of the DIMACS variable `j + 1`, `None` if the
+    /// model doesn't hold it. The auxiliary variables are ignored.
+    pub fn solution(&self, model: &[isize]) -> Vec<Option<bool>> {
+        let mut solution = vec![None; self.nvar];
+        for lit in model.iter() {
+            let var = lit.unsigned_abs();
+            if var >= 1 && var <= self.nvar {
+                solution[var - 1] = Some(*lit > 0);
+            }
+        }
+        solution
+    }
+
+    /// Parse the output of a SAT solver in the format of the SAT competition (an `s` line, and
+    /// `v` lines holding the model when satisfiable), and return the model, `None` if the
+    /// `Cnf` is unsatisfiable.
+    ///
+    /// Returns an `Error` if the output has no `s` line or if a literal can't be parsed.
+    pub fn parse_model(output: &str) -> io::Result<Option<Vec<isize>>> {
+        let mut status = None;
+        let mut model = Vec::new();
+        for line in output.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("s") => status = Some(words.collect::<Vec<_>>().join(" ")),
+                Some("v") => {
+                    for word in words {
+                        let lit: isize = word.parse().map_err(|_| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!("invalid literal \"{}\" in the model", word),
+                            )
+                        })?;
+                        if lit != 0 {
+                            model.push(lit);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        match status.as_deref() {
+            Some("SATISFIABLE") => Ok(Some(model)),
+            Some("UNSATISFIABLE") => Ok(None),
+            Some(status) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown status \"{}\"", status),
+            )),
+            None => Err(Error::new(ErrorKind::InvalidData, "no status line")),
+        }
+    }
+
+    /// Parse DIMACS with `x` lines for the XOR clauses, as written by `write_dimacs`.
+    ///
+    /// A clause may span several lines and the last one may miss its final 0. The comments, the
+    /// number of clauses of the header and anything after a `%` line are ignored. Every DIMACS
+    /// variable is a variable of the `System`, so `nvar` is the number of variables of the header.
+    pub fn parse_dimacs(content: &str) -> Result<Cnf, DimacsError> {
+        let mut cnf: Option<Cnf> = None;
+        // the literals of the clause being read, and whether it's a XOR clause
+        let mut clause: Vec<isize> = Vec::new();
+        let mut xor = false;
+        for (i, line) in content.lines().enumerate() {
+            let syntax = |column: usize, token: &str, expected| {
+                DimacsError::Syntax(SyntaxError {
+                    line: i + 1,
+                    column: column + 1,
+                    token: token.to_string(),
+                    expected,
+                })
+            };
+            let mut tokens = split_tokens(line).into_iter().peekable();
+            match tokens.peek() {
+                None => continue,
+                Some((_, token)) if token.starts_with('c') => continue,
+                Some((_, token)) if token.starts_with('%') => break,
+                Some((column, "p")) => {
+                    let column = *column;
+                    if cnf.is_some() || !clause.is_empty() {
+                        return Err(syntax(column, "p", "a clause"));
+                    }
+                    tokens.next();
+                    match tokens.next() {
+                        Some((_, "cnf")) => (),
+                        Some((column, token)) => return Err(syntax(column, token, "`cnf`")),
+                        None => return Err(syntax(line.len(), "", "`cnf`")),
+                    }
+                    let mut numbers = Vec::new();
+                    for _ in 0..2 {
+                        match tokens.next() {
+                            Some((column, token)) => numbers.push(
+                                token
+                                    .parse::<usize>()
+                                    .map_err(|_| syntax(column, token, "a number"))?,
+                            ),
+                            None => return Err(syntax(line.len(), "", "a number")),
+                        }
+                    }
+                    if let Some((column, token)) = tokens.next() {
+                        return Err(syntax(column, token, "the end of the line"));
+                    }
+                    cnf = Some(Cnf {
+                        nvar: numbers[0],
+                        nb_vars: numbers[0],
+                        clauses: Vec::new(),
+                        xors: Vec::new(),
+                    });
+                    continue;
+                }
+                _ => (),
+            }
+            let cnf = match cnf.as_mut() {
+                Some(cnf) => cnf,
+                None => {
+                    let (column, token) = tokens.next().unwrap();
+                    return Err(syntax(column, token, "the `p cnf` header"));
+                }
+            };
+            for (column, mut token) in tokens {
+                if token.starts_with('x') {
+                    if !clause.is_empty() || xor {
+                        return Err(syntax(column, token, "a literal"));
+                    }
+                    xor = true;
+                    token = &token[1..];
+                    if token.is_empty() {
+                        continue;
+                    }
+                }
+                let lit: isize = token
+                    .parse()
+                    .map_err(|_| syntax(column, token, "a literal"))?;
+                if lit.unsigned_abs() > cnf.nb_vars {
+                    return Err(DimacsError::VarOutOfRange {
+                        line: i + 1,
+                        var: lit.unsigned_abs(),
+                        nb_vars: cnf.nb_vars,
+                    });
+                }
+                if lit != 0 {
+                    clause.push(lit);
+                } else {
+                    cnf.push_parsed(std::mem::take(&mut clause), xor);
+                    xor = false;
+                }
+            }
+        }
+        let mut cnf = cnf.ok_or_else(|| {
+            DimacsError::Syntax(SyntaxError {
+                line: content.lines().count().max(1),
+                column: 1,
+                token: String::new(),
+                expected: "the `p cnf` header",
+            })
+        })?;
+        if !clause.is_empty() || xor {
+            cnf.push_parsed(clause, xor);
+        }
+        Ok(cnf)
+    }
+
+    /// Parse DIMACS from a file at `path` (see `parse_dimacs`).
+    pub fn parse_dimacs_from_file(path: &PathBuf) -> Result<Cnf, DimacsError> {
+        Cnf::parse_dimacs(&fs::read_to_string(path)?)
+    }
+
+    /// Build a `System` whose solutions are the assignments satisfying the `Cnf`, the DIMACS
+    /// variable `j + 1` being the variable `x_j`.
+    ///
+    /// The clauses are grouped so that each group has at most `max_vars` variables (a clause
+    /// with more variables being alone in its group), and each group is built into a `Bdd`
+    /// with `build_bdd_from_spec`. The XOR clauses and the clauses of a single literal are then
+    /// fixed in the `LinBank`.
+    ///
+    /// Returns an `Error` if the `Cnf` has more than `utils::MAX_NVAR` variables, if a group
+    /// of clauses or the linear equations have no solution, or if the built `System` breaks one
+    /// of its invariants.
+    pub fn to_system(&self, max_vars: usize) -> Result<System, DimacsError> {
+        let unsatisfiable = |clauses: Vec<usize>, xors: Vec<usize>| {
+            Err(DimacsError::Unsatisfiable { clauses, xors })
+        };
+        // every lhs holds nb_vars bits, which may come from the header of a file
+        if self.nb_vars > utils::MAX_NVAR {
+            return Err(DimacsError::TooManyVars { nb_vars: self.nb_vars });
+        }
+        let mut system = System::new();
+        system.set_nvar(self.nb_vars);
+        // the clauses without duplicate literals, the ones always satisfied being left empty
+        let mut clauses: Vec<Vec<isize>> = Vec::with_capacity(self.clauses.len());
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if clause.is_empty() {
+                return unsatisfiable(vec![i], Vec::new());
+            }
+            let mut clause = clause.clone();
+            clause.sort_unstable_by_key(|lit| (lit.unsigned_abs(), *lit));
+            clause.dedup();
+            if clause.windows(2).any(|pair| pair[0] == -pair[1]) {
+                clause.clear();
+            }
+            clauses.push(clause);
+        }
+
+        let vars: Vec<Vec<usize>> = clauses
+            .iter()
+            .map(|clause| clause.iter().map(|lit| lit.unsigned_abs() - 1).collect())
+            .collect();
+        let items = (0..clauses.len()).filter(|i| clauses[*i].len() > 1).collect();
+        for (i, (group_vars, group)) in utils::group_by_shared_vars(&vars, items, max_vars)
+            .into_iter()
+            .enumerate()
+        {
+            let group_clauses: Vec<Vec<isize>> =
+                group.iter().map(|c| clauses[*c].clone()).collect();
+            let mut spec = match clauses_to_bdd_spec(Id::new(i), &group_vars, &group_clauses) {
+                Some(spec) => spec,
+                None => return unsatisfiable(group, Vec::new()),
+            };
+            system.push_bdd(utils::build_bdd_from_spec(&mut spec, self.nb_vars))?;
+        }
+
+        let units = (0..clauses.len())
+            .filter(|i| clauses[*i].len() == 1)
+            .map(|i| (vec![i], Vec::new(), vec![clauses[i][0].unsigned_abs()], clauses[i][0] > 0));
+        let xors = self
+            .xors
+            .iter()
+            .enumerate()
+            .map(|(i, (vars, rhs))| (Vec::new(), vec![i], vars.clone(), *rhs));
+        for (clause, xor, vars, rhs) in units.chain(xors) {
+            let mut lhs = Vob::from_elem(self.nb_vars, false);
+            for var in vars.iter() {
+                lhs.set(var - 1, !lhs[var - 1]);
+            }
+            let reduced = system.reduce_lin_eq(LinEq::new(lhs.clone(), rhs));
+            match (reduced.get_lhs().iter_set_bits(..).next(), reduced.get_rhs()) {
+                (Some(_), _) => system.fix(lhs.iter_set_bits(..).collect(), rhs)?,
+                (None, false) => (),
+                (None, true) => return unsatisfiable(clause, xor),
+            }
+        }
+        system.check_invariants()?;
+        Ok(system)
+    }
+
+    /// Push a parsed clause, a negated literal flipping the rhs of a XOR clause.
+    fn push_parsed(&mut self, clause: Vec<isize>, xor: bool) {
+        if !xor {
+            self.clauses.push(clause);
+            return;
+        }
+        let rhs = clause.iter().filter(|lit| **lit < 0).count() % 2 == 0;
+        let mut vars: Vec<usize> = clause.iter().map(|lit| lit.unsigned_abs()).collect();
+        vars.sort_unstable();
+        // x + x = 0
+        let mut reduced: Vec<usize> = Vec::with_capacity(vars.len());
+        for var in vars {
+            if reduced.last() == Some(&var) {
+                reduced.pop();
+            } else {
+                reduced.push(var);
+            }
+        }
+        self.push_xor(reduced, rhs);
+    }
+
+    /// Return a new auxiliary variable.
+    fn new_var(&mut self) -> usize {
+        self.nb_vars += 1;
+        self.nb_vars
+    }
+
+    /// Push a XOR clause, an empty one being either always satisfied or never.
+    fn push_xor(&mut self, vars: Vec<usize>, rhs: bool) {
+        match (vars.is_empty(), rhs) {
+            (true, false) => (),
+            (true, true) => self.clauses.push(Vec::new()),
+            (false, _) => self.xors.push((vars, rhs)),
+        }
+    }
+
+    /// Push the clauses encoding `bdd`.
+    fn push_bdd(&mut self, bdd: &Bdd) {
+        let sink = bdd.get_sink_level_index();
+        let mut levels = bdd.iter_levels();
+        let source = match levels.next() {
+            Some(source) if sink > 0 => source,
+            _ => return,
+        };
+        // the variables of the nodes of the current level
+        let mut reached: AHashMap<Id, isize> = AHashMap::default();
+        for (id, _) in source.iter_nodes() {
+            let r = self.new_var() as isize;
+            self.clauses.push(vec![r]);
+            reached.insert(id, r);
+        }
+        let mut level = source;
+        for (i, below) in levels.enumerate() {
+            let vars: Vec<usize> = level.iter_set_lhs().map(|var| var + 1).collect();
+            let lhs = match vars.len() {
+                0 => Lhs::Zero,
+                1 => Lhs::Lit(vars[0] as isize),
+                _ => {
+                    let y = self.new_var();
+                    let mut vars = vars;
+                    vars.push(y);
+                    self.push_xor(vars, false);
+                    Lhs::Lit(y as isize)
+                }
+            };
+            let mut reached_below = AHashMap::default();
+            if i + 1 < sink {
+                for (id, _) in below.iter_nodes() {
+                    reached_below.insert(id, self.new_var() as isize);
+                }
+            }
+            for (id, node) in level.iter_nodes() {
+                let r = reached[&id];
+                for (edge, child) in [(false, node.get_e0()), (true, node.get_e1())].iter() {
+                    // the literal which is true when the edge is not taken
+                    let not_taken = match (lhs, edge) {
+                        (Lhs::Zero, false) => None,
+                        (Lhs::Zero, true) => continue,
+                        (Lhs::Lit(y), false) => Some(y),
+                        (Lhs::Lit(y), true) => Some(-y),
+                    };
+                    let mut clause = vec![-r];
+                    clause.extend(not_taken);
+                    match child {
+                        Some(_) if i + 1 == sink => continue,
+                        Some(child) => clause.push(reached_below[child]),
+                        None => (),
+                    }
+                    self.clauses.push(clause);
+                }
+            }
+            reached = reached_below;
+            level = below;
+        }
+    }
+}
+
+/// Split `line` on whitespaces, returning the tokens with their column (from 0).
+fn split_tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push((s, &line[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+    tokens
+}
+
+/// Return the spec of a `Bdd` whose paths are the assignments of `vars` satisfying `clauses`,
+/// with a level per variable, or `None` if no assignment satisfies them. The variables of the
+/// clauses must be in `vars`, sorted.
+///
+/// The nodes are the sets of clauses left to satisfy once the variables above are assigned, a
+/// node whose clauses are all satisfied being the sink. The sets are found level by level from
+/// the source, then the nodes are built from the sink up, leaving out the ones without edge.
+fn clauses_to_bdd_spec(id: Id, vars: &[usize], clauses: &[Vec<isize>]) -> Option<BddSpec> {
+    // the level of the last variable of each clause
+    let last: Vec<usize> = clauses
+        .iter()
+        .map(|clause| {
+            let var = clause.iter().map(|lit| lit.unsigned_abs() - 1).max().unwrap();
+            vars.binary_search(&var).unwrap()
+        })
+        .collect();
+    // the edges of the nodes of each level, a node being an index in the level below
+    let mut edges: Vec<Vec<[Edge; 2]>> = Vec::with_capacity(vars.len());
+    let mut pending: Vec<Vec<usize>> = vec![(0..clauses.len()).collect()];
+    for (level, var) in vars.iter().enumerate() {
+        let lit = *var as isize + 1;
+        let mut below: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut pending_below = Vec::new();
+        let mut level_edges = Vec::with_capacity(pending.len());
+        for clauses_left in pending.iter() {
+            let mut node_edges = [Edge::Missing, Edge::Missing];
+            for (edge, lit) in node_edges.iter_mut().zip([-lit, lit].iter()) {
+                let left: Vec<usize> = clauses_left
+                    .iter()
+                    .filter(|c| !clauses[**c].contains(lit))
+                    .cloned()
+                    .collect();
+                *edge = if left.iter().any(|c| last[*c] == level) {
+                    Edge::Missing
+                } else if left.is_empty() {
+                    Edge::Sink
+                } else {
+                    let next = pending_below.len();
+                    let node = *below.entry(left.clone()).or_insert(next);
+                    if node == next {
+                        pending_below.push(left);
+                    }
+                    Edge::Node(node)
+                };
+            }
+            level_edges.push(node_edges);
+        }
+        edges.push(level_edges);
+        pending = pending_below;
+    }
+
+    let sink = Id::new(1);
+    let mut next = 2;
+    let mut levels: Vec<LevelSpec> = Vec::with_capacity(vars.len() + 1);
+    levels.push(LevelSpec::new(
+        Vec::new(),
+        vec![NodeSpec::new(sink, Id::new(0), Id::new(0))],
+    ));
+    // the id of each node of the level below, `None` if it has no edge
+    let mut ids_below: Vec<Option<Id>> = Vec::new();
+    for (level_edges, var) in edges.iter().zip(vars.iter()).rev() {
+        let mut nodes = Vec::new();
+        let ids: Vec<Option<Id>> = level_edges
+            .iter()
+            .map(|node_edges| {
+                let [e0, e1] = node_edges.map(|edge| match edge {
+                    Edge::Missing => None,
+                    Edge::Sink => Some(sink),
+                    Edge::Node(node) => ids_below[node],
+                });
+                if e0.is_none() && e1.is_none() {
+                    return None;
+                }
+                let id = Id::new(next);
+                next += 1;
+                let none = Id::new(0);
+                nodes.push(NodeSpec::new(id, e0.unwrap_or(none), e1.unwrap_or(none)));
+                Some(id)
+            })
+            .collect();
+        levels.push(LevelSpec::new(vec![*var as i64], nodes));
+        ids_below = ids;
+    }
+    // no assignment satisfies the clauses if the source has no edge
+    ids_below.first().cloned().flatten()?;
+    levels.reverse();
+    Some(BddSpec::new(id, levels))
+}
+
+/// An edge of a node of `clauses_to_bdd_spec` while its levels are found.
+#[derive(Clone, Copy)]
+enum Edge {
+    /// A clause can't be satisfied along the edge.
+    Missing,
+    /// All the clauses are satisfied along the edge.
+    Sink,
+    /// The edge points to the node of this index in the level below.
+    Node(usize),
+}
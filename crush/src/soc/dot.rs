@@ -0,0 +1,322 @@
+//! Graphviz rendering of a whole `System`, with annotations.
+//!
+//! `utils::print_bdd_to_dot_format` draws a single `Bdd` with anonymous nodes. A `Dot` draws every
+//! `Bdd` of a `System` in its own cluster, and can annotate the drawing :
+//! - the id of each node, and the number of paths from it to the sink (see `Bdd::path_weights`)
+//! - a colour per node from a weight, like the lowest differential weight of the trails going
+//!   through it, from red for the lowest weight to blue for the highest
+//! - a path of a `Bdd`, given by the edges taken from the source, drawn in bold
+//! - a window of levels, the levels outside of it not being drawn, so that large `Bdd`s stay
+//!   viewable. The edges leaving the window end on a `...` node.
+//!
+//! With the `differential` feature, the weights can be taken from an `NWArena` or a `WDLevel`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use crate::soc::{bdd::Bdd, symbols::SymbolTable, system::System, Id};
+use crate::AHashMap;
+
+/// The options of a drawing (see the module documentation), nothing but the `Bdd`s being drawn by
+/// default.
+#[derive(Debug, Clone, Default)]
+pub struct Dot {
+    node_ids: bool,
+    path_counts: bool,
+    /// For each `Bdd`, the weight of the nodes of each level.
+    weights: AHashMap<Id, BTreeMap<usize, AHashMap<Id, u32>>>,
+    /// For each `Bdd`, the edges of the path to highlight.
+    paths: AHashMap<Id, Vec<bool>>,
+    window: Option<Range<usize>>,
+}
+
+impl Dot {
+    /// Construct a `Dot` with the default options.
+    pub fn new() -> Dot {
+        Default::default()
+    }
+
+    /// Label each node with its id, which is local to its level.
+    pub fn show_node_ids(&mut self, show: bool) {
+        self.node_ids = show;
+    }
+
+    /// Label each node with the number of paths from it to the sink.
+    pub fn show_path_counts(&mut self, show: bool) {
+        self.path_counts = show;
+    }
+
+    /// Colour the nodes of the level `level` of the `Bdd` `bdd` by their weight. The colours go
+    /// from red to blue between the lowest and the highest weight of the levels drawn, the nodes
+    /// without weight being left uncoloured.
+    pub fn set_weights<I: IntoIterator<Item = (Id, u32)>>(&mut self, bdd: Id, level: usize, weights: I) {
+        self.weights
+            .entry(bdd)
+            .or_default()
+            .entry(level)
+            .or_default()
+            .extend(weights);
+    }
+
+    /// Highlight a path of the `Bdd` `bdd`, `edges` being the edges taken from the source (for
+    /// a path given as `LinEq`s, their rhs). The path is highlighted as long as it exists.
+    pub fn set_path(&mut self, bdd: Id, edges: Vec<bool>) {
+        self.paths.insert(bdd, edges);
+    }
+
+    /// Only draw the levels in `levels`, the sink being the level after the last level.
+    pub fn set_window(&mut self, levels: Range<usize>) {
+        self.window = Some(levels);
+    }
+
+    /// Write the .dot representation of `bdd` to `writer`, the variables being named by
+    /// `symbols` if any.
+    pub fn write_bdd<W: Write>(
+        &self,
+        bdd: &Bdd,
+        symbols: Option<&SymbolTable>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.write_header(writer)?;
+        self.write_cluster(bdd, symbols.unwrap_or(&SymbolTable::new()), writer)?;
+        writeln!(writer, "}}")
+    }
+
+    /// Write the .dot representation of all the `Bdd`s of `system` to `writer`, by increasing id,
+    /// the variables being named by the symbols of the `System` if it has some.
+    pub fn write_system<W: Write>(&self, system: &System, writer: &mut W) -> io::Result<()> {
+        let unnamed = SymbolTable::new();
+        let symbols = system.get_symbols().unwrap_or(&unnamed);
+        let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort();
+        self.write_header(writer)?;
+        for id in ids {
+            self.write_cluster(system.get_bdd(id)?, symbols, writer)?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Write the .dot representation of `system` to the file at `path` (see `write_system`).
+    pub fn write_system_to_file(&self, system: &System, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_system(system, &mut writer)?;
+        writer.flush()
+    }
+
+    /// Draw `system` as a PDF at `path` with its extension set to pdf, using GraphViz, and return
+    /// the handle of the GraphViz process, like `utils::draw_shard_as_pdf` : wait on it before
+    /// exiting.
+    ///
+    /// Returns an `Error` if GraphViz can't be started.
+    pub fn draw_system_as_pdf(&self, system: &System, path: &Path) -> io::Result<Child> {
+        let out_path = format!("-o{}", path.with_extension("pdf").display());
+        let mut dot = Command::new("dot")
+            .args(["-Tpdf", &out_path])
+            .stdin(Stdio::piped())
+            .spawn()?;
+        {
+            let child_in = dot.stdin.take().expect("Child stdin not captured");
+            let mut writer = BufWriter::new(child_in);
+            self.write_system(system, &mut writer)?;
+            writer.flush()?;
+            // closing stdin lets GraphViz start drawing
+        }
+        Ok(dot)
+    }
+
+    fn write_header<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "digraph \"DD\" {{")?;
+        writeln!(writer, "center = true;")?;
+        writeln!(writer, "edge [dir = none];")
+    }
+
+    /// Write `bdd` as a cluster, its nodes being named `b<bdd>_<level>_<id>`.
+    fn write_cluster<W: Write>(
+        &self,
+        bdd: &Bdd,
+        symbols: &SymbolTable,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let bdd_id = bdd.get_id();
+        let sink = bdd.get_sink_level_index();
+        let window = self.window.clone().unwrap_or(0..sink + 1);
+        let start = window.start.min(sink);
+        let end = window.end.min(sink + 1).max(start + 1);
+        let counts = if self.path_counts {
+            Some(bdd.path_weights())
+        } else {
+            None
+        };
+        let path = self.path(bdd);
+        let weights = self.weights.get(&bdd_id);
+        let weight_of = |level: usize, id: Id| -> Option<u32> {
+            weights
+                .and_then(|levels| levels.get(&level))
+                .and_then(|nodes| nodes.get(&id))
+                .cloned()
+        };
+        let in_window = weights
+            .map(|levels| levels.range(start..end).flat_map(|(_, nodes)| nodes.values()))
+            .into_iter()
+            .flatten();
+        let (lowest, highest) = in_window.fold((u32::MAX, 0), |(lowest, highest), weight| {
+            (lowest.min(*weight), highest.max(*weight))
+        });
+        let name = |level: usize, id: Id| format!("\"b{}_{}_{}\"", bdd_id, level, id);
+
+        writeln!(writer, "subgraph \"cluster_{}\" {{", bdd_id)?;
+        writeln!(writer, "label = \"bdd {}\";", bdd_id)?;
+        // the lhs of the levels, one above the other
+        for i in start..end {
+            let lhs = if i == sink {
+                "sink".to_string()
+            } else {
+                symbols.format_lhs(&bdd.level(i).unwrap().get_lhs())
+            };
+            writeln!(writer, "\"b{}_l{}\" [shape = plaintext; label = \"{}. {}\"];", bdd_id, i, i, lhs)?;
+            if i > start {
+                writeln!(writer, "\"b{0}_l{1}\" -> \"b{0}_l{2}\" [style = invis];", bdd_id, i - 1, i)?;
+            }
+        }
+        // the nodes, on the rank of their lhs
+        for i in start..end {
+            write!(writer, "{{ rank = same; \"b{}_l{}\";", bdd_id, i)?;
+            for (id, _) in bdd.level(i).unwrap().iter_nodes() {
+                let mut labels = Vec::new();
+                if self.node_ids {
+                    labels.push(id.to_string());
+                }
+                if let Some(counts) = &counts {
+                    labels.push(counts[i][*id].to_string());
+                }
+                let mut attributes = if i == sink {
+                    vec!["shape = box".to_string(), "label = \"T\"".to_string()]
+                } else if labels.is_empty() {
+                    vec!["shape = point".to_string(), "label = \"\"".to_string(), "width = 0.1".to_string()]
+                } else {
+                    vec![format!("label = \"{}\"", labels.join("\\n"))]
+                };
+                if let Some(weight) = weight_of(i, id) {
+                    let hue = if highest > lowest {
+                        0.66 * (weight - lowest) as f64 / (highest - lowest) as f64
+                    } else {
+                        0.0
+                    };
+                    attributes.push(format!(
+                        "style = filled; fillcolor = \"{:.3} 1.000 1.000\"; tooltip = \"weight {}\"",
+                        hue, weight
+                    ));
+                }
+                if path.nodes.contains(&(i, id)) {
+                    attributes.push("color = red; penwidth = 3".to_string());
+                }
+                write!(writer, " {} [{}];", name(i, id), attributes.join("; "))?;
+            }
+            writeln!(writer, " }}")?;
+        }
+        // the edges, those leaving the window ending on a single node
+        let mut cut = false;
+        for i in start..end.min(sink) {
+            for (id, node) in bdd.level(i).unwrap().iter_nodes() {
+                for (edge, child) in [(false, node.get_e0()), (true, node.get_e1())].iter() {
+                    let child = match child {
+                        Some(child) => *child,
+                        None => continue,
+                    };
+                    let target = if i + 1 < end {
+                        name(i + 1, child)
+                    } else {
+                        cut = true;
+                        format!("\"b{}_cut\"", bdd_id)
+                    };
+                    let mut attributes = Vec::new();
+                    if !edge {
+                        attributes.push("style = dashed");
+                    }
+                    if path.edges.contains(&(i, id, *edge)) {
+                        attributes.push("color = red; penwidth = 3");
+                    }
+                    writeln!(writer, "{} -> {} [{}];", name(i, id), target, attributes.join("; "))?;
+                }
+            }
+        }
+        if cut {
+            writeln!(writer, "\"b{}_cut\" [shape = plaintext; label = \"...\"];", bdd_id)?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Return the nodes and the edges of the path to highlight in `bdd`, if any.
+    fn path(&self, bdd: &Bdd) -> Highlight {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let path = match self.paths.get(&bdd.get_id()) {
+            Some(path) => path,
+            None => return Highlight { nodes, edges },
+        };
+        let mut current = bdd.level(0).and_then(|level| level.iter_nodes().next());
+        for (i, edge) in path.iter().enumerate() {
+            let (id, node) = match current {
+                Some(current) => current,
+                None => break,
+            };
+            nodes.push((i, id));
+            let child = if *edge { node.get_e1() } else { node.get_e0() };
+            current = match (child, bdd.level(i + 1)) {
+                (Some(child), Some(level)) => {
+                    edges.push((i, id, *edge));
+                    level.iter_nodes().find(|(id, _)| *id == child)
+                }
+                _ => None,
+            };
+        }
+        if let Some((id, _)) = current {
+            nodes.push((path.len(), id));
+        }
+        Highlight { nodes, edges }
+    }
+}
+
+/// The nodes and edges of a highlighted path.
+struct Highlight {
+    /// The nodes, as (level, id).
+    nodes: Vec<(usize, Id)>,
+    /// The edges, as (level, id of the parent, edge).
+    edges: Vec<(usize, Id, bool)>,
+}
+
+#[cfg(feature = "differential")]
+mod differential {
+    use super::Dot;
+    use crate::soc::bdd::differential::wd::{NWDistribution, WDLevel};
+    use crate::soc::bdd::differential::NWArena;
+    use crate::soc::Id;
+
+    impl Dot {
+        /// Colour the nodes of the `Bdd` `bdd` by the lowest weight of the trails going through
+        /// them in `arena` (see `set_weights`).
+        pub fn set_arena_weights(&mut self, bdd: Id, arena: &NWArena) {
+            for (depth, level) in arena.iter_levels() {
+                // the bit w of a weight is set if a trail of weight w goes through the node
+                let weights = level
+                    .iter()
+                    .filter(|(_, weights)| **weights != 0)
+                    .map(|(id, weights)| (*id, weights.trailing_zeros()));
+                self.set_weights(bdd, *depth, weights);
+            }
+        }
+
+        /// Colour the nodes of the level `depth` of the `Bdd` `bdd` by their lowest existing
+        /// weight in `level` (see `set_weights`).
+        pub fn set_level_weights<W: NWDistribution>(&mut self, bdd: Id, depth: usize, level: &WDLevel<W>) {
+            let weights = level
+                .iter()
+                .map(|(id, dist)| (*id, dist.lowest_existing_weight()));
+            self.set_weights(bdd, depth, weights);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Module estimating the cost of resolving a dependency without mutating the `System`.
+//!
+//! Resolving a dependency joins the `Bdd`s involved, adds its levels one into the other until a
+//! 0-level is left and absorbs it (see `Solver::resolve`). Joining doesn't create any node, the
+//! nodes come from the swaps and the adds, each of which may double the size of a level.
+//!
+//! `System::estimate_resolution` replays those operations on a copy of the `Bdd`s involved as
+//! long as it holds at most a given number of nodes, which gives the exact number of nodes.
+//! Past this limit, the number of nodes of each level is bounded instead : swapping the level `i`
+//! with the level `i + 1`, or adding it to the level `i + 1`, gives a level `i + 1` with at most
+//! two nodes per node of the level `i`, and at most one node per pair of children taken from the
+//! level `i + 2` (a missing edge counting as a child).
+
+use std::io::Error;
+
+use crate::soc::{bdd::Bdd, system::System, Id};
+
+/// The number of nodes of the `Bdd` resolving a dependency, at each stage of the resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    /// The number of nodes once the `Bdd`s involved are joined, which is always exact.
+    pub joined: usize,
+    /// The highest number of nodes while swapping and adding the levels.
+    pub peak: usize,
+    /// The number of nodes once the 0-level is absorbed.
+    pub resolved: usize,
+    /// True if `peak` and `resolved` are the numbers of nodes the resolution will produce, false
+    /// if they are upper bounds.
+    pub exact: bool,
+}
+
+/// An operation of the resolution between the level `i` and the level `i + 1` of the joined `Bdd`.
+#[derive(Clone, Copy)]
+enum Step {
+    Swap(usize),
+    Add(usize),
+}
+
+impl System {
+    /// Estimate the number of nodes of the `Bdd` resolving the dependency of `join_order`, as
+    /// returned by `Dependency::best_join_order`, without mutating the `System` (see the module
+    /// documentation). The operations are replayed while the `Bdd` holds at most `limit` nodes,
+    /// a `limit` of 0 only bounds the numbers of nodes.
+    ///
+    /// `resolved` is 0 if the 0-level shows that the `System` has no solution.
+    ///
+    /// Returns an `Error` if a `Bdd` of `join_order` is not in the `System`.
+    pub fn estimate_resolution(
+        &self,
+        join_order: &(Vec<Id>, Vec<usize>),
+        limit: usize,
+    ) -> Result<Estimate, Error> {
+        let bdds = join_order
+            .0
+            .iter()
+            .map(|id| self.get_bdd(*id))
+            .collect::<Result<Vec<&Bdd>, Error>>()?;
+        let levels = &join_order.1;
+        let mut widths: Vec<usize> = bdds
+            .iter()
+            .flat_map(|bdd| {
+                let sink = bdd.get_sink_level_index();
+                bdd.iter_levels().take(sink).map(|level| level.get_nodes_len())
+            })
+            .collect();
+        widths.push(1);
+        let joined: usize = widths.iter().sum();
+        let mut replayed = if joined <= limit {
+            Some(join_copies(&bdds))
+        } else {
+            None
+        };
+        let mut peak = joined;
+        for step in resolution_steps(levels) {
+            let size = match replayed.as_mut() {
+                Some(bdd) => {
+                    match step {
+                        Step::Swap(above) => bdd.swap(above, above + 1),
+                        Step::Add(above) => bdd.add(above, above + 1),
+                    }
+                    bdd.get_size()
+                }
+                None => {
+                    let above = match step {
+                        Step::Swap(above) | Step::Add(above) => above,
+                    };
+                    bound_level(&mut widths, above);
+                    widths.iter().fold(0, |acc: usize, width| acc.saturating_add(*width))
+                }
+            };
+            peak = peak.max(size);
+            if size > limit {
+                if let Some(bdd) = replayed.take() {
+                    widths = bdd.iter_levels().map(|level| level.get_nodes_len()).collect();
+                }
+            }
+        }
+        // the last level added to is the 0-level
+        let zero = if levels.len() > 1 { levels[0] + 1 } else { levels[0] };
+        let exact = replayed.is_some();
+        let resolved = match replayed.as_mut() {
+            Some(bdd) => {
+                if bdd.try_absorb(zero, false) {
+                    bdd.get_size()
+                } else {
+                    0
+                }
+            }
+            None => {
+                widths.remove(zero);
+                widths.iter().fold(0, |acc: usize, width| acc.saturating_add(*width))
+            }
+        };
+        Ok(Estimate {
+            joined,
+            peak,
+            resolved,
+            exact,
+        })
+    }
+}
+
+/// Join copies of `bdds`, like `System::join_bdds`.
+fn join_copies(bdds: &[&Bdd]) -> Bdd {
+    let mut joined = bdds[0].clone();
+    for bdd in bdds.iter().skip(1) {
+        let sink_level_id = joined.get_sink_level_index();
+        for level in Bdd::clone(bdd).drain_levels() {
+            joined.add_existing_level(level);
+        }
+        joined.merge_sink_source(sink_level_id);
+    }
+    joined
+}
+
+/// Return the operations resolving the dependency of the levels `levels` of the joined `Bdd`,
+/// in the order of `Solver::resolve`.
+fn resolution_steps(levels: &[usize]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for i in (0..levels.len() - 1).rev() {
+        for j in (levels[i] + 1..levels[i + 1]).rev() {
+            steps.push(Step::Swap(j));
+        }
+        steps.push(Step::Add(levels[i]));
+        if i != 0 {
+            steps.push(Step::Swap(levels[i]));
+        }
+    }
+    steps
+}
+
+/// Bound the number of nodes of the level below `above` once `above` is swapped with it or added
+/// to it, the other levels keeping at most their number of nodes.
+fn bound_level(widths: &mut [usize], above: usize) {
+    let children = widths[above + 2].saturating_add(1);
+    let pairs = children.saturating_mul(children) - 1;
+    widths[above + 1] = widths[above].saturating_mul(2).min(pairs);
+}
@@ -6,24 +6,29 @@
 //!
 //! x1 + x3 + x5 in a 7 variables system would be stored as [0101010]
 //!
-//! The nodes are a stored as a `AHasmap` of `Node` with the `Id` of a node as its key.
-//! All ids are supposed to be unique in the entirity of the system. The Hashmap uses
-//! AHash as its default hasher for speedup over SipHash.
+//! The nodes are stored in a vector of slots and the `Id` of a node is the index of its slot,
+//! so following an edge is an index in the vector of the level below. Ids are therefore
+//! local to a level: the same id is used by nodes of different levels.
+//!
+//! Removing a node empties its slot without moving the other nodes, so the ids of the remaining
+//! nodes stay valid and an edge pointing to a removed node can still be detected (`get_node`
+//! returns `None`). Empty slots are never reused, `compact` removes them and returns the mapping
+//! from the old ids to the new ones, which must be applied to the edges of the level above
+//! with `remap_edges`.
 
 extern crate vob;
 
-use std::collections::hash_map::{Iter, IterMut};
 use std::fmt;
 
 use vob::{IterSetBits, Vob};
 
-use crate::{AHashMap, AHashSet};
 use crate::soc::{Id, node::Node};
 
 /// A level inside a Binary Decision Diagram
 #[derive(Default, Clone)]
 pub struct Level {
-    nodes: AHashMap<Id, Node>,
+    nodes: Vec<Option<Node>>,
+    len: usize,
     lhs: Vob,
 }
 
@@ -95,98 +100,150 @@ impl Level {
             .expect("attempt to access a var outside of lhs")
     }
 
-    /// Return an `Iterator` over `nodes`.
+    /// Return an `Iterator` over the ids and the nodes of the level.
     #[inline]
-    pub fn iter_nodes(&self) -> Iter<Id, Node> {
-        self.nodes.iter()
+    pub fn iter_nodes(&self) -> impl Iterator<Item = (Id, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| node.as_ref().map(|node| (Id::new(i), node)))
     }
 
-    /// Return an `Iterator` over `nodes`.
+    /// Return an `Iterator` over the ids and the mutable nodes of the level.
     #[inline]
-    pub fn iter_mut_nodes(&mut self) -> IterMut<Id, Node> {
-        self.nodes.iter_mut()
+    pub fn iter_mut_nodes(&mut self) -> impl Iterator<Item = (Id, &mut Node)> {
+        self.nodes
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, node)| node.as_mut().map(|node| (Id::new(i), node)))
     }
 
-    /// Get ref to the map of nodes
+    /// Return an `Iterator` over the ids of the nodes of the level.
     #[inline]
-    pub fn get_nodes(&self) -> &AHashMap<Id, Node> {
-        &self.nodes
+    pub fn iter_ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.iter_nodes().map(|(id, _)| id)
     }
 
-    /// Get a mutable ref to the map of nodes
+    /// Returns a reference to the node
     #[inline]
-    pub fn get_mut_nodes(&mut self) -> &mut AHashMap<Id, Node> {
-        &mut self.nodes
+    pub fn get_node(&self, node_id: &Id) -> Option<&Node> {
+        self.nodes.get(**node_id).and_then(Option::as_ref)
     }
 
-    /// Returns a reference to the node
-    pub fn get_node(&self, node_id: &Id) -> Option<&Node> {
-        self.nodes.get(node_id)
+    /// Returns a mutable reference to the node
+    #[inline]
+    pub fn get_mut_node(&mut self, node_id: &Id) -> Option<&mut Node> {
+        self.nodes.get_mut(**node_id).and_then(Option::as_mut)
     }
 
     /// Get the number of nodes of the level.
     #[inline]
     pub fn get_nodes_len(&self) -> usize {
+        self.len
+    }
+
+    /// Return the number of slots of the level, i.e. one more than the highest id in use
+    /// (removed nodes included until the level is compacted).
+    #[inline]
+    pub fn get_slots_len(&self) -> usize {
         self.nodes.len()
     }
 
-    /// Add a new `node` in the level with its `id` set at `n_id` and edges set to e0 and e1.
-    pub fn add_edged_node(&mut self, n_id: Id, e0: Option<Id>, e1: Option<Id>) {
-        let n = Node::with_edges(e0, e1);
-        self.nodes.insert(n_id, n);
+    /// Add a new `node` in the level with its edges set to e0 and e1 and return its id.
+    pub fn add_edged_node(&mut self, e0: Option<Id>, e1: Option<Id>) -> Id {
+        self.nodes.push(Some(Node::with_edges(e0, e1)));
+        self.len += 1;
+        Id::new(self.nodes.len() - 1)
     }
 
-    /// Add a new `node` in the level with its `id` set at `n_id` and edges set to `None`.
-    pub fn add_new_node(&mut self, n_id: Id) {
-        let n = Node::new();
-        self.nodes.insert(n_id, n);
+    /// Add a new `node` in the level with its edges set to `None` and return its id.
+    pub fn add_new_node(&mut self) -> Id {
+        self.add_edged_node(None, None)
     }
 
-    /// Replace `nodes` by the given `AHashMap` of nodes and resize it to reduce
-    /// its memory footprint. We assume that no node will be insert after
+    /// Replace `nodes` by the given nodes, the id of a node being its index, and shrink the
+    /// storage to reduce its memory footprint. We assume that no node will be insert after
     /// replacing the nodes hence the shrinking.
-    pub fn replace_nodes(&mut self, nodes: AHashMap<Id, Node>) {
-        self.nodes = nodes;
+    pub fn replace_nodes(&mut self, nodes: Vec<Node>) {
+        self.len = nodes.len();
+        self.nodes = nodes.into_iter().map(Some).collect();
         self.nodes.shrink_to_fit();
     }
 
-    /// Remove any node not present in parents and insert in parents the edges of the remaining nodes
+    /// Remove any node not marked in `reached` and mark in `reached` the children of the remaining
+    /// nodes.
+    ///
+    /// When called, `reached[id]` should be true if the node `id` has a parent. When returning,
+    /// `reached` holds the same information for the nodes of the level below.
     /// Return true if at least a node was removed
-    pub fn remove_orphans(&mut self, parents: &mut AHashSet<Id>) -> bool {
-        let len = self.nodes.len();
-        let mut to_remove = AHashSet::with_capacity_and_hasher(len, Default::default());
-        self.nodes.iter().for_each(|(id, node)| {
-            if parents.remove(id) {
-                if let Some(e0) = node.get_e0() {
-                    parents.insert(e0);
+    pub fn remove_orphans(&mut self, reached: &mut Vec<bool>) -> bool {
+        let len = self.len;
+        let mut below = Vec::new();
+        let mut mark = |edge: Option<Id>| {
+            if let Some(edge) = edge {
+                if below.len() <= *edge {
+                    below.resize(*edge + 1, false);
                 }
-                if let Some(e1) = node.get_e1() {
-                    parents.insert(e1);
+                below[*edge] = true;
+            }
+        };
+        for (i, slot) in self.nodes.iter_mut().enumerate() {
+            if let Some(node) = slot {
+                if reached.get(i).cloned().unwrap_or(false) {
+                    mark(node.get_e0());
+                    mark(node.get_e1());
+                } else {
+                    *slot = None;
+                    self.len -= 1;
                 }
-            } else {
-                to_remove.insert(*id);
             }
-        });
-        self.remove_nodes_from_set(&to_remove);
-        len > self.nodes.len()
+        }
+        *reached = below;
+        len > self.len
     }
 
-    /// Remove all nodes which ids are in the keys of the provided map
-    pub fn remove_nodes_from_map(&mut self, map: &AHashMap<Id, Id>) {
-        map.keys().for_each(|key| {
-            self.nodes.remove(key);
-        });
+    /// Remove a single node who has the provided Id and return it
+    pub fn remove_node(&mut self, to_remove: Id) -> Option<Node> {
+        let node = self.nodes.get_mut(*to_remove).and_then(Option::take);
+        if node.is_some() {
+            self.len -= 1;
+        }
+        node
     }
 
-    /// Remove all nodes which ids are in the provided set
-    pub fn remove_nodes_from_set(&mut self, map: &AHashSet<Id>) {
-        map.iter().for_each(|key| {
-            self.nodes.remove(key);
-        });
+    /// Point the edges of all nodes to the ids given by `map`: an edge to `id` becomes an edge
+    /// to `map[id]`, and is disconnected if `map[id]` is `None` or if `id` is out of `map`.
+    pub fn remap_edges(&mut self, map: &[Option<Id>]) {
+        let remap = |edge: Option<Id>| edge.and_then(|edge| map.get(*edge).cloned().flatten());
+        for (_, node) in self.iter_mut_nodes() {
+            let (e0, e1) = (remap(node.get_e0()), remap(node.get_e1()));
+            *node = Node::with_edges(e0, e1);
+        }
     }
-    /// Remove a single node who has the provided Id
-    pub fn remove_node(&mut self, to_remove: Id) {
-        self.nodes.remove(&to_remove);
+
+    /// Remove the slots of the removed nodes, giving new ids to the remaining nodes (their
+    /// order is kept).
+    ///
+    /// Return `None` if there was nothing to remove, or else the mapping from the old ids to the new
+    /// ones to give to `remap_edges` on the level above.
+    pub fn compact(&mut self) -> Option<Vec<Option<Id>>> {
+        if self.len == self.nodes.len() {
+            return None;
+        }
+        let mut next = 0;
+        let map = self
+            .nodes
+            .iter()
+            .map(|slot| {
+                slot.as_ref().map(|_| {
+                    next += 1;
+                    Id::new(next - 1)
+                })
+            })
+            .collect();
+        self.nodes.retain(Option::is_some);
+        self.nodes.shrink_to_fit();
+        Some(map)
     }
 
     /// Check if `nodes` has at least one node with `e0` pointing to a valid `node` and one node
@@ -198,11 +255,11 @@ impl Level {
     /// Short-circuited (will exit as soon as both type of edge has been found to avoid iterating the whole level).
     pub fn check_outgoing_edges(&self) -> (bool, bool) {
         let (mut has_zero_edge, mut has_one_edge) = (false, false);
-        for node in self.nodes.iter() {
-            if !has_zero_edge && node.1.get_e0().is_some() {
+        for (_, node) in self.iter_nodes() {
+            if !has_zero_edge && node.get_e0().is_some() {
                 has_zero_edge = true;
             }
-            if !has_one_edge && node.1.get_e1().is_some() {
+            if !has_one_edge && node.get_e1().is_some() {
                 has_one_edge = true;
             }
             // the first node must have an edge, it would be an orphan otherwise
@@ -216,8 +273,8 @@ impl Level {
 
     /// Flip the edges of all nodes in the level.
     pub fn flip_edges(&mut self) {
-        self.nodes.iter_mut().for_each(|node| {
-            node.1.flip_edges();
+        self.iter_mut_nodes().for_each(|(_, node)| {
+            node.flip_edges();
         });
     }
 
@@ -226,22 +283,18 @@ impl Level {
     /// We can then simply grab the node, look at its edges and then
     /// delete the level
     pub fn pop_source(&mut self) -> Node {
-        self.nodes
-            .drain()
-            .map(|(_, n)| n)
-            .collect::<Vec<Node>>()
-            .pop()
-            .unwrap()
+        self.len = 0;
+        self.nodes.drain(..).flatten().next().unwrap()
     }
 }
 
 impl fmt::Debug for Level {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "lhs {:?}", self.lhs)?;
-        if self.nodes.is_empty() {
+        if self.len == 0 {
             write!(f, "No nodes at this level")?;
         } else {
-            for n in self.nodes.iter() {
+            for n in self.iter_nodes() {
                 writeln!(f, "{:?}", n)?;
             }
         }
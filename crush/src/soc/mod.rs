@@ -7,11 +7,21 @@ use std::ops::Deref;
 
 pub use node::Node;
 
+pub mod anf;
 pub mod bdd;
+pub mod binary;
+pub mod cnf;
+pub mod dot;
+pub mod estimate;
 mod level;
 mod node;
+pub mod oplog;
+pub mod stats;
+pub mod symbols;
 pub mod system;
+mod tracker;
 pub mod utils;
+pub mod validate;
 #[macro_export]
 /// Macro to generate bdds :
 ///
@@ -47,7 +57,7 @@ macro_rules! bdd {
         [$($crate::soc::utils::LevelSpec::new($crate::soc::utils::vars(nom::types::CompleteStr(&$lhs)).expect("wrong format for lhs").1, [
             $($crate::soc::utils::NodeSpec::new(Id::new($id_node), Id::new($e0), Id::new($e1)))
             ,*].to_vec()))
-        ,*].to_vec()),$nvar);
+        ,*].to_vec()),$nvar)
     }
 }
 
@@ -0,0 +1,502 @@
+//! Recording of the operations applied to a `System`, to replay them later.
+//!
+//! Once `System::record_operations` is called, each call to `join_bdds`, `swap`, `add`, `absorb`,
+//! `drop`, `fix`, `scan_absorb_lin_eqs` and `scan_absorb_all_lin_eqs` is kept in an `OpLog` with
+//! its arguments, followed by its `Outcome` : the sizes of the `System` once it is done, and the
+//! error it returned if any. `replay` applies the operations of an `OpLog` to the `System` the log
+//! was recorded on (saved with the `binary` module for instance) up to a chosen step, checking
+//! that each of them has the same outcome, to reproduce a failure happening deep in a solving.
+//!
+//! The other mutations of the `System` (sifting, `get_bdd_mut`, `par_iter_bdds_mut`, pushing and
+//! popping `Bdd`s) are not recorded, a log of a solving using them won't replay.
+//!
+//! With `System::record_operations_to` each operation is also written to a sink before it is
+//! applied and its outcome once it is done, so that the operation which crashed the process, or
+//! exhausted its memory, ends the log written, without outcome.
+//!
+//! The log is a text, one line per operation and one per outcome :
+//! ```text
+//! oplog 1 nvar=6 nodes=9 lin_eqs=0
+//! swap bdd=1 above=0 below=1
+//! => nodes=9 lin_eqs=0 bdd_nodes=4
+//! fix lhs=0+2 rhs=1
+//! => nodes=7 lin_eqs=1
+//! absorb bdd=1 level=2 edge=1
+//! => nodes=7 lin_eqs=1 bdd_nodes=4 error=Out of range of levels : trying to absorb 2, sink level is 2
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::soc::{system::System, Id};
+
+/// The version of the log format, written in its first line.
+pub const VERSION: usize = 1;
+
+/// An operation applied to a `System`, with its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// `System::join_bdds(into, from)`.
+    Join { into: Id, from: Id },
+    /// `System::swap(bdd, above, below)`.
+    Swap { bdd: Id, above: usize, below: usize },
+    /// `System::add(bdd, above, below)`.
+    Add { bdd: Id, above: usize, below: usize },
+    /// `System::absorb(bdd, level, edge)`.
+    Absorb { bdd: Id, level: usize, edge: bool },
+    /// `System::drop(bdd, level)`.
+    Drop { bdd: Id, level: usize },
+    /// `System::fix(lhs, rhs)`.
+    Fix { lhs: Vec<usize>, rhs: bool },
+    /// `System::scan_absorb_lin_eqs(bdd)`.
+    Scan { bdd: Id },
+    /// `System::scan_absorb_all_lin_eqs()`.
+    ScanAll,
+}
+
+impl Op {
+    /// Apply the operation to `system`.
+    pub fn apply(&self, system: &mut System) -> Result<(), Error> {
+        match self {
+            Op::Join { into, from } => system.join_bdds(*into, *from).map(|_| ()),
+            Op::Swap { bdd, above, below } => system.swap(*bdd, *above, *below),
+            Op::Add { bdd, above, below } => system.add(*bdd, *above, *below),
+            Op::Absorb { bdd, level, edge } => system.absorb(*bdd, *level, *edge),
+            Op::Drop { bdd, level } => system.drop(*bdd, *level),
+            Op::Fix { lhs, rhs } => system.fix(lhs.clone(), *rhs),
+            Op::Scan { bdd } => system.scan_absorb_lin_eqs(*bdd).map(|_| ()),
+            Op::ScanAll => {
+                system.scan_absorb_all_lin_eqs();
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the `Bdd` the operation is applied to, `into` for a join.
+    pub fn bdd(&self) -> Option<Id> {
+        match self {
+            Op::Join { into, .. } => Some(*into),
+            Op::Swap { bdd, .. }
+            | Op::Add { bdd, .. }
+            | Op::Absorb { bdd, .. }
+            | Op::Drop { bdd, .. }
+            | Op::Scan { bdd } => Some(*bdd),
+            Op::Fix { .. } | Op::ScanAll => None,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Join { into, from } => write!(f, "join into={} from={}", into, from),
+            Op::Swap { bdd, above, below } => {
+                write!(f, "swap bdd={} above={} below={}", bdd, above, below)
+            }
+            Op::Add { bdd, above, below } => {
+                write!(f, "add bdd={} above={} below={}", bdd, above, below)
+            }
+            Op::Absorb { bdd, level, edge } => {
+                write!(f, "absorb bdd={} level={} edge={}", bdd, level, *edge as u8)
+            }
+            Op::Drop { bdd, level } => write!(f, "drop bdd={} level={}", bdd, level),
+            Op::Fix { lhs, rhs } => {
+                let lhs: Vec<String> = lhs.iter().map(|var| var.to_string()).collect();
+                write!(f, "fix lhs={} rhs={}", lhs.join("+"), *rhs as u8)
+            }
+            Op::Scan { bdd } => write!(f, "scan bdd={}", bdd),
+            Op::ScanAll => write!(f, "scan_all"),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Op, Error> {
+        let mut tokens = s.split_whitespace();
+        let name = tokens.next().ok_or_else(|| invalid("empty operation"))?;
+        let fields = Fields::parse(tokens)?;
+        let op = match name {
+            "join" => Op::Join {
+                into: Id::new(fields.get("into")?),
+                from: Id::new(fields.get("from")?),
+            },
+            "swap" => Op::Swap {
+                bdd: Id::new(fields.get("bdd")?),
+                above: fields.get("above")?,
+                below: fields.get("below")?,
+            },
+            "add" => Op::Add {
+                bdd: Id::new(fields.get("bdd")?),
+                above: fields.get("above")?,
+                below: fields.get("below")?,
+            },
+            "absorb" => Op::Absorb {
+                bdd: Id::new(fields.get("bdd")?),
+                level: fields.get("level")?,
+                edge: fields.get_bool("edge")?,
+            },
+            "drop" => Op::Drop {
+                bdd: Id::new(fields.get("bdd")?),
+                level: fields.get("level")?,
+            },
+            "fix" => Op::Fix {
+                lhs: fields
+                    .raw("lhs")?
+                    .split('+')
+                    .filter(|var| !var.is_empty())
+                    .map(|var| parse_number("lhs", var))
+                    .collect::<Result<Vec<usize>, Error>>()?,
+                rhs: fields.get_bool("rhs")?,
+            },
+            "scan" => Op::Scan {
+                bdd: Id::new(fields.get("bdd")?),
+            },
+            "scan_all" => Op::ScanAll,
+            _ => return Err(invalid(format!("unknown operation `{}`", name))),
+        };
+        Ok(op)
+    }
+}
+
+/// What an operation left : the sizes of the `System` once it is done, and the error it returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The total number of nodes of the `System`.
+    pub nodes: usize,
+    /// The number of `LinEq` in the `LinBank`.
+    pub lin_eqs: usize,
+    /// The number of nodes of the `Bdd` the operation was applied to (see `Op::bdd`), `None` if
+    /// there is none or if it is not in the `System` anymore.
+    pub bdd_nodes: Option<usize>,
+    /// The message of the error returned by the operation, if it failed.
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    /// Return the outcome of `op` on `system`, `error` being the error it returned if any.
+    fn of(system: &System, op: &Op, error: Option<&Error>) -> Outcome {
+        Outcome {
+            nodes: system.get_size(),
+            lin_eqs: system.get_lin_bank_size(),
+            bdd_nodes: op
+                .bdd()
+                .and_then(|id| system.get_bdd(id).ok())
+                .map(|bdd| bdd.get_size()),
+            error: error.map(|e| e.to_string().replace('\n', " ")),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "=> nodes={} lin_eqs={}", self.nodes, self.lin_eqs)?;
+        if let Some(bdd_nodes) = self.bdd_nodes {
+            write!(f, " bdd_nodes={}", bdd_nodes)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " error={}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Outcome, Error> {
+        let s = s
+            .trim()
+            .strip_prefix("=>")
+            .ok_or_else(|| invalid("an outcome starts with `=>`"))?;
+        // the error is the end of the line, it may hold anything
+        let (s, error) = match s.find(" error=") {
+            Some(index) => (&s[..index], Some(s[index + " error=".len()..].to_string())),
+            None => (s, None),
+        };
+        let fields = Fields::parse(s.split_whitespace())?;
+        Ok(Outcome {
+            nodes: fields.get("nodes")?,
+            lin_eqs: fields.get("lin_eqs")?,
+            bdd_nodes: fields.get_opt("bdd_nodes")?,
+            error,
+        })
+    }
+}
+
+/// An operation and its outcome, `None` if the operation didn't finish.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub op: Op,
+    pub outcome: Option<Outcome>,
+}
+
+/// The operations applied to a `System` since it started recording (see the module
+/// documentation).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpLog {
+    /// `nvar` of the `System` when it started recording.
+    nvar: usize,
+    /// The total number of nodes of the `System` when it started recording.
+    nodes: usize,
+    /// The number of `LinEq` in the `LinBank` when it started recording.
+    lin_eqs: usize,
+    steps: Vec<Step>,
+}
+
+impl OpLog {
+    /// Construct an empty `OpLog` for `system`.
+    pub fn new(system: &System) -> OpLog {
+        OpLog {
+            nvar: system.get_nvar(),
+            nodes: system.get_size(),
+            lin_eqs: system.get_lin_bank_size(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Return the steps recorded, in the order the operations were applied.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Return the number of steps recorded.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Return true if no step was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Return the first line of the log.
+    fn header(&self) -> String {
+        format!(
+            "oplog {} nvar={} nodes={} lin_eqs={}",
+            VERSION, self.nvar, self.nodes, self.lin_eqs
+        )
+    }
+
+    /// Write the log to `writer` (see the module documentation for the format).
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", self.header())?;
+        for step in self.steps.iter() {
+            writeln!(writer, "{}", step.op)?;
+            if let Some(outcome) = &step.outcome {
+                writeln!(writer, "{}", outcome)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the log to the file at `path`.
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a log written by `write` or by a sink of `System::record_operations_to` from `reader`.
+    ///
+    /// Returns an `Error` of kind `InvalidData` giving the line if the log is malformed. Only the
+    /// last operation may have no outcome.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<OpLog> {
+        let mut lines = reader.lines().enumerate();
+        let at = |line: usize, e: Error| invalid(format!("line {} : {}", line + 1, e));
+        let header = match lines.next() {
+            Some((_, header)) => header?,
+            None => return Err(invalid("empty log")),
+        };
+        let mut tokens = header.split_whitespace();
+        if tokens.next() != Some("oplog") {
+            return Err(at(0, invalid("not an operation log")));
+        }
+        let version = tokens
+            .next()
+            .ok_or_else(|| invalid("no version"))
+            .and_then(|version| parse_number("version", version))
+            .map_err(|e| at(0, e))?;
+        if version == 0 || version > VERSION {
+            return Err(at(0, invalid(format!("unsupported version {}", version))));
+        }
+        let fields = Fields::parse(tokens).map_err(|e| at(0, e))?;
+        let mut log = OpLog {
+            nvar: fields.get("nvar").map_err(|e| at(0, e))?,
+            nodes: fields.get("nodes").map_err(|e| at(0, e))?,
+            lin_eqs: fields.get("lin_eqs").map_err(|e| at(0, e))?,
+            steps: Vec::new(),
+        };
+        for (line, text) in lines {
+            let text = text?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            match log.steps.last_mut() {
+                Some(step) if step.outcome.is_none() => {
+                    step.outcome = Some(text.parse().map_err(|e| at(line, e))?);
+                }
+                _ => log.steps.push(Step {
+                    op: text.parse().map_err(|e| at(line, e))?,
+                    outcome: None,
+                }),
+            }
+        }
+        Ok(log)
+    }
+
+    /// Read a log from the file at `path` (see `read`).
+    pub fn read_from_file(path: &Path) -> io::Result<OpLog> {
+        OpLog::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Replay the first `steps` operations of `log` on `system`, which should be the `System` the
+/// log was recorded on, all of them if `log` has less than `steps` operations.
+///
+/// An operation recorded as failed is expected to fail again, the replay then goes on. An
+/// operation without outcome is applied as is, it is the one which didn't finish when the log
+/// was written.
+///
+/// Returns an `Error` if `system` is not the `System` the log starts from, or if an operation
+/// doesn't have the outcome recorded : `system` is then left after that operation.
+pub fn replay(system: &mut System, log: &OpLog, steps: usize) -> Result<(), Error> {
+    let start = OpLog::new(system);
+    if (start.nvar, start.nodes, start.lin_eqs) != (log.nvar, log.nodes, log.lin_eqs) {
+        return Err(invalid(format!(
+            "the log starts from `{}`, the system is `{}`",
+            log.header(),
+            start.header()
+        )));
+    }
+    for (index, step) in log.steps.iter().take(steps).enumerate() {
+        let result = step.op.apply(system);
+        let expected = match &step.outcome {
+            Some(expected) => expected,
+            None => return result,
+        };
+        let outcome = Outcome::of(system, &step.op, result.as_ref().err());
+        if outcome != *expected {
+            return Err(invalid(format!(
+                "step {} `{}` diverged : recorded `{}`, replayed `{}`",
+                index, step.op, expected, outcome
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The recording of the operations of a `System` (see `System::record_operations`).
+pub(crate) struct Recorder {
+    log: OpLog,
+    /// Behind a `Mutex` for the `System` to stay `Sync`.
+    sink: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Recorder {
+    /// Start recording the operations of `system`, writing them to `sink` if any.
+    pub(crate) fn new(system: &System, sink: Option<Box<dyn Write + Send>>) -> io::Result<Recorder> {
+        let log = OpLog::new(system);
+        let sink = match sink {
+            Some(mut sink) => {
+                writeln!(sink, "{}", log.header())?;
+                sink.flush()?;
+                Some(Mutex::new(sink))
+            }
+            None => None,
+        };
+        Ok(Recorder { log, sink })
+    }
+
+    /// Record `op`, which is about to be applied.
+    pub(crate) fn start(&mut self, op: Op) {
+        self.write(&op);
+        self.log.steps.push(Step { op, outcome: None });
+    }
+
+    /// Record the outcome of the last operation on `system`.
+    pub(crate) fn end(&mut self, system: &System, error: Option<&Error>) {
+        let outcome = match self.log.steps.last() {
+            Some(step) => Outcome::of(system, &step.op, error),
+            None => return,
+        };
+        self.write(&outcome);
+        self.log.steps.last_mut().unwrap().outcome = Some(outcome);
+    }
+
+    /// Write a line to the sink, flushing it so that the line is there if the process dies. The
+    /// errors are ignored, the recording going on in memory.
+    fn write<T: fmt::Display>(&mut self, line: &T) {
+        if let Some(Ok(sink)) = self.sink.as_mut().map(Mutex::get_mut) {
+            let _ = writeln!(sink, "{}", line).and_then(|_| sink.flush());
+        }
+    }
+
+    /// Return the log recorded so far.
+    pub(crate) fn log(&self) -> &OpLog {
+        &self.log
+    }
+
+    /// Stop recording and return the log.
+    pub(crate) fn into_log(self) -> OpLog {
+        self.log
+    }
+}
+
+/// The `key=value` fields of a line.
+struct Fields<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> Fields<'a> {
+    fn parse<I: Iterator<Item = &'a str>>(tokens: I) -> Result<Fields<'a>, Error> {
+        tokens
+            .map(|token| {
+                let mut split = token.splitn(2, '=');
+                match (split.next(), split.next()) {
+                    (Some(key), Some(value)) => Ok((key, value)),
+                    _ => Err(invalid(format!("`{}` is not a `key=value` field", token))),
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Fields)
+    }
+
+    fn raw(&self, key: &str) -> Result<&'a str, Error> {
+        self.0
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| invalid(format!("no `{}` field", key)))
+    }
+
+    fn get(&self, key: &str) -> Result<usize, Error> {
+        parse_number(key, self.raw(key)?)
+    }
+
+    fn get_opt(&self, key: &str) -> Result<Option<usize>, Error> {
+        match self.raw(key) {
+            Ok(value) => parse_number(key, value).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn get_bool(&self, key: &str) -> Result<bool, Error> {
+        match self.raw(key)? {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            value => Err(invalid(format!("`{}={}` is not 0 or 1", key, value))),
+        }
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<usize, Error> {
+    usize::from_str(value).map_err(|_| invalid(format!("`{}={}` is not a number", key, value)))
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,175 @@
+//! Per-level statistics of a `Bdd` or a `System`.
+//!
+//! `Bdd::stats` and `System::stats` return, for each level, its number of nodes, the number of
+//! those nodes having a single outgoing edge and the number of variables of its lhs. The widths
+//! of the levels are what the soft limits of the solvers bound, and a level made mostly of
+//! single-edge nodes is close to being absorbed or dropped. The reports can be written as JSON
+//! or CSV, and `solver::profile` writes the report of the joined `Bdd` after each join of a
+//! solving.
+
+use std::io::{self, Write};
+
+use crate::soc::{bdd::Bdd, system::System, Id};
+
+/// The header of the CSV written by `SystemStats::write_csv`, one row per level.
+pub const CSV_HEADER: &str = "bdd,level,nodes,single_edge,lhs_weight";
+
+/// The statistics of a level of a `Bdd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelStats {
+    /// The index of the level in the `Bdd`, the last level being the sink.
+    pub level: usize,
+    /// The number of nodes of the level.
+    pub nodes: usize,
+    /// The number of nodes with exactly one outgoing edge.
+    pub single_edge: usize,
+    /// The number of variables of the lhs.
+    pub lhs_weight: usize,
+}
+
+impl LevelStats {
+    /// Return the fraction of the nodes of the level with exactly one outgoing edge, 0 if the
+    /// level has no node.
+    pub fn single_edge_fraction(&self) -> f64 {
+        if self.nodes == 0 {
+            0.0
+        } else {
+            self.single_edge as f64 / self.nodes as f64
+        }
+    }
+
+    /// Return the JSON object describing the level, on a single line.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"level":{},"nodes":{},"single_edge":{},"single_edge_fraction":{},"lhs_weight":{}}}"#,
+            self.level,
+            self.nodes,
+            self.single_edge,
+            self.single_edge_fraction(),
+            self.lhs_weight
+        )
+    }
+}
+
+/// The statistics of a `Bdd`, level by level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BddStats {
+    /// The `Id` of the `Bdd`.
+    pub bdd: Id,
+    /// The statistics of each level, from the source to the sink.
+    pub levels: Vec<LevelStats>,
+}
+
+impl BddStats {
+    /// Return the total number of nodes of the `Bdd`.
+    pub fn nodes(&self) -> usize {
+        self.levels.iter().map(|level| level.nodes).sum()
+    }
+
+    /// Return the number of nodes of the widest level.
+    pub fn width(&self) -> usize {
+        self.levels.iter().map(|level| level.nodes).max().unwrap_or(0)
+    }
+
+    /// Return the JSON object describing the `Bdd`, on a single line.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"bdd":{},"nodes":{},"width":{},"levels":[{}]}}"#,
+            self.bdd,
+            self.nodes(),
+            self.width(),
+            self.levels
+                .iter()
+                .map(LevelStats::to_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    /// Return the CSV rows of the levels, with the columns of `CSV_HEADER`.
+    pub fn csv_rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.levels.iter().map(move |level| {
+            format!(
+                "{},{},{},{},{}",
+                self.bdd, level.level, level.nodes, level.single_edge, level.lhs_weight
+            )
+        })
+    }
+}
+
+/// The statistics of a `System`, `Bdd` by `Bdd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemStats {
+    /// The number of variables of the `System`.
+    pub nvar: usize,
+    /// The number of `LinEq` in the `LinBank`.
+    pub lin_eqs: usize,
+    /// The statistics of each `Bdd`, by increasing `Id`.
+    pub bdds: Vec<BddStats>,
+}
+
+impl SystemStats {
+    /// Return the total number of nodes of the `System`.
+    pub fn nodes(&self) -> usize {
+        self.bdds.iter().map(BddStats::nodes).sum()
+    }
+
+    /// Return the JSON object describing the `System`, on a single line.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"nvar":{},"lin_eqs":{},"nodes":{},"bdds":[{}]}}"#,
+            self.nvar,
+            self.lin_eqs,
+            self.nodes(),
+            self.bdds
+                .iter()
+                .map(BddStats::to_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    /// Write the levels of every `Bdd` as CSV to `writer`, starting with `CSV_HEADER`.
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for row in self.bdds.iter().flat_map(BddStats::csv_rows) {
+            writeln!(writer, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+impl Bdd {
+    /// Return the statistics of each level of the `Bdd` (see `stats`).
+    pub fn stats(&self) -> BddStats {
+        BddStats {
+            bdd: self.get_id(),
+            levels: self
+                .iter_levels()
+                .enumerate()
+                .map(|(i, level)| LevelStats {
+                    level: i,
+                    nodes: level.get_nodes_len(),
+                    single_edge: level
+                        .iter_nodes()
+                        .filter(|(_, node)| node.get_e0().is_some() != node.get_e1().is_some())
+                        .count(),
+                    lhs_weight: level.iter_set_lhs().count(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl System {
+    /// Return the statistics of each `Bdd` of the `System` (see `stats`).
+    pub fn stats(&self) -> SystemStats {
+        let mut bdds: Vec<BddStats> = self.iter_bdds().map(|(_, bdd)| bdd.stats()).collect();
+        bdds.sort_by_key(|stats| stats.bdd);
+        SystemStats {
+            nvar: self.get_nvar(),
+            lin_eqs: self.get_lin_bank_size(),
+            bdds,
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Names of the variables of a `System`.
+//!
+//! The variables are indices everywhere in the `System`, a `SymbolTable` gives some of them a
+//! name (ex: `k12` for the bit 12 of a key, `s_r2_b5_out1` for an output bit of a S-box) and a
+//! `Role` telling what they stand for. It is filled by whoever builds the `System`, kept through
+//! the solving, saved in the .bdd and binary formats and used when printing the levels and the
+//! solutions. The variables without a symbol are printed as `x` followed by their index.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+use vob::Vob;
+
+use crate::AHashMap;
+
+/// What a variable stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Key,
+    Plaintext,
+    Ciphertext,
+    /// An intermediate value of the primitive, like the output of a S-box.
+    State,
+    /// The input of a hash function.
+    Message,
+    Other,
+}
+
+impl Role {
+    /// All the roles, in the order of their number in the binary format.
+    pub const ALL: [Role; 6] = [
+        Role::Key,
+        Role::Plaintext,
+        Role::Ciphertext,
+        Role::State,
+        Role::Message,
+        Role::Other,
+    ];
+
+    /// Return the name of the role in the .bdd format.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Key => "key",
+            Role::Plaintext => "plaintext",
+            Role::Ciphertext => "ciphertext",
+            Role::State => "state",
+            Role::Message => "message",
+            Role::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Role, Error> {
+        Role::ALL
+            .iter()
+            .find(|role| role.as_str() == s)
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("unknown role `{}`", s)))
+    }
+}
+
+/// The name and the role of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub role: Role,
+}
+
+/// The symbols of the variables of a `System` (see the module documentation).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: BTreeMap<usize, Symbol>,
+    vars: AHashMap<String, usize>,
+}
+
+impl SymbolTable {
+    /// Construct an empty `SymbolTable`.
+    pub fn new() -> SymbolTable {
+        Default::default()
+    }
+
+    /// Give the variable `var` the name `name` and the role `role`, replacing its previous
+    /// symbol.
+    ///
+    /// Returns an `Error` if `name` is already the name of another variable, or if it is not a
+    /// valid name : names are not empty and made of letters, digits and `_`, and don't start
+    /// with a digit. A name `x` followed by digits is only valid for the variable of that
+    /// number, being how the variables without symbol are printed.
+    pub fn insert(&mut self, var: usize, name: &str, role: Role) -> Result<(), Error> {
+        let valid = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("`{}` is not a valid variable name", name),
+            ));
+        }
+        if let Some(digits) = name.strip_prefix('x') {
+            if !digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit())
+                && digits.parse() != Ok(var)
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("`{}` is reserved for the variable of that number, not {}", name, var),
+                ));
+            }
+        }
+        match self.vars.get(name) {
+            Some(other) if *other != var => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("`{}` is already the name of variable {}", name, other),
+                ));
+            }
+            _ => (),
+        }
+        if let Some(previous) = self.symbols.get(&var) {
+            self.vars.remove(&previous.name);
+        }
+        self.vars.insert(name.to_string(), var);
+        self.symbols.insert(
+            var,
+            Symbol {
+                name: name.to_string(),
+                role,
+            },
+        );
+        Ok(())
+    }
+
+    /// Give the variables `vars` the names `prefix` followed by their position in `vars` and
+    /// the role `role` (ex: `k0`, `k1`... for the bits of a key).
+    pub fn insert_all(&mut self, vars: &[usize], prefix: &str, role: Role) -> Result<(), Error> {
+        for (i, var) in vars.iter().enumerate() {
+            self.insert(*var, &format!("{}{}", prefix, i), role)?;
+        }
+        Ok(())
+    }
+
+    /// Return the symbol of `var`, if it has one.
+    pub fn get(&self, var: usize) -> Option<&Symbol> {
+        self.symbols.get(&var)
+    }
+
+    /// Return the variable named `name`, if there is one.
+    pub fn get_var(&self, name: &str) -> Option<usize> {
+        self.vars.get(name).cloned()
+    }
+
+    /// Return the name of `var`, `x` followed by its index if it has no symbol.
+    pub fn name(&self, var: usize) -> String {
+        match self.symbols.get(&var) {
+            Some(symbol) => symbol.name.clone(),
+            None => format!("x{}", var),
+        }
+    }
+
+    /// Return the variables of role `role`, by increasing index.
+    pub fn vars_with_role(&self, role: Role) -> Vec<usize> {
+        self.iter()
+            .filter(|(_, symbol)| symbol.role == role)
+            .map(|(var, _)| var)
+            .collect()
+    }
+
+    /// Iterate over the variables which have a symbol and their symbol, by increasing index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Symbol)> {
+        self.symbols.iter().map(|(var, symbol)| (*var, symbol))
+    }
+
+    /// Return the number of variables which have a symbol.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Return true if no variable has a symbol.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Return `lhs` as the sum of the names of its variables, `0` if it is empty.
+    pub fn format_lhs(&self, lhs: &Vob) -> String {
+        let names: Vec<String> = lhs.iter_set_bits(..).map(|var| self.name(var)).collect();
+        if names.is_empty() {
+            "0".to_string()
+        } else {
+            names.join(" + ")
+        }
+    }
+
+    /// Return the value of each variable which has a symbol in `solution`, as `name=value`
+    /// separated by spaces, the value being `?` if the variable is not determined.
+    pub fn format_solution(&self, solution: &[Option<bool>]) -> String {
+        self.iter()
+            .filter(|(var, _)| *var < solution.len())
+            .map(|(var, symbol)| {
+                let value = match solution[var] {
+                    Some(true) => "1",
+                    Some(false) => "0",
+                    None => "?",
+                };
+                format!("{}={}", symbol.name, value)
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
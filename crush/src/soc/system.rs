@@ -5,29 +5,72 @@
 //! This object will be mutated through it's different methods (fix, drop, add, swap, absorb, scan)
 //! in order to remove all the linear dependencies among the levels of the different `Bdd`s so
 //! the solutions to the system of equations it represents can be extracted.
+//!
+//! A `System` owns its `Bdd`s directly and is `Send` and `Sync`. The operations which only touch
+//! one `Bdd` at a time (scanning for `LinEq`, substituting a variable of the `LinBank`) are run on
+//! all the `Bdd`s in parallel using rayon, and `par_iter_bdds_mut` allows to reduce independent
+//! `Bdd`s concurrently. Joining needs two `Bdd`s at once and takes the `System` by `&mut`, like
+//! every other operation mutating it.
 
-use std::cell::RefCell;
 use std::fmt;
-use std::io::{self, Error, ErrorKind};
+use std::io::{self, Error, ErrorKind, Write};
 use std::result::Result;
 
+use num_bigint::BigUint;
+use rand::Rng;
+use rayon::prelude::*;
 use vob::Vob;
 
 use crate::AHashMap;
-use crate::algebra;
+use crate::algebra::{self, AffineSpace, Matrix, PackedMatrix};
 use crate::soc::{
-    bdd::{Bdd, LinEq},
+    bdd::{sifting::Sifting, Bdd, LinEq, PathSampler, Paths},
+    oplog::{Op, OpLog, Recorder},
+    symbols::SymbolTable,
+    tracker::DependencyTracker,
     Id,
 };
 
-/// Cloning a SoC should only happen when the SoC is of a sensible size!
-#[derive(Clone)]
 /// A system of Bdds providing a number of methods to interact safely with the Bdds it contains
 #[derive(Default)]
 pub struct System {
-    bdds: AHashMap<Id, RefCell<Bdd>>,
+    bdds: AHashMap<Id, Bdd>,
     nvar: usize,
     lin_bank: LinBank,
+    dropped_vars: Vec<usize>,
+    /// The names of the variables, if the builder of the `System` gave some.
+    symbols: Option<SymbolTable>,
+    /// The reduction of the lhs of the levels, when the dependencies are tracked (see
+    /// `track_dependencies`).
+    tracker: Option<DependencyTracker>,
+    /// True if the `Bdd`s were mutated out of the sight of the tracker, which has to be built
+    /// again.
+    tracker_stale: bool,
+    /// True if an operation showed that the `System` has no solution (see `has_contradiction`).
+    contradiction: bool,
+    /// The operations applied, when they are recorded (see `record_operations`).
+    recorder: Option<Recorder>,
+}
+
+/// Cloning a SoC should only happen when the SoC is of a sensible size!
+///
+/// The clone doesn't record its operations, even if the `System` does (see `record_operations`):
+/// the operations applied to a clone, for instance by `count_solutions`, are not the ones of the
+/// `System`.
+impl Clone for System {
+    fn clone(&self) -> Self {
+        System {
+            bdds: self.bdds.clone(),
+            nvar: self.nvar,
+            lin_bank: self.lin_bank.clone(),
+            dropped_vars: self.dropped_vars.clone(),
+            symbols: self.symbols.clone(),
+            tracker: self.tracker.clone(),
+            tracker_stale: self.tracker_stale,
+            contradiction: self.contradiction,
+            recorder: None,
+        }
+    }
 }
 
 /// `LinBank` is the structure holding the valid linear equations
@@ -104,6 +147,7 @@ impl System {
 
     /// Set `nvar` of the `System`
     pub fn set_nvar(&mut self, nvar: usize) {
+        self.tracker_stale = self.tracker.is_some();
         self.nvar = nvar;
     }
 
@@ -129,14 +173,19 @@ impl System {
                 "A Bdd with the same id is already in the system",
             ));
         }
-        self.bdds.insert(bdd.get_id(), RefCell::new(bdd));
+        let id = bdd.get_id();
+        if self.tracker.is_some() {
+            let lhs = bdd.get_lhs();
+            self.update_tracker(|tracker| tracker.push_bdd(id, lhs));
+        }
+        self.bdds.insert(id, bdd);
         Ok(())
     }
 
     /// Return a reference to the `Bdd` which `id` is equal to `bdd_id`.
     ///
     /// Will return an `Error` if there is no `Bdd` matching this condition.
-    pub fn get_bdd(&self, bdd_id: Id) -> Result<&RefCell<Bdd>, Error> {
+    pub fn get_bdd(&self, bdd_id: Id) -> Result<&Bdd, Error> {
         match self.bdds.get(&bdd_id) {
             Some(bdd) => Ok(bdd),
             None => Err(Error::new(
@@ -146,6 +195,29 @@ impl System {
         }
     }
 
+    /// Return a mutable reference to the `Bdd` which `id` is equal to `bdd_id`.
+    ///
+    /// If the dependencies are tracked, they are tracked again from scratch on the next
+    /// operation, as the `Bdd` may be changed in any way.
+    ///
+    /// Will return an `Error` if there is no `Bdd` matching this condition.
+    pub fn get_bdd_mut(&mut self, bdd_id: Id) -> Result<&mut Bdd, Error> {
+        self.tracker_stale = self.tracker.is_some();
+        self.bdd_mut(bdd_id)
+    }
+
+    /// Return a mutable reference to the `Bdd` which `id` is equal to `bdd_id`, for the
+    /// operations keeping the tracker up to date.
+    fn bdd_mut(&mut self, bdd_id: Id) -> Result<&mut Bdd, Error> {
+        match self.bdds.get_mut(&bdd_id) {
+            Some(bdd) => Ok(bdd),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("id {} not present in system", bdd_id),
+            )),
+        }
+    }
+
     /// Split the `System` into 2 `System` removing from self all `Bdd` whose ids
     /// are contains in `ids` and returning a new `System` made of those `Bdd`.
     ///
@@ -157,6 +229,8 @@ impl System {
         }
         let mut sys = System::from_elem(bdds)?;
         sys.lin_bank = self.lin_bank.clone();
+        sys.dropped_vars = self.dropped_vars.clone();
+        sys.symbols = self.symbols.clone();
         Ok(sys)
     }
 
@@ -168,13 +242,20 @@ impl System {
         for bdd in system.drain_bdds() {
             // TODO -> error handling should take into account middle crash and rollback system to its initial state
             // to avoid half merging if one bdd have a different nvar
-            if bdd.1.borrow().get_levels_size() > 1 {
-                self.push_bdd(bdd.1.into_inner())?;
+            if bdd.1.get_levels_size() > 1 {
+                self.push_bdd(bdd.1)?;
             }
         }
         for lin_eq in system.lin_bank.lin_eqs.drain(..) {
             self.push_lin_eq_to_lin_bank(lin_eq);
         }
+        self.sync_tracker();
+        for var in system.dropped_vars.drain(..) {
+            if !self.dropped_vars.contains(&var) {
+                self.dropped_vars.push(var);
+            }
+        }
+        self.debug_validate();
         Ok(())
     }
 
@@ -186,20 +267,31 @@ impl System {
     /// `bdd_id_1` and `bdd_id_2` are equals or one is not found in the
     /// `System`.
     pub fn join_bdds(&mut self, bdd_1_id: Id, bdd_2_id: Id) -> Result<Id, Error> {
+        self.recorded(
+            || Op::Join { into: bdd_1_id, from: bdd_2_id },
+            |system| system.apply_join_bdds(bdd_1_id, bdd_2_id),
+        )
+    }
+
+    /// `join_bdds`, without recording it.
+    fn apply_join_bdds(&mut self, bdd_1_id: Id, bdd_2_id: Id) -> Result<Id, Error> {
         if bdd_1_id == bdd_2_id {
             return Err(Error::new(
                 ErrorKind::InvalidData,
                 "bdd_1_id is equal to bdd_2_id",
             ));
         }
-        let bdd_1 = self.get_bdd(bdd_1_id)?;
-        let bdd_2 = self.get_bdd(bdd_2_id)?;
-        let sink_level_id = bdd_1.borrow().get_sink_level_index();
-        for level in bdd_2.borrow_mut().drain_levels() {
-            bdd_1.borrow_mut().add_existing_level(level)
+        self.get_bdd(bdd_1_id)?;
+        self.get_bdd(bdd_2_id)?;
+        let mut bdd_2 = self.bdds.remove(&bdd_2_id).unwrap();
+        let bdd_1 = self.bdd_mut(bdd_1_id)?;
+        let sink_level_id = bdd_1.get_sink_level_index();
+        for level in bdd_2.drain_levels() {
+            bdd_1.add_existing_level(level)
         }
-        bdd_1.borrow_mut().merge_sink_source(sink_level_id);
-        self.bdds.remove(&bdd_2_id);
+        bdd_1.merge_sink_source(sink_level_id);
+        self.update_tracker(|tracker| tracker.join(bdd_1_id, bdd_2_id));
+        self.debug_validate();
         Ok(bdd_1_id)
     }
 
@@ -212,6 +304,23 @@ impl System {
         bdd_id: Id,
         level_index_above: usize,
         level_index_below: usize,
+    ) -> Result<(), Error> {
+        self.recorded(
+            || Op::Swap {
+                bdd: bdd_id,
+                above: level_index_above,
+                below: level_index_below,
+            },
+            |system| system.apply_swap(bdd_id, level_index_above, level_index_below),
+        )
+    }
+
+    /// `swap`, without recording it.
+    fn apply_swap(
+        &mut self,
+        bdd_id: Id,
+        level_index_above: usize,
+        level_index_below: usize,
     ) -> Result<(), Error> {
         if level_index_below != level_index_above + 1 {
             return Err(Error::new(
@@ -219,11 +328,13 @@ impl System {
                 "Level 1 is not directly above Level 2",
             ));
         }
-        let bdd = self.get_bdd(bdd_id)?;
-        if level_index_below >= bdd.borrow().get_sink_level_index() {
+        let bdd = self.bdd_mut(bdd_id)?;
+        if level_index_below >= bdd.get_sink_level_index() {
             return Err(Error::new(ErrorKind::InvalidData, "Out of range of levels"));
         }
-        bdd.borrow_mut().swap(level_index_above, level_index_below);
+        bdd.swap(level_index_above, level_index_below);
+        self.update_tracker(|tracker| tracker.swap(bdd_id, level_index_above));
+        self.debug_validate();
         Ok(())
     }
 
@@ -236,6 +347,23 @@ impl System {
         bdd_id: Id,
         level_index_above: usize,
         level_index_below: usize,
+    ) -> Result<(), Error> {
+        self.recorded(
+            || Op::Add {
+                bdd: bdd_id,
+                above: level_index_above,
+                below: level_index_below,
+            },
+            |system| system.apply_add(bdd_id, level_index_above, level_index_below),
+        )
+    }
+
+    /// `add`, without recording it.
+    fn apply_add(
+        &mut self,
+        bdd_id: Id,
+        level_index_above: usize,
+        level_index_below: usize,
     ) -> Result<(), Error> {
         if level_index_above >= level_index_below {
             return Err(Error::new(
@@ -243,61 +371,270 @@ impl System {
                 "Level above is not above Level below",
             ));
         }
-        let bdd = self.get_bdd(bdd_id)?;
-        if level_index_below >= bdd.borrow().get_sink_level_index() {
+        let bdd = self.bdd_mut(bdd_id)?;
+        if level_index_below >= bdd.get_sink_level_index() {
             return Err(Error::new(
                 ErrorKind::InvalidData,
                 format!(
                     "Out of range of levels : trying to add on level {}, sink level is {}",
                     level_index_below,
-                    bdd.borrow().get_sink_level_index()
+                    bdd.get_sink_level_index()
                 ),
             ));
         }
-        bdd.borrow_mut().add(level_index_above, level_index_below);
+        bdd.add(level_index_above, level_index_below);
+        self.update_tracker(|tracker| tracker.add(bdd_id, level_index_above, level_index_below));
+        self.debug_validate();
         Ok(())
     }
 
     /// Performs an `absorb` operation on the `Bdd` with the `id` specified on `level_index` and along the edge specified.
     ///
-    /// Returns an `Error` if `level_index` is out of the range of the levels the `Bdd`, or
-    /// if `bdd_id` is not found in the `System`.
+    /// Returns an `Error` if `level_index` is out of the range of the levels the `Bdd`, if
+    /// `bdd_id` is not found in the `System`, or if no node of the level has an outgoing edge
+    /// along `edge` : the `System` then has no solution (see `has_contradiction`).
     pub fn absorb(&mut self, bdd_id: Id, level_index: usize, edge: bool) -> Result<(), Error> {
-        let bdd = self.get_bdd(bdd_id)?;
-        if level_index >= bdd.borrow().get_sink_level_index() {
+        self.recorded(
+            || Op::Absorb {
+                bdd: bdd_id,
+                level: level_index,
+                edge,
+            },
+            |system| system.apply_absorb(bdd_id, level_index, edge),
+        )
+    }
+
+    /// `absorb`, without recording it.
+    fn apply_absorb(&mut self, bdd_id: Id, level_index: usize, edge: bool) -> Result<(), Error> {
+        let bdd = self.bdd_mut(bdd_id)?;
+        if level_index >= bdd.get_sink_level_index() {
             return Err(Error::new(
                 ErrorKind::InvalidData,
                 format!(
                     "Out of range of levels : trying to absorb {}, sink level is {}",
                     level_index,
-                    bdd.borrow().get_sink_level_index()
+                    bdd.get_sink_level_index()
                 ),
             ));
         }
-        bdd.borrow_mut().absorb(level_index, edge);
+        if !bdd.try_absorb(level_index, edge) {
+            self.contradiction = true;
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "No path along {} edges at level {}, the system has no solution",
+                    edge, level_index
+                ),
+            ));
+        }
+        self.update_tracker(|tracker| tracker.remove_level(bdd_id, level_index));
+        self.debug_validate();
         Ok(())
     }
 
+    /// Reorder the levels of the `Bdd` with the `id` specified to reduce its number of nodes (see
+    /// `bdd::sifting`) and return its number of nodes afterwards.
+    ///
+    /// The levels are moved, and with linear sifting their `lhs` are changed: the `Dependency`s
+    /// extracted before sifting don't apply anymore and have to be extracted again.
+    ///
+    /// Returns an `Error` if `bdd_id` is not found in the `System`.
+    pub fn sift(&mut self, bdd_id: Id, sifting: Sifting) -> Result<usize, Error> {
+        let size = self.get_bdd_mut(bdd_id)?.sift(sifting);
+        self.debug_validate();
+        Ok(size)
+    }
+
+    /// Sift every `Bdd` of the `System` with more than `threshold` nodes (see `sift`), in
+    /// parallel, and return the number of nodes of the `System` afterwards.
+    pub fn sift_above(&mut self, threshold: usize, sifting: Sifting) -> usize {
+        self.bdds
+            .par_iter_mut()
+            .filter(|(_, bdd)| bdd.get_size() > threshold)
+            .for_each(|(_, bdd)| {
+                bdd.sift(sifting);
+            });
+        self.tracker_stale = self.tracker.is_some();
+        self.debug_validate();
+        self.get_size()
+    }
+
     /// Performs a `drop` operation on the `Bdd` with the `id` specified on `level_index`.
     ///
+    /// If the level holds the only occurence of a variable in the `System`, this variable
+    /// is recorded as dropped (see `get_dropped_vars`) as the `System` doesn't hold any
+    /// information about it anymore. Finding it scans the lhs of every level of the `System`,
+    /// use `drop_levels` to drop several levels of a `Bdd` with a single scan.
+    ///
     /// Returns an `Error` if `level_index` is out of the range of the levels the `Bdd`, or
     /// if `bdd_id` is not found in the `System`.
     pub fn drop(&mut self, bdd_id: Id, level_index: usize) -> Result<(), Error> {
+        self.drop_levels(bdd_id, &[level_index])
+    }
+
+    /// Performs a `drop` operation on each of the `levels` of the `Bdd` with the `id` specified,
+    /// the lowest level first so that the indices of the levels left to drop stay valid. Each
+    /// `drop` is recorded on its own.
+    ///
+    /// The occurences of the variables in the `System` are counted once, and updated after each
+    /// `drop`, instead of scanning every level of the `System` for each of them.
+    ///
+    /// Returns an `Error` if a level is out of the range of the levels the `Bdd`, or if `bdd_id`
+    /// is not found in the `System`. The levels below the one in error are dropped.
+    pub fn drop_levels(&mut self, bdd_id: Id, levels: &[usize]) -> Result<(), Error> {
+        let mut levels = levels.to_vec();
+        levels.sort_unstable();
+        levels.dedup();
+        let mut occurences = None;
+        for level_index in levels.into_iter().rev() {
+            self.recorded(
+                || Op::Drop {
+                    bdd: bdd_id,
+                    level: level_index,
+                },
+                |system| system.apply_drop(bdd_id, level_index, &mut occurences),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `drop`, without recording it. `occurences` holds the number of levels and `LinEq`s of the
+    /// `System` each variable appears in, counted on the first call.
+    fn apply_drop(
+        &mut self,
+        bdd_id: Id,
+        level_index: usize,
+        occurences: &mut Option<Vec<usize>>,
+    ) -> Result<(), Error> {
         let bdd = self.get_bdd(bdd_id)?;
-        if level_index >= bdd.borrow().get_sink_level_index() {
+        if level_index >= bdd.get_sink_level_index() {
             return Err(Error::new(
                 ErrorKind::InvalidData,
                 format!(
                     "Out of range of levels : trying to drop {}, sink level is {}",
                     level_index,
-                    bdd.borrow().get_sink_level_index()
+                    bdd.get_sink_level_index()
                 ),
             ));
         }
-        bdd.borrow_mut().drop(level_index);
+        let dropped_lhs = bdd.get_lhs_level(level_index);
+        let occurences = occurences.get_or_insert_with(|| self.count_occurences());
+        if let Some(var) = dropped_lhs
+            .iter_set_bits(..)
+            .filter(|var| occurences[*var] == 1)
+            .last()
+        {
+            self.dropped_vars.push(var);
+        }
+        // the other levels of the `Bdd` are only swapped, their lhs are kept
+        for var in dropped_lhs.iter_set_bits(..) {
+            occurences[var] -= 1;
+        }
+        self.bdd_mut(bdd_id)?.drop(level_index);
+        self.update_tracker(|tracker| tracker.remove_level(bdd_id, level_index));
+        self.debug_validate();
         Ok(())
     }
 
+    /// Return the number of levels and `LinEq`s each variable appears in.
+    fn count_occurences(&self) -> Vec<usize> {
+        let mut occurences = vec![0; self.nvar];
+        let lhs = self.bdds.values().flat_map(|bdd| bdd.get_lhs());
+        for lhs in lhs.chain(self.lin_bank.lin_eqs.iter().map(LinEq::get_lhs)) {
+            for var in lhs.iter_set_bits(..) {
+                occurences[var] += 1;
+            }
+        }
+        occurences
+    }
+
+    /// Return the variables which were dropped from the `System` as the only occurence
+    /// of a variable in the `System` (see `drop`).
+    pub fn get_dropped_vars(&self) -> &[usize] {
+        &self.dropped_vars
+    }
+
+    /// Set the names of the variables of the `System`. They are kept by every operation.
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = Some(symbols);
+    }
+
+    /// Return the names of the variables of the `System`, if it has some.
+    pub fn get_symbols(&self) -> Option<&SymbolTable> {
+        self.symbols.as_ref()
+    }
+
+    /// Return the name of `var`, `x` followed by its index if it has none.
+    pub fn var_name(&self, var: usize) -> String {
+        match &self.symbols {
+            Some(symbols) => symbols.name(var),
+            None => format!("x{}", var),
+        }
+    }
+
+    /// Return `solution` as `name=value` separated by spaces, the value being `?` if the variable
+    /// is not determined. Only the variables which have a name are given if the `System` has
+    /// names, all the variables otherwise.
+    pub fn format_solution(&self, solution: &[Option<bool>]) -> String {
+        match &self.symbols {
+            Some(symbols) => symbols.format_solution(solution),
+            None => solution
+                .iter()
+                .enumerate()
+                .map(|(var, value)| match value {
+                    Some(value) => format!("x{}={}", var, *value as u8),
+                    None => format!("x{}=?", var),
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        }
+    }
+
+    /// Record the operations applied to the `System` from now on in an `OpLog`, to replay them
+    /// later (see `soc::oplog`). Recording again starts a new log.
+    pub fn record_operations(&mut self) {
+        // without sink, nothing can fail
+        self.recorder = Some(Recorder::new(self, None).unwrap());
+    }
+
+    /// Same as `record_operations`, also writing each operation to `sink` before applying it, and
+    /// its outcome once it is done.
+    ///
+    /// Returns an `Error` if the start of the log can't be written, the errors writing the
+    /// operations afterwards are ignored.
+    pub fn record_operations_to(&mut self, sink: Box<dyn Write + Send>) -> Result<(), Error> {
+        self.recorder = Some(Recorder::new(self, Some(sink))?);
+        Ok(())
+    }
+
+    /// Return the operations recorded so far, if they are recorded (see `record_operations`).
+    pub fn get_operation_log(&self) -> Option<&OpLog> {
+        self.recorder.as_ref().map(|recorder| recorder.log())
+    }
+
+    /// Stop recording the operations and return the log, if they were recorded.
+    pub fn stop_recording(&mut self) -> Option<OpLog> {
+        self.recorder.take().map(|recorder| recorder.into_log())
+    }
+
+    /// Apply an operation with `apply`, recording it as `op` if the operations are recorded.
+    fn recorded<T>(
+        &mut self,
+        op: impl FnOnce() -> Op,
+        apply: impl FnOnce(&mut System) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        match self.recorder.as_mut() {
+            Some(recorder) => recorder.start(op()),
+            None => return apply(self),
+        }
+        let result = apply(self);
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.end(self, result.as_ref().err());
+            self.recorder = Some(recorder);
+        }
+        result
+    }
+
     /// Fix the of a linear combination of variables in the `System` by adding a new LinEq to the LinBank.
     ///
     /// `lhs` contain all the variable of the left hand side of the equation
@@ -307,15 +644,34 @@ impl System {
     /// fix(vec![1,2,3], true) -> x1 + x2 + x3 = 1;
     /// ```
     ///
-    /// Return an `Error` if the fix was not linearly independant from the LinBank.
+    /// Return an `Error` if the fix was not linearly independant from the LinBank, or if the
+    /// `System` has no solution once it is fixed (see `has_contradiction`).
     pub fn fix(&mut self, lhs: Vec<usize>, rhs: bool) -> Result<(), io::Error> {
+        let recorded = lhs.clone();
+        self.recorded(
+            || Op::Fix { lhs: recorded, rhs },
+            |system| system.apply_fix(lhs, rhs),
+        )
+    }
+
+    /// `fix`, without recording it.
+    fn apply_fix(&mut self, lhs: Vec<usize>, rhs: bool) -> Result<(), io::Error> {
         let mut lhs_as_vob = Vob::new();
         lhs_as_vob.resize(self.nvar, false);
         for var in lhs.iter() {
             lhs_as_vob.set(*var, true);
         }
         let lin_eq = LinEq::new(lhs_as_vob, rhs);
-        match self.push_lin_eq_to_lin_bank(lin_eq) {
+        let pushed = self.push_lin_eq_to_lin_bank(lin_eq);
+        self.sync_tracker();
+        self.debug_validate();
+        if self.contradiction {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "linear equation contradicting the system, which has no solution",
+            ));
+        }
+        match pushed {
             Some(_) => Ok(()),
             None => Err(Error::new(
                 ErrorKind::InvalidData,
@@ -329,49 +685,141 @@ impl System {
     /// Returns the number of `LinEq` correctly absorbed or an `Error` if `bdd_id` is not in the
     /// `System`.
     pub fn scan_absorb_lin_eqs(&mut self, bdd_id: Id) -> Result<usize, io::Error> {
+        self.recorded(
+            || Op::Scan { bdd: bdd_id },
+            |system| system.apply_scan_absorb_lin_eqs(bdd_id),
+        )
+    }
+
+    /// `scan_absorb_lin_eqs`, without recording it.
+    fn apply_scan_absorb_lin_eqs(&mut self, bdd_id: Id) -> Result<usize, io::Error> {
         let mut absorbed = 0;
-        let bdd = self.get_bdd(bdd_id)?;
-        let mut lin_eqs = bdd.borrow_mut().scan_absorb_lin_eq();
+        let mut lin_eqs = self.bdd_mut(bdd_id)?.scan_absorb_lin_eq();
         for lin_eq in lin_eqs.drain(..) {
             if self.push_lin_eq_to_lin_bank(lin_eq).is_some() {
                 absorbed += 1;
             }
         }
+        self.sync_tracker();
+        self.debug_validate();
         Ok(absorbed)
     }
 
+    /// Scan all the `Bdd`s of the `System` for `LinEq` and push the `LinEq`s found to the `LinBank`.
+    ///
+    /// The `Bdd`s are scanned in parallel, then the `LinEq`s found are pushed one after the other,
+    /// each substitution in the `Bdd`s being made in parallel. The `Bdd`s reduced to their sink
+    /// are removed from the `System`.
+    ///
+    /// Returns the number of `LinEq` correctly absorbed.
+    pub fn scan_absorb_all_lin_eqs(&mut self) -> usize {
+        self.recorded(|| Op::ScanAll, |system| Ok(system.apply_scan_absorb_all_lin_eqs()))
+            .unwrap()
+    }
+
+    /// `scan_absorb_all_lin_eqs`, without recording it.
+    fn apply_scan_absorb_all_lin_eqs(&mut self) -> usize {
+        let mut absorbed = 0;
+        let lin_eqs: Vec<Vec<LinEq>> = self
+            .bdds
+            .par_iter_mut()
+            .map(|(_, bdd)| bdd.scan_absorb_lin_eq())
+            .collect();
+        for lin_eq in lin_eqs.into_iter().flatten() {
+            if self.push_lin_eq_to_lin_bank(lin_eq).is_some() {
+                absorbed += 1;
+            }
+        }
+        self.bdds.retain(|_, bdd| bdd.get_sink_level_index() > 0);
+        self.sync_tracker();
+        self.debug_validate();
+        absorbed
+    }
+
     /// Attempt to push the `LinEq` to the `LinBank` and if successfull remove the higher
     /// variable of the  modified `LinEq` from the whole `System`.
     ///
     /// Return `Some(modified lin_eq)` if successfull or `None` if `lin_eq` was not linearly
     /// independant from the `LinBank`.
+    ///
+    /// A `lin_eq` contradicting the `LinBank`, or leaving a `Bdd` without path once substituted,
+    /// shows that the `System` has no solution (see `has_contradiction`).
     fn push_lin_eq_to_lin_bank(&mut self, lin_eq: LinEq) -> Option<LinEq> {
+        let lin_eq = self.lin_bank.reduce_lin_eq(lin_eq);
+        if lin_eq.get_lhs_max_set_bit().is_none() {
+            // 0 = rhs
+            self.contradiction |= lin_eq.get_rhs();
+            return None;
+        }
         match self.lin_bank.push_lin_eq(lin_eq) {
             Some(eq) => {
                 let var = eq.get_lhs_max_set_bit().unwrap();
-                for bdd in self.bdds.iter_mut() {
-                    bdd.1.borrow_mut().replace_var_in_bdd(var, &eq);
-                }
+                let consistent = self
+                    .bdds
+                    .par_iter_mut()
+                    .map(|(_, bdd)| bdd.replace_var_in_bdd(var, &eq))
+                    .reduce(|| true, |a, b| a && b);
+                self.contradiction |= !consistent;
+                self.update_tracker(|tracker| tracker.substitute(var, &eq.get_lhs()));
                 Some(eq)
             }
             None => None,
         }
     }
 
+    /// With the `validate` feature, panic in debug builds if the `System` breaks one of its
+    /// invariants (see `validate`). Does nothing otherwise.
+    #[inline]
+    fn debug_validate(&self) {
+        #[cfg(all(debug_assertions, feature = "validate"))]
+        {
+            let report = self.validate();
+            assert!(report.is_valid(), "{}", report);
+        }
+    }
+
+    /// Return true if an operation showed that the `System` has no solution : a `LinEq`
+    /// contradicting the `LinBank`, a substitution leaving a `Bdd` without path, or an
+    /// absorbtion along an edge no node of the level has.
+    ///
+    /// The operation which found the contradiction may be left half done, the `System` is only
+    /// meant to be dropped then (for instance when backtracking on a guess).
+    pub fn has_contradiction(&self) -> bool {
+        self.contradiction
+    }
+
     /// Get the number of nodes inside the `System`.
     pub fn get_size(&self) -> usize {
         self.bdds
             .iter()
-            .fold(0, |acc, bdd| acc + bdd.1.borrow().get_size())
+            .fold(0, |acc, bdd| acc + bdd.1.get_size())
     }
 
     /// Iterate over the `bdds` of the `System`.
-    pub fn iter_bdds(&self) -> std::collections::hash_map::Iter<Id, RefCell<Bdd>> {
+    pub fn iter_bdds(&self) -> std::collections::hash_map::Iter<'_, Id, Bdd> {
         self.bdds.iter()
     }
 
+    /// Iterate in parallel over the `bdds` of the `System`.
+    pub fn par_iter_bdds(&self) -> impl ParallelIterator<Item = (&Id, &Bdd)> {
+        self.bdds.par_iter()
+    }
+
+    /// Iterate in parallel over mutable references to the `bdds` of the `System`, to reduce
+    /// independent `Bdd`s concurrently.
+    ///
+    /// /!\ The `Bdd`s are not checked afterwards: the operations applied should keep the
+    /// invariants of the `Bdd` (see `soc::validate`) and shouldn't change the variables it
+    /// depends on, as the `LinBank` is not updated. If the dependencies are tracked, they are
+    /// tracked again from scratch on the next operation.
+    pub fn par_iter_bdds_mut(&mut self) -> impl ParallelIterator<Item = (&Id, &mut Bdd)> {
+        self.tracker_stale = self.tracker.is_some();
+        self.bdds.par_iter_mut()
+    }
+
     /// Drain over the `bdds` of the `System`.
-    pub fn drain_bdds(&mut self) -> std::collections::hash_map::Drain<Id, RefCell<Bdd>> {
+    pub fn drain_bdds(&mut self) -> std::collections::hash_map::Drain<'_, Id, Bdd> {
+        self.tracker_stale = self.tracker.is_some();
         self.bdds.drain()
     }
 
@@ -380,7 +828,10 @@ impl System {
     /// Return an Error if `bdd_id` is not in the `System`.
     pub fn pop_bdd(&mut self, bdd_id: Id) -> Result<Bdd, io::Error> {
         match self.bdds.remove(&bdd_id) {
-            Some(bdd_ref) => Ok(bdd_ref.into_inner()),
+            Some(bdd) => {
+                self.update_tracker(|tracker| tracker.pop_bdd(bdd_id));
+                Ok(bdd)
+            }
             None => Err(Error::new(
                 ErrorKind::InvalidData,
                 format!("id {} not present in system", *bdd_id),
@@ -392,52 +843,247 @@ impl System {
     pub fn get_system_lhs(&self) -> Vec<(Id, Vec<Vob>)> {
         let mut system_lhs = Vec::new();
         for bdd in self.bdds.iter() {
-            system_lhs.push((*bdd.0, bdd.1.borrow().get_lhs()));
+            system_lhs.push((*bdd.0, bdd.1.get_lhs()));
         }
         system_lhs
     }
 
+    /// Track the linear dependencies among the levels of the `Bdd`s from now on : the reduction
+    /// of their lhs is kept and updated by each operation instead of being done again by each
+    /// call to `linear_dependencies` (see `soc::tracker`).
+    ///
+    /// The operations are a bit slower while tracking. `get_bdd_mut`, `par_iter_bdds_mut`,
+    /// `drain_bdds` and sifting hide the changes they make, the reduction is then done again
+    /// from scratch on the next operation.
+    pub fn track_dependencies(&mut self) {
+        if self.tracker.is_none() || self.tracker_stale {
+            self.tracker = Some(DependencyTracker::new(self));
+            self.tracker_stale = false;
+        }
+    }
+
+    /// Stop tracking the linear dependencies (see `track_dependencies`).
+    pub fn untrack_dependencies(&mut self) {
+        self.tracker = None;
+        self.tracker_stale = false;
+    }
+
+    /// Return true if the linear dependencies are tracked (see `track_dependencies`).
+    pub fn is_tracking_dependencies(&self) -> bool {
+        self.tracker.is_some()
+    }
+
+    /// Return the linear dependencies among the levels of the `Bdd`s, the columns being the
+    /// levels in the order of `get_system_lhs`. This is the same `Matrix` as
+    /// `algebra::extract_linear_dependencies` of the lhs given by `get_system_lhs` concatenated.
+    ///
+    /// When tracking the dependencies only the dependencies are reduced, otherwise all the lhs
+    /// are.
+    pub fn linear_dependencies(&self) -> Matrix {
+        match self.get_tracker() {
+            Some(tracker) => {
+                let order: Vec<Id> = self.bdds.keys().cloned().collect();
+                tracker.linear_dependencies(&order)
+            }
+            None => {
+                let lhs: Vec<Vob> = self.bdds.values().flat_map(|bdd| bdd.get_lhs()).collect();
+                PackedMatrix::from_rows(&lhs).linear_dependencies()
+            }
+        }
+    }
+
+    /// Return the number of linear dependencies among the levels of the `Bdd`s, the number of
+    /// rows of `linear_dependencies`.
+    pub fn count_linear_dependencies(&self) -> usize {
+        match self.get_tracker() {
+            Some(tracker) => tracker.dependencies_len(),
+            None => self.linear_dependencies().row_size(),
+        }
+    }
+
+    /// Return the tracker of the dependencies if they are tracked and it is up to date.
+    pub(crate) fn get_tracker(&self) -> Option<&DependencyTracker> {
+        match self.tracker_stale {
+            true => None,
+            false => self.tracker.as_ref(),
+        }
+    }
+
+    /// Update the tracker of the dependencies, if any, after an operation : with `update` if it
+    /// is up to date, by building it again otherwise.
+    fn update_tracker<F: FnOnce(&mut DependencyTracker)>(&mut self, update: F) {
+        if self.tracker_stale {
+            self.tracker_stale = false;
+            self.tracker = Some(DependencyTracker::new(self));
+        } else if let Some(tracker) = self.tracker.as_mut() {
+            update(tracker);
+        }
+    }
+
+    /// Update the tracker of the dependencies, if any, once `LinEq`s were absorbed : the
+    /// levels absorbed are removed, as well as the `Bdd`s which are not in the `System` anymore.
+    /// Only the `Bdd`s which lost levels are matched with their tracked levels.
+    fn sync_tracker(&mut self) {
+        if self.tracker_stale {
+            return self.update_tracker(|_| ());
+        }
+        if let Some(tracker) = self.tracker.as_mut() {
+            let ids: Vec<Id> = self.bdds.keys().cloned().collect();
+            tracker.retain(&ids);
+            for (id, bdd) in self.bdds.iter() {
+                let tracked = tracker.get_lhs(*id).map(|lhs| lhs.len());
+                if tracked != Some(bdd.get_levels_size() - 1) {
+                    tracker.sync_bdd(*id, &bdd.get_lhs());
+                }
+            }
+        }
+    }
+
     /// Return the solutions to the `System` using the `LinBank` and the paths in the
     /// remaining BDDs. If multiple BDDs are still in the system it will join all of them to
     /// find the solutions.
     ///
-    /// Will use the `algebra::solve_linear_system` to find the different solutions.
+    /// Each solution is the `AffineSpace::fixed` of a path (see `affine_solutions`), the
+    /// variables which are not determined by the path being `None`.
+    ///
+    /// /!\ This collects every solution, use `solutions` to walk them lazily when the
+    /// `System` may have a lot of them.
     pub fn calculate_solutions(&mut self) -> Vec<Vec<Option<bool>>> {
-        let keys: Vec<Id> = self.bdds.keys().cloned().collect();
-        let remaining_id = match keys.len() {
-            // everything in linbank
-            0 => {
-                let lhs = self.lin_bank.get_lhs();
-                let rhs = self.lin_bank.get_rhs();
-                return vec![algebra::solve_linear_system(matrix![lhs], rhs)];
-            }
-            // only one BDD left
-            1 => keys[0],
-            // multiple BDD, join everything first
-            _ => {
-                for key in 1..keys.len() {
-                    self.join_bdds(keys[0], keys[key]).unwrap();
+        self.solutions().collect()
+    }
+
+    /// Return a lazy iterator over the solutions to the `System`. If multiple BDDs are still
+    /// in the system it will join all of them first.
+    ///
+    /// Each solution is the `AffineSpace::fixed` of a path (see `affine_solutions`), the
+    /// variables which are not determined by the path being `None`.
+    pub fn solutions(&mut self) -> Solutions<'_> {
+        Solutions {
+            spaces: self.affine_solutions(),
+        }
+    }
+
+    /// Return a lazy iterator over the solutions to the `System` as affine spaces. If multiple
+    /// BDDs are still in the system it will join all of them first.
+    ///
+    /// Each path of the remaining `Bdd` is combined with the `LinBank` and solved using
+    /// `algebra::solve_affine_system`, paths contradicting the `LinBank` are skipped. The
+    /// solutions of the `System` are the union of the spaces, which are disjoint.
+    pub fn affine_solutions(&mut self) -> AffineSolutions<'_> {
+        let paths = match self.join_all_bdds() {
+            Some(id) => Some(self.bdds.get_mut(&id).unwrap().paths()),
+            None => None,
+        };
+        AffineSolutions {
+            paths,
+            lin_bank: &self.lin_bank,
+            nvar: self.nvar,
+            exhausted: false,
+        }
+    }
+
+    /// Return the exact number of assignments of the `nvar` variables satisfying the `System`,
+    /// without enumerating them.
+    ///
+    /// The counting is made on a copy of the `System` where all the `Bdd`s are joined and the
+    /// remaining linear dependencies, among the levels or with the `LinBank`, are resolved
+    /// by adding and absorbing. Once no dependency is left the `lhs` of the levels and of the
+    /// `LinBank` are linearly independent, so each path of the remaining `Bdd` is satisfied by
+    /// `2**(nvar - rank)` assignments.
+    ///
+    /// The dropped variables (see `drop`) are not counted: if some variables were dropped the
+    /// result is the number of assignments of the other variables which can be extended to a
+    /// solution of the `System` as it was before dropping.
+    ///
+    /// /!\ As it clones the `System` it should only be called on a `System` of a sensible size.
+    pub fn count_solutions(&self) -> BigUint {
+        let mut system = self.clone();
+        let (paths, nlevels) = match system.join_all_bdds() {
+            Some(id) => {
+                let bdd = system.bdds.get_mut(&id).unwrap();
+                if !system.lin_bank.absorb_dependencies(bdd) {
+                    return BigUint::from(0_usize);
+                }
+                match bdd.get_sink_level_index() {
+                    0 => (BigUint::from(1_usize), 0),
+                    sink_level_index => (bdd.count_paths(), sink_level_index),
                 }
-                keys[0]
             }
+            None => (BigUint::from(1_usize), 0),
         };
-        let paths = self
-            .get_bdd(remaining_id)
-            .unwrap()
-            .borrow()
-            .get_all_valid_path();
-        let mut solutions = Vec::new();
-        for path in paths {
-            let mut lin_bank = self.lin_bank.clone();
-            for eq in path {
-                lin_bank.push_lin_eq(eq);
-            }
-            solutions.push(algebra::solve_linear_system(
-                matrix![lin_bank.get_lhs()],
-                lin_bank.get_rhs(),
-            ));
+        let rank = nlevels + system.lin_bank.lin_eqs.len();
+        let free_vars = self
+            .nvar
+            .saturating_sub(rank)
+            .saturating_sub(self.dropped_vars.len());
+        paths << free_vars
+    }
+
+    /// Draw a solution of the `System` uniformly at random, or return `None` if none was found
+    /// after `max_attempts` paths were drawn (see `SolutionSampler`). If multiple BDDs are still
+    /// in the system it will join all of them first.
+    ///
+    /// To draw many solutions, use `solution_sampler` to avoid computing the weights of the
+    /// nodes again for each solution.
+    pub fn random_solution<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_attempts: usize,
+    ) -> Option<Vec<bool>> {
+        self.solution_sampler().sample(rng, max_attempts)
+    }
+
+    /// Return a `SolutionSampler` able to draw solutions of the `System` uniformly at random.
+    /// If multiple BDDs are still in the system it will join all of them first.
+    pub fn solution_sampler(&mut self) -> SolutionSampler<'_> {
+        let paths = match self.join_all_bdds() {
+            Some(id) => Some(self.bdds.get_mut(&id).unwrap().path_sampler()),
+            None => None,
+        };
+        SolutionSampler {
+            paths,
+            lin_bank: &self.lin_bank,
+            nvar: self.nvar,
+        }
+    }
+
+    /// Join all the `Bdd`s of the `System` into one.
+    ///
+    /// Return the `Id` of the remaining `Bdd` or `None` if the `System` has no `Bdd`.
+    fn join_all_bdds(&mut self) -> Option<Id> {
+        let keys: Vec<Id> = self.bdds.keys().cloned().collect();
+        for key in keys.iter().skip(1) {
+            self.join_bdds(keys[0], *key).unwrap();
         }
-        solutions
+        keys.first().cloned()
+    }
+
+    /// Iterate over the `LinEq`s of the `LinBank`, in the order they were pushed.
+    pub fn iter_lin_eqs(&self) -> std::slice::Iter<'_, LinEq> {
+        self.lin_bank.lin_eqs.iter()
+    }
+
+    /// Replace the `LinBank` by the given `LinEq`s, without substituting their highest
+    /// variable in the `Bdd`s.
+    ///
+    /// Only meant to restore a `LinBank` saved along with the `Bdd`s it was applied to.
+    pub(crate) fn restore_lin_bank(&mut self, lin_eqs: Vec<LinEq>) {
+        self.lin_bank.lin_eqs = lin_eqs;
+    }
+
+    /// Replace the dropped variables by the given ones.
+    ///
+    /// Only meant to restore the dropped variables saved along with the `System`.
+    pub(crate) fn restore_dropped_vars(&mut self, dropped_vars: Vec<usize>) {
+        self.dropped_vars = dropped_vars;
+    }
+
+    /// Add to `lin_eq` the equations of the `LinBank` (see `LinBank::reduce_lin_eq`).
+    ///
+    /// If the returned `LinEq` has an all zero `lhs`, `lin_eq` was not linearly independent
+    /// from the `LinBank`, and contradicts it if its `rhs` is true.
+    pub fn reduce_lin_eq(&self, lin_eq: LinEq) -> LinEq {
+        self.lin_bank.reduce_lin_eq(lin_eq)
     }
 
     /// Return the number of `LinEq` in the `LinBank`.
@@ -468,7 +1114,23 @@ impl LinBank {
     ///
     /// Return `Some(modified lin_eq)` if the lin_eq was pushed
     /// and `None` if it wasn't.
-    pub fn push_lin_eq(&mut self, mut lin_eq: LinEq) -> Option<LinEq> {
+    pub fn push_lin_eq(&mut self, lin_eq: LinEq) -> Option<LinEq> {
+        let lin_eq = self.reduce_lin_eq(lin_eq);
+        match lin_eq.get_lhs_max_set_bit() {
+            Some(_) => {
+                self.lin_eqs.push(lin_eq.clone());
+                Some(lin_eq)
+            }
+            None => None,
+        }
+    }
+
+    /// Add to `lin_eq` all the equations of the `LinBank` whose highest set bit is also
+    /// set in `lin_eq` (see `LinBank` doc).
+    ///
+    /// If the returned `LinEq` has an all zero `lhs`, `lin_eq` was not linearly independent
+    /// from the `LinBank`, and contradicts it if its `rhs` is true.
+    pub fn reduce_lin_eq(&self, mut lin_eq: LinEq) -> LinEq {
         for lin_bank_eq in self.lin_eqs.iter() {
             if lin_eq
                 .get_lhs()
@@ -478,12 +1140,60 @@ impl LinBank {
                 lin_eq.add_lin_eq(&lin_bank_eq)
             }
         }
-        match lin_eq.get_lhs_max_set_bit() {
-            Some(_) => {
-                self.lin_eqs.push(lin_eq.clone());
-                Some(lin_eq)
+        lin_eq
+    }
+
+    /// Resolve all the linear dependencies among the levels of `bdd` and the equations of
+    /// the `LinBank`, by adding the levels of a dependency together and absorbing the
+    /// resulting level along the sum of the `rhs` of the equations involved.
+    ///
+    /// Return false if one of the absorbtion would remove every path, meaning that the `Bdd`
+    /// and the `LinBank` have no common solution.
+    fn absorb_dependencies(&self, bdd: &mut Bdd) -> bool {
+        loop {
+            let mut rows = bdd.get_lhs();
+            let nlevels = rows.len();
+            if nlevels == 0 {
+                return true;
             }
-            None => None,
+            rows.extend(self.get_lhs());
+            let deps = algebra::extract_linear_dependencies(matrix![rows]);
+            // resolve the dependency with the smallest span of levels first
+            let dep = match deps
+                .iter_rows()
+                .filter(|dep| dep.iter_set_bits(..nlevels).next().is_some())
+                .min_by_key(|dep| {
+                    let mut levels = dep.iter_set_bits(..nlevels);
+                    let first = levels.next().unwrap();
+                    levels.last().unwrap_or(first) - first
+                }) {
+                Some(dep) => dep,
+                None => return true,
+            };
+            let levels: Vec<usize> = dep.iter_set_bits(..nlevels).collect();
+            let edge = dep
+                .iter_set_bits(nlevels..)
+                .fold(false, |rhs, i| rhs ^ self.lin_eqs[i - nlevels].get_rhs());
+            // same sequence of operations as the default `Solver::resolve`
+            for i in (0..levels.len() - 1).rev() {
+                for j in (levels[i] + 1..levels[i + 1]).rev() {
+                    bdd.swap(j, j + 1);
+                }
+                bdd.add(levels[i], levels[i] + 1);
+                if i != 0 {
+                    bdd.swap(levels[i], levels[i] + 1);
+                }
+            }
+            let level_index = if levels.len() > 1 {
+                levels[0] + 1
+            } else {
+                levels[0]
+            };
+            let (has_0edge, has_1edge) = bdd.level(level_index).unwrap().check_outgoing_edges();
+            if (edge && !has_1edge) || (!edge && !has_0edge) {
+                return false;
+            }
+            bdd.absorb(level_index, edge);
         }
     }
 
@@ -516,3 +1226,111 @@ impl fmt::Debug for LinBank {
         Ok(())
     }
 }
+
+/// Lazy iterator over the solutions of a `System`, created by `System::solutions`.
+pub struct Solutions<'a> {
+    spaces: AffineSolutions<'a>,
+}
+
+impl<'a> Iterator for Solutions<'a> {
+    type Item = Vec<Option<bool>>;
+
+    fn next(&mut self) -> Option<Vec<Option<bool>>> {
+        self.spaces.next().map(|space| space.fixed())
+    }
+}
+
+/// Lazy iterator over the solutions of a `System` as affine spaces, one for each path of the
+/// remaining `Bdd` consistent with the `LinBank`, created by `System::affine_solutions`.
+///
+/// Only one path of the remaining `Bdd` and one copy of the `LinBank` are held
+/// in memory at a time.
+pub struct AffineSolutions<'a> {
+    paths: Option<Paths<'a>>,
+    lin_bank: &'a LinBank,
+    nvar: usize,
+    exhausted: bool,
+}
+
+impl<'a> Iterator for AffineSolutions<'a> {
+    type Item = AffineSpace;
+
+    fn next(&mut self) -> Option<AffineSpace> {
+        let paths = match self.paths.as_mut() {
+            Some(paths) => paths,
+            // everything in linbank
+            None => {
+                if self.exhausted {
+                    return None;
+                }
+                self.exhausted = true;
+                return algebra::solve_affine_system(
+                    matrix![self.lin_bank.get_lhs()],
+                    self.lin_bank.get_rhs(),
+                    self.nvar,
+                );
+            }
+        };
+        'paths: for path in paths {
+            let mut lin_bank = self.lin_bank.clone();
+            for eq in path {
+                let eq = lin_bank.reduce_lin_eq(eq);
+                if eq.get_lhs_max_set_bit().is_none() {
+                    if eq.get_rhs() {
+                        // the path contradicts the LinBank
+                        continue 'paths;
+                    }
+                    continue;
+                }
+                lin_bank.lin_eqs.push(eq);
+            }
+            return algebra::solve_affine_system(
+                matrix![lin_bank.get_lhs()],
+                lin_bank.get_rhs(),
+                self.nvar,
+            );
+        }
+        None
+    }
+}
+
+/// Draw solutions of a `System` uniformly at random, created by `System::solution_sampler`.
+///
+/// A path of the remaining `Bdd` is drawn uniformly at random (see `PathSampler`), combined with
+/// the `LinBank`, and a random solution of the resulting linear system is drawn using
+/// `algebra::random_solution`.
+///
+/// Once combined with the `LinBank` all the paths have the same number of solutions, unless
+/// they are inconsistent with it. Inconsistent paths are rejected and another path is drawn,
+/// so a `System` with linear dependencies left may need several attempts per solution.
+///
+/// The values of the dropped variables of the `System` are random.
+pub struct SolutionSampler<'a> {
+    paths: Option<PathSampler<'a>>,
+    lin_bank: &'a LinBank,
+    nvar: usize,
+}
+
+impl<'a> SolutionSampler<'a> {
+    /// Draw a solution uniformly at random, or return `None` if every path drawn in
+    /// `max_attempts` attempts was inconsistent with the `LinBank`.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, max_attempts: usize) -> Option<Vec<bool>> {
+        for _ in 0..max_attempts {
+            let mut lhs = self.lin_bank.get_lhs();
+            let mut rhs = self.lin_bank.get_rhs();
+            if let Some(paths) = self.paths.as_ref() {
+                for eq in paths.sample(rng)? {
+                    lhs.push(eq.get_lhs());
+                    rhs.push(eq.get_rhs());
+                }
+            }
+            if lhs.is_empty() {
+                return Some((0..self.nvar).map(|_| rng.gen()).collect());
+            }
+            if let Some(solution) = algebra::random_solution(matrix![lhs], rhs, rng) {
+                return Some(solution.iter().collect());
+            }
+        }
+        None
+    }
+}
@@ -1,6 +1,24 @@
+use std::collections::HashMap;
 use std::io::Error;
 
-use crate::soc::{Id, utils};
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use vob::Vob;
+
+use crate::algebra::{self, AffineSpace};
+use crate::soc::{anf::{self, AnfError, Polynomial}, bdd::{sifting::Sifting, LinEq}, Id, binary, cnf::{self, Cnf, DimacsError}, level::Level, system::System, utils::{self, SyntaxError}};
+use crate::soc::dot::Dot;
+use crate::soc::oplog::{self, Op, OpLog};
+use crate::soc::stats::LevelStats;
+use crate::soc::symbols::{Role, SymbolTable};
+use crate::soc::validate::Violation;
+use crate::solver::budget::{Bounded, Budget, CancelToken, Reason};
+use crate::solver::checkpoint::{self, Checkpoint, Checkpointer};
+use crate::solver::event::{JsonLines, Observed, Observer, Silent};
+use crate::solver::guess::GuessAndDetermine;
+use crate::solver::profile::{Format, Profiler};
+use crate::solver::{Dependency, Solver};
 
 #[test]
 fn swap_test() {
@@ -60,6 +78,67 @@ fn drop_test() {
     assert_eq!(bdd, expected_result);
 }
 
+#[test]
+fn sifting_test() -> Result<(), Error> {
+    // x0 = x3, x1 = x4 and x2 = x5, with each pair next to each other: 10 nodes
+    let bdd = bdd!(6;0;[("0",[(1;2,3)]);("3",[(2;4,0);(3;0,4)]);("1",[(4;5,6)]);("4",[(5;7,0);(6;0,7)]);
+        ("2",[(7;8,9)]);("5",[(8;10,0);(9;0,10)]);("",[(10;0,0)])]);
+    assert_eq!(bdd.get_size(), 10);
+    let expected = brute_force_count(&[&bdd], 6);
+    // x0, x1, x2, x3, x4, x5: 22 nodes
+    let mut scrambled = bdd.clone();
+    scrambled.swap(1, 2);
+    scrambled.swap(3, 4);
+    scrambled.swap(2, 3);
+    assert_eq!(scrambled.get_size(), 22);
+
+    let mut sifted = scrambled.clone();
+    assert_eq!(sifted.sift(Sifting::rudell()), sifted.get_size());
+    assert_eq!(sifted.get_size(), 10);
+    assert!(sifted.validate().is_valid());
+    assert_eq!(brute_force_count(&[&sifted], 6), expected);
+
+    // the sums x0 + x3, x1 + x4 and x2 + x5 only have 0-edges
+    let mut sifted = scrambled.clone();
+    assert!(sifted.sift(Sifting::linear()) < 10);
+    assert!(sifted.validate().is_valid());
+    assert_eq!(brute_force_count(&[&sifted], 6), expected);
+
+    // the level x3 is moved back below x0
+    let mut sifted = scrambled.clone();
+    assert_eq!(sifted.sift_level(3, Sifting::rudell()), 1);
+    assert_eq!(sifted.get_lhs()[1], bdd.get_lhs()[1]);
+
+    let mut system = system![scrambled.clone()]?;
+    assert_eq!(system.sift_above(22, Sifting::rudell()), 22);
+    assert_eq!(system.sift_above(21, Sifting::rudell()), 10);
+    assert_eq!(system.sift(Id::new(0), Sifting::linear())?, system.get_size());
+    assert_eq!(system.count_solutions(), expected.into());
+    Ok(())
+}
+
+#[test]
+fn node_arena_test() {
+    // the 1-edge of the source jumps to the sink, the chain of nodes replacing it must be shared
+    // with the node 3 of the second spec which represents the same function
+    let bdd = bdd!(5;0;[("1+2",[(1;2,6)]);("3+2",[(2;4,5)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let expected = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;7,7)]);("0+4",[(4;0,6);(5;6,0);(7;6,6)]);("",[(6;0,0)])]);
+    assert_eq!(bdd, expected);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    // 3 independent lhs out of 5 variables, each path stands for 4 solutions
+    assert_eq!(bdd.count_paths(), 6_usize.into());
+    assert_eq!(brute_force_count(&[&bdd], 5), 6 * 4);
+
+    // the removed nodes leave no empty slot once the absorb is done
+    let mut bdd = bdd;
+    bdd.absorb(2, false);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    assert!(bdd
+        .iter_levels()
+        .all(|level| level.get_slots_len() == level.get_nodes_len()));
+    assert_eq!(bdd.count_paths(), 3_usize.into());
+}
+
 #[test]
 fn count_path_test() {
     let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
@@ -136,6 +215,82 @@ fn fix_test() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn scan_absorb_all_test() -> Result<(), Error> {
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<System>();
+
+    // x1 = 0 in the first Bdd and x1 + x3 = 1 in the second one, x3 = 1 is then substituted in
+    // the first Bdd where it gives x0 + x2 = 1
+    let bdd = bdd!(4;0;[("1",[(1;2,0)]);("0+2",[(2;3,4)]);("3",[(3;5,0);(4;0,5)]);("",[(5;0,0)])]);
+    let bdd_2 = bdd!(4;1;[("1+3",[(1;0,2)]);("",[(2;0,0)])]);
+    let expected = brute_force_count(&[&bdd, &bdd_2], 4);
+    let mut system = system![bdd, bdd_2]?;
+    let mut sequential = system.clone();
+    let size: usize = system.par_iter_bdds().map(|(_, bdd)| bdd.get_size()).sum();
+    assert_eq!(size, system.get_size());
+
+    let mut absorbed = 0;
+    loop {
+        match system.scan_absorb_all_lin_eqs() {
+            0 => break,
+            n => absorbed += n,
+        }
+    }
+    assert_eq!(absorbed, 3);
+    assert_eq!(system.iter_bdds().len(), 0);
+    assert_eq!(system.count_solutions(), expected.into());
+    assert_eq!(system.count_solutions(), 2_usize.into());
+
+    // same result as scanning the Bdds one after the other
+    for _ in 0..2 {
+        for id in [Id::new(0), Id::new(1)].iter() {
+            if sequential.get_bdd(*id).is_ok() {
+                sequential.scan_absorb_lin_eqs(*id)?;
+            }
+        }
+    }
+    assert_eq!(sequential.get_lin_bank_size(), 3);
+    let mut solutions = system.calculate_solutions();
+    let mut sequential_solutions = sequential.calculate_solutions();
+    solutions.sort();
+    sequential_solutions.sort();
+    assert_eq!(solutions, sequential_solutions);
+    Ok(())
+}
+
+#[test]
+fn observer_events_test() -> Result<(), Error> {
+    let bdd = bdd!(4;0;[("1",[(1;2,0)]);("0+2",[(2;3,4)]);("3",[(3;5,0);(4;0,5)]);("",[(5;0,0)])]);
+    let bdd_2 = bdd!(4;1;[("1+3",[(1;0,2)]);("",[(2;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    let observer = JsonLines::new(Vec::new());
+    {
+        let mut observed = Observed::new(&mut system, &observer);
+        observed.swap(Id::new(0), 1, 2)?;
+        // a failed operation is not notified
+        assert!(observed.swap(Id::new(2), 1, 2).is_err());
+        while observed.scan_absorb_all_lin_eqs() > 0 {}
+        observed.progress();
+    }
+    let output = String::from_utf8(observer.into_inner()?).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(
+        lines[0],
+        r#"{"event":"swap","bdd":0,"above":1,"below":2,"nodes":5}"#
+    );
+    let found = lines.iter().filter(|line| line.starts_with(r#"{"event":"lin_eq_found""#));
+    assert_eq!(found.count(), 3);
+    assert!(lines.contains(&r#"{"event":"bdd_removed","bdd":0}"#));
+    assert!(lines.contains(&r#"{"event":"bdd_removed","bdd":1}"#));
+    assert_eq!(
+        lines[6],
+        r#"{"event":"progress","bdds":0,"nodes":0,"biggest":0,"lin_eqs":3,"resolved":null,"remaining":null,"dropped":null,"max_nodes":null}"#
+    );
+    Ok(())
+}
+
 #[test]
 fn test_equality() {
     let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
@@ -143,3 +298,1679 @@ fn test_equality() {
     ("0+4",[(40000;0,60000);(50000;60000,0)]);("",[(60000;0,0)])]);
     assert_eq!(bdd, same_bdd)
 }
+
+#[test]
+fn paths_test() {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let paths: Vec<Vec<bool>> = bdd
+        .paths()
+        .map(|path| path.iter().map(|eq| eq.get_rhs()).collect())
+        .collect();
+    assert_eq!(paths.len(), 3);
+    assert!(paths.contains(&vec![false, false, true]));
+    assert!(paths.contains(&vec![false, true, false]));
+    assert!(paths.contains(&vec![true, false, true]));
+
+    // more than the 20 paths get_all_valid_path used to be capped to
+    let bdd = bdd!(6;0;[("0",[(1;2,2)]);("1",[(2;3,3)]);("2",[(3;4,4)]);("3",[(4;5,5)]);("4",[(5;6,6)]);("5",[(6;7,7)]);("",[(7;0,0)])]);
+    assert_eq!(bdd.paths().count(), 64);
+    assert_eq!(bdd.get_all_valid_path().len(), 64);
+
+    let bdd = bdd!(5;0;[("",[(6;0,0)])]);
+    let paths = bdd.get_all_valid_path();
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].is_empty());
+}
+
+#[test]
+fn solutions_test() -> Result<(), Error> {
+    // the third level is the sum of the first two, half of the paths are inconsistent
+    let bdd = bdd!(3;0;[("1",[(1;2,2)]);("2",[(2;3,3)]);("1+2",[(3;4,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd]?;
+    let solutions: Vec<Vec<Option<bool>>> = system.solutions().collect();
+    assert_eq!(solutions.len(), 4);
+    for solution in solutions.iter() {
+        assert_eq!(solution[0], None);
+        assert!(solution[1].is_some() && solution[2].is_some());
+    }
+
+    // x1 + x2 = 1 leaves x1 and x2 undetermined, the affine space keeps their relation
+    let bdd = bdd!(3;0;[("1+2",[(1;0,2)]);("",[(2;0,0)])]);
+    let mut system = system![bdd]?;
+    assert_eq!(system.calculate_solutions(), vec![vec![None, None, None]]);
+    let spaces: Vec<AffineSpace> = system.affine_solutions().collect();
+    assert_eq!(spaces.len(), 1);
+    assert_eq!((spaces[0].rank(), spaces[0].nullity()), (1, 2));
+    assert!(spaces[0].contains(&vob![true, false, true]));
+    assert!(!spaces[0].contains(&vob![true, true, true]));
+
+    let bdd = bdd!(3;0;[("1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(3;1;[("0+2",[(1;2,0)]);("",[(2;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    system.fix(vec![1], true)?;
+    assert_eq!(
+        system.calculate_solutions(),
+        vec![vec![Some(true), Some(true), Some(true)]]
+    );
+    Ok(())
+}
+
+/// Return true if the assignment `x` of the variables is a solution of `bdd`
+fn is_solution(bdd: &crate::soc::bdd::Bdd, x: usize) -> bool {
+    let mut node = bdd.level(0).unwrap().iter_nodes().next().unwrap().0;
+    for level in bdd.iter_levels().take(bdd.get_levels_size() - 1) {
+        let value = level.iter_set_lhs().fold(false, |acc, var| acc ^ (x >> var & 1 == 1));
+        let edges = level.get_node(&node).unwrap();
+        match if value { edges.get_e1() } else { edges.get_e0() } {
+            Some(next) => node = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Count the assignments of the `nvar` variables for which every `Bdd` of `bdds` has a path
+fn brute_force_count(bdds: &[&crate::soc::bdd::Bdd], nvar: usize) -> usize {
+    (0..1_usize << nvar)
+        .filter(|x| bdds.iter().all(|bdd| is_solution(bdd, *x)))
+        .count()
+}
+
+#[test]
+fn count_solutions_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let expected = brute_force_count(&[&bdd], 5);
+    let system = system![bdd]?;
+    assert_eq!(system.count_solutions(), expected.into());
+    assert_eq!(system.count_solutions(), 12_usize.into());
+
+    // dependency between the 2 Bdds : x1 + x2 = 0
+    let bdd = bdd!(3;0;[("1",[(1;2,3)]);("2",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(3;1;[("1+2",[(1;2,0)]);("",[(2;0,0)])]);
+    let expected = brute_force_count(&[&bdd, &bdd_2], 3);
+    let mut system = system![bdd, bdd_2]?;
+    assert_eq!(system.count_solutions(), expected.into());
+    assert_eq!(system.count_solutions(), 4_usize.into());
+
+    system.fix(vec![2], false)?;
+    assert_eq!(system.count_solutions(), 2_usize.into());
+    system.fix(vec![0], true)?;
+    assert_eq!(system.count_solutions(), 1_usize.into());
+
+    // x1 = x2 in the first Bdd contradicts x1 + x2 = 1
+    let bdd = bdd!(3;0;[("1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(3;1;[("1+2",[(1;0,2)]);("",[(2;0,0)])]);
+    let expected = brute_force_count(&[&bdd, &bdd_2], 3);
+    let system = system![bdd, bdd_2]?;
+    assert_eq!(system.count_solutions(), expected.into());
+    assert_eq!(system.count_solutions(), 0_usize.into());
+    Ok(())
+}
+
+#[test]
+fn cnf_test() -> Result<(), Error> {
+    // the second Bdd has a zero level, with a single edge
+    let bdd = bdd!(4;0;[("1",[(1;2,3)]);("0+2",[(2;4,4);(3;0,4)]);("3",[(4;5,0)]);("",[(5;0,0)])]);
+    let bdd_2 = bdd!(4;1;[("0+1+3",[(1;2,3)]);("",[(2;0,4);(3;4,0)]);("",[(4;0,0)])]);
+    let mut system = system![bdd.clone(), bdd_2.clone()]?;
+    system.fix(vec![1, 2, 3], true)?;
+    let cnf = Cnf::from_system(&system);
+    assert_eq!(cnf.get_nvar(), 4);
+    // x3 is substituted by x1 + x2 + 1 : 3 levels with several variables, 7 nodes above the sinks
+    assert_eq!(cnf.get_nb_vars(), 4 + 3 + 7);
+
+    // the projections of the models are exactly the solutions of the System
+    let satisfied = |a: usize| {
+        let value = |lit: isize| (a >> (lit.unsigned_abs() - 1) & 1 == 1) == (lit > 0);
+        cnf.iter_clauses()
+            .all(|clause| clause.iter().any(|lit| value(*lit)))
+            && cnf.iter_xors().all(|(vars, rhs)| {
+                vars.iter().fold(false, |acc, var| acc ^ value(*var as isize)) == *rhs
+            })
+    };
+    let mut projections: Vec<usize> = (0..1_usize << cnf.get_nb_vars())
+        .filter(|a| satisfied(*a))
+        .map(|a| a & 0b1111)
+        .collect();
+    projections.sort();
+    projections.dedup();
+    let expected: Vec<usize> = (0..16_usize)
+        .filter(|x| is_solution(&bdd, *x) && is_solution(&bdd_2, *x))
+        .filter(|x| (x >> 1 ^ x >> 2 ^ x >> 3) & 1 == 1)
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(projections, expected);
+
+    let mut bytes = Vec::new();
+    cnf.write_dimacs(&mut bytes)?;
+    let dimacs = String::from_utf8(bytes).unwrap();
+    let mut lines = dimacs.lines().skip_while(|line| line.starts_with('c'));
+    let nb_clauses = cnf.iter_clauses().len() + cnf.iter_xors().len();
+    assert_eq!(lines.next(), Some(format!("p cnf 14 {}", nb_clauses).as_str()));
+    assert_eq!(lines.count(), nb_clauses);
+    assert!(dimacs.contains("\nx2 3 4 0\n"));
+    assert!(dimacs.contains("\nx-1 3 "));
+
+    let model = Cnf::parse_model("c comment\ns SATISFIABLE\nv 1 -2 3\nv -4 5 0\n")?.unwrap();
+    assert_eq!(
+        cnf.solution(&model),
+        vec![Some(true), Some(false), Some(true), Some(false)]
+    );
+    assert_eq!(Cnf::parse_model("s UNSATISFIABLE\n")?, None);
+    assert!(Cnf::parse_model("v 1 2 0\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn dimacs_test() -> Result<(), Error> {
+    let content = "c a small instance\n\
+                   p cnf 7 8\n\
+                   1 -2 3 0\n\
+                   2 4 -4 0\n\
+                   -1 -3\n\
+                   5 0 -3 4 6 6 0\n\
+                   x1 -5 7 0\n\
+                   x 2 6 2 0\n\
+                   -7 0 4 -6 7 0\n\
+                   2 -5";
+    let cnf = Cnf::parse_dimacs(content)?;
+    assert_eq!(cnf.get_nvar(), 7);
+    assert_eq!(cnf.iter_clauses().len(), 7);
+    assert_eq!(
+        cnf.iter_xors().cloned().collect::<Vec<_>>(),
+        vec![(vec![1, 5, 7], false), (vec![6], true)]
+    );
+
+    let satisfied = |x: usize| {
+        let value = |lit: isize| (x >> (lit.unsigned_abs() - 1) & 1 == 1) == (lit > 0);
+        cnf.iter_clauses()
+            .all(|clause| clause.iter().any(|lit| value(*lit)))
+            && cnf.iter_xors().all(|(vars, rhs)| {
+                vars.iter().fold(false, |acc, var| acc ^ value(*var as isize)) == *rhs
+            })
+    };
+    let expected = (0..1_usize << 7).filter(|x| satisfied(*x)).count();
+    assert!(expected > 0);
+    for max_vars in [cnf::MAX_VARS, 3, 1].iter() {
+        let system = cnf.to_system(*max_vars)?;
+        assert_eq!(system.get_nvar(), 7);
+        // the unit clause and the XOR clauses are in the LinBank
+        assert_eq!(system.get_lin_bank_size(), 3);
+        assert_eq!(system.count_solutions(), expected.into());
+    }
+
+    // the DIMACS written by the export is read back to the same clauses
+    let bdd = bdd!(4;0;[("1",[(1;2,3)]);("0+2",[(2;4,4);(3;0,4)]);("3",[(4;5,0)]);("",[(5;0,0)])]);
+    let mut system = system![bdd]?;
+    system.fix(vec![1, 2, 3], false)?;
+    let exported = Cnf::from_system(&system);
+    let mut bytes = Vec::new();
+    exported.write_dimacs(&mut bytes)?;
+    let parsed = Cnf::parse_dimacs(&String::from_utf8(bytes).unwrap())?;
+    assert_eq!(parsed.get_nb_vars(), exported.get_nb_vars());
+    assert!(parsed.iter_clauses().eq(exported.iter_clauses()));
+    assert!(parsed.iter_xors().eq(exported.iter_xors()));
+
+    let unsatisfiable = |content: &str| {
+        match Cnf::parse_dimacs(content).unwrap().to_system(cnf::MAX_VARS) {
+            Err(DimacsError::Unsatisfiable { clauses, xors }) => (clauses, xors),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    };
+    assert_eq!(
+        unsatisfiable("p cnf 3 4\n1 2 0\n1 -2 0\n-1 2 0\n3 0\n-1 -2 0\n"),
+        (vec![0, 1, 2, 4], vec![])
+    );
+    assert_eq!(unsatisfiable("p cnf 3 3\n1 0\nx1 2 0\nx2 0\n"), (vec![], vec![1]));
+    assert_eq!(unsatisfiable("p cnf 3 2\n1 2 0\n0\n"), (vec![1], vec![]));
+
+    match Cnf::parse_dimacs("p cnf 3 1\n1 -4 0\n") {
+        Err(DimacsError::VarOutOfRange { line: 2, var: 4, nb_vars: 3 }) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+    match Cnf::parse_dimacs("c no header\n1 2 0\n") {
+        Err(DimacsError::Syntax(SyntaxError { line: 2, column: 1, .. })) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+    match Cnf::parse_dimacs("p cnf 3 1\n1 x2 0\n") {
+        Err(e @ DimacsError::Syntax(SyntaxError { line: 2, column: 3, .. })) => {
+            assert_eq!(e.to_string(), "line 2, column 3: unexpected `x2`, expected a literal")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(Cnf::parse_dimacs("p cnf three 1\n").is_err());
+    match Cnf::parse_dimacs("p cnf 1000000000000 1\n1 0\n")?.to_system(cnf::MAX_VARS) {
+        Err(DimacsError::TooManyVars { nb_vars: 1000000000000 }) => (),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    // a clause is built into a Bdd with a level per variable, even when it has many variables
+    let long: Vec<String> = (1..=3000).map(|var| var.to_string()).collect();
+    let content = format!("p cnf 3000 2\n{} 0\n-1 -2 0\n", long.join(" "));
+    let system = Cnf::parse_dimacs(&content)?.to_system(cnf::MAX_VARS)?;
+    let levels: Vec<usize> = system.iter_bdds().map(|(_, bdd)| bdd.get_levels_size()).collect();
+    assert_eq!(levels.iter().max(), Some(&3001));
+    Ok(())
+}
+
+#[test]
+fn anf_test() -> Result<(), Error> {
+    let content = "# a small system\n\
+                   x0*x1 + x2 + 1\n\
+                   x1*x2 + x(3)\n\
+                   \n\
+                   x0*x1*x2 + x_4 + x[5]\n\
+                   x3*x4 + x5*x0 + x3*x4*1 + x4*x3\n\
+                   x2 + x5 + 1\n\
+                   x1*x1 + x1 + x0*0\n";
+    let polynomials = anf::parse_anf_from_str(content)?;
+    assert_eq!(polynomials.len(), 6);
+    assert_eq!(polynomials[3].to_string(), "x0*x5 + x3*x4");
+    assert_eq!(polynomials[5].to_string(), "0");
+    assert_eq!(polynomials[2].degree(), 3);
+    assert_eq!(polynomials[2].nonlinear_vars(), vec![0, 1, 2]);
+    assert_eq!("x1*x0 + 1".parse::<Polynomial>()?, Polynomial::new(vec![vec![0, 1], vec![]]));
+
+    let eval = |polynomial: &Polynomial, x: usize| {
+        polynomial.get_monomials().iter().fold(false, |acc, monomial| {
+            acc ^ monomial.iter().all(|var| x >> var & 1 == 1)
+        })
+    };
+    let expected = (0..64_usize)
+        .filter(|x| polynomials.iter().all(|polynomial| !eval(polynomial, *x)))
+        .count();
+    assert!(expected > 0);
+    // a single group of nonlinear polynomials, then groups of at most 4 nonlinear variables
+    for (max_vars, nb_bdds) in [(anf::MAX_VARS, 2), (4, 3)].iter() {
+        let system = anf::build_system_from_anf(&polynomials, *max_vars)?;
+        assert_eq!(system.get_nvar(), 6);
+        assert_eq!(system.iter_bdds().len(), *nb_bdds);
+        let bdds: Vec<&crate::soc::bdd::Bdd> = system.iter_bdds().map(|(_, bdd)| bdd).collect();
+        assert_eq!(brute_force_count(&bdds, 6), expected);
+        assert_eq!(system.count_solutions(), expected.into());
+    }
+    match anf::build_system_from_anf(&polynomials, 3) {
+        Err(AnfError::TooManyVars { polynomial: 3, vars: 4, max_vars: 3 }) => (),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+    let contradiction = anf::parse_anf_from_str("x0*x1 + 1\nx0*x1*x2 + x0*x1\nx0*x2\n")?;
+    match anf::build_system_from_anf(&contradiction, anf::MAX_VARS) {
+        Err(AnfError::NoSolution { polynomials }) => assert_eq!(polynomials, vec![0, 1, 2]),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    let huge = anf::parse_anf_from_str("x0*x1\nx1 + x1000000000000\n")?;
+    match anf::build_system_from_anf(&huge, anf::MAX_VARS) {
+        Err(AnfError::VarOutOfRange { polynomial: 1, var: 1000000000000 }) => (),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    match anf::parse_anf_from_str("x1 + x2\nx1 + * x3\n") {
+        Err(AnfError::Syntax(SyntaxError { line: 2, column: 6, .. })) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+    match anf::parse_anf_from_str("x1*x(2\n") {
+        Err(e @ AnfError::Syntax(SyntaxError { line: 1, column: 7, .. })) => {
+            assert_eq!(
+                e.to_string(),
+                "line 1, column 7: unexpected end of line, expected the index of a variable"
+            )
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(anf::parse_anf_from_str("x1 x2").is_err());
+    assert!(anf::parse_anf_from_str("y1 + 1").is_err());
+    assert!(anf::parse_anf_from_str("x1 +").is_err());
+    Ok(())
+}
+
+#[test]
+fn count_solutions_dropped_test() -> Result<(), Error> {
+    // x0 only appears in the first level
+    let bdd = bdd!(2;0;[("0+1",[(1;2,2)]);("1",[(2;3,0)]);("",[(3;0,0)])]);
+    let mut system = system![bdd]?;
+    assert_eq!(system.count_solutions(), 2_usize.into());
+    system.drop(Id::new(0), 0)?;
+    assert_eq!(system.get_dropped_vars(), &[0]);
+    // x1 = 0 is the only assignment of x1 that can be extended
+    assert_eq!(system.count_solutions(), 1_usize.into());
+    Ok(())
+}
+
+#[test]
+fn drop_levels_test() -> Result<(), Error> {
+    // x1 and x4 only appear in the levels 0 and 2, x2 in the levels 0 and 1
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut system = system![bdd.clone()]?;
+    system.drop(Id::new(0), 2)?;
+    system.drop(Id::new(0), 0)?;
+    let mut batched = system![bdd]?;
+    batched.drop_levels(Id::new(0), &[0, 2])?;
+    assert_eq!(batched.get_dropped_vars(), &[4, 1]);
+    assert_eq!(batched.get_dropped_vars(), system.get_dropped_vars());
+    assert_eq!(batched.get_bdd(Id::new(0))?, system.get_bdd(Id::new(0))?);
+    assert!(batched.drop_levels(Id::new(0), &[0, 1]).is_err());
+    Ok(())
+}
+
+#[test]
+fn random_path_test() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let sampler = bdd.path_sampler();
+    assert_eq!(sampler.count_paths(), 3_usize.into());
+    let mut drawn: HashMap<Vec<bool>, usize> = HashMap::new();
+    for _ in 0..3000 {
+        let path = sampler.sample(&mut rng).unwrap();
+        *drawn
+            .entry(path.iter().map(|eq| eq.get_rhs()).collect())
+            .or_insert(0) += 1;
+    }
+    assert_eq!(drawn.len(), 3);
+    for count in drawn.values() {
+        assert!(*count > 800 && *count < 1200);
+    }
+
+    let bdd = bdd!(5;0;[("",[(6;0,0)])]);
+    assert_eq!(bdd.random_path(&mut rng).map(|path| path.len()), Some(0));
+}
+
+#[test]
+fn random_solution_test() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    // dependency between the 2 Bdds : x1 + x2 = 0, half of the paths are rejected
+    let bdd = bdd!(3;0;[("1",[(1;2,3)]);("2",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(3;1;[("1+2",[(1;2,0)]);("",[(2;0,0)])]);
+    let (check, check_2) = (bdd.clone(), bdd_2.clone());
+    let mut system = system![bdd, bdd_2]?;
+    let sampler = system.solution_sampler();
+    let mut drawn: HashMap<usize, usize> = HashMap::new();
+    for _ in 0..4000 {
+        let solution = sampler.sample(&mut rng, 100).unwrap();
+        let x = solution
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, b)| acc | (*b as usize) << i);
+        assert!(is_solution(&check, x) && is_solution(&check_2, x));
+        *drawn.entry(x).or_insert(0) += 1;
+    }
+    assert_eq!(drawn.len(), 4);
+    for count in drawn.values() {
+        assert!(*count > 800 && *count < 1200);
+    }
+
+    // x1 = x2 in the first Bdd contradicts x1 + x2 = 1
+    let bdd = bdd!(3;0;[("1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(3;1;[("1+2",[(1;0,2)]);("",[(2;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    assert_eq!(system.random_solution(&mut rng, 100), None);
+    Ok(())
+}
+
+#[test]
+fn binary_round_trip_test() -> Result<(), Error> {
+    let bdd = bdd!(6;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(6;1;[("1+3",[(1;2,3)]);("5",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    system.fix(vec![0, 2], true)?;
+    system.drop(Id::new(1), 1)?;
+    assert_eq!(system.get_dropped_vars(), &[5]);
+    let mut symbols = SymbolTable::new();
+    symbols.insert_all(&[0, 1, 2], "k", Role::Key)?;
+    symbols.insert(4, "s_r0_b1_out0", Role::State)?;
+    system.set_symbols(symbols);
+
+    let mut bytes = Vec::new();
+    binary::write_system(&system, &mut bytes)?;
+    let read = binary::read_system(&mut bytes.as_slice())?;
+    assert_eq!(read.get_nvar(), system.get_nvar());
+    assert_eq!(read.get_dropped_vars(), system.get_dropped_vars());
+    assert_eq!(read.get_symbols(), system.get_symbols());
+    assert_eq!(read.iter_bdds().len(), system.iter_bdds().len());
+    for (id, bdd) in system.iter_bdds() {
+        assert_eq!(read.get_bdd(*id)?, bdd);
+    }
+    let lin_eqs = |system: &System| -> Vec<(Vob, bool)> {
+        system
+            .iter_lin_eqs()
+            .map(|eq| (eq.get_lhs(), eq.get_rhs()))
+            .collect()
+    };
+    assert_eq!(lin_eqs(&read), lin_eqs(&system));
+
+    // truncated data, wrong magic and unsupported version
+    for len in 0..bytes.len() {
+        assert!(binary::read_system(&mut &bytes[..len]).is_err());
+    }
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert!(binary::read_system(&mut wrong_magic.as_slice()).is_err());
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = 3;
+    assert!(binary::read_system(&mut wrong_version.as_slice()).is_err());
+    // a huge nvar with a single byte of lhs fails without allocating the bitsets of nvar bits
+    let mut huge_nvar = b"CRHS\x02".to_vec();
+    huge_nvar.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x40]);
+    huge_nvar.extend_from_slice(&[1, 0, 1, 0xff]);
+    assert!(binary::read_system(&mut huge_nvar.as_slice()).is_err());
+    // huge counts of LinEq or dropped variables fail without allocating them up front
+    let headers: [&[usize]; 2] = [&[5 << 40, 0, 5 << 40], &[5 << 40, 0, 0, 5 << 40]];
+    for counts in headers.iter() {
+        let mut hostile = b"CRHS\x02".to_vec();
+        for count in counts.iter() {
+            binary::write_varint(&mut hostile, *count)?;
+        }
+        assert!(binary::read_system(&mut hostile.as_slice()).is_err());
+    }
+    // a LinBank equation with an all zero lhs breaks the invariants of the System
+    let zero_lin_eq = b"CRHS\x02\x02\x00\x01\x00\x00\x00\x00";
+    let e = binary::read_system(&mut &zero_lin_eq[..]).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+
+    // version 1 had no symbols section
+    system.set_symbols(SymbolTable::new());
+    let mut bytes = Vec::new();
+    binary::write_system(&system, &mut bytes)?;
+    assert_eq!(bytes.pop(), Some(0));
+    bytes[4] = 1;
+    let read = binary::read_system(&mut bytes.as_slice())?;
+    assert_eq!(read.get_symbols(), None);
+    for (id, bdd) in system.iter_bdds() {
+        assert_eq!(read.get_bdd(*id)?, bdd);
+    }
+    Ok(())
+}
+
+#[test]
+fn symbols_test() -> Result<(), Error> {
+    let mut symbols = SymbolTable::new();
+    symbols.insert_all(&[0, 1], "k", Role::Key)?;
+    symbols.insert(3, "p0", Role::Plaintext)?;
+    assert!(symbols.insert(4, "k0", Role::Key).is_err());
+    assert!(symbols.insert(4, "0k", Role::Key).is_err());
+    assert!(symbols.insert(4, "k-1", Role::Key).is_err());
+    assert!(symbols.insert(4, "", Role::Key).is_err());
+    // the names of the variables without symbol are only theirs
+    assert!(symbols.insert(4, "x2", Role::Key).is_err());
+    assert!(symbols.insert(4, "x02", Role::Key).is_err());
+    assert!(symbols.insert(4, "x99999999999999999999999", Role::Key).is_err());
+    symbols.insert(4, "x4", Role::Key)?;
+    symbols.insert(4, "x", Role::Key)?;
+    symbols.insert(4, "x2_out", Role::Key)?;
+    // renaming a variable frees its previous name
+    symbols.insert(1, "k_1", Role::Key)?;
+    symbols.insert(4, "k1", Role::Key)?;
+    assert_eq!(symbols.get_var("k_1"), Some(1));
+    assert_eq!(symbols.vars_with_role(Role::Key), vec![0, 1, 4]);
+    assert_eq!(symbols.name(2), "x2");
+    let mut lhs = Vob::from_elem(5, false);
+    assert_eq!(symbols.format_lhs(&lhs), "0");
+    lhs.set(2, true);
+    lhs.set(3, true);
+    assert_eq!(symbols.format_lhs(&lhs), "x2 + p0");
+    assert_eq!(
+        symbols.format_solution(&[Some(true), None, Some(false), Some(false)]),
+        "k0=1 k_1=? p0=0"
+    );
+
+    // the symbols go through the .bdd format and the solving
+    let text = "5 2\n0 4\n1+2:(1;2,3)|\n3+2:(2;4,5)(3;4,0)|\n0+4:(4;0,6)(5;6,0)|\n:(6;0,0)|\n---\n\
+                1 3\n1+3:(1;2,3)|\n4:(2;4,4)(3;0,4)|\n:(4;0,0)|\n---\n\
+                symbols 3\n0 k0 key\n1 k1 key\n4 s_r0_b0_out0 state\n";
+    let mut system = utils::try_build_system_from_spec(utils::parse_system_spec_from_str(text)?)?;
+    assert_eq!(system.var_name(4), "s_r0_b0_out0");
+    let dir = std::env::temp_dir().join(format!("crush_symbols_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    utils::print_system_to_file(&system, &dir.join("a.bdd"));
+    let printed = std::fs::read_to_string(dir.join("a.bdd"))?;
+    assert!(printed.ends_with("symbols 3\n0 k0 key\n1 k1 key\n4 s_r0_b0_out0 state\n"));
+    let read = utils::try_build_system_from_spec(utils::parse_system_spec_from_str(&printed)?)?;
+    assert_eq!(read.get_symbols(), system.get_symbols());
+    std::fs::remove_dir_all(&dir)?;
+    let names = system.get_symbols().cloned();
+    let solutions = DefaultSolver.solve::<SpanDependency>(&mut system)?;
+    assert_eq!(system.get_symbols().cloned(), names);
+    for solution in solutions.iter() {
+        assert!(system.format_solution(solution).starts_with("k0="));
+    }
+
+    use utils::SpecError;
+    let parse = |symbols: &str| {
+        let text = format!("3 1\n0 2\n1+2:(1;2,2)|\n:(2;0,0)|\n---\n{}", symbols);
+        utils::parse_system_spec_from_str(&text).expect_err("malformed symbols accepted")
+    };
+    assert!(matches!(
+        parse("symbols 2\n0 k0 key\n"),
+        SpecError::CountMismatch { line: 6, what: "symbols", announced: 2, found: 1 }
+    ));
+    assert!(matches!(
+        parse("symbols 1\n3 k0 key\n"),
+        SpecError::InvalidSymbol { line: Some(7), var: 3, .. }
+    ));
+    assert!(matches!(
+        parse("symbols 1\n0 k0 nonce\n"),
+        SpecError::InvalidSymbol { line: Some(7), var: 0, .. }
+    ));
+    assert!(matches!(
+        parse("symbols 2\n0 k0 key\n1 k0 key\n"),
+        SpecError::InvalidSymbol { line: Some(8), var: 1, .. }
+    ));
+    match parse("symbols 1\n0 k0\n") {
+        SpecError::Syntax(SyntaxError { line, .. }) => assert_eq!(line, 7),
+        e => panic!("unexpected error {}", e),
+    }
+    match parse("symbols 1\n0 k0 key\n1 2\n") {
+        SpecError::Syntax(SyntaxError { line, .. }) => assert_eq!(line, 8),
+        e => panic!("unexpected error {}", e),
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_text_conversion_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd, bdd_2]?;
+    let dir = std::env::temp_dir().join(format!("crush_binary_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let (text, bin, text_again) = (dir.join("a.bdd"), dir.join("a.crhs"), dir.join("b.bdd"));
+
+    utils::print_system_to_file(&system, &text);
+    binary::convert_text_to_binary(&text, &bin)?;
+    assert!(binary::is_binary_file(&bin)?);
+    assert!(!binary::is_binary_file(&text)?);
+    let read = binary::read_system_from_file(&bin)?;
+    for (id, bdd) in system.iter_bdds() {
+        assert_eq!(read.get_bdd(*id)?, bdd);
+    }
+
+    binary::convert_binary_to_text(&bin, &text_again)?;
+    let read = utils::build_system_from_spec(utils::parse_system_spec_from_file(&text_again));
+    for (id, bdd) in system.iter_bdds() {
+        assert_eq!(read.get_bdd(*id)?, bdd);
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn checkpoint_test() -> Result<(), Error> {
+    let bdd = bdd!(6;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(6;1;[("1+3",[(1;2,3)]);("5",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    system.fix(vec![0, 2], true)?;
+    system.drop(Id::new(1), 1)?;
+    let state = Checkpoint {
+        solved: 3,
+        remaining: 200,
+        dropped: 1,
+        max_nodes: 1 << 20,
+        context: vec!["drop".to_string(), "0123abcd".to_string()],
+    };
+
+    let mut bytes = Vec::new();
+    checkpoint::write_checkpoint(&state, &system, &mut bytes)?;
+    let (read_state, read) = checkpoint::read_checkpoint(&mut bytes.as_slice())?;
+    assert_eq!(read_state, state);
+    assert_eq!(read.get_dropped_vars(), system.get_dropped_vars());
+    assert_eq!(read.get_lin_bank_size(), system.get_lin_bank_size());
+    for (id, bdd) in system.iter_bdds() {
+        assert_eq!(read.get_bdd(*id)?, bdd);
+    }
+    for len in 0..bytes.len() {
+        assert!(checkpoint::read_checkpoint(&mut &bytes[..len]).is_err());
+    }
+    // a plain binary file is not a checkpoint
+    let mut plain = Vec::new();
+    binary::write_system(&system, &mut plain)?;
+    assert!(checkpoint::read_checkpoint(&mut plain.as_slice()).is_err());
+
+    let dir = std::env::temp_dir().join(format!("crush_checkpoint_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("solver.ckpt");
+    let mut checkpointer = Checkpointer::new(path.clone(), std::time::Duration::from_secs(3600));
+    checkpointer.set_context(vec!["no_drop".to_string()]);
+    assert!(!checkpointer.save_if_due(&state, &system)?);
+    assert!(!path.exists());
+    checkpointer.save(&state, &system)?;
+    let (read_state, _) = checkpoint::read_checkpoint_from_file(&path)?;
+    assert_eq!(read_state.context, vec!["no_drop".to_string()]);
+    assert_eq!(read_state.solved, state.solved);
+    // only the latest checkpoint is left
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn parse_spec_from_str_test() -> Result<(), Error> {
+    let text = "5 2\n0 4\n1+2:(1;2,3)|\n3+2:(2;4,5)(3;4,0)|\n0+4:(4;0,6)(5;6,0)|\n:(6;0,0)|\n---\n\
+                1 3\r\n1+3:(1;2,3)|\r\n4:(2;4,4)(3;0,4)|\r\n:(4;0,0)|\r\n---\r\n\n";
+    let system = utils::try_build_system_from_spec(utils::parse_system_spec_from_str(text)?)?;
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    assert_eq!(*system.get_bdd(Id::new(0))?, bdd);
+    assert_eq!(*system.get_bdd(Id::new(1))?, bdd_2);
+    Ok(())
+}
+
+#[test]
+fn parse_spec_errors_test() {
+    use utils::SpecError;
+    let parse = |text: &str| {
+        utils::parse_system_spec_from_str(text).expect_err("malformed spec accepted")
+    };
+    match parse("3 1\n0 2\n1+2:(1;2,x)|\n:(2;0,0)|\n---\n") {
+        SpecError::Syntax(SyntaxError { line, column, token, .. }) => {
+            assert_eq!((line, column, token.as_str()), (3, 10, "x"));
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    match parse("3 1\n0 2\n1+2(1;2,3)|\n") {
+        SpecError::Syntax(SyntaxError { line, column, token, .. }) => {
+            assert_eq!((line, column, token.as_str()), (3, 4, "("));
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    match parse("3 1\n0 2\n1+2:(1;2,3)\n") {
+        SpecError::Syntax(SyntaxError { line, column, token, .. }) => {
+            assert_eq!((line, column, token.as_str()), (3, 12, ""));
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    assert!(matches!(
+        parse("3 1\n0 2\n1+2:(1;2,3)|\n"),
+        SpecError::Truncated { line: 4, bdd: Some(_) }
+    ));
+    assert!(matches!(
+        parse("3 2\n0 2\n1+2:(1;2,2)|\n:(2;0,0)|\n---\n"),
+        SpecError::CountMismatch { line: 1, what: "bdds", announced: 2, found: 1 }
+    ));
+    assert!(matches!(
+        parse("3 1\n0 3\n1+2:(1;2,2)|\n:(2;0,0)|\n---\n"),
+        SpecError::CountMismatch { line: 2, what: "levels", announced: 3, found: 2 }
+    ));
+    match parse("3 1\n0 2\n1+2:(1;2,3)|\n:(2;0,0)|\n---\n") {
+        SpecError::DanglingEdge { line, node, edge, .. } => {
+            assert_eq!((line, *node, *edge), (Some(3), 1, 3));
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    match parse("3 1\n0 3\n1+2:(1;2,2)|\n0:(2;3,0)|\n:(2;0,0)|\n---\n") {
+        SpecError::DuplicateNode { line, node, .. } => assert_eq!((line, *node), (Some(5), 2)),
+        e => panic!("unexpected error {}", e),
+    }
+    // a bdd without level has no line to report
+    assert!(matches!(parse("3 1\n0 0\n---\n"), SpecError::InvalidBdd { line: None, .. }));
+    match parse("3 1\n0 2\n1+3:(1;2,2)|\n:(2;0,0)|\n---\n") {
+        SpecError::NvarMismatch { line, var, nvar, .. } => {
+            assert_eq!((line, var, nvar), (Some(3), 3, 3));
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    assert!(matches!(
+        parse("3 1\n0 2\n1:(1;3,0)(2;3,0)|\n:(3;0,0)|\n---\n"),
+        SpecError::InvalidBdd { line: Some(3), .. }
+    ));
+    match parse("1000000000000 1\n0 2\n0:(1;2,2)|\n:(2;0,0)|\n---\n") {
+        SpecError::TooManyVars { line, nvar } => assert_eq!((line, nvar), (Some(1), 1000000000000)),
+        e => panic!("unexpected error {}", e),
+    }
+    let huge = utils::SystemSpec::new(utils::MAX_NVAR + 1, Vec::new());
+    assert!(matches!(
+        utils::try_build_system_from_spec(huge),
+        Err(SpecError::TooManyVars { line: None, .. })
+    ));
+
+    // the structure of a Bdd is checked once it is built
+    let build = |text: &str| {
+        let spec = utils::parse_system_spec_from_str(text).expect("well formed spec rejected");
+        utils::try_build_system_from_spec(spec).expect_err("invalid bdd accepted")
+    };
+    match build("3 1\n0 3\n1:(1;2,3)|\n2:(2;4,0)(3;0,0)|\n:(4;0,0)|\n---\n") {
+        SpecError::InvalidBdd { reason, .. } => assert!(reason.contains("dead end"), "{}", reason),
+        e => panic!("unexpected error {}", e),
+    }
+    match build("3 1\n0 3\n1:(1;2,2)|\n2:(2;4,0)(3;0,4)|\n:(4;0,0)|\n---\n") {
+        SpecError::InvalidBdd { reason, .. } => assert!(reason.contains("orphan"), "{}", reason),
+        e => panic!("unexpected error {}", e),
+    }
+
+    let missing = std::env::temp_dir().join("crush_no_such_file.bdd");
+    assert!(matches!(
+        utils::try_parse_system_spec_from_file(&missing),
+        Err(SpecError::Io(_))
+    ));
+}
+
+/// The lhs of a level and its nodes as (e0, e1), an edge being the id of the child plus one,
+/// 0 meaning no edge.
+type RawLevel<'a> = (&'a [usize], &'a [(usize, usize)]);
+
+/// Build a `Bdd` of id 0 and nvar 5 from its levels, without any reduction.
+fn raw_bdd(levels: &[RawLevel]) -> crate::soc::bdd::Bdd {
+    let edge = |e: usize| if e == 0 { None } else { Some(Id::new(e - 1)) };
+    let mut bdd = crate::soc::bdd::Bdd::new();
+    for (lhs, nodes) in levels.iter() {
+        let mut level = Level::new();
+        level.set_lhs(lhs.to_vec(), 5);
+        for (e0, e1) in nodes.iter() {
+            level.add_edged_node(edge(*e0), edge(*e1));
+        }
+        bdd.add_existing_level(level);
+    }
+    bdd
+}
+
+#[test]
+fn validate_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    assert!(bdd.validate().is_valid());
+    let bdd_2 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    system.fix(vec![3], true)?;
+    system.add(Id::new(0), 0, 2)?;
+    system.join_bdds(Id::new(0), Id::new(1))?;
+    system.absorb(Id::new(0), 1, false)?;
+    assert!(system.validate().is_valid(), "{}", system.validate());
+
+    let bdd_id = Id::new(0);
+    let broken = raw_bdd(&[
+        (&[1], &[(1, 2), (1, 0)]),
+        (&[2], &[(1, 2), (1, 9)]),
+        (&[3], &[(1, 0), (1, 0), (0, 0)]),
+        (&[4], &[(0, 0)]),
+    ]);
+    let violations = broken.validate().violations().to_vec();
+    let expected = [
+        Violation::Source { bdd: bdd_id, nodes: 2 },
+        Violation::SinkLhs { bdd: bdd_id },
+        Violation::DanglingEdge { bdd: bdd_id, level: 1, node: Id::new(1), edge: Id::new(8) },
+        Violation::DeadEnd { bdd: bdd_id, level: 2, node: Id::new(2) },
+        Violation::SameChildren { bdd: bdd_id, level: 2, nodes: (Id::new(0), Id::new(1)) },
+        Violation::Orphan { bdd: bdd_id, level: 2, node: Id::new(2) },
+    ];
+    for violation in expected.iter() {
+        assert!(violations.contains(violation), "missing {}", violation);
+    }
+    assert_eq!(violations.len(), expected.len(), "{}", broken.validate());
+
+    // a LinEq of the wrong size, the node ids being local to a level two bdds can use the same
+    let bdd = raw_bdd(&[(&[1], &[(1, 1)]), (&[], &[(0, 0)])]);
+    let mut system = system![bdd.clone()]?;
+    let mut bdd_2 = bdd;
+    bdd_2.set_id(Id::new(1));
+    system.push_bdd(bdd_2)?;
+    system.restore_lin_bank(vec![LinEq::new(Vob::from_elem(4, true), true)]);
+    assert_eq!(
+        system.validate().violations(),
+        &[Violation::LinEqLength { index: 0, len: 4, nvar: 5 }]
+    );
+    let error = system.check_invariants().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn compact_sparse_test() {
+    // the nodes 1 and 2 of the level 1 are orphans, and so is the node 2 of the level 2
+    let mut bdd = raw_bdd(&[
+        (&[1], &[(1, 0)]),
+        (&[2], &[(1, 2), (3, 3), (3, 3)]),
+        (&[3], &[(1, 0), (0, 1), (1, 1)]),
+        (&[], &[(0, 0)]),
+    ]);
+    bdd.remove_orphans_start(1);
+    let slots = |bdd: &crate::soc::bdd::Bdd| {
+        bdd.iter_levels()
+            .map(|level| (level.get_slots_len(), level.get_nodes_len()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(slots(&bdd), vec![(1, 1), (3, 1), (3, 2), (1, 1)]);
+    // only the level 1 has more than half of its slots empty
+    bdd.compact_sparse();
+    assert_eq!(slots(&bdd), vec![(1, 1), (1, 1), (3, 2), (1, 1)]);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    assert_eq!(bdd.count_paths(), 2_usize.into());
+    bdd.compact();
+    assert_eq!(slots(&bdd), vec![(1, 1), (1, 1), (2, 2), (1, 1)]);
+}
+
+#[test]
+fn reduce_test() {
+    // absorbing the level 3 along 1 leaves the node 5 without edge, the dead end removal then
+    // makes the nodes 2 and 3 equal, two levels above the absorbed one
+    let mut bdd = bdd!(5;0;[("1",[(1;2,3)]);("2",[(2;4,5);(3;4,0)]);("3",[(4;6,6);(5;7,0)]);("4",[(6;8,8);(7;8,0)]);("",[(8;0,0)])]);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    bdd.absorb(3, true);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    assert_eq!(bdd.get_size(), 4);
+
+    // a spec is not always reduced, the nodes 2 and 3 are equal
+    let bdd = bdd!(5;0;[("1",[(1;2,3)]);("2",[(2;4,0);(3;4,0)]);("3",[(4;5,5)]);("",[(5;0,0)])]);
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    assert_eq!(bdd.get_size(), 4);
+}
+
+/// Build a system of 12 random quadratic equations of which `secret` is a solution, so that
+/// absorbing along the value of a level at `secret` never leaves the system without solution.
+fn quadratic_system(rng: &mut StdRng, secret: &[bool]) -> Result<System, Error> {
+    let nvar = secret.len();
+    let mut polynomials = Vec::new();
+    for _ in 0..12 {
+        let mut monomials: Vec<Vec<usize>> = (0..2)
+            .map(|_| vec![rng.gen_range(0, nvar), rng.gen_range(0, nvar)])
+            .collect();
+        monomials.extend((0..3).map(|_| vec![rng.gen_range(0, nvar)]));
+        if monomials.iter().fold(false, |acc, monomial| acc ^ monomial.iter().all(|var| secret[*var])) {
+            monomials.push(vec![]);
+        }
+        polynomials.push(Polynomial::new(monomials));
+    }
+    Ok(anf::build_system_from_anf(&polynomials, anf::MAX_VARS)?)
+}
+
+#[test]
+fn dependency_tracker_test() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+    let mut system = quadratic_system(&mut rng, &secret)?;
+    let from_scratch = |system: &System| {
+        let lhs: Vec<Vob> = system.get_system_lhs().into_iter().flat_map(|(_, lhs)| lhs).collect();
+        algebra::extract_linear_dependencies(matrix![lhs])
+    };
+    assert_eq!(system.linear_dependencies(), from_scratch(&system));
+    system.track_dependencies();
+    assert!(system.is_tracking_dependencies());
+    let mut dependencies = Vec::new();
+    for step in 0..300 {
+        dependencies.push(system.count_linear_dependencies());
+        let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+        if ids.is_empty() {
+            break;
+        }
+        ids.sort();
+        let id = ids[rng.gen_range(0, ids.len())];
+        let levels = system.get_bdd(id)?.get_levels_size() - 1;
+        match rng.gen_range(0, 7) {
+            0 if ids.len() > 1 => {
+                let other = *ids.iter().find(|other| **other != id).unwrap();
+                system.join_bdds(id, other)?;
+            }
+            1 if levels > 1 => {
+                let above = rng.gen_range(0, levels - 1);
+                system.swap(id, above, above + 1)?;
+            }
+            2 | 3 if levels > 1 => {
+                let above = rng.gen_range(0, levels - 1);
+                system.add(id, above, rng.gen_range(above + 1, levels))?;
+            }
+            4 if levels > 0 && step % 3 == 0 => system.drop(id, rng.gen_range(0, levels))?,
+            4 if levels > 0 => {
+                let level = rng.gen_range(0, levels);
+                let lhs = system.get_bdd(id)?.get_lhs_level(level);
+                let edge = lhs.iter_set_bits(..).fold(false, |acc, var| acc ^ secret[var]);
+                system.absorb(id, level, edge)?;
+            }
+            5 if step % 5 == 0 => {
+                // sifting is not tracked, the dependencies are tracked again from scratch
+                system.sift(id, Sifting::linear())?;
+            }
+            _ => {
+                system.scan_absorb_all_lin_eqs();
+            }
+        }
+        let expected = from_scratch(&system);
+        assert_eq!(system.linear_dependencies(), expected, "step {}", step);
+        assert_eq!(system.count_linear_dependencies(), expected.row_size());
+    }
+    // the operations did create and resolve dependencies
+    assert!(dependencies.iter().any(|count| *count > 2));
+    assert_eq!(system.count_linear_dependencies(), 0);
+    Ok(())
+}
+
+/// Return the join orders of the dependencies of `system`, the `Bdd`s being joined in the order
+/// of `System::get_system_lhs`.
+fn join_orders(system: &System) -> Vec<(Vec<Id>, Vec<usize>)> {
+    let lhs = system.get_system_lhs();
+    system
+        .linear_dependencies()
+        .iter_rows()
+        .map(|dependency| {
+            let mut join_order = (Vec::new(), Vec::new());
+            let (mut start, mut offset) = (0, 0);
+            for (id, lhs) in lhs.iter() {
+                let involved: Vec<usize> = (0..lhs.len())
+                    .filter(|level| dependency.get(start + level).unwrap())
+                    .collect();
+                if !involved.is_empty() {
+                    join_order.0.push(*id);
+                    join_order.1.extend(involved.iter().map(|level| level + offset));
+                    offset += lhs.len();
+                }
+                start += lhs.len();
+            }
+            join_order
+        })
+        .collect()
+}
+
+#[test]
+fn estimate_resolution_test() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(1);
+    let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+    let mut system = quadratic_system(&mut rng, &secret)?;
+    system.scan_absorb_all_lin_eqs();
+    let mut resolved = 0;
+    while system.count_linear_dependencies() > 0 {
+        let join_order = join_orders(&system).remove(0);
+        let exact = system.estimate_resolution(&join_order, usize::MAX)?;
+        let bound = system.estimate_resolution(&join_order, 0)?;
+        let partial = system.estimate_resolution(&join_order, exact.joined)?;
+        assert!(exact.exact);
+        assert!(!bound.exact);
+        for estimate in [bound, partial].iter() {
+            assert_eq!(estimate.joined, exact.joined);
+            assert!(estimate.peak >= exact.peak);
+            assert!(estimate.resolved >= exact.resolved);
+        }
+
+        // resolve the dependency like `Solver::resolve`
+        let size_before = system.get_size();
+        let root = join_order.0[0];
+        let size = |system: &System| system.get_bdd(root).unwrap().get_size();
+        for id in join_order.0.iter().skip(1) {
+            system.join_bdds(root, *id)?;
+        }
+        assert_eq!(size(&system), exact.joined);
+        let levels = &join_order.1;
+        let mut peak = size(&system);
+        for i in (0..levels.len() - 1).rev() {
+            for j in (levels[i] + 1..levels[i + 1]).rev() {
+                system.swap(root, j, j + 1)?;
+                peak = peak.max(size(&system));
+            }
+            system.add(root, levels[i], levels[i] + 1)?;
+            peak = peak.max(size(&system));
+            if i != 0 {
+                system.swap(root, levels[i], levels[i] + 1)?;
+                peak = peak.max(size(&system));
+            }
+        }
+        system.absorb(root, levels[0] + 1, false)?;
+        assert_eq!(peak, exact.peak);
+        assert_eq!(size(&system), exact.resolved);
+        assert!(system.get_size() <= size_before - exact.joined + exact.resolved);
+        system.scan_absorb_all_lin_eqs();
+        resolved += 1;
+    }
+    assert!(resolved > 1);
+    Ok(())
+}
+
+/// A `Dependency` whose distance is the number of levels between its first and last levels.
+struct SpanDependency((Vec<Id>, Vec<usize>));
+
+impl Dependency for SpanDependency {
+    fn minimize_distance(&self) -> usize {
+        self.0 .1.last().unwrap() - self.0 .1[0]
+    }
+
+    fn best_join_order(&self) -> (Vec<Id>, Vec<usize>) {
+        self.0.clone()
+    }
+
+    fn extract(system: &System) -> Vec<SpanDependency> {
+        join_orders(system).into_iter().map(SpanDependency).collect()
+    }
+}
+
+/// The default `Solver`, silent.
+struct DefaultSolver;
+
+impl Solver for DefaultSolver {
+    fn observer(&self) -> &dyn Observer {
+        &Silent
+    }
+}
+
+#[test]
+fn guess_and_determine_test() -> Result<(), Error> {
+    for seed in 0..4 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+        let system = quadratic_system(&mut rng, &secret)?;
+        let mut expected = DefaultSolver.solve::<SpanDependency>(&mut system.clone())?;
+        expected.sort();
+        let secret: Vec<Option<bool>> = secret.into_iter().map(Some).collect();
+        assert!(expected.contains(&secret));
+        // guessing before every resolution, the wrong guesses end with contradictions
+        for (threshold, max_guesses) in [(0, 4), (usize::MAX, 4)].iter() {
+            let mut solver = GuessAndDetermine::with_observer(*threshold, Box::new(Silent));
+            solver.set_max_guesses(*max_guesses);
+            let mut solutions = solver.solve::<SpanDependency>(&mut system.clone())?;
+            solutions.sort();
+            assert_eq!(solutions, expected, "seed {} threshold {}", seed, threshold);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn contradiction_test() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+    let mut system = quadratic_system(&mut rng, &secret)?;
+    let solutions = DefaultSolver.solve::<SpanDependency>(&mut system.clone())?;
+    // fixing the variables one after the other to an assignment which is not a solution
+    let wrong = (0..16)
+        .map(|var| {
+            let mut wrong: Vec<Option<bool>> = secret.iter().cloned().map(Some).collect();
+            wrong[var] = Some(!secret[var]);
+            wrong
+        })
+        .find(|wrong| !solutions.contains(wrong))
+        .unwrap();
+    let mut contradicted = false;
+    for (var, value) in wrong.into_iter().enumerate() {
+        if system.fix(vec![var], value.unwrap()).is_err() && system.has_contradiction() {
+            contradicted = true;
+            break;
+        }
+    }
+    assert!(contradicted);
+    Ok(())
+}
+
+#[test]
+fn merge_level_one_test() {
+    // the children of the source are equal, the level 1 is the last one to merge
+    let mut bdd = raw_bdd(&[
+        (&[1], &[(1, 2)]),
+        (&[2], &[(1, 0), (1, 0)]),
+        (&[3], &[(1, 1)]),
+        (&[], &[(0, 0)]),
+    ]);
+    bdd.merge_equals_node_start(1);
+    bdd.compact();
+    assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    assert_eq!(bdd.get_size(), 4);
+}
+
+#[test]
+fn zero_level_test() -> Result<(), Error> {
+    // a level with an all zero lhs and only 1edges is the equation 0 = 1
+    let bdd = raw_bdd(&[
+        (&[1], &[(1, 2)]),
+        (&[], &[(0, 1), (0, 2)]),
+        (&[2], &[(1, 0), (0, 1)]),
+        (&[], &[(0, 0)]),
+    ]);
+    let lin_eqs = bdd.clone().scan_absorb_lin_eq();
+    let last = lin_eqs.last().unwrap();
+    assert!(last.get_lhs_max_set_bit().is_none() && last.get_rhs());
+    let mut system = system![bdd]?;
+    system.scan_absorb_lin_eqs(Id::new(0))?;
+    assert!(system.has_contradiction());
+
+    // substituting x2 by x1 makes the lhs of the level 0 all zero, and it has only a 1edge
+    let mut bdd = raw_bdd(&[(&[1, 2], &[(0, 1)]), (&[], &[(0, 0)])]);
+    let mut lhs = Vob::from_elem(5, false);
+    lhs.set(1, true);
+    lhs.set(2, true);
+    assert!(!bdd.replace_var_in_bdd(2, &LinEq::new(lhs.clone(), false)));
+    let mut bdd = raw_bdd(&[(&[1, 2], &[(0, 1)]), (&[], &[(0, 0)])]);
+    assert!(bdd.replace_var_in_bdd(2, &LinEq::new(lhs, true)));
+    assert_eq!(bdd.get_size(), 1);
+    Ok(())
+}
+
+#[test]
+fn solve_bounded_test() -> Result<(), Error> {
+    let mut stopped = 0;
+    for seed in 0..4 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+        let system = quadratic_system(&mut rng, &secret)?;
+        let mut expected = DefaultSolver.solve::<SpanDependency>(&mut system.clone())?;
+        expected.sort();
+        let solutions = match DefaultSolver.solve_bounded::<SpanDependency>(system.clone(), &Budget::new())? {
+            Bounded::Done(solutions, stats) => {
+                assert_eq!(stats.remaining, 0);
+                solutions
+            }
+            Bounded::Exceeded(_) => panic!("an unlimited budget was exceeded"),
+        };
+        assert_eq!(solutions.len(), expected.len());
+        // the partially solved system has the same solutions
+        let mut budget = Budget::new();
+        budget.set_max_nodes(system.get_size());
+        match DefaultSolver.solve_bounded::<SpanDependency>(system.clone(), &budget)? {
+            Bounded::Done(_, stats) => assert!(stats.peak <= system.get_size()),
+            Bounded::Exceeded(mut exceeded) => {
+                match exceeded.reason {
+                    Reason::Nodes { nodes } => assert!(nodes > system.get_size()),
+                    reason => panic!("unexpected {:?}", reason),
+                }
+                assert!(exceeded.stats.peak <= system.get_size());
+                let mut solutions = DefaultSolver.solve::<SpanDependency>(&mut exceeded.system)?;
+                solutions.sort();
+                assert_eq!(solutions, expected, "seed {}", seed);
+                stopped += 1;
+            }
+        }
+        let cancel = CancelToken::new();
+        let mut budget = Budget::new();
+        budget.set_cancel_token(cancel.clone());
+        cancel.cancel();
+        match DefaultSolver.solve_bounded::<SpanDependency>(system.clone(), &budget)? {
+            Bounded::Exceeded(exceeded) => {
+                assert_eq!(exceeded.reason, Reason::Cancelled);
+                assert_eq!(exceeded.stats.resolved, 0);
+            }
+            Bounded::Done(..) => panic!("a cancelled solving went through"),
+        }
+        let mut budget = Budget::new();
+        budget.set_time_limit(std::time::Duration::from_secs(0));
+        match DefaultSolver.solve_bounded::<SpanDependency>(system.clone(), &budget)? {
+            Bounded::Exceeded(exceeded) => assert_eq!(exceeded.reason, Reason::Time),
+            Bounded::Done(..) => panic!("an elapsed solving went through"),
+        }
+    }
+    assert!(stopped > 0);
+    Ok(())
+}
+
+#[test]
+fn oplog_test() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+    let initial = quadratic_system(&mut rng, &secret)?;
+    let dir = std::env::temp_dir().join(format!("crush_oplog_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("solve.oplog");
+
+    let mut system = initial.clone();
+    system.record_operations_to(Box::new(std::fs::File::create(&path)?))?;
+    assert!(system.absorb(Id::new(0), 1000, true).is_err());
+    system.fix(vec![0, 3], secret[0] ^ secret[3])?;
+    // the operations on the clones, counting the solutions, are not recorded
+    let count = system.count_solutions();
+    assert!(system.clone().get_operation_log().is_none());
+    assert_eq!(system.get_operation_log().map(|log| log.len()), Some(2));
+    let expected = DefaultSolver.solve::<SpanDependency>(&mut system)?;
+    let log = system.stop_recording().unwrap();
+    assert!(system.get_operation_log().is_none());
+    assert!(log.steps().iter().all(|step| step.outcome.is_some()));
+    assert!(log.steps()[0].outcome.as_ref().unwrap().error.is_some());
+    assert_eq!(log.steps()[1].op, Op::Fix { lhs: vec![0, 3], rhs: secret[0] ^ secret[3] });
+    for op in ["join", "swap", "add", "absorb", "scan_all"].iter() {
+        assert!(log.steps().iter().any(|step| step.op.to_string().starts_with(op)), "no {}", op);
+    }
+
+    // the sink and the log written afterwards hold the same log
+    let mut written = Vec::new();
+    log.write(&mut written)?;
+    assert_eq!(std::fs::read(&path)?, written);
+    assert_eq!(OpLog::read_from_file(&path)?, log);
+
+    // replaying all the steps gives the same system, any prefix of them can be replayed
+    let mut replayed = initial.clone();
+    oplog::replay(&mut replayed, &OpLog::read_from_file(&path)?, usize::MAX)?;
+    assert_eq!(replayed.calculate_solutions(), expected);
+    assert_eq!(replayed.count_solutions(), count);
+    let half = log.len() / 2;
+    let mut replayed = initial.clone();
+    oplog::replay(&mut replayed, &log, half)?;
+    assert_eq!(Some(replayed.get_size()), log.steps()[half - 1].outcome.as_ref().map(|o| o.nodes));
+
+    // a log cut in the middle of an operation replays up to it, included
+    let cut = String::from_utf8(written.clone()).unwrap();
+    let cut: Vec<&str> = cut.lines().take(2 * half + 2).collect();
+    let cut = OpLog::read(cut.join("\n").as_bytes())?;
+    assert_eq!(cut.len(), half + 1);
+    assert!(cut.steps()[half].outcome.is_none());
+    let mut replayed = initial.clone();
+    oplog::replay(&mut replayed, &cut, usize::MAX)?;
+    let mut expected = initial.clone();
+    oplog::replay(&mut expected, &log, half + 1)?;
+    assert_eq!(replayed.get_size(), expected.get_size());
+
+    // replaying on another system, or diverging from the log, fails
+    let mut other = quadratic_system(&mut rng, &secret)?;
+    other.fix(vec![1], secret[1])?;
+    assert!(oplog::replay(&mut other, &log, usize::MAX).is_err());
+    let text = String::from_utf8(written).unwrap();
+    let diverging = text.replacen("=> nodes=", "=> nodes=1", 1);
+    let diverging = OpLog::read(diverging.as_bytes())?;
+    let error = oplog::replay(&mut initial.clone(), &diverging, usize::MAX).unwrap_err();
+    assert!(error.to_string().starts_with("step 0 "), "{}", error);
+
+    // malformed logs
+    for text in [
+        "",
+        "oplog 2 nvar=1 nodes=1 lin_eqs=0\n",
+        "oplog 1 nvar=1 nodes=1\n",
+        "oplog 1 nvar=1 nodes=1 lin_eqs=0\nswap bdd=0 above=0\n",
+        "oplog 1 nvar=1 nodes=1 lin_eqs=0\nrotate bdd=0\n",
+        "oplog 1 nvar=1 nodes=1 lin_eqs=0\nabsorb bdd=0 level=0 edge=2\n",
+        "oplog 1 nvar=1 nodes=1 lin_eqs=0\nscan bdd=0\nscan bdd=0\n",
+    ]
+    .iter()
+    {
+        assert!(OpLog::read(text.as_bytes()).is_err(), "{:?} accepted", text);
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn dot_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd, bdd_2]?;
+    let mut symbols = SymbolTable::new();
+    symbols.insert_all(&[0, 1, 2], "k", Role::Key)?;
+    system.set_symbols(symbols);
+    let render = |dot: &Dot| -> Result<String, Error> {
+        let mut written = Vec::new();
+        dot.write_system(&system, &mut written)?;
+        Ok(String::from_utf8(written).unwrap())
+    };
+
+    let plain = render(&Dot::new())?;
+    assert!(plain.contains("subgraph \"cluster_0\"") && plain.contains("subgraph \"cluster_1\""));
+    assert!(plain.contains("label = \"0. k1 + k2\"") && plain.contains("label = \"1. k2 + x3\""));
+    assert!(plain.contains("\"b0_0_0\" -> \"b0_1_1\" [];"));
+    assert!(plain.contains("\"b0_0_0\" -> \"b0_1_0\" [style = dashed];"));
+    assert!(!plain.contains("...") && !plain.contains("color"));
+    assert_eq!(plain.matches('{').count(), plain.matches('}').count());
+
+    let mut dot = Dot::new();
+    dot.show_node_ids(true);
+    dot.show_path_counts(true);
+    dot.set_weights(Id::new(0), 1, vec![(Id::new(0), 2), (Id::new(1), 5)]);
+    dot.set_path(Id::new(0), vec![true, false, true]);
+    dot.set_window(1..3);
+    let annotated = render(&dot)?;
+    // levels 1 and 2 of the first bdd, the edges of the level 2 leaving the window
+    assert!(!annotated.contains("\"b0_l0\"") && !annotated.contains("\"b0_l3\""));
+    assert!(annotated.contains("\"b0_2_0\" -> \"b0_cut\""));
+    assert!(annotated.contains("\"b1_l2\" [shape = plaintext; label = \"2. sink\"]"));
+    // the node 0 of the level 1 has 2 paths to the sink and the lowest weight
+    assert!(annotated.contains("\"b0_1_0\" [label = \"0\\n2\"; style = filled; fillcolor = \"0.000 1.000 1.000\""));
+    assert!(annotated.contains("\"b0_1_1\" [label = \"1\\n1\"; style = filled; fillcolor = \"0.660 1.000 1.000\""));
+    // the path goes through the node 1 of the level 1 and the node 0 of the level 2
+    assert!(annotated.contains("\"b0_1_1\" -> \"b0_2_0\" [style = dashed; color = red; penwidth = 3];"));
+    assert!(annotated.contains("\"b0_2_0\" -> \"b0_cut\" [color = red; penwidth = 3];"));
+    assert_eq!(annotated.matches("penwidth").count(), 4);
+    Ok(())
+}
+
+struct ProfiledSolver(Profiler);
+
+impl Solver for ProfiledSolver {
+    fn observer(&self) -> &dyn Observer {
+        &Silent
+    }
+
+    fn profiler(&self) -> Option<&Profiler> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn stats_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,4);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd_2, bdd]?;
+    let stats = system.stats();
+    assert_eq!(stats.bdds.iter().map(|bdd| bdd.bdd).collect::<Vec<_>>(), vec![Id::new(0), Id::new(1)]);
+    assert_eq!(stats.nodes(), system.get_size());
+    let level = |level, nodes, single_edge, lhs_weight| LevelStats { level, nodes, single_edge, lhs_weight };
+    assert_eq!(
+        stats.bdds[0].levels,
+        vec![level(0, 1, 0, 2), level(1, 2, 1, 2), level(2, 2, 2, 2), level(3, 1, 0, 0)]
+    );
+    assert_eq!(stats.bdds[0].width(), 2);
+    assert_eq!(stats.bdds[1].levels[1].single_edge_fraction(), 0.5);
+    assert_eq!(stats.bdds[1], system.get_bdd(Id::new(1))?.stats());
+
+    let json = stats.to_json();
+    assert!(json.starts_with(r#"{"nvar":5,"lin_eqs":0,"nodes":10,"bdds":[{"bdd":0,"nodes":6,"width":2,"levels":["#));
+    assert!(json.contains(r#"{"level":1,"nodes":2,"single_edge":1,"single_edge_fraction":0.5,"lhs_weight":2}"#));
+    assert_eq!(json.matches('{').count(), json.matches('}').count());
+    let mut csv = Vec::new();
+    stats.write_csv(&mut csv)?;
+    let csv = String::from_utf8(csv).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 1 + 4 + 3);
+    assert_eq!(rows[0], "bdd,level,nodes,single_edge,lhs_weight");
+    assert_eq!(rows[3], "0,2,2,2,2");
+
+    // a profile of the joined bdd is written after each join of a solving
+    let mut rng = StdRng::seed_from_u64(0);
+    let secret: Vec<bool> = (0..16).map(|_| rng.gen()).collect();
+    let initial = quadratic_system(&mut rng, &secret)?;
+    let dir = std::env::temp_dir().join(format!("crush_stats_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let mut profiles = Vec::new();
+    for name in ["profile.json", "profile.csv"].iter() {
+        let path = dir.join(name);
+        let mut solver = ProfiledSolver(Profiler::create(&path)?);
+        let mut system = initial.clone();
+        let expected = DefaultSolver.solve::<SpanDependency>(&mut initial.clone())?;
+        assert_eq!(solver.solve::<SpanDependency>(&mut system)?, expected);
+        solver.0.flush()?;
+        assert!(solver.0.get_steps() > 0);
+        profiles.push((solver.0.get_format(), solver.0.get_steps(), std::fs::read_to_string(&path)?));
+    }
+    let (format, steps, json) = &profiles[0];
+    assert_eq!(*format, Format::JsonLines);
+    assert_eq!(json.lines().count(), *steps);
+    for (i, line) in json.lines().enumerate() {
+        assert!(line.starts_with(&format!(r#"{{"step":{},"bdd":"#, i)), "{}", line);
+    }
+    let (format, csv_steps, csv) = &profiles[1];
+    assert_eq!(*format, Format::Csv);
+    assert_eq!(csv_steps, steps);
+    assert_eq!(csv.lines().next(), Some("step,bdd,level,nodes,single_edge,lhs_weight"));
+    assert_eq!(csv.lines().last().unwrap().split(',').next(), Some((steps - 1).to_string().as_str()));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Build a random `Bdd` of id `id` over `nvar` variables, with 1 to `max_levels` levels above
+/// the sink holding at most `max_width` nodes each. If `reduced` is false the equal nodes are
+/// not merged, and the `Bdd` may break the `SameChildren` invariant.
+fn random_bdd(
+    rng: &mut StdRng,
+    id: usize,
+    nvar: usize,
+    max_levels: usize,
+    max_width: usize,
+    reduced: bool,
+) -> crate::soc::bdd::Bdd {
+    let levels = rng.gen_range(1, max_levels + 1);
+    let mut widths = vec![1];
+    for i in 1..levels {
+        widths.push(rng.gen_range(1, max_width.min(2 * widths[i - 1]) + 1));
+    }
+    widths.push(1);
+    let mut bdd = crate::soc::bdd::Bdd::new();
+    for i in 0..levels {
+        // every node of the level below is the child of a random edge, the other edges pointing
+        // to a random child or nowhere
+        let children = widths[i + 1];
+        let mut edges: Vec<Option<Id>> = (0..2 * widths[i])
+            .map(|_| {
+                if rng.gen_bool(0.3) {
+                    None
+                } else {
+                    Some(Id::new(rng.gen_range(0, children)))
+                }
+            })
+            .collect();
+        let mut slots: Vec<usize> = (0..edges.len()).collect();
+        for child in 0..children {
+            let slot = slots.remove(rng.gen_range(0, slots.len()));
+            edges[slot] = Some(Id::new(child));
+        }
+        let mut level = Level::new();
+        let mut lhs: Vec<usize> = (0..nvar).filter(|_| rng.gen_bool(0.4)).collect();
+        if lhs.is_empty() {
+            lhs.push(rng.gen_range(0, nvar));
+        }
+        level.set_lhs(lhs, nvar);
+        for node in edges.chunks(2) {
+            match (node[0], node[1]) {
+                (None, None) => level.add_edged_node(Some(Id::new(rng.gen_range(0, children))), None),
+                (e0, e1) => level.add_edged_node(e0, e1),
+            };
+        }
+        bdd.add_existing_level(level);
+    }
+    let mut sink = Level::new();
+    sink.set_lhs(vec![], nvar);
+    sink.add_new_node();
+    bdd.add_existing_level(sink);
+    bdd.set_id(Id::new(id));
+    if reduced {
+        bdd.merge_all_equals_node();
+        bdd.compact();
+        assert!(bdd.validate().is_valid(), "{}", bdd.validate());
+    }
+    bdd
+}
+
+/// Build a random `System` of `bdds` reduced `Bdd`s over `nvar` variables (see `random_bdd`).
+fn random_system(rng: &mut StdRng, nvar: usize, bdds: usize) -> Result<System, Error> {
+    System::from_elem((0..bdds).map(|id| random_bdd(rng, id, nvar, 4, 3, true)).collect())
+}
+
+/// Return the bits of `lhs` as a mask, the bit `i` of the mask being the variable `i`.
+fn lhs_mask(lhs: &Vob) -> usize {
+    lhs.iter_set_bits(..).fold(0, |mask, var| mask | 1 << var)
+}
+
+/// Return true if the path of the assignment `x` (a mask of the variables set) in `bdd` leads
+/// to the sink, the edge taken at each level being the value of its lhs.
+fn bdd_accepts(bdd: &crate::soc::bdd::Bdd, x: usize) -> bool {
+    let mut node = match bdd.level(0).and_then(|source| source.iter_ids().next()) {
+        Some(source) => source,
+        None => return false,
+    };
+    for level in bdd.iter_levels().take(bdd.get_sink_level_index()) {
+        let edge = (lhs_mask(&level.get_lhs()) & x).count_ones() % 2 == 1;
+        let next = level.get_node(&node).and_then(|node| if edge { node.get_e1() } else { node.get_e0() });
+        node = match next {
+            Some(next) => next,
+            None => return false,
+        };
+    }
+    true
+}
+
+/// Return every assignment of the variables of `system` satisfying all its `Bdd`s and all
+/// the `LinEq` of its `LinBank`, by trying the 2^nvar of them.
+fn brute_force(system: &System) -> Vec<usize> {
+    let lin_eqs: Vec<(usize, bool)> = system
+        .iter_lin_eqs()
+        .map(|lin_eq| (lhs_mask(&lin_eq.get_lhs()), lin_eq.get_rhs()))
+        .collect();
+    (0..1 << system.get_nvar())
+        .filter(|x| {
+            lin_eqs.iter().all(|(lhs, rhs)| ((lhs & x).count_ones() % 2 == 1) == *rhs)
+                && system.iter_bdds().all(|(_, bdd)| bdd_accepts(bdd, *x))
+        })
+        .collect()
+}
+
+/// Return the assignments of `solutions` giving the value `rhs` to `lhs`.
+fn restrict(solutions: &[usize], lhs: usize, rhs: bool) -> Vec<usize> {
+    solutions
+        .iter()
+        .cloned()
+        .filter(|x| ((lhs & x).count_ones() % 2 == 1) == rhs)
+        .collect()
+}
+
+/// Return the variables occuring in a single level of `system` and in no `LinEq`, with the
+/// `Bdd` and the index of this level.
+fn single_occurences(system: &System) -> Vec<(usize, Id, usize)> {
+    let mut occurences: HashMap<usize, Vec<(Id, usize)>> = HashMap::new();
+    for (id, bdd) in system.iter_bdds() {
+        for (i, lhs) in bdd.get_lhs().iter().enumerate() {
+            for var in lhs.iter_set_bits(..) {
+                occurences.entry(var).or_default().push((*id, i));
+            }
+        }
+    }
+    let in_lin_bank = system
+        .iter_lin_eqs()
+        .fold(0, |mask, lin_eq| mask | lhs_mask(&lin_eq.get_lhs()));
+    let mut singles: Vec<(usize, Id, usize)> = occurences
+        .into_iter()
+        .filter(|(var, levels)| levels.len() == 1 && in_lin_bank & 1 << var == 0)
+        .map(|(var, levels)| (var, levels[0].0, levels[0].1))
+        .collect();
+    singles.sort();
+    singles
+}
+
+#[test]
+fn random_operations_test() -> Result<(), Error> {
+    let nvar = 6;
+    let mut satisfiable = 0;
+    for seed in 0..300 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut system = random_system(&mut rng, nvar, 3)?;
+        let mut expected = brute_force(&system);
+        if !expected.is_empty() {
+            satisfiable += 1;
+        }
+        for step in 0..8 {
+            let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+            ids.sort();
+            if ids.is_empty() {
+                break;
+            }
+            let id = ids[rng.gen_range(0, ids.len())];
+            let sink = system.get_bdd(id)?.get_sink_level_index();
+            let context = format!("at step {} of seed {}", step, seed);
+            match rng.gen_range(0, 7) {
+                0 if sink >= 2 => {
+                    let above = rng.gen_range(0, sink - 1);
+                    system.swap(id, above, above + 1)?;
+                    assert_eq!(brute_force(&system), expected, "swap {}", context);
+                }
+                1 if sink >= 2 => {
+                    let above = rng.gen_range(0, sink - 1);
+                    let below = rng.gen_range(above + 1, sink);
+                    system.add(id, above, below)?;
+                    assert_eq!(brute_force(&system), expected, "add {}", context);
+                }
+                2 if ids.len() >= 2 => {
+                    let from = *ids.iter().find(|other| **other != id).unwrap();
+                    system.join_bdds(id, from)?;
+                    assert_eq!(brute_force(&system), expected, "join {}", context);
+                }
+                3 if sink >= 1 => {
+                    // the equation of the level is forgotten once absorbed
+                    let level = rng.gen_range(0, sink);
+                    let lhs = lhs_mask(&system.get_bdd(id)?.get_lhs_level(level));
+                    let edge = rng.gen();
+                    let absorbed = system.absorb(id, level, edge);
+                    if absorbed.is_err() {
+                        assert!(system.has_contradiction());
+                        assert!(restrict(&expected, lhs, edge).is_empty(), "absorb {}", context);
+                        break;
+                    }
+                    assert_eq!(
+                        restrict(&brute_force(&system), lhs, edge),
+                        restrict(&expected, lhs, edge),
+                        "absorb {}",
+                        context
+                    );
+                }
+                4 => {
+                    system.scan_absorb_all_lin_eqs();
+                    if system.has_contradiction() {
+                        assert!(expected.is_empty(), "scan {}", context);
+                        break;
+                    }
+                    assert_eq!(brute_force(&system), expected, "scan {}", context);
+                }
+                5 => {
+                    let vars: Vec<usize> = (0..nvar).filter(|_| rng.gen_bool(0.3)).collect();
+                    let rhs = rng.gen();
+                    let fixed = system.fix(vars.clone(), rhs);
+                    let lhs = vars.iter().fold(0, |mask, var| mask | 1 << var);
+                    expected = restrict(&expected, lhs, rhs);
+                    if system.has_contradiction() {
+                        assert!(fixed.is_err());
+                        assert!(expected.is_empty(), "fix {}", context);
+                        break;
+                    }
+                    assert_eq!(brute_force(&system), expected, "fix {}", context);
+                }
+                6 => {
+                    // dropping the only occurence of a variable projects the solutions
+                    let singles = single_occurences(&system);
+                    if singles.is_empty() {
+                        continue;
+                    }
+                    let (var, id, level) = singles[rng.gen_range(0, singles.len())];
+                    system.drop(id, level)?;
+                    let dropped = system.get_dropped_vars().last().unwrap();
+                    assert!(singles.contains(&(*dropped, id, level)));
+                    let mut projected: Vec<usize> =
+                        expected.iter().flat_map(|x| vec![*x, x ^ 1 << var]).collect();
+                    projected.sort();
+                    projected.dedup();
+                    assert_eq!(brute_force(&system), projected, "drop {}", context);
+                }
+                _ => continue,
+            }
+            expected = brute_force(&system);
+            assert!(system.validate().is_valid(), "{}", system.validate());
+        }
+    }
+    assert!(satisfiable > 150, "only {} satisfiable systems", satisfiable);
+    Ok(())
+}
+
+#[test]
+fn random_merge_test() {
+    let nvar = 6;
+    for seed in 0..300 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bdd = random_bdd(&mut rng, 0, nvar, 5, 4, false);
+        let accepted = |bdd: &crate::soc::bdd::Bdd| -> Vec<usize> {
+            (0..1 << nvar).filter(|x| bdd_accepts(bdd, *x)).collect()
+        };
+        let expected = accepted(&bdd);
+        let size = bdd.get_size();
+        let sink = bdd.get_sink_level_index();
+        bdd.merge_equals_node_start(sink - 1);
+        bdd.compact();
+        assert_eq!(accepted(&bdd), expected, "seed {}", seed);
+        assert!(bdd.get_size() <= size);
+        bdd.merge_all_equals_node();
+        bdd.compact();
+        assert_eq!(accepted(&bdd), expected, "seed {}", seed);
+        assert!(bdd.validate().is_valid(), "seed {}: {}", seed, bdd.validate());
+    }
+}
+
+#[test]
+fn random_solve_test() -> Result<(), Error> {
+    let nvar = 6;
+    let mut satisfiable = 0;
+    for seed in 0..100 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut system = random_system(&mut rng, nvar, 4)?;
+        let expected = brute_force(&system);
+        // a contradiction may be found halfway through an operation, the system is then left
+        // as is (see `System::has_contradiction`)
+        let solved = DefaultSolver.solve::<SpanDependency>(&mut system);
+        if solved.is_err() || system.has_contradiction() {
+            assert!(system.has_contradiction() && expected.is_empty(), "seed {}", seed);
+            continue;
+        }
+        assert_eq!(system.count_solutions(), BigUint::from(expected.len()), "seed {}", seed);
+        // the affine spaces of the paths are disjoint
+        let mut found: Vec<usize> = system
+            .affine_solutions()
+            .flat_map(|space| space.iter().map(|x| lhs_mask(&x)).collect::<Vec<_>>())
+            .collect();
+        found.sort();
+        assert_eq!(found, expected, "seed {}", seed);
+        if !found.is_empty() {
+            satisfiable += 1;
+        }
+    }
+    assert!(satisfiable > 50, "only {} satisfiable systems", satisfiable);
+    Ok(())
+}
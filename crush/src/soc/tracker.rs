@@ -0,0 +1,310 @@
+//! Incremental tracking of the linear dependencies among the levels of a `System`.
+//!
+//! Extracting the dependencies from scratch reduces the lhs of every level of the `System`, which
+//! is what a solver would do after each resolution. The `DependencyTracker` instead keeps the
+//! reduction of all the lhs and updates it with each operation :
+//! - each level is held by a slot, the slots of a `Bdd` being listed in the order of its levels
+//! - each row of the reduction is a combination of slots along with the sum of their lhs, the
+//!   combinations of all the rows being linearly independent (one row per slot)
+//! - the rows whose sum is not zero have distinct highest set bits (the pivots), the rows whose
+//!   sum is zero are the dependencies
+//!
+//! Joining and swapping only move the slots. Adding a level to another changes the combinations
+//! involving the level added to, removing a level (absorbing or dropping it) removes its slot from
+//! the rows, and substituting a variable of the `LinBank` changes the sums, the rows whose sum
+//! changed being reduced again. None of those go through the rows of the other slots.
+
+use vob::Vob;
+
+use crate::AHashMap;
+use crate::algebra::{Matrix, PackedMatrix};
+use crate::soc::{system::System, Id};
+
+/// A row of the reduction : a combination of slots and the sum of their lhs.
+#[derive(Clone)]
+struct Row {
+    combination: Vob,
+    lhs: Vob,
+}
+
+/// The incrementally maintained reduction of the lhs of the levels of a `System` (see the module
+/// documentation). The operations mirror the ones of `System`, which calls them on its tracker.
+#[derive(Clone)]
+pub(crate) struct DependencyTracker {
+    /// The slots of the levels of each `Bdd`, from the source to the level above the sink.
+    bdds: AHashMap<Id, Vec<usize>>,
+    /// The lhs of the level held by each slot, `None` if the slot is free.
+    slots: Vec<Option<Vob>>,
+    free: Vec<usize>,
+    rows: Vec<Row>,
+    /// The row of each pivot, by highest set bit of its sum.
+    pivots: Vec<Option<usize>>,
+}
+
+impl DependencyTracker {
+    /// Construct the tracker of the levels of `system`.
+    pub(crate) fn new(system: &System) -> DependencyTracker {
+        let levels = system.iter_bdds().map(|(_, bdd)| bdd.get_levels_size() - 1).sum();
+        let mut tracker = DependencyTracker {
+            bdds: AHashMap::default(),
+            slots: vec![None; levels],
+            free: (0..levels).rev().collect(),
+            rows: Vec::with_capacity(levels),
+            pivots: vec![None; system.get_nvar()],
+        };
+        for (id, bdd) in system.iter_bdds() {
+            tracker.push_bdd(*id, bdd.get_lhs());
+        }
+        tracker
+    }
+
+    /// Return the number of dependencies.
+    pub(crate) fn dependencies_len(&self) -> usize {
+        self.rows.len() - self.pivots.iter().flatten().count()
+    }
+
+    /// Iterate over the ids of the `Bdd`s tracked.
+    pub(crate) fn iter_ids(&self) -> impl Iterator<Item = &Id> {
+        self.bdds.keys()
+    }
+
+    /// Return the lhs tracked for the levels of the `Bdd` `id`, `None` if it is not tracked.
+    pub(crate) fn get_lhs(&self, id: Id) -> Option<Vec<&Vob>> {
+        self.bdds.get(&id).map(|slots| {
+            slots
+                .iter()
+                .map(|slot| self.slots[*slot].as_ref().unwrap())
+                .collect()
+        })
+    }
+
+    /// Return the linear dependencies among the levels, as `algebra::extract_linear_dependencies`
+    /// of the lhs of the `Bdd`s of `order` concatenated. Only the dependencies are reduced,
+    /// they are usually a lot less than the levels.
+    pub(crate) fn linear_dependencies(&self, order: &[Id]) -> Matrix {
+        let mut columns = vec![0; self.slots.len()];
+        let mut len = 0;
+        for id in order.iter() {
+            for slot in self.bdds[id].iter() {
+                columns[*slot] = len;
+                len += 1;
+            }
+        }
+        let dependencies: Vec<Vob> = self
+            .rows
+            .iter()
+            .filter(|row| row.lhs.iter_set_bits(..).next().is_none())
+            .map(|row| {
+                let mut dependency = Vob::from_elem(len, false);
+                for slot in row.combination.iter_set_bits(..) {
+                    dependency.set(columns[slot], true);
+                }
+                dependency
+            })
+            .collect();
+        let mut dependencies = PackedMatrix::from_rows(&dependencies);
+        dependencies.row_echelon();
+        let mut rows = dependencies.to_rows();
+        rows.reverse();
+        Matrix::from_rows(rows)
+    }
+
+    /// Track the levels of a new `Bdd`, of lhs `lhs`.
+    pub(crate) fn push_bdd(&mut self, id: Id, lhs: Vec<Vob>) {
+        let slots = lhs.into_iter().map(|lhs| self.insert(lhs)).collect();
+        if let Some(previous) = self.bdds.insert(id, slots) {
+            for slot in previous {
+                self.remove(slot);
+            }
+        }
+    }
+
+    /// Stop tracking the levels of the `Bdd` `id`.
+    pub(crate) fn pop_bdd(&mut self, id: Id) {
+        if let Some(slots) = self.bdds.remove(&id) {
+            for slot in slots {
+                self.remove(slot);
+            }
+        }
+    }
+
+    /// The `Bdd` `from` was joined below the `Bdd` `into`.
+    pub(crate) fn join(&mut self, into: Id, from: Id) {
+        let mut slots = self.bdds.remove(&from).unwrap();
+        self.bdds.get_mut(&into).unwrap().append(&mut slots);
+    }
+
+    /// The level `above` of the `Bdd` `id` was swapped with the level below it.
+    pub(crate) fn swap(&mut self, id: Id, above: usize) {
+        self.bdds.get_mut(&id).unwrap().swap(above, above + 1);
+    }
+
+    /// The level `above` of the `Bdd` `id` was added to the level `below` : it was first swapped
+    /// down to the level above `below` (see `Bdd::add`).
+    pub(crate) fn add(&mut self, id: Id, above: usize, below: usize) {
+        let slots = self.bdds.get_mut(&id).unwrap();
+        slots[above..below].rotate_left(1);
+        let (added, to) = (slots[below - 1], slots[below]);
+        // the lhs of `to` is now the sum of both lhs, which changes the combinations giving
+        // the same sums but not the sums
+        let lhs = self.slots[added].clone().unwrap();
+        self.slots[to].as_mut().unwrap().xor(&lhs);
+        for row in self.rows.iter_mut() {
+            if row.combination.get(to).unwrap() {
+                let flipped = !row.combination.get(added).unwrap();
+                row.combination.set(added, flipped);
+            }
+        }
+    }
+
+    /// The level `level` of the `Bdd` `id` was absorbed or dropped.
+    pub(crate) fn remove_level(&mut self, id: Id, level: usize) {
+        let slot = self.bdds.get_mut(&id).unwrap().remove(level);
+        self.remove(slot);
+    }
+
+    /// The variable `var` was replaced by the `LinEq` of lhs `eq` in every level (see
+    /// `Bdd::replace_var_in_bdd`). The levels it absorbed are removed by `sync_bdd`.
+    pub(crate) fn substitute(&mut self, var: usize, eq: &Vob) {
+        for lhs in self.slots.iter_mut().flatten() {
+            if lhs.get(var).unwrap() {
+                lhs.xor(eq);
+            }
+        }
+        // the substitution is linear, the sums are substituted as well and the rows whose sum
+        // changed are reduced again
+        let mut changed = Vec::new();
+        for i in (0..self.rows.len()).rev() {
+            if self.rows[i].lhs.get(var).unwrap() {
+                changed.push(self.remove_row(i));
+            }
+        }
+        for mut row in changed {
+            row.lhs.xor(eq);
+            self.reduce(row);
+        }
+    }
+
+    /// Match the levels tracked for the `Bdd` `id` with `lhs`, the lhs of its levels once some
+    /// of them were absorbed : the levels which are not in `lhs` anymore are removed. If `lhs`
+    /// is not made of tracked levels, all the levels are tracked again.
+    ///
+    /// Two levels with the same lhs can be mistaken for one another, which doesn't change the
+    /// dependencies.
+    pub(crate) fn sync_bdd(&mut self, id: Id, lhs: &[Vob]) {
+        let slots = match self.bdds.remove(&id) {
+            Some(slots) => slots,
+            None => return self.push_bdd(id, lhs.to_vec()),
+        };
+        let mut kept = Vec::with_capacity(lhs.len());
+        let mut removed = Vec::new();
+        for slot in slots {
+            if kept.len() < lhs.len() && self.slots[slot].as_ref() == Some(&lhs[kept.len()]) {
+                kept.push(slot);
+            } else {
+                removed.push(slot);
+            }
+        }
+        if kept.len() < lhs.len() {
+            removed.append(&mut kept);
+            for slot in removed {
+                self.remove(slot);
+            }
+            self.push_bdd(id, lhs.to_vec());
+        } else {
+            for slot in removed {
+                self.remove(slot);
+            }
+            self.bdds.insert(id, kept);
+        }
+    }
+
+    /// Stop tracking the `Bdd`s which are not in `ids`.
+    pub(crate) fn retain(&mut self, ids: &[Id]) {
+        let removed: Vec<Id> = self
+            .bdds
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .cloned()
+            .collect();
+        for id in removed {
+            self.pop_bdd(id);
+        }
+    }
+
+    /// Give a slot to the level of lhs `lhs` and reduce it, returning the slot.
+    fn insert(&mut self, lhs: Vob) -> usize {
+        if self.free.is_empty() {
+            let capacity = self.slots.len();
+            let grown = (capacity * 2).max(64);
+            for row in self.rows.iter_mut() {
+                row.combination.resize(grown, false);
+            }
+            self.slots.resize(grown, None);
+            self.free.extend((capacity..grown).rev());
+        }
+        let slot = self.free.pop().unwrap();
+        let mut combination = Vob::from_elem(self.slots.len(), false);
+        combination.set(slot, true);
+        self.slots[slot] = Some(lhs.clone());
+        self.reduce(Row { combination, lhs });
+        slot
+    }
+
+    /// Remove the slot `slot` from the rows and free it.
+    ///
+    /// One of the rows involving the slot is added to the others involving it, then removed :
+    /// a dependency if there is one, otherwise the row of the lowest pivot, so the highest set
+    /// bits of the other rows don't change.
+    fn remove(&mut self, slot: usize) {
+        let involving: Vec<usize> = (0..self.rows.len())
+            .filter(|i| self.rows[*i].combination.get(slot).unwrap())
+            .collect();
+        let chosen = involving
+            .iter()
+            .min_by_key(|i| self.rows[**i].lhs.iter_set_bits(..).last())
+            .cloned()
+            .unwrap();
+        let row = self.remove_row(chosen);
+        for other in self.rows.iter_mut() {
+            if other.combination.get(slot).unwrap() {
+                other.combination.xor(&row.combination);
+                other.lhs.xor(&row.lhs);
+            }
+        }
+        self.slots[slot] = None;
+        self.free.push(slot);
+    }
+
+    /// Reduce `row` with the pivots and push it, as a new pivot or as a dependency.
+    fn reduce(&mut self, mut row: Row) {
+        while let Some(highest) = row.lhs.iter_set_bits(..).last() {
+            match self.pivots[highest] {
+                Some(pivot) => {
+                    let pivot = &self.rows[pivot];
+                    row.combination.xor(&pivot.combination);
+                    row.lhs.xor(&pivot.lhs);
+                }
+                None => {
+                    self.pivots[highest] = Some(self.rows.len());
+                    break;
+                }
+            }
+        }
+        self.rows.push(row);
+    }
+
+    /// Remove the row `i` and return it, the last row taking its place.
+    fn remove_row(&mut self, i: usize) -> Row {
+        if let Some(highest) = self.rows[i].lhs.iter_set_bits(..).last() {
+            self.pivots[highest] = None;
+        }
+        let row = self.rows.swap_remove(i);
+        if i < self.rows.len() {
+            if let Some(highest) = self.rows[i].lhs.iter_set_bits(..).last() {
+                self.pivots[highest] = Some(i);
+            }
+        }
+        row
+    }
+}
@@ -2,9 +2,11 @@
 //! print a Bdd to .dot format for visualization, print systems to .bdd format
 //! and needed structures for it.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
 use std::path::PathBuf;
 use std::process::Child;
 use std::str::FromStr;
@@ -15,6 +17,8 @@ use nom::types::CompleteStr;
 use crate::soc::{
     bdd::Bdd,
     Id,
+    level::Level,
+    symbols::{Role, SymbolTable},
     system::System};
 
 /// A specification of a `Node` inside a Bdd
@@ -110,6 +114,7 @@ impl BddSpec {
 pub struct SystemSpec {
     nvar:usize,
     bdds:Vec<BddSpec>,
+    symbols: Option<SymbolTable>,
 }
 
 impl SystemSpec {
@@ -117,9 +122,232 @@ impl SystemSpec {
     pub fn new(nvar:usize, bdds: Vec<BddSpec>) -> SystemSpec {
         SystemSpec{
             nvar,
-            bdds
+            bdds,
+            symbols: None,
         }
     }
+
+    /// Set the names of the variables of the `System` built from the spec.
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = Some(symbols);
+    }
+}
+
+/// An error found while parsing a .bdd file or building a `System` from a `SystemSpec`.
+///
+/// Lines and columns start at 1. The errors found when building from a spec which doesn't
+/// come from a file have no line.
+#[derive(Debug)]
+pub enum SpecError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// A line doesn't follow the .bdd format.
+    Syntax(SyntaxError),
+    /// The file ended in the middle of the header or of the Bdd `bdd`.
+    Truncated { line: usize, bdd: Option<Id> },
+    /// The number of `what` announced on the line doesn't match the number found.
+    CountMismatch {
+        line: usize,
+        what: &'static str,
+        announced: usize,
+        found: usize,
+    },
+    /// The id of a node is used twice in a Bdd.
+    DuplicateNode {
+        line: Option<usize>,
+        bdd: Id,
+        node: Id,
+    },
+    /// An edge of `node` points to `edge`, which is not a node of a level below.
+    DanglingEdge {
+        line: Option<usize>,
+        bdd: Id,
+        node: Id,
+        edge: Id,
+    },
+    /// A variable of a lhs is not in `0..nvar`.
+    NvarMismatch {
+        line: Option<usize>,
+        bdd: Id,
+        var: i64,
+        nvar: usize,
+    },
+    /// The Bdd doesn't have the structure of a Bdd (a single source, a single sink...).
+    InvalidBdd {
+        line: Option<usize>,
+        bdd: Id,
+        reason: String,
+    },
+    /// A symbol can't be given to the variable `var` (see `SymbolTable::insert`).
+    InvalidSymbol {
+        line: Option<usize>,
+        var: usize,
+        reason: String,
+    },
+    /// `nvar` is larger than `MAX_NVAR`.
+    TooManyVars { line: Option<usize>, nvar: usize },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let at_line = |f: &mut fmt::Formatter, line: &Option<usize>| match line {
+            Some(line) => write!(f, "line {}: ", line),
+            None => Ok(()),
+        };
+        match self {
+            SpecError::Io(e) => write!(f, "{}", e),
+            SpecError::Syntax(e) => write!(f, "{}", e),
+            SpecError::Truncated { line, bdd } => match bdd {
+                Some(bdd) => write!(f, "line {}: file truncated inside bdd {}", line, bdd),
+                None => write!(f, "line {}: file truncated, no header", line),
+            },
+            SpecError::CountMismatch {
+                line,
+                what,
+                announced,
+                found,
+            } => write!(
+                f,
+                "line {}: {} {} announced but {} found",
+                line, announced, what, found
+            ),
+            SpecError::DuplicateNode { line, bdd, node } => {
+                at_line(f, line)?;
+                write!(f, "node id {} used twice in bdd {}", node, bdd)
+            }
+            SpecError::DanglingEdge {
+                line,
+                bdd,
+                node,
+                edge,
+            } => {
+                at_line(f, line)?;
+                write!(
+                    f,
+                    "edge from node {} to {} in bdd {}, which is not a node of a level below",
+                    node, edge, bdd
+                )
+            }
+            SpecError::NvarMismatch {
+                line,
+                bdd,
+                var,
+                nvar,
+            } => {
+                at_line(f, line)?;
+                write!(
+                    f,
+                    "variable {} in bdd {} is out of range, nvar is {}",
+                    var, bdd, nvar
+                )
+            }
+            SpecError::InvalidBdd { line, bdd, reason } => {
+                at_line(f, line)?;
+                write!(f, "invalid bdd {}: {}", bdd, reason)
+            }
+            SpecError::InvalidSymbol { line, var, reason } => {
+                at_line(f, line)?;
+                write!(f, "invalid symbol of variable {}: {}", var, reason)
+            }
+            SpecError::TooManyVars { line, nvar } => {
+                at_line(f, line)?;
+                write!(f, "nvar is {}, at most {} variables are supported", nvar, MAX_NVAR)
+            }
+        }
+    }
+}
+
+impl error::Error for SpecError {}
+
+impl From<io::Error> for SpecError {
+    fn from(e: io::Error) -> Self {
+        SpecError::Io(e)
+    }
+}
+
+impl From<SpecError> for io::Error {
+    fn from(e: SpecError) -> Self {
+        match e {
+            SpecError::Io(e) => e,
+            e => io::Error::new(ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+/// A token of a line which is not what the parser expected, in a .bdd, .anf or DIMACS file.
+///
+/// Lines and columns start at 1, `token` is empty at the end of the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.token.is_empty() {
+            write!(f, "line {}, column {}: unexpected end of line", self.line, self.column)?;
+        } else {
+            write!(f, "line {}, column {}: unexpected `{}`", self.line, self.column, self.token)?;
+        }
+        write!(f, ", expected {}", self.expected)
+    }
+}
+
+/// The number of variables allowed in a group by default when grouping polynomials or clauses
+/// (see `group_by_shared_vars`), each `Bdd` then has at most 1024 paths.
+pub const MAX_VARS: usize = 10;
+
+/// The largest `nvar` of a `System` built from a .bdd file, a spec, a DIMACS or an ANF file.
+/// Every lhs holds `nvar` bits, a larger `nvar` read from a file would only make the building
+/// run out of memory.
+pub const MAX_NVAR: usize = 1 << 20;
+
+/// A group of items sharing variables : the union of their variables and the items.
+pub(crate) type VarGroup = (Vec<usize>, Vec<usize>);
+
+/// Group the `items` so that the items of a group share variables, `vars[i]` being the sorted
+/// variables of the item `i`. Return for each group the union of the variables of its items
+/// and its items, sorted.
+///
+/// The items are taken by decreasing number of variables and each one joins the group with
+/// which it shares the most variables, if the group doesn't grow over `max_vars` variables.
+/// An item without variables, or with more than `max_vars`, is left alone in its group.
+pub(crate) fn group_by_shared_vars(
+    vars: &[Vec<usize>],
+    mut items: Vec<usize>,
+    max_vars: usize,
+) -> Vec<VarGroup> {
+    items.sort_by_key(|i| std::cmp::Reverse(vars[*i].len()));
+    let mut groups: Vec<VarGroup> = Vec::new();
+    for i in items {
+        let best = groups
+            .iter()
+            .enumerate()
+            .filter(|_| !vars[i].is_empty())
+            .map(|(g, (group_vars, _))| {
+                let shared = vars[i].iter().filter(|var| group_vars.contains(var)).count();
+                (g, shared, group_vars.len() + vars[i].len() - shared)
+            })
+            .filter(|(_, shared, union)| *shared > 0 && *union <= max_vars)
+            .max_by_key(|(g, shared, _)| (*shared, std::cmp::Reverse(*g)));
+        match best {
+            Some((g, _, _)) => {
+                let (group_vars, group) = &mut groups[g];
+                group_vars.extend(vars[i].iter().cloned());
+                group_vars.sort_unstable();
+                group_vars.dedup();
+                group.push(i);
+            }
+            None => groups.push((vars[i].clone(), vec![i])),
+        }
+    }
+    for (_, group) in groups.iter_mut() {
+        group.sort_unstable();
+    }
+    groups
 }
 
 /// From a `SystemSpec` build a `System` following the specifications.
@@ -127,79 +355,190 @@ impl SystemSpec {
 /// We create an empty `System` with the `nvar` set to the spec and 
 /// push to it every `Bdd` created using the spec.
 /// If some Id of Bdds in the spec are not unique their order is used as Id
-pub fn build_system_from_spec(mut spec: SystemSpec) -> System {
+///
+/// /!\ Panics if the spec is malformed, use `try_build_system_from_spec` to get the error instead.
+pub fn build_system_from_spec(spec: SystemSpec) -> System {
+    try_build_system_from_spec(spec).unwrap_or_else(|e| panic!("Building system: {}", e))
+}
+
+/// From a `SystemSpec` build a `System` following the specifications, or return a `SpecError`
+/// if `nvar` is larger than `MAX_NVAR` or if a `BddSpec` is malformed (see
+/// `try_build_bdd_from_spec`).
+///
+/// If some Id of Bdds in the spec are not unique their order is used as Id
+pub fn try_build_system_from_spec(mut spec: SystemSpec) -> Result<System, SpecError> {
+    if spec.nvar > MAX_NVAR {
+        return Err(SpecError::TooManyVars { line: None, nvar: spec.nvar });
+    }
     let mut system = System::new();
-    system.set_nvar(spec.nvar as usize);
+    system.set_nvar(spec.nvar);
     let ids:HashSet<Id> = spec.bdds.iter().map(|bdd| bdd.id).collect();
     let nbr_bdd = spec.bdds.len();
     for (i,bdd_spec) in spec.bdds.iter_mut().enumerate(){
          if ids.len() != nbr_bdd {
             bdd_spec.id = Id::new(i);
         }
-        system.push_bdd(build_bdd_from_spec(bdd_spec,spec.nvar as usize)).expect("No reason to crash since we are using the nvar of the system
-        to set the one of the Bdds we are pushing");
+        system.push_bdd(try_build_bdd_from_spec(bdd_spec,spec.nvar)?)?;
     }
-    system
+    let nvar = spec.nvar;
+    if let Some(symbols) = spec.symbols {
+        if let Some((var, _)) = symbols.iter().find(|(var, _)| *var >= nvar) {
+            return Err(SpecError::InvalidSymbol {
+                line: None,
+                var,
+                reason: format!("out of range, nvar is {}", nvar),
+            });
+        }
+        system.set_symbols(symbols);
+    }
+    Ok(system)
+}
+
+/// From a `BddSpec` and a `nvar` build a `Bdd` following the specifications, or return a
+/// `SpecError` if the spec is malformed.
+///
+/// The spec must have a source level and a sink level with a single node each, the sink having
+/// no edge. The ids of the nodes must be unique and not 0, each edge must point to a node of a
+/// level below, and the variables must be in `0..nvar` (or `-1`), `nvar` being at most
+/// `MAX_NVAR`.
+/// Once built, we check that the `Bdd` holds its invariants (see `Bdd::validate`) : no jumping
+/// edge is left, and no node is an orphan or a dead end.
+pub fn try_build_bdd_from_spec(spec: &mut BddSpec, nvar: usize) -> Result<Bdd, SpecError> {
+    if nvar > MAX_NVAR {
+        return Err(SpecError::TooManyVars { line: None, nvar });
+    }
+    check_bdd_spec(spec, nvar, None)?;
+    let bdd = build_bdd_from_spec(spec, nvar);
+    let report = bdd.validate();
+    match report.violations().first() {
+        Some(violation) => Err(SpecError::InvalidBdd {
+            line: None,
+            bdd: spec.id,
+            reason: violation.to_string(),
+        }),
+        None => Ok(bdd),
+    }
+}
+
+/// Check that `spec` can be built into a `Bdd` (see `try_build_bdd_from_spec`).
+///
+/// `lines` gives the line of each level in the file the spec comes from, if any.
+fn check_bdd_spec(spec: &BddSpec, nvar: usize, lines: Option<&[usize]>) -> Result<(), SpecError> {
+    let line = |level: usize| lines.and_then(|lines| lines.get(level).cloned());
+    let invalid = |level: usize, reason: String| SpecError::InvalidBdd {
+        line: line(level),
+        bdd: spec.id,
+        reason,
+    };
+    if spec.levels.is_empty() {
+        return Err(invalid(0, "no level".to_string()));
+    }
+    let mut level_of: HashMap<Id, usize> = HashMap::new();
+    for (i, level) in spec.levels.iter().enumerate() {
+        if let Some(var) = level.lhs.iter().find(|var| **var < -1 || **var >= nvar as i64) {
+            return Err(SpecError::NvarMismatch {
+                line: line(i),
+                bdd: spec.id,
+                var: *var,
+                nvar,
+            });
+        }
+        for node in level.rhs.iter() {
+            if *node.id == 0 {
+                return Err(invalid(i, "node id 0 is reserved for missing edges".to_string()));
+            }
+            if level_of.insert(node.id, i).is_some() {
+                return Err(SpecError::DuplicateNode {
+                    line: line(i),
+                    bdd: spec.id,
+                    node: node.id,
+                });
+            }
+        }
+    }
+    for (i, level) in spec.levels.iter().enumerate() {
+        for node in level.rhs.iter() {
+            for edge in [node.e0, node.e1].iter().filter(|edge| ***edge != 0) {
+                match level_of.get(edge) {
+                    Some(below) if *below > i => (),
+                    _ => {
+                        return Err(SpecError::DanglingEdge {
+                            line: line(i),
+                            bdd: spec.id,
+                            node: node.id,
+                            edge: *edge,
+                        })
+                    }
+                }
+            }
+        }
+    }
+    let last = spec.levels.len() - 1;
+    if spec.levels[0].rhs.len() != 1 {
+        return Err(invalid(0, format!("{} nodes in the source level", spec.levels[0].rhs.len())));
+    }
+    if spec.levels[last].rhs.len() != 1 {
+        return Err(invalid(last, format!("{} nodes in the sink level", spec.levels[last].rhs.len())));
+    }
+    Ok(())
 }
 
 /// From a `BddSpec` and a `nvar` build a `Bdd` following the specifications.
-/// 
-/// We create an empty `Bdd`, set its `id` according to the spec then create all the levels
-/// (removing the `-1` from the `lhs` beforehand) without connecting the nodes.
-/// 
-/// Once all the level have been created we connect all the nodes to each other following the
-/// `e0` and `e1` specs. All the id of the nodes are then reset to initialize `next_id` of the
-/// `Bdd`. Finally we remove any jumping edges by calling `add_same_edge_node_at_level` on all the
-/// levels of the `Bdd`.
-/// WARNING! There is an unconfirmed case which indicates that the removal of jumping edges does NOT
-/// work as intended! This will be investigated when I get the time.
-// FIXME, the case referred to is the original PRINCE or LowMC S-box used in our differential
-// experiments. It was built from a .bdd file, and we did had to change the .bdd to not include
-// jumping edges b/c they caused us trouble. I was not aware of the fact that this fn is supposed
-// to handle jumping edges at the time, otherwise I would have looked into it then. Maybe it's a
-// too early short-circuit again?
-// Of course, it may also have been some other error on our part, which is why this case is
-// "unconfirmed".
+///
+/// The nodes of each level are numbered in the order of the spec, the ids of the spec being
+/// only used to resolve the `e0` and `e1` specs. A jumping edge (pointing to a node more than
+/// one level below) is replaced by a chain of nodes having both edges pointing to the next node
+/// of the chain, the chains leading to the same node being shared. Finally we merge the nodes
+/// representing the same function, as the spec may not be reduced.
+///
+/// Edges pointing to an unknown id or to a node which is not below are ignored, use
+/// `try_build_bdd_from_spec` to reject such specs.
 pub fn build_bdd_from_spec(spec: &mut BddSpec, nvar: usize) -> Bdd {
     let mut bdd = Bdd::new();
     bdd.set_id(spec.id);
-    let next_id = spec.levels.iter().fold(0,|last_id,level|
-    {
-        let level_id =level.rhs.iter().fold(0,|last_id_level,node| {
-            if *node.id > last_id_level {
-                *node.id
-            } else {
-                last_id_level
-            }
-        });
-        if level_id>last_id {
-            level_id
-        } else {
-            last_id
-        }
-    });
+    let mut levels: Vec<Level> = Vec::with_capacity(spec.levels.len());
+    let mut positions: HashMap<Id, (usize, Id)> = HashMap::new();
     for (i,level_spec) in spec.levels.iter_mut().enumerate(){
         level_spec.remove_minus_one();
-        bdd.add_level();
-        bdd.set_lhs_level(i,level_spec.lhs.iter().map(|i| *i as usize).collect(),nvar);
-        bdd.add_nodes_to_level(i,level_spec.rhs.iter().map(|node| node.id).collect());
+        let mut level = Level::new();
+        level.set_lhs(level_spec.lhs.iter().map(|i| *i as usize).collect(),nvar);
+        for node_spec in level_spec.rhs.iter() {
+            positions.insert(node_spec.id, (i, level.add_new_node()));
+        }
+        levels.push(level);
     }
-    bdd.set_next_id(next_id+1);
-    for level_spec in spec.levels.iter(){
+    // the node of a chain at a level, keyed by the level and the node it points to
+    let mut chains: HashMap<(usize, Id), Id> = HashMap::new();
+    for (i,level_spec) in spec.levels.iter().enumerate(){
         for node_spec in level_spec.rhs.iter(){
-            if *node_spec.e0 != 0 {
-                bdd.connect_nodes_from_spec(node_spec.id, node_spec.e0, 0);
+            let id = positions[&node_spec.id].1;
+            let mut edges = [None, None];
+            for (edge, child) in edges.iter_mut().zip([node_spec.e0, node_spec.e1].iter()) {
+                let (child_level, mut child) = match positions.get(child) {
+                    Some(&(child_level, child)) if child_level > i => (child_level, child),
+                    _ => continue,
+                };
+                for level in (i + 1..child_level).rev() {
+                    child = *chains.entry((level, child)).or_insert_with(|| {
+                        levels[level].add_edged_node(Some(child), Some(child))
+                    });
+                }
+                *edge = Some(child);
+            }
+            let node = levels[i].get_mut_node(&id).unwrap();
+            if let Some(e0) = edges[0] {
+                node.connect_e0(e0);
             }
-            if *node_spec.e1 != 0 {
-                bdd.connect_nodes_from_spec(node_spec.id, node_spec.e1, 1);
+            if let Some(e1) = edges[1] {
+                node.connect_e1(e1);
             }
         }
     }
-    if spec.levels.len() > 2 {
-        for i in 1..spec.levels.len()-2 {
-            bdd.add_same_edges_node_at_level(i);
-        }
+    for level in levels {
+        bdd.add_existing_level(level);
     }
+    bdd.merge_all_equals_node();
+    bdd.compact();
     bdd
 }
 
@@ -214,10 +553,6 @@ ws!(
     map_res!(digit,|CompleteStr(s)| FromStr::from_str(s))
 ));
 
-named!(line_break <CompleteStr,Option<CompleteStr>>,
-    opt!(alt!(tag!("\n")|tag!("\r\n")))
-);
-
 named!(minus_one <CompleteStr, i64>,
 ws!(
     map_res!(
@@ -270,73 +605,264 @@ named!(node<CompleteStr,NodeSpec>,
     )
 );
 
-named!(rhs<CompleteStr,Vec<NodeSpec>>,
-    many0!(
-        node
-));
+/// Return a SystemSpec from the parsing of a .bdd file using the correct format
+///
+/// /!\ Panics if the file can't be read or doesn't follow the format, use
+/// `try_parse_system_spec_from_file` to get the error instead.
+pub fn parse_system_spec_from_file(path: &PathBuf) -> SystemSpec {
+    try_parse_system_spec_from_file(path)
+        .unwrap_or_else(|e| panic!("Parsing file {}: {}", path.display(), e))
+}
 
-named!(level<CompleteStr,LevelSpec>,
-    do_parse!(
-        a:lhs>>
-        char!(':')>>
-        b:rhs>>
-        char!('|')>>
-        line_break>>
-        (LevelSpec::new(a, b))
-));
+/// Return a SystemSpec from the parsing of a .bdd file using the correct format, or a
+/// `SpecError` telling where and why the file is malformed.
+pub fn try_parse_system_spec_from_file(path: &PathBuf) -> Result<SystemSpec, SpecError> {
+    let file = File::open(path)?;
+    let mut file_content = String::new();
+    BufReader::new(file).read_to_string(&mut file_content)?;
+    parse_system_spec_from_str(&file_content)
+}
 
-named!(levels<CompleteStr,Vec<LevelSpec>>,
-    many0!(
-        level
-));
+/// Return a SystemSpec from the parsing of a string using the .bdd format.
+///
+/// The format is parsed line by line: a line `nvar nbdds`, then for each bdd a line `id nlevels`
+/// followed by one line per level and a line `---`. Empty lines are ignored. The file may end
+/// with the names of the variables : a line `symbols nsymbols` followed by one line
+/// `var name role` per variable (see `SymbolTable`).
+///
+/// Besides the syntax, we check that the numbers of bdds and levels announced are the ones found,
+/// and that each `BddSpec` is well formed (see `try_build_bdd_from_spec`), so that the errors
+/// can be reported with the line they come from.
+pub fn parse_system_spec_from_str(content: &str) -> Result<SystemSpec, SpecError> {
+    let end_line = content.lines().count() + 1;
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, text)| (i + 1, text))
+        .filter(|(_, text)| !text.trim().is_empty());
+    let (header_line, header) = lines
+        .next()
+        .ok_or(SpecError::Truncated { line: end_line, bdd: None })?;
+    let (nvar, nbdds) = parse_parameters(header_line, header, "`nvar nbdds`")?;
+    if nvar > MAX_NVAR {
+        return Err(SpecError::TooManyVars { line: Some(header_line), nvar });
+    }
+    let mut bdds = Vec::new();
+    let mut symbols = None;
+    while let Some((bdd_line, text)) = lines.next() {
+        if let Some(count) = text.trim_start().strip_prefix("symbols") {
+            let nsymbols = parse_count(bdd_line, text, count)?;
+            let mut table = SymbolTable::new();
+            for found in 0..nsymbols {
+                let (line, text) = lines.next().ok_or(SpecError::CountMismatch {
+                    line: bdd_line,
+                    what: "symbols",
+                    announced: nsymbols,
+                    found,
+                })?;
+                let (var, name, role) = parse_symbol(line, text, nvar)?;
+                table.insert(var, name, role).map_err(|e| SpecError::InvalidSymbol {
+                    line: Some(line),
+                    var,
+                    reason: e.to_string(),
+                })?;
+            }
+            if let Some((line, text)) = lines.next() {
+                return Err(syntax_error(line, text, text.trim_start(), "the end of the file"));
+            }
+            symbols = Some(table);
+            break;
+        }
+        let (id, nlevels) = parse_parameters(bdd_line, text, "`id nlevels`")?;
+        let id = Id::new(id);
+        let mut levels = Vec::new();
+        let mut level_lines = Vec::new();
+        loop {
+            let (line, text) = lines
+                .next()
+                .ok_or(SpecError::Truncated { line: end_line, bdd: Some(id) })?;
+            if text.trim() == "---" {
+                break;
+            }
+            levels.push(parse_level(line, text)?);
+            level_lines.push(line);
+        }
+        if levels.len() != nlevels {
+            return Err(SpecError::CountMismatch {
+                line: bdd_line,
+                what: "levels",
+                announced: nlevels,
+                found: levels.len(),
+            });
+        }
+        let spec = BddSpec::new(id, levels);
+        check_bdd_spec(&spec, nvar, Some(&level_lines))?;
+        bdds.push(spec);
+    }
+    if bdds.len() != nbdds {
+        return Err(SpecError::CountMismatch {
+            line: header_line,
+            what: "bdds",
+            announced: nbdds,
+            found: bdds.len(),
+        });
+    }
+    let mut spec = SystemSpec::new(nvar, bdds);
+    if let Some(symbols) = symbols {
+        spec.set_symbols(symbols);
+    }
+    Ok(spec)
+}
 
-named!(bdd<CompleteStr,BddSpec>,
-    do_parse!(
-        param: parameters>>
-        line_break>>
-        levels: levels>>
-        tag!("---")>>
-        line_break>>
-        (BddSpec::new(Id::new(param.0 as usize), levels))
-));
+/// Parse the number ending the line `symbols nsymbols`, `count` being the end of the line.
+fn parse_count(line: usize, text: &str, count: &str) -> Result<usize, SpecError> {
+    match usize(CompleteStr(count)) {
+        Ok((rest, count)) if rest.trim().is_empty() => Ok(count),
+        Ok((rest, _)) => Err(syntax_error(line, text, rest.0, "the end of the line")),
+        Err(e) => Err(syntax_error(line, text, error_rest(e, count), "`symbols nsymbols`")),
+    }
+}
 
-named!(bdds<CompleteStr,Vec<BddSpec>>,
-    many0!(
-        bdd
-));
+/// Parse a line `var name role` giving a symbol to a variable in `0..nvar`.
+fn parse_symbol(line: usize, text: &str, nvar: usize) -> Result<(usize, &str, Role), SpecError> {
+    let mut tokens = text.split_whitespace();
+    // the end of the line starting at `token`, a slice of `text`
+    let rest = |token: &str| &text[token.as_ptr() as usize - text.as_ptr() as usize..];
+    let mut next = |expected: &'static str| {
+        tokens
+            .next()
+            .ok_or_else(|| syntax_error(line, text, "", expected))
+    };
+    let var_token = next("`var name role`")?;
+    let var = usize::from_str(var_token)
+        .map_err(|_| syntax_error(line, text, rest(var_token), "a variable"))?;
+    let name = next("a name")?;
+    let role_token = next("a role")?;
+    if let Some(token) = tokens.next() {
+        return Err(syntax_error(line, text, rest(token), "the end of the line"));
+    }
+    let invalid = |reason: String| SpecError::InvalidSymbol {
+        line: Some(line),
+        var,
+        reason,
+    };
+    if var >= nvar {
+        return Err(invalid(format!("out of range, nvar is {}", nvar)));
+    }
+    let role = Role::from_str(role_token).map_err(|e| invalid(e.to_string()))?;
+    Ok((var, name, role))
+}
 
-named!(full_parser<CompleteStr,SystemSpec>,
-    do_parse!(
-        params:parameters>>
-        line_break>>
-        bdds:bdds>>
-        (SystemSpec::new(params.0,bdds))
-    )
-);
+/// Parse a line made of two numbers, nothing else is allowed on the line.
+fn parse_parameters(line: usize, text: &str, expected: &'static str) -> Result<(usize, usize), SpecError> {
+    match parameters(CompleteStr(text)) {
+        Ok((rest, params)) => {
+            if rest.trim().is_empty() {
+                Ok(params)
+            } else {
+                Err(syntax_error(line, text, rest.0, "the end of the line"))
+            }
+        }
+        Err(e) => Err(syntax_error(line, text, error_rest(e, text), expected)),
+    }
+}
 
-/// Return a SystemSpec from the parsing of a .bdd file using the correct format
-pub fn parse_system_spec_from_file(path: &PathBuf) -> SystemSpec {
-    let file = File::open(path).unwrap();
-    let mut file_content = String::new();
-    BufReader::new(file).read_to_string(&mut file_content).unwrap();
-    let result = full_parser(CompleteStr(&file_content)).expect("Parsing file");
-    result.1
+/// Parse a line `lhs:nodes|` describing a level.
+fn parse_level(line: usize, text: &str) -> Result<LevelSpec, SpecError> {
+    let (rest, lhs) = lhs(CompleteStr(text))
+        .map_err(|e| syntax_error(line, text, error_rest(e, text), "a variable"))?;
+    let mut rest = rest.0.trim_start();
+    rest = rest
+        .strip_prefix(':')
+        .ok_or_else(|| syntax_error(line, text, rest, "a variable or `:`"))?;
+    let mut nodes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if !rest.starts_with('(') {
+            break;
+        }
+        let (after, node) = node(CompleteStr(rest))
+            .map_err(|e| syntax_error(line, text, error_rest(e, rest), "a node `(id;e0,e1)`"))?;
+        nodes.push(node);
+        rest = after.0;
+    }
+    rest = rest
+        .strip_prefix('|')
+        .ok_or_else(|| syntax_error(line, text, rest, "a node `(id;e0,e1)` or `|`"))?;
+    if !rest.trim().is_empty() {
+        return Err(syntax_error(line, text, rest.trim_start(), "the end of the line"));
+    }
+    Ok(LevelSpec::new(lhs, nodes))
+}
+
+/// Return the input left when the parser failed, which is where the error is.
+fn error_rest<'a>(error: nom::Err<CompleteStr<'a>>, input: &'a str) -> &'a str {
+    match error {
+        nom::Err::Error(nom::Context::Code(rest, _))
+        | nom::Err::Failure(nom::Context::Code(rest, _)) => rest.0,
+        nom::Err::Incomplete(_) => &input[input.len()..],
+    }
+}
+
+/// Build a `SpecError::Syntax` for the line `text`, `rest` being the end of the line starting
+/// at the offending token.
+fn syntax_error(line: usize, text: &str, rest: &str, expected: &'static str) -> SpecError {
+    let column = text[..text.len() - rest.len()].chars().count() + 1;
+    let is_delimiter = |c: char| c.is_whitespace() || ":|(),;+".contains(c);
+    let token = match rest.chars().next() {
+        Some(c) if is_delimiter(c) => c.to_string(),
+        Some(_) => rest.split(is_delimiter).next().unwrap_or_default().to_string(),
+        None => String::new(),
+    };
+    SpecError::Syntax(SyntaxError {
+        line,
+        column,
+        token,
+        expected,
+    })
 }
 
 /// Write `.dot` language representation of the given bdd to a file at path
+///
+/// See `soc::dot` to draw a whole `System`, with annotations.
 pub fn print_bdd_to_dot_format(bdd: &Bdd, path:&PathBuf) {
     let write_file = File::create(path).unwrap();
     let mut writer = BufWriter::new(&write_file);
 
-    to_dot_format(&bdd, &mut writer);
+    to_dot_format(bdd, None, &mut writer);
+
+    writer.flush().expect("Failed to write to file");
+}
+
+/// Same as `print_bdd_to_dot_format`, the variables of the lhs being given their name in
+/// `symbols` (see `System::get_symbols`).
+pub fn print_bdd_to_dot_format_with_symbols(bdd: &Bdd, symbols: &SymbolTable, path: &PathBuf) {
+    let write_file = File::create(path).unwrap();
+    let mut writer = BufWriter::new(&write_file);
+
+    to_dot_format(bdd, Some(symbols), &mut writer);
 
     writer.flush().expect("Failed to write to file");
 }
 
+/// Return for each level of `bdd` the number given to its node of id 0 in the .bdd and .dot
+/// representations, the ids of the nodes being local to a level while the nodes of a .bdd file
+/// need a number unique in the bdd (and not 0).
+fn node_numbers(bdd: &Bdd) -> Vec<usize> {
+    let mut first = 1;
+    bdd.iter_levels()
+        .map(|level| {
+            first += level.get_slots_len();
+            first - level.get_slots_len()
+        })
+        .collect()
+}
+
 /// Write .bdd representation of a bdd to a Buffered write of a file
 fn print_bdd_to_file_format(bdd: &Bdd,writer: &mut BufWriter<&File>){
     writeln!(writer, "{} {}",*bdd.get_id(),bdd.iter_levels().count()).unwrap();
-    for level in bdd.iter_levels() {
+    let numbers = node_numbers(bdd);
+    for (level_index,level) in bdd.iter_levels().enumerate() {
         for (i,bit) in level.iter_set_lhs().enumerate(){
             if i != 0 {
                 write!(writer,"+").unwrap();
@@ -346,14 +872,14 @@ fn print_bdd_to_file_format(bdd: &Bdd,writer: &mut BufWriter<&File>){
         write!(writer,":").unwrap();
         for (id,node) in level.iter_nodes() {
             let e0 = match node.get_e0(){
-                Some(e0) => *e0,
+                Some(e0) => numbers[level_index+1] + *e0,
                 None => 0,
             };
             let e1 = match node.get_e1(){
-                Some(e1) => *e1,
+                Some(e1) => numbers[level_index+1] + *e1,
                 None => 0,
             };
-            write!(writer,"({};{},{})",*id,e0,e1).unwrap();
+            write!(writer,"({};{},{})",numbers[level_index] + *id,e0,e1).unwrap();
         }
         writeln!(writer,"|").unwrap();
     }
@@ -371,7 +897,13 @@ pub fn print_system_to_file(system: &System, path: &PathBuf){
 }
     ids.sort();
     for id in ids {
-        print_bdd_to_file_format(&system.get_bdd(*id).unwrap().borrow(), &mut writer);
+        print_bdd_to_file_format(system.get_bdd(*id).unwrap(), &mut writer);
+    }
+    if let Some(symbols) = system.get_symbols().filter(|symbols| !symbols.is_empty()) {
+        writeln!(writer, "symbols {}", symbols.len()).unwrap();
+        for (var, symbol) in symbols.iter() {
+            writeln!(writer, "{} {} {}", var, symbol.name, symbol.role).unwrap();
+        }
     }
 }
 
@@ -421,7 +953,7 @@ pub fn draw_shard_as_pdf(shard: &Bdd, path:&PathBuf) -> Child {
         let child_in = dot.stdin.take().expect("Child stdin not captured");
         let mut writer = BufWriter::new(child_in);
 
-        to_dot_format(&shard, &mut writer);
+        to_dot_format(shard, None, &mut writer);
         writer.flush().unwrap();
         // Child stdin is dropped, closing the child stdin's underlying file handle. This will
         // essentially give an "EOF" to GraphViz, making it no longer wait on user input and thus
@@ -430,10 +962,14 @@ pub fn draw_shard_as_pdf(shard: &Bdd, path:&PathBuf) -> Child {
     dot
 }
 
-/// Write .dot language representation of the given shard into `writer`.
-fn to_dot_format<W: Write> (shard: &Bdd, writer: &mut BufWriter<W>) {
+/// Write .dot language representation of the given shard into `writer`, the variables being
+/// named by `symbols` if any (`x` followed by their index otherwise).
+fn to_dot_format<W: Write> (shard: &Bdd, symbols: Option<&SymbolTable>, writer: &mut BufWriter<W>) {
     // Setup
     let num_levels = shard.iter_levels().count();
+    let numbers = node_numbers(shard);
+    let unnamed = SymbolTable::new();
+    let symbols = symbols.unwrap_or(&unnamed);
 
     // Metadata:
     writeln!(writer, "digraph \"DD\" {{").unwrap(); // I believe DD is just an ID.
@@ -447,16 +983,7 @@ fn to_dot_format<W: Write> (shard: &Bdd, writer: &mut BufWriter<W>) {
 
     for (i,level) in shard.iter_levels().enumerate() {
         write!(writer, "\"{}. ",i).unwrap(); // Line/row number
-        if level.iter_set_lhs().count() == 0 { // No variable is set
-            write!(writer, "0").unwrap();
-        } else {
-            for (j, bit) in level.iter_set_lhs().enumerate() {
-                if j > 0 {
-                    write!(writer, " + ").unwrap();
-                }
-                write!(writer, "x{}", bit).unwrap();
-            }
-        }
+        write!(writer, "{}", symbols.format_lhs(&level.get_lhs())).unwrap(); // "0" if no variable is set
         write!(writer, "\" -> ").unwrap();
         if i == num_levels - 2 { // Skip terminal lvl + started at index 0 ==> -2 ?
             break;
@@ -469,25 +996,16 @@ fn to_dot_format<W: Write> (shard: &Bdd, writer: &mut BufWriter<W>) {
         write!(writer, "{{ rank = same; ").unwrap(); // Tell GraphViz that these are on the same level
         write!(writer, "\"{}. ", i).unwrap(); // Line/row/"rank" number
 
-        // I'm a bit unsure of the purpose of this if-else. I understand what it does, but not why.
+        // I'm a bit unsure of the purpose of writing the lhs again. I understand what it does, but not why.
         // Theory: Links these to the rank above w/same "ID"? Printed dot file both support and object
         // to this theory, and hard to find something in the GV doc.
-        if level.iter_set_lhs().count() == 0 { // No variable is set
-            write!(writer, "0").unwrap();
-        } else {
-            for (j,bit) in level.iter_set_lhs().enumerate() {
-                if j > 0 {
-                    write!(writer, " + ").unwrap();
-                }
-                write!(writer, "x{}", bit).unwrap();
-            }
-        }
+        write!(writer, "{}", symbols.format_lhs(&level.get_lhs())).unwrap(); // "0" if no variable is set
         writeln!(writer, "\";").unwrap();
 
         // Add node to rank. (In GraphViz: level == rank)
         for (id,_) in level.iter_nodes(){
             // Remove the ID by setting label = "", and reducing drawing size by making the node shape to a point.
-            writeln!(writer, "\"{}\" [label = \"\"; shape = point; width = 0.06];", *id).unwrap();
+            writeln!(writer, "\"{}\" [label = \"\"; shape = point; width = 0.06];", numbers[i] + *id).unwrap();
         }
         writeln!(writer, "}}").unwrap(); // Rank (/level) done
 
@@ -498,27 +1016,26 @@ fn to_dot_format<W: Write> (shard: &Bdd, writer: &mut BufWriter<W>) {
 
     // Add terminal node, set node shape to box
     writeln!(writer, "{{ rank = same; \"CONST NODES\";").unwrap(); //
-    writeln!(writer, "{{ node [shape = box]; \"{}\";", *shard.iter_levels().last().unwrap()
+    let sink = numbers[num_levels - 1] + *shard.iter_levels().last().unwrap()
         .iter_nodes().last().unwrap()
-        .0).unwrap();
+        .0;
+    writeln!(writer, "{{ node [shape = box]; \"{}\";", sink).unwrap();
     writeln!(writer, "}}").unwrap();
     writeln!(writer, "}}").unwrap();
 
     // Add edges between relevant nodes, including correct style
-    for level in shard.iter_levels() {
+    for (i,level) in shard.iter_levels().enumerate() {
         for (id,node) in level.iter_nodes() {
             if let Some(e0) = node.get_e0() {
-                writeln!(writer, "\"{}\" -> \"{}\" [style = dashed];",*id,*e0).unwrap();
+                writeln!(writer, "\"{}\" -> \"{}\" [style = dashed];",numbers[i] + *id,numbers[i+1] + *e0).unwrap();
             }
             if let Some(e1) = node.get_e1() {
-                writeln!(writer, "\"{}\" -> \"{}\";",*id,*e1).unwrap();
+                writeln!(writer, "\"{}\" -> \"{}\";",numbers[i] + *id,numbers[i+1] + *e1).unwrap();
             }
         }
     }
     // Label the terminal node as the True node
-    writeln!(writer, "\"{}\" [label = \"T\"];", *shard.iter_levels().last().unwrap()
-        .iter_nodes().last().unwrap()
-        .0).unwrap();
+    writeln!(writer, "\"{}\" [label = \"T\"];", sink).unwrap();
     writeln!(writer, "}}").unwrap();
 }
 
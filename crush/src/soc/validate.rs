@@ -0,0 +1,349 @@
+//! Module providing a checker of the structural invariants of a `Bdd` and of a `System`.
+//!
+//! The operations on a `Bdd` (swap, add, absorb, drop, joining...) assume that :
+//! - the source level holds a single node
+//! - the sink level holds a single node, without outgoing edges and with the all zero vector
+//!   as its lhs
+//! - every node but the source has a parent in the level directly above (no orphans)
+//! - every node but the sink has an edge, pointing to a node of the level directly below
+//!   (no dead ends, no jumping edges)
+//! - two nodes of the same level never have the same children (equal nodes are merged)
+//! - all the lhs have `nvar` bits
+//!
+//! And the `LinBank` of a `System` assumes that its `LinEq` are non zero and have distinct
+//! highest set bits. When the dependencies are tracked, the levels tracked are the levels of
+//! the `Bdd`s.
+//!
+//! `Bdd::validate` and `System::validate` check all of those and return a `Report` listing every
+//! violation found. With the `validate` feature, the `System` checks itself after each operation
+//! in debug builds and panics with the `Report` if an invariant is broken. The loaders of the
+//! binary, DIMACS and ANF formats always check the `System` they build with
+//! `System::check_invariants`, and the .bdd loader checks each `Bdd`.
+
+use std::fmt;
+use std::io::{self, Error, ErrorKind};
+
+use crate::AHashMap;
+use crate::soc::{bdd::Bdd, Id, system::System};
+
+/// A broken invariant of a `Bdd` or of a `System`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The `Bdd` has no level.
+    NoLevel { bdd: Id },
+    /// The source level doesn't hold exactly one node.
+    Source { bdd: Id, nodes: usize },
+    /// The sink level doesn't hold exactly one node.
+    Sink { bdd: Id, nodes: usize },
+    /// A node of the sink level has an outgoing edge.
+    SinkEdge { bdd: Id, node: Id },
+    /// The lhs of the sink level is not the all zero vector.
+    SinkLhs { bdd: Id },
+    /// A node isn't the child of any node of the level above.
+    Orphan { bdd: Id, level: usize, node: Id },
+    /// A node which is not in the sink level has no outgoing edge.
+    DeadEnd { bdd: Id, level: usize, node: Id },
+    /// An edge points to a node which is not in the level below.
+    DanglingEdge {
+        bdd: Id,
+        level: usize,
+        node: Id,
+        edge: Id,
+    },
+    /// Two nodes of the same level have the same children.
+    SameChildren {
+        bdd: Id,
+        level: usize,
+        nodes: (Id, Id),
+    },
+    /// The lhs of a level doesn't have `nvar` bits.
+    LhsLength {
+        bdd: Id,
+        level: usize,
+        len: usize,
+        nvar: usize,
+    },
+    /// The lhs of a `LinEq` of the `LinBank` doesn't have `nvar` bits.
+    LinEqLength { index: usize, len: usize, nvar: usize },
+    /// A `LinEq` of the `LinBank` has the all zero vector as its lhs.
+    ZeroLinEq { index: usize },
+    /// Two `LinEq` of the `LinBank` have the same highest set bit.
+    SharedPivot { indexes: (usize, usize), var: usize },
+    /// The levels tracked for the dependencies are not the levels of the `Bdd` (see
+    /// `System::track_dependencies`).
+    TrackedLevels { bdd: Id },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::NoLevel { bdd } => write!(f, "bdd {}: no level", bdd),
+            Violation::Source { bdd, nodes } => {
+                write!(f, "bdd {}: {} nodes in the source level", bdd, nodes)
+            }
+            Violation::Sink { bdd, nodes } => {
+                write!(f, "bdd {}: {} nodes in the sink level", bdd, nodes)
+            }
+            Violation::SinkEdge { bdd, node } => {
+                write!(f, "bdd {}: sink node {} has an outgoing edge", bdd, node)
+            }
+            Violation::SinkLhs { bdd } => write!(f, "bdd {}: the lhs of the sink is not zero", bdd),
+            Violation::Orphan { bdd, level, node } => {
+                write!(f, "bdd {}, level {}: node {} is an orphan", bdd, level, node)
+            }
+            Violation::DeadEnd { bdd, level, node } => {
+                write!(f, "bdd {}, level {}: node {} is a dead end", bdd, level, node)
+            }
+            Violation::DanglingEdge {
+                bdd,
+                level,
+                node,
+                edge,
+            } => write!(
+                f,
+                "bdd {}, level {}: edge from node {} to {}, which is not in the level below",
+                bdd, level, node, edge
+            ),
+            Violation::SameChildren { bdd, level, nodes } => write!(
+                f,
+                "bdd {}, level {}: nodes {} and {} have the same children",
+                bdd, level, nodes.0, nodes.1
+            ),
+            Violation::LhsLength {
+                bdd,
+                level,
+                len,
+                nvar,
+            } => write!(
+                f,
+                "bdd {}, level {}: lhs of {} bits, nvar is {}",
+                bdd, level, len, nvar
+            ),
+            Violation::LinEqLength { index, len, nvar } => write!(
+                f,
+                "LinBank equation {}: lhs of {} bits, nvar is {}",
+                index, len, nvar
+            ),
+            Violation::ZeroLinEq { index } => {
+                write!(f, "LinBank equation {}: the lhs is zero", index)
+            }
+            Violation::SharedPivot { indexes, var } => write!(
+                f,
+                "LinBank equations {} and {} have the same highest variable {}",
+                indexes.0, indexes.1, var
+            ),
+            Violation::TrackedLevels { bdd } => write!(
+                f,
+                "bdd {}: the levels tracked for the dependencies are not the levels of the bdd",
+                bdd
+            ),
+        }
+    }
+}
+
+/// The result of a check, listing all the `Violation`s found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Return true if no invariant is broken.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Return the `Violation`s found.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.violations.is_empty() {
+            return write!(f, "no violation");
+        }
+        writeln!(f, "{} violation(s) :", self.violations.len())?;
+        for violation in self.violations.iter() {
+            writeln!(f, "- {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Bdd {
+    /// Check the structural invariants of the `Bdd` (see the module documentation), the `nvar`
+    /// being the number of bits of the lhs of the source.
+    pub fn validate(&self) -> Report {
+        let mut report = Report::default();
+        let nvar = self.iter_levels().next().map_or(0, |level| level.get_lhs().len());
+        check_bdd(self, nvar, &mut report.violations);
+        report
+    }
+}
+
+impl System {
+    /// Check the structural invariants of all the `Bdd`s and of the `LinBank` (see the module
+    /// documentation).
+    pub fn validate(&self) -> Report {
+        let mut report = Report::default();
+        let violations = &mut report.violations;
+        let nvar = self.get_nvar();
+        let mut ids: Vec<Id> = self.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort();
+        for id in ids.iter() {
+            check_bdd(self.get_bdd(*id).unwrap(), nvar, violations);
+        }
+
+        let mut pivots: AHashMap<usize, usize> = AHashMap::default();
+        for (index, lin_eq) in self.iter_lin_eqs().enumerate() {
+            let len = lin_eq.get_lhs().len();
+            if len != nvar {
+                violations.push(Violation::LinEqLength { index, len, nvar });
+            }
+            let var = match lin_eq.get_lhs_max_set_bit() {
+                Some(var) => var,
+                None => {
+                    violations.push(Violation::ZeroLinEq { index });
+                    continue;
+                }
+            };
+            if let Some(other) = pivots.insert(var, index) {
+                violations.push(Violation::SharedPivot {
+                    indexes: (other, index),
+                    var,
+                });
+            }
+        }
+
+        if let Some(tracker) = self.get_tracker() {
+            let mut tracked: Vec<Id> = tracker.iter_ids().cloned().collect();
+            tracked.sort();
+            for id in tracked {
+                let lhs = self.get_bdd(id).map(|bdd| bdd.get_lhs());
+                let matching = match (lhs, tracker.get_lhs(id)) {
+                    (Ok(lhs), Some(tracked)) => lhs.iter().eq(tracked),
+                    _ => false,
+                };
+                if !matching {
+                    violations.push(Violation::TrackedLevels { bdd: id });
+                }
+            }
+            for id in ids.iter().filter(|id| tracker.get_lhs(**id).is_none()) {
+                violations.push(Violation::TrackedLevels { bdd: *id });
+            }
+        }
+        report
+    }
+
+    /// Return an `InvalidData` `Error` listing the violations if the `System` breaks one of its
+    /// invariants (see `validate`).
+    pub fn check_invariants(&self) -> io::Result<()> {
+        let report = self.validate();
+        if report.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::InvalidData, report.to_string()))
+        }
+    }
+}
+
+/// Push the violations of `bdd` to `violations`.
+fn check_bdd(bdd: &Bdd, nvar: usize, violations: &mut Vec<Violation>) {
+    let id = bdd.get_id();
+    let levels: Vec<_> = bdd.iter_levels().collect();
+    if levels.is_empty() {
+        violations.push(Violation::NoLevel { bdd: id });
+        return;
+    }
+    let sink = levels.len() - 1;
+
+    if levels[0].get_nodes_len() != 1 {
+        violations.push(Violation::Source {
+            bdd: id,
+            nodes: levels[0].get_nodes_len(),
+        });
+    }
+    if levels[sink].get_nodes_len() != 1 {
+        violations.push(Violation::Sink {
+            bdd: id,
+            nodes: levels[sink].get_nodes_len(),
+        });
+    }
+    if levels[sink].iter_set_lhs().next().is_some() {
+        violations.push(Violation::SinkLhs { bdd: id });
+    }
+
+    for (i, level) in levels.iter().enumerate() {
+        let len = level.get_lhs().len();
+        if len != nvar {
+            violations.push(Violation::LhsLength {
+                bdd: id,
+                level: i,
+                len,
+                nvar,
+            });
+        }
+
+        let mut functions = AHashMap::default();
+        for (node_id, node) in level.iter_nodes() {
+            let edges = [node.get_e0(), node.get_e1()];
+            if i == sink {
+                if edges.iter().any(Option::is_some) {
+                    violations.push(Violation::SinkEdge {
+                        bdd: id,
+                        node: node_id,
+                    });
+                }
+                continue;
+            }
+            if edges.iter().all(Option::is_none) {
+                violations.push(Violation::DeadEnd {
+                    bdd: id,
+                    level: i,
+                    node: node_id,
+                });
+                continue;
+            }
+            for edge in edges.iter().flatten() {
+                if levels[i + 1].get_node(edge).is_none() {
+                    violations.push(Violation::DanglingEdge {
+                        bdd: id,
+                        level: i,
+                        node: node_id,
+                        edge: *edge,
+                    });
+                }
+            }
+            if let Some(other) = functions.insert((edges[0], edges[1]), node_id) {
+                violations.push(Violation::SameChildren {
+                    bdd: id,
+                    level: i,
+                    nodes: (other, node_id),
+                });
+            }
+        }
+
+        if i > 0 {
+            let mut children: Vec<Id> = levels[i - 1]
+                .iter_nodes()
+                .flat_map(|(_, node)| vec![node.get_e0(), node.get_e1()])
+                .flatten()
+                .collect();
+            children.sort();
+            let orphans: Vec<Id> = level
+                .iter_nodes()
+                .map(|(node_id, _)| node_id)
+                .filter(|node_id| children.binary_search(node_id).is_err())
+                .collect();
+            for node in orphans {
+                violations.push(Violation::Orphan {
+                    bdd: id,
+                    level: i,
+                    node,
+                });
+            }
+        }
+    }
+}
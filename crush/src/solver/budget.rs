@@ -0,0 +1,230 @@
+//! Bounds on a solving : a number of nodes, a wall-clock limit and a cancellation token.
+//!
+//! `Solver::solve_bounded` and `DroppingSolver::solve_bounded` check their `Budget` between two
+//! steps of the solving, the `System` is then always left between two resolutions :
+//! - the number of nodes a resolution would reach is estimated before it is made (see
+//!   `System::estimate_resolution`), a resolution exceeding the budget is not started
+//! - the wall-clock limit and the cancellation token are checked before each step, a step being
+//!   made whatever the time it takes
+//!
+//! When the budget is exceeded the solving stops and returns the partially solved `System`, with
+//! the statistics of the solving so far. It can be checkpointed or solved again with a larger
+//! budget. After `Solver::solve_bounded` its solutions are the same as the ones of the `System`
+//! given, after `DroppingSolver::solve_bounded` they are only their projection on the variables
+//! not dropped yet.
+
+use std::io::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::soc::{system::System, Id};
+
+/// A flag shared between the solving and whoever may want to stop it, from another thread for
+/// instance. Clones of a `CancelToken` share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Construct a `CancelToken` which is not cancelled.
+    pub fn new() -> CancelToken {
+        Default::default()
+    }
+
+    /// Ask the solvings checking this token to stop at their next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Return true if `cancel` was called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The bounds of a solving, all of them unlimited by default.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    max_nodes: Option<usize>,
+    time_limit: Option<Duration>,
+    cancel: Option<CancelToken>,
+}
+
+impl Budget {
+    /// Construct an unlimited `Budget`.
+    pub fn new() -> Budget {
+        Default::default()
+    }
+
+    /// Stop before the total number of nodes of the `System` exceeds `max_nodes`.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = Some(max_nodes);
+    }
+
+    /// Stop once `time_limit` elapsed since the start of the solving.
+    pub fn set_time_limit(&mut self, time_limit: Duration) {
+        self.time_limit = Some(time_limit);
+    }
+
+    /// Stop once `cancel` is cancelled.
+    pub fn set_cancel_token(&mut self, cancel: CancelToken) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Start measuring a solving against the `Budget`.
+    pub fn start(&self) -> Meter<'_> {
+        Meter {
+            budget: self,
+            start: Instant::now(),
+            stats: Default::default(),
+        }
+    }
+}
+
+/// The bound which stopped a solving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The next resolution would have reached at least `nodes` nodes in the `System`.
+    Nodes { nodes: usize },
+    /// The wall-clock limit elapsed.
+    Time,
+    /// The `CancelToken` was cancelled.
+    Cancelled,
+}
+
+/// What a bounded solving did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of dependencies resolved.
+    pub resolved: usize,
+    /// The number of variables dropped.
+    pub dropped: usize,
+    /// The number of dependencies left, as of the last extraction.
+    pub remaining: usize,
+    /// The highest total number of nodes of the `System` between two steps.
+    pub peak: usize,
+    /// The time spent solving.
+    pub elapsed: Duration,
+}
+
+/// A solving stopped by its `Budget`.
+pub struct Exceeded {
+    pub reason: Reason,
+    /// The `System` as it was when the solving stopped. It has the same solutions as the
+    /// `System` given if no variable was dropped (see `Stats::dropped`), their projection on the
+    /// variables left otherwise.
+    pub system: System,
+    pub stats: Stats,
+}
+
+/// The result of a bounded solving.
+pub enum Bounded<T> {
+    /// The solving went through, with the statistics of the solving.
+    Done(T, Stats),
+    /// The `Budget` was exceeded.
+    Exceeded(Box<Exceeded>),
+}
+
+/// Measure a solving against a `Budget`, keeping its `Stats`.
+pub struct Meter<'a> {
+    budget: &'a Budget,
+    start: Instant,
+    stats: Stats,
+}
+
+impl<'a> Meter<'a> {
+    /// Check the wall-clock limit, the cancellation token and the number of nodes of `system`,
+    /// returning the first bound exceeded if any.
+    pub fn check(&mut self, system: &System) -> Option<Reason> {
+        let nodes = system.get_size();
+        self.stats.peak = self.stats.peak.max(nodes);
+        self.stats.elapsed = self.start.elapsed();
+        if let Some(cancel) = &self.budget.cancel {
+            if cancel.is_cancelled() {
+                return Some(Reason::Cancelled);
+            }
+        }
+        if let Some(time_limit) = self.budget.time_limit {
+            if self.stats.elapsed >= time_limit {
+                return Some(Reason::Time);
+            }
+        }
+        match self.budget.max_nodes {
+            Some(max_nodes) if nodes > max_nodes => Some(Reason::Nodes { nodes }),
+            _ => None,
+        }
+    }
+
+    /// Same as `check`, then check whether the resolution of the dependency of `join_order`
+    /// would exceed the number of nodes of the `Budget`.
+    ///
+    /// The resolution is only replayed if the upper bound of `System::estimate_resolution`
+    /// exceeds the budget.
+    pub fn check_resolution(
+        &mut self,
+        system: &System,
+        join_order: &(Vec<Id>, Vec<usize>),
+    ) -> Result<Option<Reason>, Error> {
+        if let Some(reason) = self.check(system) {
+            return Ok(Some(reason));
+        }
+        let max_nodes = match self.budget.max_nodes {
+            Some(max_nodes) => max_nodes,
+            None => return Ok(None),
+        };
+        let mut involved = 0;
+        for id in join_order.0.iter() {
+            involved += system.get_bdd(*id)?.get_size();
+        }
+        let others = system.get_size() - involved;
+        let limit = max_nodes.saturating_sub(others);
+        let mut estimate = system.estimate_resolution(join_order, 0)?;
+        if estimate.peak > limit {
+            estimate = system.estimate_resolution(join_order, limit)?;
+        }
+        if estimate.peak > limit {
+            return Ok(Some(Reason::Nodes {
+                nodes: others.saturating_add(estimate.peak),
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Count a resolved dependency.
+    pub fn resolved(&mut self) {
+        self.stats.resolved += 1;
+    }
+
+    /// Count a dropped variable.
+    pub fn dropped(&mut self) {
+        self.stats.dropped += 1;
+    }
+
+    /// Set the number of dependencies left.
+    pub fn remaining(&mut self, remaining: usize) {
+        self.stats.remaining = remaining;
+    }
+
+    /// Return the statistics of the solving so far.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            elapsed: self.start.elapsed(),
+            ..self.stats
+        }
+    }
+
+    /// Return the `Bounded` result of a solving stopped by `reason`, `system` being the
+    /// partially solved `System`.
+    pub fn exceeded<T>(&self, reason: Reason, system: System) -> Bounded<T> {
+        Bounded::Exceeded(Box::new(Exceeded {
+            reason,
+            system,
+            stats: self.stats(),
+        }))
+    }
+
+    /// Return the `Bounded` result of a solving which went through.
+    pub fn done<T>(&self, result: T) -> Bounded<T> {
+        Bounded::Done(result, self.stats())
+    }
+}
@@ -0,0 +1,189 @@
+//! Periodic checkpoints of a solving, to resume it after a crash.
+//!
+//! A checkpoint holds the `System`, with its `LinBank` and dropped variables, along with the
+//! counters of the solver and a free-form context given by the caller (ex: the plaintext and
+//! ciphertext a cipher system was built for). It is written in the following format :
+//!
+//! ```text
+//! magic            "CRHK"
+//! version          varint (currently 1)
+//! solved           varint
+//! remaining        varint
+//! dropped          varint
+//! max nodes        varint
+//! number of context strings  varint
+//! for each context string :
+//!     length           varint
+//!     bytes            utf-8
+//! system           the System in the binary format (see `soc::binary`)
+//! ```
+//!
+//! A `Checkpointer` writes the checkpoint to a temporary file which is then renamed over the
+//! previous one, so the file at its path is always the latest complete checkpoint.
+
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::soc::{
+    binary::{self, read_varint, write_varint},
+    system::System,
+};
+
+/// The first bytes of a checkpoint.
+pub const MAGIC: &[u8; 4] = b"CRHK";
+/// The version of the format written by `write_checkpoint`.
+pub const VERSION: usize = 1;
+
+/// The state of a solver saved along with the `System`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The number of dependencies resolved.
+    pub solved: usize,
+    /// The number of dependencies left.
+    pub remaining: usize,
+    /// The number of variables dropped.
+    pub dropped: usize,
+    /// The highest total number of nodes reached.
+    pub max_nodes: usize,
+    /// Whatever the caller needs to make sense of the solutions once resumed.
+    pub context: Vec<String>,
+}
+
+/// Save checkpoints to a file at a given interval.
+#[derive(Debug)]
+pub struct Checkpointer {
+    path: PathBuf,
+    interval: Duration,
+    context: Vec<String>,
+    last: Cell<Instant>,
+}
+
+impl Checkpointer {
+    /// Construct a `Checkpointer` saving to `path` at most once every `interval`.
+    pub fn new(path: PathBuf, interval: Duration) -> Checkpointer {
+        Checkpointer {
+            path,
+            interval,
+            context: Vec::new(),
+            last: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Set the context saved in each `Checkpoint` : `save` writes it in place of the context of
+    /// the `Checkpoint` it is given.
+    pub fn set_context(&mut self, context: Vec<String>) {
+        self.context = context;
+    }
+
+    /// Return the path of the checkpoint file.
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Save the checkpoint if `interval` elapsed since the last one (or since the construction
+    /// of the `Checkpointer`), and return true if it was saved.
+    pub fn save_if_due(&self, checkpoint: &Checkpoint, system: &System) -> io::Result<bool> {
+        if self.last.get().elapsed() < self.interval {
+            return Ok(false);
+        }
+        self.save(checkpoint, system)?;
+        Ok(true)
+    }
+
+    /// Save the checkpoint now.
+    pub fn save(&self, checkpoint: &Checkpoint, system: &System) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        {
+            let file = File::create(&tmp)?;
+            let mut writer = BufWriter::new(&file);
+            let checkpoint = Checkpoint {
+                context: self.context.clone(),
+                ..checkpoint.clone()
+            };
+            write_checkpoint(&checkpoint, system, &mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        self.last.set(Instant::now());
+        Ok(())
+    }
+}
+
+/// Write the `Checkpoint` and the `System` to `writer`.
+pub fn write_checkpoint<W: Write>(
+    checkpoint: &Checkpoint,
+    system: &System,
+    writer: &mut W,
+) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_varint(writer, VERSION)?;
+    write_varint(writer, checkpoint.solved)?;
+    write_varint(writer, checkpoint.remaining)?;
+    write_varint(writer, checkpoint.dropped)?;
+    write_varint(writer, checkpoint.max_nodes)?;
+    write_varint(writer, checkpoint.context.len())?;
+    for s in checkpoint.context.iter() {
+        write_varint(writer, s.len())?;
+        writer.write_all(s.as_bytes())?;
+    }
+    binary::write_system(system, writer)
+}
+
+/// Read a `Checkpoint` and its `System` from `reader`.
+///
+/// Returns an `Error` if the data doesn't start with the magic bytes, if the version is not
+/// supported, if the data is truncated or if the `System` is not valid.
+pub fn read_checkpoint<R: Read>(reader: &mut R) -> io::Result<(Checkpoint, System)> {
+    let mut magic = [0_u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a CRHS checkpoint (wrong magic bytes)",
+        ));
+    }
+    let version = read_varint(reader)?;
+    if version == 0 || version > VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "unsupported version {} of the checkpoint format (supported up to {})",
+                version, VERSION
+            ),
+        ));
+    }
+    let mut checkpoint = Checkpoint {
+        solved: read_varint(reader)?,
+        remaining: read_varint(reader)?,
+        dropped: read_varint(reader)?,
+        max_nodes: read_varint(reader)?,
+        context: Vec::new(),
+    };
+    let ncontext = read_varint(reader)?;
+    for _ in 0..ncontext {
+        let len = read_varint(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated context"));
+        }
+        let s = String::from_utf8(bytes)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        checkpoint.context.push(s);
+    }
+    let system = binary::read_system(reader)?;
+    Ok((checkpoint, system))
+}
+
+/// Read a `Checkpoint` and its `System` from a file at `path`.
+pub fn read_checkpoint_from_file(path: &PathBuf) -> io::Result<(Checkpoint, System)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_checkpoint(&mut reader)
+}
@@ -0,0 +1,365 @@
+//! Events emitted by the solvers while they mutate a `System`, and the observers receiving them.
+//!
+//! The default implementations of `Solver` and `DroppingSolver` apply their operations through
+//! an `Observed` system, which notifies the `Observer` of the solver after each of them. Three
+//! observers are provided :
+//! - `Terminal`, the default, which clears the terminal and prints a summary of the `System` on
+//!   each `Event::Progress`
+//! - `Silent`, which ignores every event
+//! - `JsonLines`, which writes each event as a JSON object on its own line, for batch jobs and
+//!   for analysing a run afterwards
+
+use std::io::{self, Error, Write};
+use std::sync::Mutex;
+
+use crate::soc::{Id, system::System};
+
+/// Something that happened while solving. The `nodes` of an operation are the number of nodes
+/// of the `Bdd` once the operation is done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The `Bdd` `from` was joined below the `Bdd` `into`.
+    Join { into: Id, from: Id, nodes: usize },
+    /// The levels `above` and `below` of the `Bdd` were swapped.
+    Swap {
+        bdd: Id,
+        above: usize,
+        below: usize,
+        nodes: usize,
+    },
+    /// The level `above` of the `Bdd` was added to the level `below`.
+    Add {
+        bdd: Id,
+        above: usize,
+        below: usize,
+        nodes: usize,
+    },
+    /// The level of the `Bdd` was absorbed along `edge`.
+    Absorb {
+        bdd: Id,
+        level: usize,
+        edge: bool,
+        nodes: usize,
+    },
+    /// The level of the `Bdd` was dropped.
+    Drop { bdd: Id, level: usize, nodes: usize },
+    /// A `LinEq` was pushed to the `LinBank`, `vars` being the variables of its lhs and
+    /// `lin_eqs` the size of the `LinBank` afterwards.
+    LinEqFound {
+        vars: Vec<usize>,
+        rhs: bool,
+        lin_eqs: usize,
+    },
+    /// The `Bdd` was reduced to its sink and removed from the `System`.
+    BddRemoved { bdd: Id },
+    /// A summary of the `System`, emitted after each step of the solving.
+    Progress(Progress),
+}
+
+/// A summary of a `System` during the solving.
+///
+/// The fields which are `None` are not tracked by the solver.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of `Bdd`s in the `System`.
+    pub bdds: usize,
+    /// The total number of nodes in the `System`.
+    pub nodes: usize,
+    /// The number of nodes of the biggest `Bdd`.
+    pub biggest: usize,
+    /// The number of `LinEq` in the `LinBank`.
+    pub lin_eqs: usize,
+    /// The number of dependencies resolved so far.
+    pub resolved: Option<usize>,
+    /// The number of dependencies left.
+    pub remaining: Option<usize>,
+    /// The number of variables dropped so far.
+    pub dropped: Option<usize>,
+    /// The highest total number of nodes reached so far.
+    pub max_nodes: Option<usize>,
+}
+
+impl Progress {
+    /// Return the summary of `system`, without the fields tracked by the solver.
+    pub fn of(system: &System) -> Progress {
+        Progress {
+            bdds: system.iter_bdds().len(),
+            nodes: system.get_size(),
+            biggest: system
+                .iter_bdds()
+                .map(|(_, bdd)| bdd.get_size())
+                .max()
+                .unwrap_or(0),
+            lin_eqs: system.get_lin_bank_size(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Receive the `Event`s emitted by a solver.
+pub trait Observer {
+    /// Called once for each `Event`, in the order they happened.
+    fn notify(&self, event: &Event);
+}
+
+/// An `Observer` ignoring every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Silent;
+
+impl Observer for Silent {
+    fn notify(&self, _event: &Event) {}
+}
+
+/// An `Observer` clearing the terminal and printing a summary of the `System` on stdout
+/// on each `Event::Progress`. The other events are ignored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Terminal;
+
+impl Observer for Terminal {
+    fn notify(&self, event: &Event) {
+        if let Event::Progress(progress) = event {
+            print!("\x1Bc");
+            println!(
+                "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}",
+                progress.bdds, progress.nodes, progress.lin_eqs
+            );
+            if let (Some(resolved), Some(remaining)) = (progress.resolved, progress.remaining) {
+                println!("solved dependencies {}, {} remaining", resolved, remaining);
+            }
+            if let Some(dropped) = progress.dropped {
+                println!("dropped variables {}", dropped);
+            }
+            println!("biggest bdd has {} nodes", progress.biggest);
+            if let Some(max_nodes) = progress.max_nodes {
+                println!("max node reach 2**{}", (max_nodes as f64).log(2.0));
+            }
+        }
+    }
+}
+
+/// An `Observer` writing each `Event` as a JSON object on its own line to a writer, the kind
+/// of event being given by the `"event"` field.
+///
+/// ```text
+/// {"event":"swap","bdd":3,"above":4,"below":5,"nodes":118}
+/// {"event":"lin_eq_found","vars":[2,17],"rhs":true,"lin_eqs":12}
+/// ```
+///
+/// Failing to write an event doesn't stop the solving: the first error is kept and can be
+/// retrieved with `take_error`, the following events are then ignored.
+pub struct JsonLines<W: Write> {
+    writer: Mutex<(W, Option<Error>)>,
+}
+
+impl<W: Write> JsonLines<W> {
+    /// Construct a `JsonLines` writing to `writer`.
+    pub fn new(writer: W) -> JsonLines<W> {
+        JsonLines {
+            writer: Mutex::new((writer, None)),
+        }
+    }
+
+    /// Return the first error met when writing, if any, and resume writing.
+    pub fn take_error(&self) -> Option<Error> {
+        self.writer.lock().unwrap().1.take()
+    }
+
+    /// Flush the writer and return it.
+    pub fn into_inner(self) -> io::Result<W> {
+        let (mut writer, error) = self.writer.into_inner().unwrap();
+        match error {
+            Some(error) => Err(error),
+            None => writer.flush().map(|_| writer),
+        }
+    }
+}
+
+impl<W: Write> Observer for JsonLines<W> {
+    fn notify(&self, event: &Event) {
+        let mut guard = self.writer.lock().unwrap();
+        let (writer, error) = &mut *guard;
+        if error.is_none() {
+            if let Err(e) = writeln!(writer, "{}", to_json(event)) {
+                *error = Some(e);
+            }
+        }
+    }
+}
+
+/// Return the JSON object describing `event`, on a single line.
+pub fn to_json(event: &Event) -> String {
+    let optional = |value: Option<usize>| match value {
+        Some(value) => value.to_string(),
+        None => "null".to_string(),
+    };
+    match event {
+        Event::Join { into, from, nodes } => format!(
+            r#"{{"event":"join","into":{},"from":{},"nodes":{}}}"#,
+            into, from, nodes
+        ),
+        Event::Swap {
+            bdd,
+            above,
+            below,
+            nodes,
+        } => format!(
+            r#"{{"event":"swap","bdd":{},"above":{},"below":{},"nodes":{}}}"#,
+            bdd, above, below, nodes
+        ),
+        Event::Add {
+            bdd,
+            above,
+            below,
+            nodes,
+        } => format!(
+            r#"{{"event":"add","bdd":{},"above":{},"below":{},"nodes":{}}}"#,
+            bdd, above, below, nodes
+        ),
+        Event::Absorb {
+            bdd,
+            level,
+            edge,
+            nodes,
+        } => format!(
+            r#"{{"event":"absorb","bdd":{},"level":{},"edge":{},"nodes":{}}}"#,
+            bdd, level, edge, nodes
+        ),
+        Event::Drop { bdd, level, nodes } => format!(
+            r#"{{"event":"drop","bdd":{},"level":{},"nodes":{}}}"#,
+            bdd, level, nodes
+        ),
+        Event::LinEqFound { vars, rhs, lin_eqs } => format!(
+            r#"{{"event":"lin_eq_found","vars":[{}],"rhs":{},"lin_eqs":{}}}"#,
+            vars.iter()
+                .map(|var| var.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            rhs,
+            lin_eqs
+        ),
+        Event::BddRemoved { bdd } => format!(r#"{{"event":"bdd_removed","bdd":{}}}"#, bdd),
+        Event::Progress(progress) => format!(
+            r#"{{"event":"progress","bdds":{},"nodes":{},"biggest":{},"lin_eqs":{},"resolved":{},"remaining":{},"dropped":{},"max_nodes":{}}}"#,
+            progress.bdds,
+            progress.nodes,
+            progress.biggest,
+            progress.lin_eqs,
+            optional(progress.resolved),
+            optional(progress.remaining),
+            optional(progress.dropped),
+            optional(progress.max_nodes)
+        ),
+    }
+}
+
+/// A `System` whose operations notify an `Observer`.
+///
+/// It has the same operations as `System`, with the same errors, and notifies the observer
+/// once an operation succeeded.
+pub struct Observed<'a> {
+    system: &'a mut System,
+    observer: &'a dyn Observer,
+}
+
+impl<'a> Observed<'a> {
+    /// Wrap `system` to notify `observer` of its operations.
+    pub fn new(system: &'a mut System, observer: &'a dyn Observer) -> Observed<'a> {
+        Observed { system, observer }
+    }
+
+    /// Return the `System`.
+    pub fn system(&self) -> &System {
+        self.system
+    }
+
+    /// Notify the observer of an event which didn't come from an operation of `Observed`.
+    pub fn notify(&self, event: &Event) {
+        self.observer.notify(event);
+    }
+
+    /// Notify the observer of the progress of the solving.
+    pub fn progress(&self) {
+        self.notify(&Event::Progress(Progress::of(self.system)));
+    }
+
+    /// See `System::join_bdds`.
+    pub fn join_bdds(&mut self, into: Id, from: Id) -> Result<Id, Error> {
+        let id = self.system.join_bdds(into, from)?;
+        let nodes = self.nodes(id);
+        self.notify(&Event::Join { into, from, nodes });
+        Ok(id)
+    }
+
+    /// See `System::swap`.
+    pub fn swap(&mut self, bdd: Id, above: usize, below: usize) -> Result<(), Error> {
+        self.system.swap(bdd, above, below)?;
+        let nodes = self.nodes(bdd);
+        self.notify(&Event::Swap {
+            bdd,
+            above,
+            below,
+            nodes,
+        });
+        Ok(())
+    }
+
+    /// See `System::add`.
+    pub fn add(&mut self, bdd: Id, above: usize, below: usize) -> Result<(), Error> {
+        self.system.add(bdd, above, below)?;
+        let nodes = self.nodes(bdd);
+        self.notify(&Event::Add {
+            bdd,
+            above,
+            below,
+            nodes,
+        });
+        Ok(())
+    }
+
+    /// See `System::absorb`.
+    pub fn absorb(&mut self, bdd: Id, level: usize, edge: bool) -> Result<(), Error> {
+        self.system.absorb(bdd, level, edge)?;
+        let nodes = self.nodes(bdd);
+        self.notify(&Event::Absorb {
+            bdd,
+            level,
+            edge,
+            nodes,
+        });
+        Ok(())
+    }
+
+    /// See `System::drop`.
+    pub fn drop(&mut self, bdd: Id, level: usize) -> Result<(), Error> {
+        self.system.drop(bdd, level)?;
+        let nodes = self.nodes(bdd);
+        self.notify(&Event::Drop { bdd, level, nodes });
+        Ok(())
+    }
+
+    /// See `System::scan_absorb_all_lin_eqs`. The observer is notified of each `LinEq` pushed
+    /// to the `LinBank`, then of each `Bdd` removed.
+    pub fn scan_absorb_all_lin_eqs(&mut self) -> usize {
+        let lin_eqs = self.system.get_lin_bank_size();
+        let bdds: Vec<Id> = self.system.iter_bdds().map(|(id, _)| *id).collect();
+        let absorbed = self.system.scan_absorb_all_lin_eqs();
+        for (i, lin_eq) in self.system.iter_lin_eqs().enumerate().skip(lin_eqs) {
+            self.observer.notify(&Event::LinEqFound {
+                vars: lin_eq.get_lhs().iter_set_bits(..).collect(),
+                rhs: lin_eq.get_rhs(),
+                lin_eqs: i + 1,
+            });
+        }
+        for bdd in bdds {
+            if self.system.get_bdd(bdd).is_err() {
+                self.notify(&Event::BddRemoved { bdd });
+            }
+        }
+        absorbed
+    }
+
+    /// Return the number of nodes of the `Bdd` `bdd`, 0 if it's not in the `System` anymore.
+    fn nodes(&self, bdd: Id) -> usize {
+        self.system.get_bdd(bdd).map_or(0, |bdd| bdd.get_size())
+    }
+}
@@ -0,0 +1,177 @@
+//! A guess-and-determine `Solver`, trading time for memory.
+//!
+//! The dependencies are resolved like the default `Solver`, as long as the resolution of the next
+//! one stays below a number of nodes (see `System::estimate_resolution`). Past it, the lhs of the
+//! level of the dependency with the most nodes is guessed instead : the `System` is split in two
+//! with `System::fix`, one `System` for each value of the lhs, and both are solved in parallel.
+//! Fixing the lhs absorbs the level, which removes the nodes which were only reached along the
+//! other edge, and leaves a dependency over one level less.
+//!
+//! A branch is abandoned as soon as it shows a contradiction (see `System::has_contradiction`),
+//! the solutions of the `System` are the solutions of the branches left.
+
+use std::io::Error;
+use std::result::Result;
+
+use super::event::{Observer, Terminal};
+use super::{Dependency, Solver};
+use crate::soc::{system::System, Id};
+
+/// A `Solver` guessing the lhs of a level when resolving a dependency would create too many
+/// nodes (see the module documentation).
+pub struct GuessAndDetermine {
+    /// The number of nodes above which a resolution is not made, a level is guessed instead.
+    threshold: usize,
+    /// The number of guesses after which the dependencies are resolved whatever their cost.
+    max_guesses: usize,
+    observer: Box<dyn Observer + Send + Sync>,
+}
+
+impl GuessAndDetermine {
+    /// Construct a `GuessAndDetermine` guessing when a resolution would reach more than
+    /// `threshold` nodes, printing its progress on the terminal.
+    pub fn new(threshold: usize) -> GuessAndDetermine {
+        GuessAndDetermine::with_observer(threshold, Box::new(Terminal))
+    }
+
+    /// Construct a `GuessAndDetermine` guessing when a resolution would reach more than
+    /// `threshold` nodes, notifying `observer` of its operations and progress. The branches
+    /// being solved in parallel, their events are interleaved.
+    pub fn with_observer(
+        threshold: usize,
+        observer: Box<dyn Observer + Send + Sync>,
+    ) -> GuessAndDetermine {
+        GuessAndDetermine {
+            threshold,
+            max_guesses: 20,
+            observer,
+        }
+    }
+
+    /// Set the number of guesses made on a branch after which the dependencies are resolved
+    /// whatever their cost, 20 by default. There are at most 2 to the power `max_guesses`
+    /// branches.
+    pub fn set_max_guesses(&mut self, max_guesses: usize) {
+        self.max_guesses = max_guesses;
+    }
+
+    /// Resolve all the dependencies of `system`, guessing when needed, and return the `System`s
+    /// of the branches which didn't end with a contradiction. Their solutions are together the
+    /// solutions of `system`, and can be walked lazily with `System::solutions`.
+    pub fn reduce<T: Dependency>(&self, mut system: System) -> Result<Vec<System>, Error> {
+        system.track_dependencies();
+        self.explore::<T>(system, 0)
+    }
+
+    /// Solve the branch of `system`, on which `guesses` guesses were made.
+    fn explore<T: Dependency>(
+        &self,
+        mut system: System,
+        guesses: usize,
+    ) -> Result<Vec<System>, Error> {
+        loop {
+            self.absorb_all_equations(&mut system)?;
+            if system.has_contradiction() {
+                return Ok(Vec::new());
+            }
+            let deps = T::extract(&system);
+            if deps.is_empty() {
+                return Ok(vec![system]);
+            }
+            let join_order = Self::pick_best_dep(deps);
+            if guesses < self.max_guesses && self.exceeds_threshold(&system, &join_order)? {
+                let guess = Self::pick_guess(&system, &join_order)?;
+                let other = system.clone();
+                let (zero, one) = rayon::join(
+                    || self.guess::<T>(system, guess.clone(), false, guesses + 1),
+                    || self.guess::<T>(other, guess.clone(), true, guesses + 1),
+                );
+                let mut systems = zero?;
+                systems.append(&mut one?);
+                return Ok(systems);
+            }
+            match Self::resolve(self, &mut system, join_order) {
+                Err(_) if system.has_contradiction() => return Ok(Vec::new()),
+                result => result?,
+            }
+            self.feedback(&system);
+        }
+    }
+
+    /// Return true if the resolution of the dependency of `join_order` would reach more than
+    /// `threshold` nodes. The resolution is only replayed if its upper bound is above it.
+    fn exceeds_threshold(
+        &self,
+        system: &System,
+        join_order: &(Vec<Id>, Vec<usize>),
+    ) -> Result<bool, Error> {
+        if system.estimate_resolution(join_order, 0)?.peak <= self.threshold {
+            return Ok(false);
+        }
+        let estimate = system.estimate_resolution(join_order, self.threshold)?;
+        Ok(estimate.peak > self.threshold)
+    }
+
+    /// Fix the variables of `guess` to `value` in `system`, then solve the branch.
+    fn guess<T: Dependency>(
+        &self,
+        mut system: System,
+        guess: Vec<usize>,
+        value: bool,
+        guesses: usize,
+    ) -> Result<Vec<System>, Error> {
+        match system.fix(guess, value) {
+            Err(_) if system.has_contradiction() => Ok(Vec::new()),
+            result => {
+                result?;
+                self.explore::<T>(system, guesses)
+            }
+        }
+    }
+
+    /// Return the variables of the lhs of the level of the dependency of `join_order` which has
+    /// the most nodes.
+    fn pick_guess(
+        system: &System,
+        join_order: &(Vec<Id>, Vec<usize>),
+    ) -> Result<Vec<usize>, Error> {
+        let mut levels = Vec::with_capacity(join_order.1.len());
+        let mut above = 0;
+        for id in join_order.0.iter() {
+            let bdd = system.get_bdd(*id)?;
+            let len = bdd.get_levels_size() - 1;
+            levels.extend(
+                join_order
+                    .1
+                    .iter()
+                    .filter(|index| (above..above + len).contains(*index))
+                    .map(|index| bdd.level(index - above).unwrap()),
+            );
+            above += len;
+        }
+        let widest = levels
+            .into_iter()
+            .max_by_key(|level| level.get_nodes_len())
+            .unwrap();
+        Ok(widest.iter_set_lhs().collect())
+    }
+}
+
+impl Solver for GuessAndDetermine {
+    /// Solve `system` by `reduce`-ing it and return the solutions of all the branches. `system`
+    /// is left empty, each branch being solved on its own `System`.
+    fn solve<T: Dependency>(
+        &mut self,
+        system: &mut System,
+    ) -> Result<Vec<Vec<Option<bool>>>, Error> {
+        let mut solutions = Vec::new();
+        for mut branch in self.reduce::<T>(std::mem::take(system))? {
+            solutions.append(&mut branch.calculate_solutions());
+        }
+        Ok(solutions)
+    }
+
+    fn observer(&self) -> &dyn Observer {
+        self.observer.as_ref()
+    }
+}
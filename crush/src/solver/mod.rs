@@ -1,10 +1,20 @@
 //! Provide the traits to create solving strategies using the apis of `soc::System`.
 
+pub mod budget;
+pub mod checkpoint;
+pub mod event;
+pub mod guess;
+pub mod profile;
 
 use std::io::Error;
+use std::path::PathBuf;
 use std::result::Result;
 
 use crate::soc::{Id, system::System};
+use self::budget::{Bounded, Budget, Meter, Reason};
+use self::checkpoint::{Checkpoint, Checkpointer};
+use self::event::{Event, Observed, Observer, Progress, Terminal};
+use self::profile::Profiler;
 
 /// Describe a dependency inside a `System` of `Bdd`. A `Dependency`
 /// is defined as a collection of levels in a `System` which can be add to create a
@@ -15,11 +25,18 @@ pub trait Dependency: Sized {
     /// Provide a way to estimate the cost of resolving the `Dependency`.
     /// Out of all dependencies, the one where the return of this function is the lowest should
     /// be the cheapest one to resolve.
+    ///
+    /// `System::estimate_resolution` gives the number of nodes of the resolution of the
+    /// `best_join_order`, at the price of replaying it on a copy of the `Bdd`s involved.
     fn minimize_distance(&self) -> usize;
     /// Return the order in which the `Bdd`s involved in the `Dependency` should be joined,
     /// and the index of the levels to add to create a 0-level in the resulting `Bdd`.
     fn best_join_order(&self) -> (Vec<Id>, Vec<usize>);
-    /// Extract all the `Dependency` in a given `System`
+    /// Extract all the `Dependency` in a given `System`.
+    ///
+    /// The solvers track the dependencies of the `System` while solving (see
+    /// `System::track_dependencies`), `System::linear_dependencies` doesn't reduce all the lhs
+    /// of the levels again then.
     fn extract(system: &System) -> Vec<Self>;
 }
 
@@ -64,16 +81,52 @@ pub trait Solver {
         &mut self,
         system: &mut System,
     ) -> Result<Vec<Vec<Option<bool>>>, Error> {
-        Self::absorb_all_equations(system)?;
+        self.solve_within::<T>(system, &mut Budget::new().start())?;
+        Ok(system.calculate_solutions())
+    }
+
+    /// Same as `solve`, but stop before exceeding `budget` and return the partially solved
+    /// `System` then (see `budget`).
+    fn solve_bounded<T: Dependency>(
+        &mut self,
+        mut system: System,
+        budget: &Budget,
+    ) -> Result<Bounded<Vec<Vec<Option<bool>>>>, Error> {
+        let mut meter = budget.start();
+        match self.solve_within::<T>(&mut system, &mut meter)? {
+            Some(reason) => Ok(meter.exceeded(reason, system)),
+            None => Ok(meter.done(system.calculate_solutions())),
+        }
+    }
+
+    /// Resolve the dependencies of `system` until there are none left or `meter` exceeds its
+    /// budget, returning the bound exceeded then. `solve` and `solve_bounded` both go through
+    /// it.
+    fn solve_within<T: Dependency>(
+        &mut self,
+        system: &mut System,
+        meter: &mut Meter,
+    ) -> Result<Option<Reason>, Error> {
+        system.track_dependencies();
+        self.absorb_all_equations(system)?;
         let mut deps = T::extract(system);
         while !deps.is_empty() {
-            Self::resolve(self, system, Self::pick_best_dep(deps))?;
+            meter.remaining(deps.len());
+            let join_order = Self::pick_best_dep(deps);
+            if let Some(reason) = meter.check_resolution(system, &join_order)? {
+                return Ok(Some(reason));
+            }
+            Self::resolve(self, system, join_order)?;
+            meter.resolved();
             Self::feedback(self, system);
-            Self::absorb_all_equations(system)?;
+            self.absorb_all_equations(system)?;
             Self::feedback(self, system);
             deps = T::extract(system);
+            self.checkpoint(system)?;
         }
-        Ok(system.calculate_solutions())
+        meter.remaining(0);
+        meter.check(system);
+        Ok(None)
     }
 
     /// Find the `Dependency` that should be resolved next and return the order in which
@@ -93,27 +146,21 @@ pub trait Solver {
         deps[id_dep].best_join_order()
     }
 
-    /// Provide information about the solving process to the user.
+    /// Return the `Observer` notified of the operations made by the `Solver` (see `event`).
+    ///
+    /// The default is the `Terminal` observer, printing the progress on stdout.
+    fn observer(&self) -> &dyn Observer {
+        &Terminal
+    }
+
+    /// Provide information about the solving process to the user, by notifying the `observer`
+    /// of an `Event::Progress`.
     ///
     /// If you need information that are not contained in the `System` (ex: number of dependencies absorbed),
     /// the most easy way of getting them is to make them a field of your `Solver` and updating
     /// the fields during the solving.
     fn feedback(&self, system: &System) {
-        print!("\x1Bc");
-        println!(
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size()
-        );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!("biggest bdd has {} nodes", max_size);
+        self.observer().notify(&Event::Progress(Progress::of(system)));
     }
 
     /// Describe the way a `Dependency` should be resolved.
@@ -125,12 +172,14 @@ pub trait Solver {
         system: &mut System,
         join_order: (Vec<Id>, Vec<usize>),
     ) -> Result<(), Error> {
+        let mut system = Observed::new(system, self.observer());
         let mut keys_iter = join_order.0.iter();
         let bdd_root_id = keys_iter.next().unwrap();
         for key in keys_iter {
             system
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
+            self.profile(system.system(), *bdd_root_id);
         }
         for i in (0..join_order.1.len() - 1).rev() {
             for j in (join_order.1[i] + 1..join_order.1[i + 1]).rev() {
@@ -140,40 +189,69 @@ pub trait Solver {
             if i != 0 {
                 system.swap(*bdd_root_id, join_order.1[i], join_order.1[i] + 1)?;
             }
-            Self::feedback(self, system);
-        } 
+            Self::feedback(self, system.system());
+        }
         system.absorb(*bdd_root_id, join_order.1[0] + 1, false)?;
         Ok(())
     }
 
     /// Go through all BDDs and check for equation to absorb
     /// until there are no left. If when absorbing a BDD is reduced to
-    /// its sink then we remove it from the system
-    fn absorb_all_equations(system: &mut System) -> Result<(), Error> {
-        let mut absorbed = true;
-        while absorbed {
-            absorbed = false;
-            let ids = system
-                .iter_bdds()
-                .map(|bdd| *bdd.0)
-                .collect::<Vec<Id>>();
-            for id in ids.iter() {
-                if system
-                    .scan_absorb_lin_eqs(*id)
-                    .expect("shouldn't crash when absorbing lin eq")
-                    > 0
-                {
-                    absorbed = true;
-                }
-            }
-            for id in ids.iter() {
-                if system.get_bdd(*id)?.borrow().get_sink_level_index() == 0 {
-                    system.pop_bdd(*id)?;
-                }
-            }
+    /// its sink then we remove it from the system.
+    ///
+    /// The BDDs are scanned in parallel (see `System::scan_absorb_all_lin_eqs`).
+    fn absorb_all_equations(&self, system: &mut System) -> Result<(), Error> {
+        let mut system = Observed::new(system, self.observer());
+        while system.scan_absorb_all_lin_eqs() > 0 {}
+        Ok(())
+    }
+
+    /// Return the `Checkpointer` saving the progress of the solving, `None` (the default) to
+    /// never save it.
+    fn checkpointer(&self) -> Option<&Checkpointer> {
+        None
+    }
+
+    /// Return the `Profiler` writing the statistics of the joined `Bdd` after each join, `None`
+    /// (the default) to not profile the solving.
+    fn profiler(&self) -> Option<&Profiler> {
+        None
+    }
+
+    /// Write the statistics of the `Bdd` `bdd` with the `profiler`, if any.
+    fn profile(&self, system: &System, bdd: Id) {
+        if let (Some(profiler), Ok(bdd)) = (self.profiler(), system.get_bdd(bdd)) {
+            profiler.record(&bdd.stats());
+        }
+    }
+
+    /// Return the counters of the `Solver` to save in a checkpoint.
+    fn checkpoint_state(&self) -> Checkpoint {
+        Default::default()
+    }
+
+    /// Restore the counters saved in a checkpoint, when resuming.
+    fn restore(&mut self, _checkpoint: &Checkpoint) {}
+
+    /// Save a checkpoint of the `System` if the `checkpointer` is due (see
+    /// `Checkpointer::save_if_due`).
+    fn checkpoint(&self, system: &System) -> Result<(), Error> {
+        if let Some(checkpointer) = self.checkpointer() {
+            checkpointer.save_if_due(&self.checkpoint_state(), system)?;
         }
         Ok(())
     }
+
+    /// Load the checkpoint at `path`, restore the counters of the `Solver` and continue
+    /// the solving of the `System` saved.
+    fn resume<T: Dependency>(
+        &mut self,
+        path: &PathBuf,
+    ) -> Result<Vec<Vec<Option<bool>>>, Error> {
+        let (checkpoint, mut system) = checkpoint::read_checkpoint_from_file(path)?;
+        self.restore(&checkpoint);
+        self.solve::<T>(&mut system)
+    }
 }
 /// Describe a `DroppingSolver` as an object able to mutate a `System` in order
 /// to remove all its linear dependencies and returning
@@ -215,24 +293,67 @@ pub trait DroppingSolver {
         system: &mut System,
         forbid_dropping: Option<&[usize]>,
     ) -> Result<Vec<Vec<Option<bool>>>, Error> {
-        Self::absorb_all_equations(system)?;
+        self.solve_within::<D, I>(system, forbid_dropping, &mut Budget::new().start())?;
+        Ok(system.calculate_solutions())
+    }
+
+    /// Same as `solve`, but stop before exceeding `budget` and return the partially solved
+    /// `System` then (see `budget`). The number of nodes is only estimated before resolving a
+    /// `Dependency`, a drop exceeding it is noticed once made.
+    fn solve_bounded<D: Dependency, I: Independency>(
+        &mut self,
+        mut system: System,
+        forbid_dropping: Option<&[usize]>,
+        budget: &Budget,
+    ) -> Result<Bounded<Vec<Vec<Option<bool>>>>, Error> {
+        let mut meter = budget.start();
+        match self.solve_within::<D, I>(&mut system, forbid_dropping, &mut meter)? {
+            Some(reason) => Ok(meter.exceeded(reason, system)),
+            None => Ok(meter.done(system.calculate_solutions())),
+        }
+    }
+
+    /// Resolve the dependencies of `system`, dropping variables on the way, until there are none
+    /// left or `meter` exceeds its budget, returning the bound exceeded then. `solve` and
+    /// `solve_bounded` both go through it.
+    fn solve_within<D: Dependency, I: Independency>(
+        &mut self,
+        system: &mut System,
+        forbid_dropping: Option<&[usize]>,
+        meter: &mut Meter,
+    ) -> Result<Option<Reason>, Error> {
+        system.track_dependencies();
+        self.absorb_all_equations(system)?;
         let mut deps = D::extract(system);
         let mut indeps = I::extract(system, forbid_dropping);
         while !deps.is_empty() {
+            meter.remaining(deps.len());
             let (id_dep, min_distance_dep) = Self::pick_best_dep(&deps);
             let (id_indep, min_distance_indep) = Self::pick_best_indep(&indeps);
             if min_distance_indep < min_distance_dep {
+                if let Some(reason) = meter.check(system) {
+                    return Ok(Some(reason));
+                }
                 Self::indep_resolver(self, system, indeps[id_indep].best_join_order())?;
+                meter.dropped();
             } else {
-                Self::dep_resolver(self, system, deps[id_dep].best_join_order())?;
+                let join_order = deps[id_dep].best_join_order();
+                if let Some(reason) = meter.check_resolution(system, &join_order)? {
+                    return Ok(Some(reason));
+                }
+                Self::dep_resolver(self, system, join_order)?;
+                meter.resolved();
             }
             Self::feedback(self, system);
-            Self::absorb_all_equations(system)?;
+            self.absorb_all_equations(system)?;
             Self::feedback(self, system);
             deps = D::extract(system);
             indeps = I::extract(system, forbid_dropping);
+            self.checkpoint(system)?;
         }
-        Ok(system.calculate_solutions())
+        meter.remaining(0);
+        meter.check(system);
+        Ok(None)
     }
 
     /// Describe the way an `Independency` should be resolved.
@@ -248,20 +369,22 @@ pub trait DroppingSolver {
         system: &mut System,
         join_order: (Vec<Id>, Vec<usize>),
     ) -> Result<(), Error> {
+        let mut system = Observed::new(system, self.observer());
         let mut keys_iter = join_order.0.iter();
         let bdd_root_id = keys_iter.next().unwrap();
         for key in keys_iter {
             system
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
+            self.profile(system.system(), *bdd_root_id);
         }
         for i in 0..join_order.1.len() - 1 {
             system.add(*bdd_root_id, join_order.1[i], join_order.1[i + 1])?;
             system.swap(*bdd_root_id, join_order.1[i + 1] - 1, join_order.1[i + 1])?;
-            Self::feedback(self, system);
+            Self::feedback(self, system.system());
         }
         system.drop(*bdd_root_id, *join_order.1.last().unwrap())?;
-        Self::feedback(self, system);
+        Self::feedback(self, system.system());
         Ok(())
     }
 
@@ -274,12 +397,14 @@ pub trait DroppingSolver {
         system: &mut System,
         join_order: (Vec<Id>, Vec<usize>),
     ) -> Result<(), Error> {
+        let mut system = Observed::new(system, self.observer());
         let mut keys_iter = join_order.0.iter();
         let bdd_root_id = keys_iter.next().unwrap();
         for key in keys_iter {
             system
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
+            self.profile(system.system(), *bdd_root_id);
         }
         for i in (0..join_order.1.len() - 1).rev() {
             for j in (join_order.1[i] + 1..join_order.1[i + 1]).rev() {
@@ -289,7 +414,7 @@ pub trait DroppingSolver {
             if i != 0 {
                 system.swap(*bdd_root_id, join_order.1[i], join_order.1[i] + 1)?;
             }
-            Self::feedback(&self, system);
+            Self::feedback(self, system.system());
         }
         system.absorb(*bdd_root_id, join_order.1[0] + 1, false)?;
         Ok(())
@@ -328,53 +453,77 @@ pub trait DroppingSolver {
 
     /// Go through all BDDs and check for equation to absorb
     /// until there are no left. If when absorbing a BDD is reduced to
-    /// its sink then we remove it from the system
-    fn absorb_all_equations(system: &mut System) -> Result<(), Error> {
-        let mut absorbed = true;
-        while absorbed {
-            absorbed = false;
-            let ids = system
-                .iter_bdds()
-                .map(|bdd| *bdd.0)
-                .collect::<Vec<Id>>();
-            for id in ids.iter() {
-                if system
-                    .scan_absorb_lin_eqs(*id)
-                    .expect("shouldn't crash when absorbing lin eq")
-                    > 0
-                {
-                    absorbed = true;
-                }
-            }
-            for id in ids.iter() {
-                if system.get_bdd(*id)?.borrow().get_sink_level_index() == 0 {
-                    system.pop_bdd(*id)?;
-                }
-            }
+    /// its sink then we remove it from the system.
+    ///
+    /// The BDDs are scanned in parallel (see `System::scan_absorb_all_lin_eqs`).
+    fn absorb_all_equations(&self, system: &mut System) -> Result<(), Error> {
+        let mut system = Observed::new(system, self.observer());
+        while system.scan_absorb_all_lin_eqs() > 0 {}
+        Ok(())
+    }
+
+    /// Return the `Checkpointer` saving the progress of the solving, `None` (the default) to
+    /// never save it.
+    fn checkpointer(&self) -> Option<&Checkpointer> {
+        None
+    }
+
+    /// Return the `Profiler` writing the statistics of the joined `Bdd` after each join, `None`
+    /// (the default) to not profile the solving.
+    fn profiler(&self) -> Option<&Profiler> {
+        None
+    }
+
+    /// Write the statistics of the `Bdd` `bdd` with the `profiler`, if any.
+    fn profile(&self, system: &System, bdd: Id) {
+        if let (Some(profiler), Ok(bdd)) = (self.profiler(), system.get_bdd(bdd)) {
+            profiler.record(&bdd.stats());
+        }
+    }
+
+    /// Return the counters of the `DroppingSolver` to save in a checkpoint.
+    fn checkpoint_state(&self) -> Checkpoint {
+        Default::default()
+    }
+
+    /// Restore the counters saved in a checkpoint, when resuming.
+    fn restore(&mut self, _checkpoint: &Checkpoint) {}
+
+    /// Save a checkpoint of the `System` if the `checkpointer` is due (see
+    /// `Checkpointer::save_if_due`).
+    fn checkpoint(&self, system: &System) -> Result<(), Error> {
+        if let Some(checkpointer) = self.checkpointer() {
+            checkpointer.save_if_due(&self.checkpoint_state(), system)?;
         }
         Ok(())
     }
 
-    /// Provide information about the solving process to the user.
+    /// Load the checkpoint at `path`, restore the counters of the `DroppingSolver` and continue
+    /// the solving of the `System` saved.
+    fn resume<D: Dependency, I: Independency>(
+        &mut self,
+        path: &PathBuf,
+        forbid_dropping: Option<&[usize]>,
+    ) -> Result<Vec<Vec<Option<bool>>>, Error> {
+        let (checkpoint, mut system) = checkpoint::read_checkpoint_from_file(path)?;
+        self.restore(&checkpoint);
+        self.solve::<D, I>(&mut system, forbid_dropping)
+    }
+
+    /// Return the `Observer` notified of the operations made by the `DroppingSolver` (see `event`).
+    ///
+    /// The default is the `Terminal` observer, printing the progress on stdout.
+    fn observer(&self) -> &dyn Observer {
+        &Terminal
+    }
+
+    /// Provide information about the solving process to the user, by notifying the `observer`
+    /// of an `Event::Progress`.
     ///
     /// If you need information that are not contained in the `System` (ex: number of dependencies absorbed),
     /// the most easy way of getting them is to make them a field of your `DroppingSolver` and updating
     /// the fields during the solving.
     fn feedback(&self, system: &System) {
-        print!("\x1Bc");
-        println!(
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
-        );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!("biggest bdd has {} nodes", max_size);
+        self.observer().notify(&Event::Progress(Progress::of(system)));
     }
 }
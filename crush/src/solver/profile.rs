@@ -0,0 +1,130 @@
+//! Width profiles of a solving, to tune the soft limits and the strategies.
+//!
+//! A `Profiler` given to a solver (see `Solver::profiler`) writes the statistics of the joined
+//! `Bdd` (see `soc::stats`) after each join of a `Bdd` into another one. The snapshots are
+//! numbered by a `step` starting at 0, and written either as JSON lines :
+//!
+//! ```text
+//! {"step":0,"bdd":3,"nodes":9,"width":4,"levels":[{"level":0,"nodes":1,...},...]}
+//! ```
+//!
+//! or as CSV, one row per level :
+//!
+//! ```text
+//! step,bdd,level,nodes,single_edge,lhs_weight
+//! 0,3,0,1,0,2
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Error, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::soc::stats::{BddStats, CSV_HEADER};
+
+/// The format of the snapshots written by a `Profiler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON object per snapshot, on its own line.
+    JsonLines,
+    /// A row per level, after a header.
+    Csv,
+}
+
+struct Output {
+    writer: Box<dyn Write + Send>,
+    error: Option<Error>,
+    step: usize,
+}
+
+/// Write a snapshot of the statistics of a `Bdd` at each step of a solving (see the module
+/// documentation).
+///
+/// As with `event::JsonLines`, failing to write doesn't stop the solving: the first error is
+/// kept and can be retrieved with `take_error`, the following snapshots are then ignored.
+pub struct Profiler {
+    format: Format,
+    output: Mutex<Output>,
+}
+
+impl Profiler {
+    /// Construct a `Profiler` writing to `writer` in `format`.
+    pub fn new(writer: Box<dyn Write + Send>, format: Format) -> Profiler {
+        Profiler {
+            format,
+            output: Mutex::new(Output {
+                writer,
+                error: None,
+                step: 0,
+            }),
+        }
+    }
+
+    /// Construct a `Profiler` writing to the file at `path`, as CSV if its extension is `csv`
+    /// and as JSON lines otherwise.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Profiler> {
+        let path = path.as_ref();
+        let format = match path.extension() {
+            Some(extension) if extension == "csv" => Format::Csv,
+            _ => Format::JsonLines,
+        };
+        let file = File::create(path)?;
+        Ok(Profiler::new(Box::new(BufWriter::new(file)), format))
+    }
+
+    /// Return the format of the snapshots.
+    pub fn get_format(&self) -> Format {
+        self.format
+    }
+
+    /// Write the snapshot of `stats` for the next step.
+    pub fn record(&self, stats: &BddStats) {
+        let mut output = self.output.lock().unwrap();
+        if output.error.is_some() {
+            return;
+        }
+        let step = output.step;
+        let written = match self.format {
+            Format::JsonLines => {
+                // the object of `stats`, with the step as first field
+                let json = stats.to_json();
+                writeln!(output.writer, r#"{{"step":{},{}"#, step, &json[1..])
+            }
+            Format::Csv => {
+                let header = if step == 0 {
+                    writeln!(output.writer, "step,{}", CSV_HEADER)
+                } else {
+                    Ok(())
+                };
+                header.and_then(|_| {
+                    stats
+                        .csv_rows()
+                        .try_for_each(|row| writeln!(output.writer, "{},{}", step, row))
+                })
+            }
+        };
+        match written {
+            Ok(()) => output.step += 1,
+            Err(e) => output.error = Some(e),
+        }
+    }
+
+    /// Return the number of snapshots written.
+    pub fn get_steps(&self) -> usize {
+        self.output.lock().unwrap().step
+    }
+
+    /// Return the first error met when writing, if any, and resume writing.
+    pub fn take_error(&self) -> Option<Error> {
+        self.output.lock().unwrap().error.take()
+    }
+
+    /// Flush the writer, returning the first error met when writing if any.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut output = self.output.lock().unwrap();
+        match output.error.take() {
+            Some(error) => Err(error),
+            None => output.writer.flush(),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Benchmarks of the join/absorb loop used by cryptapath on a 3 rounds DES system.
+//!
+//! The fixture `data/des3.bdd` was generated with
+//! `cryptapath cipher -c des -r 3 -o benches/data/des3.bdd`.
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use crush::{
+    soc::{system::System, utils},
+    solver::event::Silent,
+};
+
+use cryptapath::strategy::UpwardSolver;
+
+/// `UpwardSolver::improved_reduce`, without the feedback on the terminal.
+fn reduce(system: &mut System) {
+    UpwardSolver::with_observer(Box::new(Silent))
+        .improved_reduce(system)
+        .unwrap();
+}
+
+fn fixture() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/des3.bdd"))
+}
+
+fn load() -> System {
+    utils::build_system_from_spec(utils::parse_system_spec_from_file(&fixture()))
+}
+
+fn bench_load(c: &mut Criterion) {
+    c.bench_function("des3 load", |b| b.iter(load));
+}
+
+fn bench_reduce(c: &mut Criterion) {
+    let mut group = c.benchmark_group("des3 join/absorb");
+    group.sample_size(10);
+    group.bench_function("reduce", |b| {
+        b.iter_batched(
+            load,
+            |mut system| reduce(&mut system),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("reduce and count", |b| {
+        b.iter_batched(
+            load,
+            |mut system| {
+                reduce(&mut system);
+                system.count_solutions()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_load, bench_reduce);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate crush;
+extern crate rand;
+extern crate vob;
+
+#[macro_use]
+pub mod bit;
+pub mod sbox;
+pub mod strategy;
+pub mod targets;
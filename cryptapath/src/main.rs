@@ -1,22 +1,24 @@
-#[macro_use]
 extern crate crush;
-extern crate rand;
+extern crate cryptapath;
 extern crate structopt;
-extern crate vob;
 #[macro_use]
 extern crate structopt_derive;
 
-#[macro_use]
-pub mod bit;
 pub mod options;
-pub mod sbox;
-pub mod strategy;
-pub mod targets;
 
+use crush::soc::anf;
+use crush::soc::binary;
+use crush::soc::cnf::{self, Cnf};
+use crush::soc::system::System;
 use crush::soc::utils::*;
+use crush::solver::budget::{Bounded, Budget, Reason};
+use crush::solver::checkpoint::{self, Checkpointer};
+use crush::solver::profile::Profiler;
+use cryptapath::{bit, strategy, targets::*};
 use options::CryptaPathOptions;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use structopt::StructOpt;
-use targets::*;
 
 fn main() {
     match CryptaPathOptions::from_args() {
@@ -27,6 +29,13 @@ fn main() {
             key,
             out,
             strategy,
+            guess_threshold,
+            max_nodes,
+            time_limit,
+            checkpoint,
+            checkpoint_interval,
+            profile,
+            resume,
         } => {
             let cipher = match build_cipher_by_name(cipher_name.as_ref(), rounds) {
                 Some(c) => c,
@@ -35,107 +44,186 @@ fn main() {
                     return;
                 }
             };
-            let (input, output, mut system) = build_system_cipher(cipher.as_ref());
-            let (plaintext, ciphertext);
-            if let Some(plaintext_ciphertext) = chosen_plaintext_ciphertext {
-                assert_eq!(
-                    plaintext_ciphertext.len(),
-                    2,
-                    "You can only provide one plaintext and one ciphertext"
-                );
-                plaintext = bit::bits_from_hex_string(&plaintext_ciphertext[0]);
-                ciphertext = bit::bits_from_hex_string(&plaintext_ciphertext[1]);
-                if let Some(partial_key) = key {
-                    let filled_key = fill_partial_value(partial_key.as_ref());
-                    assert_eq!(cipher.key_length(), filled_key.0.len(),
-                    "the provided partial key has a size different from the key expected by the chosen cipher");
-                    fix_system_values_cipher_with_partial_key(
-                        &mut system,
-                        &plaintext,
-                        &ciphertext,
-                        filled_key,
-                        &input,
-                        &output,
-                    );
-                } else {
-                    fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
+            let resumed = match (resume, &checkpoint) {
+                (false, _) => None,
+                (true, None) => {
+                    println!("--resume needs the checkpoint to resume from, given with --checkpoint.");
+                    return;
                 }
-            } else if let Some(partial_key) = key {
-                let filled_key = fill_partial_value(partial_key.as_ref());
-                let tmp = get_random_plaintext_ciphertext_with_partial_key(
-                    cipher.as_ref(),
-                    filled_key.0.clone(),
-                );
-                plaintext = tmp.0;
-                ciphertext = tmp.1;
-                fix_system_values_cipher_with_partial_key(
-                    &mut system,
-                    &plaintext,
-                    &ciphertext,
-                    filled_key,
-                    &input,
-                    &output,
-                );
-            } else {
-                let tmp = get_random_plaintext_ciphertext_key(cipher.as_ref());
-                plaintext = tmp.0;
-                ciphertext = tmp.1;
-                fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
-            }
-            if let Some(path) = out {
-                print_system_to_file(&system, &path);
-            }
-            let forbid_dropping: Vec<usize> = (0..cipher.key_length()).collect();
-            let mut sols = match strategy {
-                Some(name) => match strategy::execute_strategy_by_name(
-                        name.as_ref(),
-                        &mut system,
-                        Some(&forbid_dropping),
-                    ) {
-                        Some(sols) => sols,
-                        None => {
-                            println!("Strategy not supported. Check --help for supported strategies.");
-                            return;
-                        }
+                (true, Some(path)) => match checkpoint::read_checkpoint_from_file(path) {
+                    Ok(resumed) => Some(resumed),
+                    Err(e) => {
+                        println!("Could not load {} : {}", path.display(), e);
+                        return;
                     }
-                ,
+                },
+            };
+            // the context of a checkpoint is the strategy, the cipher, the number of rounds, the
+            // plaintext and the ciphertext
+            let (plaintext, ciphertext, system, strategy, resumed) = match resumed {
+                Some((resumed, system)) => {
+                    if resumed.context.len() != 5 {
+                        println!("The checkpoint was not saved by a cipher run.");
+                        return;
+                    }
+                    if resumed.context[1] != cipher_name || resumed.context[2] != rounds.to_string() {
+                        println!(
+                            "The checkpoint was saved for {} rounds of {}, not {} rounds of {}.",
+                            resumed.context[2], resumed.context[1], rounds, cipher_name
+                        );
+                        return;
+                    }
+                    let plaintext = bit::bits_from_hex_string(&resumed.context[3]);
+                    let ciphertext = bit::bits_from_hex_string(&resumed.context[4]);
+                    let strategy = Some(resumed.context[0].clone());
+                    (plaintext, ciphertext, system, strategy, Some(resumed))
+                }
                 None => {
-                    strategy::execute_strategy_by_name("no_drop", &mut system, None).unwrap()
+                    let (input, output, mut system) = build_system_cipher(cipher.as_ref());
+                    let (plaintext, ciphertext);
+                    if let Some(plaintext_ciphertext) = chosen_plaintext_ciphertext {
+                        assert_eq!(
+                            plaintext_ciphertext.len(),
+                            2,
+                            "You can only provide one plaintext and one ciphertext"
+                        );
+                        plaintext = bit::bits_from_hex_string(&plaintext_ciphertext[0]);
+                        ciphertext = bit::bits_from_hex_string(&plaintext_ciphertext[1]);
+                        if let Some(partial_key) = key {
+                            let filled_key = fill_partial_value(partial_key.as_ref());
+                            assert_eq!(cipher.key_length(), filled_key.0.len(),
+                            "the provided partial key has a size different from the key expected by the chosen cipher");
+                            fix_system_values_cipher_with_partial_key(
+                                &mut system,
+                                &plaintext,
+                                &ciphertext,
+                                filled_key,
+                                &input,
+                                &output,
+                            );
+                        } else {
+                            fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
+                        }
+                    } else if let Some(partial_key) = key {
+                        let filled_key = fill_partial_value(partial_key.as_ref());
+                        let tmp = get_random_plaintext_ciphertext_with_partial_key(
+                            cipher.as_ref(),
+                            filled_key.0.clone(),
+                        );
+                        plaintext = tmp.0;
+                        ciphertext = tmp.1;
+                        fix_system_values_cipher_with_partial_key(
+                            &mut system,
+                            &plaintext,
+                            &ciphertext,
+                            filled_key,
+                            &input,
+                            &output,
+                        );
+                    } else {
+                        let tmp = get_random_plaintext_ciphertext_key(cipher.as_ref());
+                        plaintext = tmp.0;
+                        ciphertext = tmp.1;
+                        fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
+                    }
+                    if let Some(path) = out {
+                        print_system_to_file(&system, &path);
+                    }
+                    (plaintext, ciphertext, system, strategy, None)
                 }
             };
-            for sol in sols.iter_mut() {
-                let _ = sol.split_off(cipher.key_length());
-                let mut binary_string_sol = String::new();
-                for var in sol.iter() {
-                    match var {
-                        Some(b) => match b {
-                            true => {
-                                binary_string_sol.push('1');
-                            }
-                            false => {
-                                binary_string_sol.push('0');
-                            }
-                        },
-                        None => {
-                            if cipher_name == "des" {
-                                // with des this will always be the case as some bits of the 64 bit key are
-                                // unused. We can therefore just push 0 and the encryption will validate.
-                                // Kind of an ugly fix, the better fix would be to limit des to 56 bits and change
-                                // the test vectors
-                                binary_string_sol.push('0')
-                            } else {
-                                panic!("Some bits of the key are not determined, something wrong happened during the solving")
-                            }
-                        }
+            let forbid_dropping: Vec<usize> = (0..cipher.key_length()).collect();
+            let name = strategy.unwrap_or_else(|| "no_drop".to_string());
+            if name == "guess" {
+                if checkpoint.is_some() {
+                    println!("The guess strategy doesn't save checkpoints.");
+                    return;
+                }
+                if profile.is_some() {
+                    println!("The guess strategy doesn't write profiles.");
+                    return;
+                }
+                if max_nodes.is_some() || time_limit.is_some() {
+                    println!("The guess strategy is bounded by --guess-threshold only.");
+                    return;
+                }
+                // each guess which didn't end with a contradiction gives a system to walk
+                let threshold = guess_threshold.unwrap_or(strategy::DEFAULT_GUESS_THRESHOLD);
+                let mut systems = strategy::reduce_system_by_guessing(system, threshold).unwrap();
+                for system in systems.iter_mut() {
+                    check_key_solutions(system, cipher.as_ref(), &plaintext, &ciphertext);
+                }
+                return;
+            }
+            let checkpointer = checkpoint.map(|path| {
+                let mut checkpointer =
+                    Checkpointer::new(path, Duration::from_secs(checkpoint_interval));
+                checkpointer.set_context(vec![
+                    name.clone(),
+                    cipher_name.clone(),
+                    rounds.to_string(),
+                    bit::bits_to_hex_string(plaintext.clone()),
+                    bit::bits_to_hex_string(ciphertext.clone()),
+                ]);
+                checkpointer
+            });
+            let profiler = match profile.map(Profiler::create).transpose() {
+                Ok(profiler) => profiler,
+                Err(e) => {
+                    println!("Could not create the profile : {}", e);
+                    return;
+                }
+            };
+            let saved = checkpointer.as_ref().map(|c| c.get_path().clone());
+            let mut budget = Budget::new();
+            if let Some(max_nodes) = max_nodes {
+                budget.set_max_nodes(max_nodes);
+            }
+            if let Some(time_limit) = time_limit {
+                budget.set_time_limit(Duration::from_secs(time_limit));
+            }
+            let reduced = strategy::reduce_system_bounded(
+                name.as_ref(),
+                system,
+                Some(&forbid_dropping),
+                &budget,
+                checkpointer,
+                profiler,
+                resumed.as_ref(),
+            );
+            match reduced {
+                None => {
+                    println!("Strategy not supported. Check --help for supported strategies.");
+                }
+                Some(Bounded::Done(mut system, _)) => {
+                    check_key_solutions(&mut system, cipher.as_ref(), &plaintext, &ciphertext);
+                }
+                Some(Bounded::Exceeded(exceeded)) => {
+                    match exceeded.reason {
+                        Reason::Nodes { nodes } => println!(
+                            "Stopped : the next resolution would reach 2**{} nodes",
+                            (nodes as f64).log(2.0)
+                        ),
+                        Reason::Time => println!("Stopped : the time limit elapsed"),
+                        Reason::Cancelled => println!("Stopped : the solving was cancelled"),
+                    }
+                    let stats = exceeded.stats;
+                    println!(
+                        "solved dependencies {}, {} remaining, dropped variables {}",
+                        stats.resolved, stats.remaining, stats.dropped
+                    );
+                    println!(
+                        "max node reach 2**{} in {:.1}s",
+                        (stats.peak as f64).log(2.0),
+                        stats.elapsed.as_secs_f64()
+                    );
+                    if let Some(path) = saved {
+                        println!(
+                            "The partially solved system was saved to {}, continue with --resume",
+                            path.display()
+                        );
                     }
                 }
-                let key = bit::bits_from_binary_string(&binary_string_sol);
-                assert_eq!(
-                    ciphertext,
-                    cipher.encrypt(plaintext.clone(), key.clone()),
-                    "A solution was found but it doesn't encrypt correctly, something went wrong"
-                );
-                println!("valid solution : {}", bit::bits_to_hex_string(key));
             }
         }
 
@@ -199,13 +287,9 @@ fn main() {
                 print_system_to_file(&system, &path);
             }
             let forbid_dropping: Vec<usize> = (0..hash.message_length()).collect();
-            let mut sols = strategy::execute_strategy_by_name(
-                "drop",
-                &mut system,
-                Some(&forbid_dropping),
-            )
-            .unwrap();
-            for sol in sols.iter_mut() {
+            strategy::reduce_system_by_strategy_name("drop", &mut system, Some(&forbid_dropping))
+                .unwrap();
+            for mut sol in system.solutions() {
                 let _ = sol.split_off(hash.message_length());
                 let mut binary_string_sol = String::new();
                 for var in sol.iter() {
@@ -244,9 +328,102 @@ fn main() {
             println!("key : {}", bit::bits_to_binary_string(key));
         }
         CryptaPathOptions::FromFile { file } => {
-            let specs = parse_system_spec_from_file(&file);
-            let mut system = build_system_from_spec(specs);
-            strategy::execute_strategy_by_name("no_drop", &mut system, None).unwrap();
+            let mut system = match load_system(&file) {
+                Ok(system) => system,
+                Err(e) => {
+                    println!("Could not load {} : {}", file.display(), e);
+                    return;
+                }
+            };
+            let solutions =
+                strategy::execute_strategy_by_name("no_drop", &mut system, None).unwrap();
+            for solution in solutions.iter() {
+                println!("solution : {}", system.format_solution(solution));
+            }
+        }
+        CryptaPathOptions::Convert { input, output } => {
+            let converted = binary::is_binary_file(&input).and_then(|is_binary| {
+                if has_extension(&output, "cnf") {
+                    Cnf::from_system(&load_system(&input)?).write_dimacs_to_file(&output)
+                } else if has_extension(&input, "anf") || has_extension(&input, "cnf") {
+                    binary::write_system_to_file(&load_system(&input)?, &output)
+                } else if is_binary {
+                    binary::convert_binary_to_text(&input, &output)
+                } else {
+                    binary::convert_text_to_binary(&input, &output)
+                }
+            });
+            if let Err(e) = converted {
+                println!("Could not convert {} : {}", input.display(), e);
+            }
+        }
+    }
+}
+
+/// Walk the solutions of the reduced `system`, check that the keys they give encrypt `plaintext`
+/// to `ciphertext` and print them.
+fn check_key_solutions(
+    system: &mut System,
+    cipher: &dyn Cipher,
+    plaintext: &[bit::Bit],
+    ciphertext: &[bit::Bit],
+) {
+    let names: Vec<String> = (0..cipher.key_length())
+        .map(|var| system.var_name(var))
+        .collect();
+    // solutions are walked one at a time, the system may have a lot of them
+    for space in system.affine_solutions() {
+        // the key bits which are not determined by the system (like the parity bits of
+        // des) are free, the keys are then the particular key plus the span of the basis
+        let keys = space.truncate(cipher.key_length());
+        let mut checked = vec![keys.get_particular().clone()];
+        for vector in keys.get_basis() {
+            let mut key = keys.get_particular().clone();
+            key.xor(vector);
+            checked.push(key);
+        }
+        for key in checked.iter() {
+            let key: Vec<bit::Bit> = key.iter().map(|b| bit!(b)).collect();
+            assert_eq!(
+                ciphertext.to_vec(),
+                cipher.encrypt(plaintext.to_vec(), key),
+                "A solution was found but it doesn't encrypt correctly, something went wrong"
+            );
+        }
+        let key: Vec<bit::Bit> = keys.get_particular().iter().map(|b| bit!(b)).collect();
+        if keys.nullity() == 0 {
+            println!("valid solution : {}", bit::bits_to_hex_string(key));
+        } else {
+            println!(
+                "valid solution : {} (key bits {} are free, 2**{} keys)",
+                bit::bits_to_hex_string(key),
+                keys.free_vars()
+                    .iter()
+                    .map(|var| names[*var].as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                keys.nullity()
+            );
         }
     }
 }
+
+/// Load a system from a file in the binary format, in the .bdd format, from polynomials in a
+/// .anf file or from clauses in a .cnf file.
+fn load_system(file: &PathBuf) -> std::io::Result<System> {
+    if has_extension(file, "anf") {
+        let polynomials = anf::parse_anf_from_file(file)?;
+        Ok(anf::build_system_from_anf(&polynomials, anf::MAX_VARS)?)
+    } else if has_extension(file, "cnf") {
+        Ok(Cnf::parse_dimacs_from_file(file)?.to_system(cnf::MAX_VARS)?)
+    } else if binary::is_binary_file(file)? {
+        binary::read_system_from_file(file)
+    } else {
+        Ok(try_build_system_from_spec(try_parse_system_spec_from_file(file)?)?)
+    }
+}
+
+/// Return true if the extension of the file is `ext`.
+fn has_extension(file: &Path, ext: &str) -> bool {
+    file.extension().is_some_and(|file_ext| file_ext == ext)
+}
@@ -38,8 +38,42 @@ pub enum CryptaPathOptions {
         out: Option<PathBuf>,
         #[structopt(short = "s", long = "strategy")]
         /// Choose the strategy when trying to solve.
-        /// Available choices: "drop" "no_drop", default: "no_drop"
+        /// Available choices: "drop" "no_drop" "lookahead" "guess", default: "no_drop"
+        /// "lookahead" is "no_drop" picking the dependencies by estimating the nodes their
+        /// resolution will create
+        /// "guess" is "no_drop" guessing the value of a level instead of making a resolution
+        /// reaching more than guess-threshold nodes, both values being solved in parallel
         strategy: Option<String>,
+        #[structopt(long = "guess-threshold")]
+        /// The number of nodes above which the "guess" strategy guesses instead of resolving
+        /// a dependency. A lower threshold uses less memory but makes more guesses.
+        /// default: 2^22
+        guess_threshold: Option<usize>,
+        #[structopt(long = "max-nodes")]
+        /// If provided, the solving stops before a resolution would make the system exceed this
+        /// number of nodes. The partially solved system is saved if --checkpoint is given.
+        max_nodes: Option<usize>,
+        #[structopt(long = "time-limit")]
+        /// If provided, the solving stops once this number of seconds elapsed. The partially
+        /// solved system is saved if --checkpoint is given.
+        time_limit: Option<u64>,
+        #[structopt(long = "checkpoint", parse(from_os_str))]
+        /// If provided, the progress of the solving is saved to this file every checkpoint-interval
+        /// seconds, so that it can be resumed with --resume after a crash.
+        checkpoint: Option<PathBuf>,
+        #[structopt(long = "checkpoint-interval", default_value = "600")]
+        /// The number of seconds between two checkpoints.
+        checkpoint_interval: u64,
+        #[structopt(long = "profile", parse(from_os_str))]
+        /// If provided, the number of nodes, of single-edge nodes and of variables of each level of
+        /// the joined BDD are written to this file after each join, as CSV if it ends with .csv and
+        /// as JSON lines otherwise.
+        profile: Option<PathBuf>,
+        #[structopt(long = "resume")]
+        /// Continue the solving from the file given by --checkpoint instead of building a new system.
+        /// The cipher and the number of rounds must be the same as for the run which saved it,
+        /// the plaintext/ciphertext and the strategy are read from the checkpoint.
+        resume: bool,
     },
     #[structopt(name = "sponge")]
     Sponge {
@@ -93,7 +127,18 @@ pub enum CryptaPathOptions {
     #[structopt(name = "from-file")]
     FromFile {
         #[structopt(short = "f", long = "file", parse(from_os_str))]
-        /// The source bdd file
+        /// The source bdd file, either a .bdd file, a file in the binary format, a .anf file of polynomials or a DIMACS .cnf file
         file: PathBuf
+    },
+    #[structopt(name = "convert")]
+    Convert {
+        #[structopt(short = "i", long = "input", parse(from_os_str))]
+        /// The system to convert, either a .bdd file, a file in the binary format, a .anf file of polynomials or a DIMACS .cnf file
+        input: PathBuf,
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        /// Where to write the converted system: in DIMACS with XOR clauses if the output ends with .cnf,
+        /// otherwise in the binary format if the input is a .bdd, .anf or .cnf file, as a .bdd file if not (the linear
+        /// equations found while solving are then lost)
+        output: PathBuf,
     }
 }
\ No newline at end of file
@@ -12,6 +12,7 @@
 
 use crate::bit::Bit;
 use crate::crush::soc::{
+    symbols::{Role, SymbolTable},
     utils::{BddSpec, LevelSpec, NodeSpec},
     Id,
 };
@@ -21,7 +22,8 @@ use std::cell::{Cell, RefCell};
 /// number of bit to be outputted by an application of the S-Box and table
 /// its lookup table.
 /// next_var_id is used when new bits need to be outputted to produce the variables
-/// and bdds hold all the BDD generated by the S-Box.
+/// and bdds hold all the BDD generated by the S-Box, outputs the variables outputted by each
+/// of them.
 #[derive(Clone)]
 pub struct Sbox {
     in_size: usize,
@@ -29,6 +31,7 @@ pub struct Sbox {
     table: Vec<u8>,
     next_var_id: Cell<usize>,
     bdds: RefCell<Vec<BddSpec>>,
+    outputs: RefCell<Vec<Vec<usize>>>,
 }
 impl Sbox {
 
@@ -42,6 +45,7 @@ impl Sbox {
             table,
             next_var_id: Cell::new(next_var_id),
             bdds: RefCell::new(Vec::new()),
+            outputs: RefCell::new(Vec::new()),
         }
     }
 
@@ -60,6 +64,7 @@ impl Sbox {
             table,
             next_var_id: Cell::new(old_sbox.next_var_id()),
             bdds: RefCell::new(old_sbox.bdds()),
+            outputs: old_sbox.outputs,
         }
     }
 
@@ -99,6 +104,9 @@ impl Sbox {
             for i in self.next_var_id.get()..self.next_var_id.get() + self.out_size {
                 out_bits.push(Bit::from_variable_id(i))
             }
+            self.outputs
+                .borrow_mut()
+                .push((self.next_var_id.get()..self.next_var_id.get() + self.out_size).collect());
             self.next_var_id.set(self.next_var_id.get() + self.out_size);
             self.bdds
                 .borrow_mut()
@@ -112,6 +120,22 @@ impl Sbox {
         self.bdds.replace(Vec::new())
     }
 
+    /// Return the variables outputted by each application of the S-Box which produced a BDD,
+    /// in the order of the BDDs.
+    pub fn outputs(&self) -> Vec<Vec<usize>> {
+        self.outputs.borrow().clone()
+    }
+
+    /// Name the variables outputted by the S-Box in `symbols`, `s3_out1` being the output bit 1 of
+    /// the fourth application of the S-Box.
+    pub fn name_outputs(&self, symbols: &mut SymbolTable) {
+        for (n, vars) in self.outputs.borrow().iter().enumerate() {
+            symbols
+                .insert_all(vars, &format!("s{}_out", n), Role::State)
+                .expect("S-Box output names are valid and unique");
+        }
+    }
+
     /// Return the next_var_id of the S-Box (ie the last variable made
     /// by the S-Box + 1 ).
     #[inline]
@@ -9,9 +9,20 @@ use std::result::Result;
 use crush::{
     algebra,
     soc::{Id, system::System},
-    solver::{Dependency, DroppingSolver, Independency, Solver},
+    solver::{
+        budget::{Bounded, Budget, Meter, Reason},
+        checkpoint::{Checkpoint, Checkpointer},
+        event::{Event, Observer, Progress, Terminal},
+        guess::GuessAndDetermine,
+        profile::Profiler,
+        Dependency, DroppingSolver, Independency, Solver,
+    },
 };
 
+/// The number of nodes above which the "guess" strategy guesses instead of resolving a
+/// dependency, when it is not given.
+pub const DEFAULT_GUESS_THRESHOLD: usize = 1 << 22;
+
 /// Describe the informations about a `Bdd` involved in a `NodeRankedDependency` or a `NodeRankedIndependency`.
 #[derive(Clone, Debug)]
 pub struct InvolvedBdd {
@@ -193,25 +204,19 @@ impl Dependency for NodeRankedDependency {
     /// Build the linear dependencies of the system.
     fn extract(system: &System) -> Vec<NodeRankedDependency> {
         let mut deps = Vec::new();
-        let mut id_lhs = system.get_system_lhs();
-        let mut lhs_concat = Vec::new();
         let mut id_levels_size = Vec::new();
-        for bdd in id_lhs.iter_mut() {
+        for (id, bdd_object) in system.iter_bdds() {
             let mut levels = Vec::new();
-            let total_size;
-            {
-                let bdd_object = system.get_bdd(bdd.0).unwrap().borrow();
-                bdd_object
-                    .iter_levels()
-                    .for_each(|level| levels.push(level.get_nodes_len()));
-                total_size = bdd_object.get_size();
-            }
+            bdd_object
+                .iter_levels()
+                .for_each(|level| levels.push(level.get_nodes_len()));
             // Removes the sink since iter_levels doesn't skip the last
             levels.pop();
-            id_levels_size.push((bdd.0, levels, total_size));
-            lhs_concat.append(&mut bdd.1);
+            id_levels_size.push((*id, levels, bdd_object.get_size()));
         }
-        let lin_dep = algebra::extract_linear_dependencies(matrix![lhs_concat]);
+        // the levels are in the same order as in get_system_lhs, the dependencies are only
+        // reduced again if the system doesn't track them
+        let lin_dep = system.linear_dependencies();
 
         for m_row in lin_dep.iter_rows() {
             let mut involved_bdds = Vec::new();
@@ -296,65 +301,206 @@ pub fn find_best_bdd_pattern_dep(deps: &[NodeRankedDependency]) -> Vec<NodeRanke
     best_deps
 }
 
-#[derive(Default)]
+/// Pick the dependency to resolve by estimating the number of nodes of the resolution of the
+/// best candidates of `minimize_distance` (see `System::estimate_resolution`), instead of
+/// trusting `minimize_distance` alone.
+#[derive(Clone, Copy, Debug)]
+pub struct Lookahead {
+    /// The number of dependencies of lowest `minimize_distance` which are estimated.
+    pub candidates: usize,
+    /// The number of nodes up to which a resolution is replayed, its number of nodes is
+    /// bounded past it.
+    pub limit: usize,
+}
+
+impl Default for Lookahead {
+    fn default() -> Lookahead {
+        Lookahead {
+            candidates: 8,
+            limit: 1 << 16,
+        }
+    }
+}
+
+impl Lookahead {
+    /// Return the join order of the candidate whose resolution peaks at the fewest nodes, then
+    /// leaves the fewest nodes. A bounded estimate is higher than the exact one, the candidates
+    /// known to stay small are preferred.
+    pub fn pick_best_dep<T: Dependency>(
+        &self,
+        system: &System,
+        mut deps: Vec<T>,
+    ) -> Result<(Vec<Id>, Vec<usize>), Error> {
+        deps.sort_by_cached_key(|dep| dep.minimize_distance());
+        deps.truncate(self.candidates.max(1));
+        let mut estimated = Vec::with_capacity(deps.len());
+        for dep in deps.iter() {
+            let join_order = dep.best_join_order();
+            let estimate = system.estimate_resolution(&join_order, self.limit)?;
+            estimated.push(((estimate.peak, estimate.resolved), join_order));
+        }
+        Ok(estimated.into_iter().min_by_key(|(cost, _)| *cost).unwrap().1)
+    }
+}
+
 pub struct UpwardSolver {
     remaining: usize,
     solved: usize,
     max_reached: Cell<usize>,
+    observer: Box<dyn Observer>,
+    checkpointer: Option<Checkpointer>,
+    profiler: Option<Profiler>,
+    lookahead: Option<Lookahead>,
+}
+
+impl Default for UpwardSolver {
+    fn default() -> UpwardSolver {
+        UpwardSolver::new()
+    }
 }
 
 impl UpwardSolver {
+    /// Construct an `UpwardSolver` printing its progress on the terminal.
     pub fn new() -> UpwardSolver {
-        Default::default()
+        UpwardSolver::with_observer(Box::new(Terminal))
+    }
+
+    /// Construct an `UpwardSolver` notifying `observer` of its operations and progress.
+    pub fn with_observer(observer: Box<dyn Observer>) -> UpwardSolver {
+        UpwardSolver {
+            remaining: 0,
+            solved: 0,
+            max_reached: Cell::new(0),
+            observer,
+            checkpointer: None,
+            profiler: None,
+            lookahead: None,
+        }
+    }
+
+    /// Periodically save the progress of the solving with `checkpointer`.
+    pub fn set_checkpointer(&mut self, checkpointer: Checkpointer) {
+        self.checkpointer = Some(checkpointer);
+    }
+
+    /// Write the statistics of the joined `Bdd` with `profiler` after each join.
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    /// Pick the dependencies to resolve with `lookahead` rather than by `minimize_distance`.
+    pub fn set_lookahead(&mut self, lookahead: Lookahead) {
+        self.lookahead = Some(lookahead);
     }
 
     pub fn improved_solve(&mut self, system: &mut System) -> Result<Vec<Vec<Option<bool>>>, Error> {
-        Self::absorb_all_equations(system)?;
+        self.improved_reduce(system)?;
+        Ok(system.calculate_solutions())
+    }
+
+    /// Resolve all the dependencies of the system without extracting the solutions,
+    /// which can then be walked lazily with `System::solutions`.
+    pub fn improved_reduce(&mut self, system: &mut System) -> Result<(), Error> {
+        self.reduce_within(system, &mut Budget::new().start())?;
+        Ok(())
+    }
+
+    /// Same as `improved_reduce`, but stop before exceeding `budget` and return the partially
+    /// reduced system then (see `crush::solver::budget`). It is saved by the checkpointer, if
+    /// any, before returning.
+    pub fn bounded_reduce(
+        &mut self,
+        mut system: System,
+        budget: &Budget,
+    ) -> Result<Bounded<System>, Error> {
+        let mut meter = budget.start();
+        match self.reduce_within(&mut system, &mut meter)? {
+            Some(reason) => {
+                if let Some(checkpointer) = self.checkpointer.as_ref() {
+                    checkpointer.save(&self.checkpoint_state(), &system)?;
+                }
+                Ok(meter.exceeded(reason, system))
+            }
+            None => Ok(meter.done(system)),
+        }
+    }
+
+    /// Resolve the dependencies of the system until there are none left or `meter` exceeds its
+    /// budget, returning the bound exceeded then.
+    fn reduce_within(
+        &mut self,
+        system: &mut System,
+        meter: &mut Meter,
+    ) -> Result<Option<Reason>, Error> {
+        system.track_dependencies();
+        self.absorb_all_equations(system)?;
         let mut deps = NodeRankedDependency::extract(system);
         self.remaining = deps.len();
         while !deps.is_empty() {
+            meter.remaining(deps.len());
             deps = find_best_bdd_pattern_dep(&deps);
-            Self::resolve(self, system, Self::pick_best_dep(deps))?;
+            let join_order = match self.lookahead {
+                Some(lookahead) => lookahead.pick_best_dep(system, deps)?,
+                None => Self::pick_best_dep(deps),
+            };
+            if let Some(reason) = meter.check_resolution(system, &join_order)? {
+                return Ok(Some(reason));
+            }
+            Self::resolve(self, system, join_order)?;
             self.solved += 1;
+            meter.resolved();
             Self::feedback(self, system);
-            Self::absorb_all_equations(system)?;
+            self.absorb_all_equations(system)?;
             deps = NodeRankedDependency::extract(system);
             self.remaining = deps.len();
             Self::feedback(self, system);
+            self.checkpoint(system)?;
         }
-        Ok(system.calculate_solutions())
+        meter.remaining(0);
+        meter.check(system);
+        Ok(None)
     }
 }
 
 impl Solver for UpwardSolver {
+    fn observer(&self) -> &dyn Observer {
+        self.observer.as_ref()
+    }
+
+    fn checkpointer(&self) -> Option<&Checkpointer> {
+        self.checkpointer.as_ref()
+    }
+
+    fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    fn checkpoint_state(&self) -> Checkpoint {
+        Checkpoint {
+            solved: self.solved,
+            remaining: self.remaining,
+            max_nodes: self.max_reached.get(),
+            ..Default::default()
+        }
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.solved = checkpoint.solved;
+        self.remaining = checkpoint.remaining;
+        self.max_reached.set(checkpoint.max_nodes);
+    }
+
     fn feedback(&self, system: &System) {
-        print!("\x1Bc");
-        println!(
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}\nsolved dependencies {}, {} remaining",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
-            self.solved,
-            self.remaining,
-        );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!("biggest bdd has {} nodes", max_size);
-        let total_nodes = system
-            .iter_bdds()
-            .fold(0, |acc, bdd| acc + bdd.1.borrow().get_size());
-        if total_nodes > self.max_reached.get() {
-            self.max_reached.set(total_nodes);
-        }
-        println!(
-            "max node reach 2**{}",
-            (self.max_reached.get() as f64).log(2.0)
-        );
+        let progress = Progress::of(system);
+        if progress.nodes > self.max_reached.get() {
+            self.max_reached.set(progress.nodes);
+        }
+        self.observer.notify(&Event::Progress(Progress {
+            resolved: Some(self.solved),
+            remaining: Some(self.remaining),
+            max_nodes: Some(self.max_reached.get()),
+            ..progress
+        }));
     }
 }
 
@@ -463,7 +609,7 @@ impl Independency for NodeRankedIndependency {
             let mut levels = Vec::new();
             let total_size;
             {
-                let bdd_object = system.get_bdd(bdd.0).unwrap().borrow();
+                let bdd_object = system.get_bdd(bdd.0).unwrap();
                 bdd_object
                     .iter_levels()
                     .for_each(|level| levels.push(level.get_nodes_len()));
@@ -520,17 +666,49 @@ impl Independency for NodeRankedIndependency {
     }
 }
 
-#[derive(Default)]
 pub struct UpwardDroppingSolver {
     remaining: usize,
     solved: usize,
     dropped: usize,
     max_reached: Cell<usize>,
+    observer: Box<dyn Observer>,
+    checkpointer: Option<Checkpointer>,
+    profiler: Option<Profiler>,
+}
+
+impl Default for UpwardDroppingSolver {
+    fn default() -> UpwardDroppingSolver {
+        UpwardDroppingSolver::new()
+    }
 }
 
 impl UpwardDroppingSolver {
+    /// Construct an `UpwardDroppingSolver` printing its progress on the terminal.
     pub fn new() -> UpwardDroppingSolver {
-        Default::default()
+        UpwardDroppingSolver::with_observer(Box::new(Terminal))
+    }
+
+    /// Construct an `UpwardDroppingSolver` notifying `observer` of its operations and progress.
+    pub fn with_observer(observer: Box<dyn Observer>) -> UpwardDroppingSolver {
+        UpwardDroppingSolver {
+            remaining: 0,
+            solved: 0,
+            dropped: 0,
+            max_reached: Cell::new(0),
+            observer,
+            checkpointer: None,
+            profiler: None,
+        }
+    }
+
+    /// Periodically save the progress of the solving with `checkpointer`.
+    pub fn set_checkpointer(&mut self, checkpointer: Checkpointer) {
+        self.checkpointer = Some(checkpointer);
+    }
+
+    /// Write the statistics of the joined `Bdd` with `profiler` after each join.
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
     }
 
     pub fn improved_solve(
@@ -538,65 +716,133 @@ impl UpwardDroppingSolver {
         system: &mut System,
         forbid_dropping: Option<&[usize]>,
     ) -> Result<Vec<Vec<Option<bool>>>, Error> {
-        Self::absorb_all_equations(system)?;
+        self.improved_reduce(system, forbid_dropping)?;
+        Ok(system.calculate_solutions())
+    }
+
+    /// Resolve all the dependencies of the system without extracting the solutions,
+    /// which can then be walked lazily with `System::solutions`.
+    pub fn improved_reduce(
+        &mut self,
+        system: &mut System,
+        forbid_dropping: Option<&[usize]>,
+    ) -> Result<(), Error> {
+        self.reduce_within(system, forbid_dropping, &mut Budget::new().start())?;
+        Ok(())
+    }
+
+    /// Same as `improved_reduce`, but stop before exceeding `budget` and return the partially
+    /// reduced system then (see `crush::solver::budget`). It is saved by the checkpointer, if
+    /// any, before returning.
+    pub fn bounded_reduce(
+        &mut self,
+        mut system: System,
+        forbid_dropping: Option<&[usize]>,
+        budget: &Budget,
+    ) -> Result<Bounded<System>, Error> {
+        let mut meter = budget.start();
+        match self.reduce_within(&mut system, forbid_dropping, &mut meter)? {
+            Some(reason) => {
+                if let Some(checkpointer) = self.checkpointer.as_ref() {
+                    checkpointer.save(&self.checkpoint_state(), &system)?;
+                }
+                Ok(meter.exceeded(reason, system))
+            }
+            None => Ok(meter.done(system)),
+        }
+    }
+
+    /// Resolve the dependencies of the system until there are none left or `meter` exceeds its
+    /// budget, returning the bound exceeded then.
+    fn reduce_within(
+        &mut self,
+        system: &mut System,
+        forbid_dropping: Option<&[usize]>,
+        meter: &mut Meter,
+    ) -> Result<Option<Reason>, Error> {
+        system.track_dependencies();
+        self.absorb_all_equations(system)?;
         let mut deps = NodeRankedDependency::extract(system);
         let mut indeps = NodeRankedIndependency::extract(system, forbid_dropping);
         self.remaining = deps.len();
         while !deps.is_empty() {
+            meter.remaining(deps.len());
             deps = find_best_bdd_pattern_dep(&deps);
             let (id_dep, min_distance_dep) = Self::pick_best_dep(&deps);
             let (id_indep, min_distance_indep) = Self::pick_best_indep(&indeps);
             if min_distance_indep < min_distance_dep {
+                if let Some(reason) = meter.check(system) {
+                    return Ok(Some(reason));
+                }
                 Self::indep_resolver(self, system, indeps[id_indep].best_join_order())?;
                 self.dropped += 1;
+                meter.dropped();
             } else {
-                Self::dep_resolver(self, system, deps[id_dep].best_join_order())?;
+                let join_order = deps[id_dep].best_join_order();
+                if let Some(reason) = meter.check_resolution(system, &join_order)? {
+                    return Ok(Some(reason));
+                }
+                Self::dep_resolver(self, system, join_order)?;
                 self.solved += 1;
+                meter.resolved();
             }
 
             Self::feedback(self, system);
-            Self::absorb_all_equations(system)?;
+            self.absorb_all_equations(system)?;
             deps = NodeRankedDependency::extract(system);
             indeps = NodeRankedIndependency::extract(system, forbid_dropping);
             self.remaining = deps.len();
             Self::feedback(self, system);
+            self.checkpoint(system)?;
         }
-        Ok(system.calculate_solutions())
+        meter.remaining(0);
+        meter.check(system);
+        Ok(None)
     }
 }
 
 impl DroppingSolver for UpwardDroppingSolver {
+    fn observer(&self) -> &dyn Observer {
+        self.observer.as_ref()
+    }
+
+    fn checkpointer(&self) -> Option<&Checkpointer> {
+        self.checkpointer.as_ref()
+    }
+
+    fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    fn checkpoint_state(&self) -> Checkpoint {
+        Checkpoint {
+            solved: self.solved,
+            remaining: self.remaining,
+            dropped: self.dropped,
+            max_nodes: self.max_reached.get(),
+            ..Default::default()
+        }
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.solved = checkpoint.solved;
+        self.remaining = checkpoint.remaining;
+        self.dropped = checkpoint.dropped;
+        self.max_reached.set(checkpoint.max_nodes);
+    }
+
     fn feedback(&self, system: &System) {
-        print!( "\x1Bc");
-        println!(
-            
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}\nsolved dependencies {}, {} remaining\ndropped variables {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
-            self.solved,
-            self.remaining,
-            self.dropped
-        )
-        ;
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!( "biggest bdd has {} nodes", max_size);
-        let total_nodes = system
-            .iter_bdds()
-            .fold(0, |acc, bdd| acc + bdd.1.borrow().get_size());
-        if total_nodes > self.max_reached.get() {
-            self.max_reached.set(total_nodes);
-        }
-        println!(
-            "max node reach 2**{}",
-            (self.max_reached.get() as f64).log(2.0)
-        );
+        let progress = Progress::of(system);
+        if progress.nodes > self.max_reached.get() {
+            self.max_reached.set(progress.nodes);
+        }
+        self.observer.notify(&Event::Progress(Progress {
+            resolved: Some(self.solved),
+            remaining: Some(self.remaining),
+            dropped: Some(self.dropped),
+            max_nodes: Some(self.max_reached.get()),
+            ..progress
+        }));
     }
 }
 
@@ -610,10 +856,122 @@ pub fn execute_strategy_by_name(
             let mut solver = UpwardSolver::new();
             Some(solver.improved_solve(system).unwrap())
         }
+        "lookahead" => {
+            let mut solver = UpwardSolver::new();
+            solver.set_lookahead(Lookahead::default());
+            Some(solver.improved_solve(system).unwrap())
+        }
         "drop" => {
             let mut solver = UpwardDroppingSolver::new();
             Some(solver.improved_solve(system, forbid_dropping).unwrap())
         }
+        "guess" => {
+            let mut solver = GuessAndDetermine::new(DEFAULT_GUESS_THRESHOLD);
+            Some(solver.solve::<NodeRankedDependency>(system).unwrap())
+        }
+        _ => None,
+    }
+}
+
+/// Resolve all the dependencies of the system like the "no_drop" strategy, but guess the value
+/// of a level when a resolution would reach more than `threshold` nodes (see `GuessAndDetermine`).
+///
+/// Returns the systems of the guesses which didn't end with a contradiction, whose solutions can
+/// be walked lazily with `System::solutions`.
+pub fn reduce_system_by_guessing(system: System, threshold: usize) -> Result<Vec<System>, Error> {
+    GuessAndDetermine::new(threshold).reduce::<NodeRankedDependency>(system)
+}
+
+/// Same as `execute_strategy_by_name` but stops once all the dependencies are resolved,
+/// leaving the solutions in the system to be walked lazily with `System::solutions`.
+///
+/// Returns `None` if the strategy is not supported.
+pub fn reduce_system_by_strategy_name(
+    name: &str,
+    system: &mut System,
+    forbid_dropping: Option<&[usize]>,
+) -> Option<()> {
+    match name {
+        "no_drop" => {
+            let mut solver = UpwardSolver::new();
+            solver.improved_reduce(system).unwrap();
+            Some(())
+        }
+        "lookahead" => {
+            let mut solver = UpwardSolver::new();
+            solver.set_lookahead(Lookahead::default());
+            solver.improved_reduce(system).unwrap();
+            Some(())
+        }
+        "drop" => {
+            let mut solver = UpwardDroppingSolver::new();
+            solver.improved_reduce(system, forbid_dropping).unwrap();
+            Some(())
+        }
         _ => None,
     }
 }
+
+/// Same as `reduce_system_by_strategy_name` but stops before exceeding `budget`, returning the
+/// partially reduced system then. If `checkpointer` is `Some` the progress is saved with it, and
+/// the partially reduced system as well. If `resumed` is `Some`, the `System` comes from this
+/// checkpoint and the counters of the solver are restored from it. If `profiler` is `Some`, the
+/// statistics of the joined `Bdd` are written with it after each join.
+///
+/// Returns `None` if the strategy is not supported.
+pub fn reduce_system_bounded(
+    name: &str,
+    system: System,
+    forbid_dropping: Option<&[usize]>,
+    budget: &Budget,
+    checkpointer: Option<Checkpointer>,
+    profiler: Option<Profiler>,
+    resumed: Option<&Checkpoint>,
+) -> Option<Bounded<System>> {
+    match name {
+        "no_drop" | "lookahead" => {
+            let mut solver = UpwardSolver::new();
+            if name == "lookahead" {
+                solver.set_lookahead(Lookahead::default());
+            }
+            if let Some(checkpointer) = checkpointer {
+                solver.set_checkpointer(checkpointer);
+            }
+            if let Some(profiler) = profiler {
+                solver.set_profiler(profiler);
+            }
+            if let Some(checkpoint) = resumed {
+                solver.restore(checkpoint);
+            }
+            let reduced = solver.bounded_reduce(system, budget).unwrap();
+            flush_profile(solver.profiler());
+            Some(reduced)
+        }
+        "drop" => {
+            let mut solver = UpwardDroppingSolver::new();
+            if let Some(checkpointer) = checkpointer {
+                solver.set_checkpointer(checkpointer);
+            }
+            if let Some(profiler) = profiler {
+                solver.set_profiler(profiler);
+            }
+            if let Some(checkpoint) = resumed {
+                solver.restore(checkpoint);
+            }
+            let reduced = solver
+                .bounded_reduce(system, forbid_dropping, budget)
+                .unwrap();
+            flush_profile(solver.profiler());
+            Some(reduced)
+        }
+        _ => None,
+    }
+}
+
+/// Flush the profile written by `profiler`, if any, reporting a failure to write it without
+/// stopping: the solving is done by then.
+fn flush_profile(profiler: Option<&Profiler>) {
+    if let Some(Err(e)) = profiler.map(Profiler::flush) {
+        println!("Could not write the profile : {}", e);
+    }
+}
@@ -21,6 +21,7 @@ use skinny64::Skinny64;
 use crate::bit::{self, Bit, *};
 use crate::sbox::Sbox;
 use crush::soc::{
+    symbols::{Role, SymbolTable},
     system::System,
     utils::{SystemSpec, *},
 };
@@ -59,10 +60,20 @@ pub fn build_system_sponge(hash: &dyn SpongeHash) -> (Vec<Bit>, System) {
     if hash.output_length() % hash.rate_length() > 0 {
         n_state += 1
     }
-    let system_spec = SystemSpec::new(
+    let mut system_spec = SystemSpec::new(
         hash.message_length() + (hash.state_length() * hash.n_rounds()) * n_state,
         bdds,
     );
+    let mut symbols = SymbolTable::new();
+    symbols
+        .insert_all(
+            &(0..hash.message_length()).collect::<Vec<usize>>(),
+            "m",
+            Role::Message,
+        )
+        .unwrap();
+    sbox.name_outputs(&mut symbols);
+    system_spec.set_symbols(symbols);
     (output, build_system_from_spec(system_spec))
 }
 
@@ -78,7 +89,25 @@ pub fn build_system_cipher(cipher: &dyn Cipher) -> (Vec<Bit>, Vec<Bit>, System)
     let output = cipher.encrypt(message_bits.clone(), key_bits);
     let mut sbox = cipher.sbox();
     let bdds = sbox.bdds();
-    let system_spec = SystemSpec::new(sbox.next_var_id(), bdds);
+    let mut system_spec = SystemSpec::new(sbox.next_var_id(), bdds);
+    let mut symbols = SymbolTable::new();
+    symbols
+        .insert_all(
+            &(0..cipher.key_length()).collect::<Vec<usize>>(),
+            "k",
+            Role::Key,
+        )
+        .unwrap();
+    symbols
+        .insert_all(
+            &(cipher.key_length()..cipher.key_length() + cipher.message_length())
+                .collect::<Vec<usize>>(),
+            "p",
+            Role::Plaintext,
+        )
+        .unwrap();
+    sbox.name_outputs(&mut symbols);
+    system_spec.set_symbols(symbols);
     (message_bits, output, build_system_from_spec(system_spec))
 }
 
@@ -316,7 +316,7 @@ mod test {
         let path_to_expected = &["SoCs", "DDTprinceS_generic.bdd"].iter().collect();
         let sys_spec = utils::parse_system_spec_from_file(&path_to_expected);
         let mut soc = utils::build_system_from_spec(sys_spec);
-        let expected = soc.drain_bdds().next().unwrap().1.into_inner();
+        let expected = soc.drain_bdds().next().unwrap().1;
 
         assert_eq!(actual, expected);
     }
@@ -329,7 +329,7 @@ mod test {
         let path_to_expected = &["SoCs", "DDTprinceSinv_generic.bdd"].iter().collect();
         let sys_spec = utils::parse_system_spec_from_file(&path_to_expected);
         let mut soc = utils::build_system_from_spec(sys_spec);
-        let expected = soc.drain_bdds().next().unwrap().1.into_inner();
+        let expected = soc.drain_bdds().next().unwrap().1;
 
         assert_eq!(actual, expected);
     }
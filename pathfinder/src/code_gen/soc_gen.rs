@@ -1,5 +1,6 @@
 use crate::code_gen::{LLHandler, SBoxHandler};
 use vob::Vob;
+use crush::soc::symbols::{Role, SymbolTable};
 use crush::soc::system::System;
 use crush::soc::Id;
 
@@ -43,6 +44,11 @@ pub fn make_soc<L, S>(llb: &L, sh: &S, nr_rounds: usize) -> (System, Vec<Vec<Id>
     // Initial in-block:
     let init_block_size = llb.block_size(0);
     let mut initial = Vec::with_capacity(init_block_size);
+    // Names of the variables: p<i> for the initial in-block, s_r<r>_b<s>_out<i> for the out bits
+    // of S-box s of round r
+    let mut symbols = SymbolTable::new();
+    symbols.insert_all(&(0..init_block_size).collect::<Vec<usize>>(), "p", Role::Plaintext)
+        .unwrap();
     for i in 0..init_block_size {
         let mut lhs = Vob::from_elem(nvar, false);
         lhs.set(i, true);
@@ -74,9 +80,11 @@ pub fn make_soc<L, S>(llb: &L, sh: &S, nr_rounds: usize) -> (System, Vec<Vec<Id>
 
             // Make LHSs for the out bits of next shard to be created
             let mut lhs_o = Vec::new();
-            for _ in 0..sh.sbox_size_out(r, s) {
+            for i in 0..sh.sbox_size_out(r, s) {
                 let mut lhs_out =  Vob::from_elem(nvar, false);
                 lhs_out.set(next_var_id, true);
+                symbols.insert(next_var_id, &format!("s_r{}_b{}_out{}", r, s, i), Role::State)
+                    .unwrap();
                 next_var_id += 1;
 
                 lhs_o.push(lhs_out.clone());
@@ -109,7 +117,9 @@ pub fn make_soc<L, S>(llb: &L, sh: &S, nr_rounds: usize) -> (System, Vec<Vec<Id>
     // of non-linear layer).
 
     // Make and return SoC. Now also includes the overview of what Shards are at what rounds
-    (System::from_elem(shards).unwrap(), rounds)
+    let mut system = System::from_elem(shards).unwrap();
+    system.set_symbols(symbols);
+    (system, rounds)
 
 }
 
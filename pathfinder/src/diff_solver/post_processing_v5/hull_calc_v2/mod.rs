@@ -301,12 +301,11 @@ impl fmt::Display for BuildMode {
 /// exactly *one* node on the end depth level.
 fn extract_a_single_path(master: Arc<Shard>, start_depth: Depth, end_depth: Depth) -> Path {
 
-    let start_nodes = master
+    let start_level = master
         .level(start_depth)
-        .expect("Start level is missing!")
-        .get_nodes();
-    assert_eq!(start_nodes.len(), 1);
-    let mut current_node = start_nodes.values().next().expect("Start level is empty!");
+        .expect("Start level is missing!");
+    assert_eq!(start_level.get_nodes_len(), 1);
+    let mut current_node = start_level.iter_nodes().next().expect("Start level is empty!").1;
 
     let mut current_depth: Depth = start_depth;
     let mut path = Vob::with_capacity(end_depth - start_depth);
@@ -388,13 +387,12 @@ pub(crate) fn extract_all_paths_concurrently<P>(master: Arc<Shard>,
 {
     let (tx, rx) = sync_channel(sender_bound);
 
-    let start_nodes = master
+    let start_level = master
         .level(start_depth)
-        .expect("Start level is missing!")
-        .get_nodes();
-    assert_eq!(start_nodes.len(), 1);
+        .expect("Start level is missing!");
+    assert_eq!(start_level.get_nodes_len(), 1);
 
-    let start_node = start_nodes.values().next().expect("Start level is empty!").clone();
+    let start_node = start_level.iter_nodes().next().expect("Start level is empty!").1.clone();
     let mut path = Vob::new();
 
     {
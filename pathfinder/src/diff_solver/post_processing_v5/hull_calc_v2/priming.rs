@@ -144,12 +144,11 @@ fn extract_alpha_to_n(master: &Arc<Shard>, master_md: &SolvedSocMeta, best_id: &
         &TargetedFactory::new(vec![best_id.clone()]),
     );
 
-    let start_nodes = master
+    let start_level = master
         .level(master_md.alpha_lvl_depth)
-        .expect("Start level is missing!")
-        .get_nodes();
-    assert_eq!(start_nodes.len(), 1);
-    let mut current_id = start_nodes.keys().next().expect("Start level is empty!").clone();
+        .expect("Start level is missing!");
+    assert_eq!(start_level.get_nodes_len(), 1);
+    let mut current_id = start_level.iter_ids().next().expect("Start level is empty!");
 
     // We start at the alpha level, and build down towards the n-level
     let mut current_depth: Depth = master_md.alpha_lvl_depth ;
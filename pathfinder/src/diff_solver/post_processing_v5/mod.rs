@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::num::NonZeroUsize;
 use std::ops::Range;
@@ -280,26 +281,26 @@ fn delete_non_sess_estimate_nodes(master: &mut Shard, master_md: &SolvedSocMeta,
     let beta_level = master.level(master_md.beta_lvl_depth)
         .expect("Beta level is missing");
 
-    let mut to_delete = beta_level.get_nodes().clone();
-    let target_node = to_delete.remove(&sess_estimate.end());
+    let mut to_delete: HashSet<Id> = beta_level.iter_ids().collect();
+    let target_node = to_delete.take(&sess_estimate.end());
     // Sanity check
     if target_node.is_none() {
         panic!("We failed for some reason to remove the End Node from the set of nodes to be deleted");
     }
-    master.delete_all_marked_nodes_from_level(to_delete.keys().collect(),
+    master.delete_all_marked_nodes_from_level(to_delete.iter().collect(),
                                               master_md.beta_lvl_depth);
 
     // Deleting from alpha level
     let alpha_level =  master.level(master_md.alpha_lvl_depth)
         .expect("Alpha level is missing");
 
-    let mut to_delete = alpha_level.get_nodes().clone();
-    let target_node = to_delete.remove(&sess_estimate.start());
+    let mut to_delete: HashSet<Id> = alpha_level.iter_ids().collect();
+    let target_node = to_delete.take(&sess_estimate.start());
     // Sanity check
     if target_node.is_none() {
         panic!("We failed for some reason to remove the Start Node from the set of nodes to be deleted");
     }
-    master.delete_all_marked_nodes_from_level(to_delete.keys().collect(),
+    master.delete_all_marked_nodes_from_level(to_delete.iter().collect(),
                                               master_md.alpha_lvl_depth);
 }
 
@@ -1,16 +1,16 @@
-use std::cell::{Ref, RefMut};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::Range;
 
 use vob::Vob;
 
-use crush::algebra::{self, Matrix};
+use crush::algebra::{Matrix, PackedMatrix};
 use crush::soc::bdd::Bdd;
 use crush::soc::bdd::differential::{PPFactory, StyledProgressBar};
 use crush::soc::Id;
 use crush::soc::system::System;
 use crush::soc::utils;
+use crush::solver::profile::Profiler;
 
 use crate::diff_solver::SPFactory;
 
@@ -42,6 +42,8 @@ pub struct SimpleSolver<F>
     progress_arena: F,
     /// ProgressBar for the progress of joining Shards into Master
     join_progress: <F as SPFactory>::ProgressBar,
+    /// Writes the statistics of `Master` after each join, if set.
+    profiler: Option<Profiler>,
 }
 
 impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
@@ -82,6 +84,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             master_block_size,
             progress_arena,
             join_progress,
+            profiler: None,
         };
 
         me
@@ -116,6 +119,11 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         self.join_progress.finish_with_message("All Shards are joined into Master");
     }
 
+    /// Write the statistics of `Master` with `profiler` after each join of a Shard into it.
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
     pub fn soc(&self) -> &System {
         &self.soc
     }
@@ -152,12 +160,16 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         self.joined_w_master.push(bottom);
 
         let lhs = self.master().get_lhs();
-        let dependencies = algebra::extract_linear_dependencies(matrix![lhs]);
+        let dependencies = PackedMatrix::from_rows(&lhs).linear_dependencies();
 
         let complexity = self.soc.get_size();
         self.librarian.record(Join(
             JoinRec::new(self.master_id, bottom, complexity, dependencies.row_size())));
 
+        if let Some(profiler) = self.profiler.as_ref() {
+            profiler.record(&self.master().stats());
+        }
+
         self.join_progress.inc(1);
     }
 
@@ -173,7 +185,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
     /// input bit to an S-box.
     fn resolve_any_deps(&mut self) {
         let lhs = self.master().get_lhs();
-        let mut dependencies = algebra::extract_linear_dependencies(matrix![lhs]);
+        let mut dependencies = PackedMatrix::from_rows(&lhs).linear_dependencies();
 
         if dependencies.is_empty() {
             return;
@@ -186,7 +198,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             self.resolve_dep(dep);
             // Update dependency matrix
             let lhs = self.master().get_lhs();
-            dependencies = algebra::extract_linear_dependencies(matrix![lhs]);
+            dependencies = PackedMatrix::from_rows(&lhs).linear_dependencies();
         }
     }
 
@@ -330,10 +342,11 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
                 &self.progress_arena,
                 self.master().get_size().checked_sub(soft_lim).unwrap_or(42) as u64);
 
+            let step = self.step;
             self.master_mut()
                 .complexity_based_wide_prune_v3(soft_lim,
                                                 active_area,
-                                                self.step,
+                                                step,
                                                 &mut prune_rec,
                                                 prune_progress,
                 );
@@ -374,18 +387,18 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
 
     /// self.master contains the `*Id*` of `Master`, whereas this method will
     /// return a reference to the `Master` *shard*.
-    /// Bypasses the `.unwrap().borrow()`, which gets a bit repetitive and space consuming...
+    /// Bypasses the `.unwrap()`, which gets a bit repetitive and space consuming...
     #[inline]
-    fn master(&self) -> Ref<Bdd> {
-        self.soc.get_bdd(self.master_id).unwrap().borrow()
+    fn master(&self) -> &Bdd {
+        self.soc.get_bdd(self.master_id).unwrap()
     }
 
     /// self.master contains the `*Id*` of `Master`, whereas this method will
     /// return a mutable reference to the `Master` *shard*.
-    /// Bypasses the `.unwrap().borrow()`...
+    /// Bypasses the `.unwrap()`...
     #[inline]
-    fn master_mut(&self) -> RefMut<Bdd> {
-        self.soc.get_bdd(self.master_id).unwrap().borrow_mut()
+    fn master_mut(&mut self) -> &mut Bdd {
+        self.soc.get_bdd_mut(self.master_id).unwrap()
     }
 
     /// Swap level at `from` in `Master` such that it ends up at `to`, i.e. level which was at
@@ -643,7 +656,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             }
             println!("LHS: {}", lhs_buff);
             println!("RHS: ");
-            for n in level.get_nodes().iter() {
+            for n in level.iter_nodes() {
                 println!("{:?}", n);
             }
         }
@@ -373,12 +373,14 @@ fn from_beginning(setup: &Setup, cipher: Box<dyn Cipher>)
 fn from_solved_soc(setup: &Setup, progress_spinner: MyStyledSpinner, cipher: &dyn Cipher)
                    -> SolvedSoC<BtHandler, SbHandler, Progress>
 {
-    CgBuilder::from_parent_folder(
-        setup,
-        cipher,
-        progress_spinner,
-        setup.in_parent_folder()
-            .expect("Why call from parent folder when no parent folder is given?")
-            .clone(),
-    )
+    let in_parent_folder = setup.in_parent_folder()
+        .expect("Why call from parent folder when no parent folder is given?")
+        .clone();
+    match CgBuilder::from_parent_folder(setup, cipher, progress_spinner, in_parent_folder) {
+        Ok(solved_soc) => solved_soc,
+        Err(e) => {
+            eprintln!("Unable to load the SoC from file: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
\ No newline at end of file
@@ -13,6 +13,7 @@ use crush::algebra::Matrix;
 use crush::soc::bdd::differential::StyledProgressBar;
 use crush::soc::Id;
 use crush::soc::system::System;
+use crush::soc::utils::SpecError;
 use pathfinder::code_gen::{LLHandler, SBoxHandler};
 use pathfinder::code_gen::gsf::GenericShard;
 use pathfinder::code_gen::soc_gen;
@@ -58,7 +59,7 @@ impl CgBuilder {
                               cipher: &dyn Cipher,
                               progress_spinner: MyStyledSpinner,
                               in_parent_folder: PathBuf)
-                              -> SolvedSoC<BtHandler, SbHandler, Progress>
+                              -> Result<SolvedSoC<BtHandler, SbHandler, Progress>, SpecError>
     {
         progress_spinner.set_message("Building metadata.");
         let raw_soc = match setup.cipher_structure {
@@ -81,8 +82,16 @@ impl CgBuilder {
         // Load SolvedSoc from file
         progress_spinner.println(&format!("Soc loaded from file: {}", file_path.display()));
         progress_spinner.set_message(&format!("Loading SoC from file: {}", file_path.display()));
-        let sys_spec = crush::soc::utils::parse_system_spec_from_file(&file_path);
-        let solved_soc = crush::soc::utils::build_system_from_spec(sys_spec);
+        let solved_soc = match crush::soc::utils::try_parse_system_spec_from_file(&file_path)
+            .and_then(crush::soc::utils::try_build_system_from_spec)
+        {
+            Ok(soc) => soc,
+            Err(e) => {
+                progress_spinner.finish_with_message(
+                    &format!("Failed to load the SoC from file: {}", file_path.display()));
+                return Err(e);
+            }
+        };
 
         // assumes all out bits are equal! (We don't support unequal step anyways).
         let step = raw_soc.sb_handler.sbox_size_out(0,0);
@@ -91,7 +100,7 @@ impl CgBuilder {
             end: solved_soc.get_nvar() };
 
         progress_spinner.finish_with_message("Successfully loaded the Solved SoC from file.");
-        SolvedSoC {
+        Ok(SolvedSoC {
             setup: setup.clone(),
             soc: solved_soc,
             lhss: raw_soc.lhss,
@@ -102,7 +111,7 @@ impl CgBuilder {
             active_area,
             step: NonZeroUsize::new(step).unwrap(),
             loggs: Loggers::new(),
-        }
+        })
     }
 
     fn reflective(setup: &Setup, cipher: &dyn Cipher) -> RawSoc<BtHandler, SbHandler>
@@ -151,7 +160,7 @@ impl CgBuilder {
         let insize = sbh.sbox_size_in[0][0];
         let cohorts = soc.iter_bdds()
             .map(|(id, shard)| {
-                let to_keep = shard.borrow().get_lhs().iter().skip(insize)
+                let to_keep = shard.get_lhs().iter().skip(insize)
                     .cloned()
                     .collect();
                 (id.clone(), to_keep)
@@ -1,4 +1,3 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Error as FmtError, Debug};
@@ -418,10 +417,9 @@ impl<B, S, F> SolvedSoC<B, S, F>
             P: PPFactory,
     {
         // Make the SoC into a single Shard
-        let master: HashMap<Id, RefCell<Shard>> = self.soc.drain_bdds().collect();
+        let master: HashMap<Id, Shard> = self.soc.drain_bdds().collect();
         debug_assert_eq!(master.len(), 1);
-        let (_master_id, master_cell) = master.into_iter().next().unwrap();
-        let master = master_cell.into_inner();
+        let (_master_id, master) = master.into_iter().next().unwrap();
 
         // Assuming bits in == bits out
         let beta_len = self.sb_handler.num_sboxes(0)*self.step.get();